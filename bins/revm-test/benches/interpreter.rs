@@ -0,0 +1,112 @@
+//! Interpreter throughput on representative workloads.
+//!
+//! Every workload is a transaction over a fixed [InMemoryDB]. State is never committed, so every
+//! run is the same. Criterion reports the time per transaction and gas/s as the throughput, one
+//! element is one unit of gas.
+//!
+//! Run with `cargo bench -p revm-test --bench interpreter`, an argument only runs the workloads
+//! whose name contains it.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use revm::{opcode, InMemoryDB, Return, EVM};
+use revm_test::{erc20_transfer, evm_with_code};
+
+type Workload = fn() -> EVM<InMemoryDB>;
+
+/// Runs `setup`, then counts down from `iterations` with `body` run in every iteration. `body`
+/// leaves the stack as it found it.
+fn count_down(setup: &[u8], iterations: u16, body: &[u8]) -> Vec<u8> {
+    let mut code = setup.to_vec();
+    code.push(opcode::PUSH2);
+    code.extend_from_slice(&iterations.to_be_bytes());
+    let loop_start = code.len() as u8;
+    code.push(opcode::JUMPDEST);
+    code.extend_from_slice(body);
+    code.extend([
+        opcode::PUSH1,
+        0x01,
+        opcode::SWAP1,
+        opcode::SUB,
+        opcode::DUP1,
+    ]);
+    code.extend([opcode::PUSH1, loop_start, opcode::JUMPI, opcode::STOP]);
+    code
+}
+
+fn arithmetic_loop() -> EVM<InMemoryDB> {
+    // x = x * 3 + 7 mod 2^256, x lives below the counter.
+    let body = [
+        opcode::SWAP1,
+        opcode::PUSH1,
+        0x03,
+        opcode::MUL,
+        opcode::PUSH1,
+        0x07,
+        opcode::ADD,
+        opcode::SWAP1,
+    ];
+    let code = count_down(&[opcode::PUSH1, 0x01], 10_000, &body);
+    evm_with_code(code, Vec::new())
+}
+
+/// [arithmetic_loop] with [revm::CfgEnv::fuse_opcodes], `PUSH1 7 ADD` and `DUP1 PUSH1` are fused.
+fn arithmetic_loop_fused() -> EVM<InMemoryDB> {
+    let mut evm = arithmetic_loop();
+    evm.env.cfg.fuse_opcodes = true;
+    evm
+}
+
+fn keccak_loop() -> EVM<InMemoryDB> {
+    // hash the two words at 0..64 and store the hash at 0.
+    let body = [
+        opcode::PUSH1,
+        0x40,
+        opcode::PUSH1,
+        0x00,
+        opcode::SHA3,
+        opcode::PUSH1,
+        0x00,
+        opcode::MSTORE,
+    ];
+    evm_with_code(count_down(&[], 5_000, &body), Vec::new())
+}
+
+fn call_chain() -> EVM<InMemoryDB> {
+    // call itself with all gas until the call depth limit of 1024 ends the chain.
+    let mut code = vec![
+        opcode::PUSH1,
+        0x00,
+        opcode::DUP1,
+        opcode::DUP1,
+        opcode::DUP1,
+    ];
+    code.extend([opcode::DUP1, opcode::ADDRESS, opcode::GAS, opcode::CALL]);
+    code.push(opcode::STOP);
+    evm_with_code(code, Vec::new())
+}
+
+fn interpreter(c: &mut Criterion) {
+    let workloads: [(&str, Workload); 5] = [
+        ("arithmetic_loop", arithmetic_loop),
+        ("arithmetic_loop_fused", arithmetic_loop_fused),
+        ("erc20_transfer", erc20_transfer),
+        ("keccak_loop", keccak_loop),
+        ("call_chain", call_chain),
+    ];
+    let mut group = c.benchmark_group("interpreter");
+    for (name, workload) in workloads {
+        let mut evm = workload();
+        let (result, _) = evm.transact();
+        assert!(
+            matches!(result.exit_reason, Return::Stop | Return::Return),
+            "{name}: {:?}",
+            result.exit_reason
+        );
+        group.throughput(Throughput::Elements(result.gas_used));
+        group.bench_function(name, |b| b.iter(|| evm.transact()));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, interpreter);
+criterion_main!(benches);
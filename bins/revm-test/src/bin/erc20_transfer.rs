@@ -0,0 +1,56 @@
+//! Throughput of ERC20 `transfer`, the most common workload.
+//!
+//! Checks with [BalanceTransferInspector] that the `erc20_transfer` workload of the
+//! `interpreter` bench is recognized as a balance transfer with both slots warm when written,
+//! then reports Mgas/s of a tight transfer loop with gas charged per opcode and per gas block.
+//!
+//! Run with `cargo run --release -p revm-test --bin erc20_transfer`.
+
+use std::time::Instant;
+
+use primitive_types::{H160, U256};
+use revm::{BalanceTransferInspector, GasBlockGranularity, Return};
+use revm_test::{balance_slot, erc20_transfer, CONTRACT};
+
+fn main() {
+    const TRANSFERS: usize = 50_000;
+
+    let mut evm = erc20_transfer();
+    let mut inspector = BalanceTransferInspector::new();
+    let (result, _) = evm.inspect(&mut inspector);
+    assert_eq!(result.exit_reason, Return::Return);
+    let transfer = match inspector.transfers() {
+        [transfer] => *transfer,
+        transfers => panic!("expected one balance transfer, got {transfers:?}"),
+    };
+    assert_eq!(transfer.address, H160::from_low_u64_be(CONTRACT));
+    assert_eq!(transfer.from_slot, balance_slot(evm.env.tx.caller));
+    assert_eq!(transfer.amount, U256::one());
+    assert!(transfer.warm);
+    println!("transfer: {} gas, {transfer:?}", result.gas_used);
+
+    for granularity in [
+        GasBlockGranularity::PerOpcode,
+        GasBlockGranularity::WholeBlock,
+    ] {
+        let mut evm = erc20_transfer();
+        evm.env.cfg.gas_block_granularity = granularity;
+        // warm up, also fills the cache of the recipient slot.
+        for _ in 0..1000 {
+            evm.transact_commit();
+        }
+
+        let timer = Instant::now();
+        let mut gas = 0;
+        for _ in 0..TRANSFERS {
+            let result = evm.transact_commit();
+            assert_eq!(result.exit_reason, Return::Return);
+            gas += result.gas_used;
+        }
+        let elapsed = timer.elapsed();
+        println!(
+            "{granularity:?}: {TRANSFERS} transfers in {elapsed:?}, {:.1} Mgas/s",
+            gas as f64 / elapsed.as_secs_f64() / 1e6
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use std::time::Instant;
+
+use primitive_types::U256;
+use revm::{Return, Stack, StackWord};
+
+/// 32 byte aligned word, the layout a SIMD implementation would use.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(C, align(32))]
+struct AlignedWord([u64; 4]);
+
+impl From<U256> for AlignedWord {
+    fn from(value: U256) -> Self {
+        Self(value.0)
+    }
+}
+
+impl From<AlignedWord> for U256 {
+    fn from(value: AlignedWord) -> Self {
+        U256(value.0)
+    }
+}
+
+impl StackWord for AlignedWord {
+    fn from_limbs(limbs: [u64; 4]) -> Self {
+        Self(limbs)
+    }
+}
+
+/// Wrapping addition, the arithmetic part of the workload.
+trait WrappingAdd: StackWord {
+    fn wrapping_add(self, other: Self) -> Self;
+}
+
+impl WrappingAdd for U256 {
+    fn wrapping_add(self, other: Self) -> Self {
+        self.overflowing_add(other).0
+    }
+}
+
+impl WrappingAdd for AlignedWord {
+    fn wrapping_add(self, other: Self) -> Self {
+        let mut out = [0u64; 4];
+        let mut carry = false;
+        for (i, limb) in out.iter_mut().enumerate() {
+            let (sum, c1) = self.0[i].overflowing_add(other.0[i]);
+            let (sum, c2) = sum.overflowing_add(carry as u64);
+            *limb = sum;
+            carry = c1 || c2;
+        }
+        Self(out)
+    }
+}
+
+/// Fibonacci like loop: `PUSH32 PUSH32 (DUP2 DUP2 ADD SWAP1 POP)*`.
+fn run<W: WrappingAdd>(iterations: usize) -> U256 {
+    let mut stack = Stack::<W>::new();
+    assert_eq!(stack.push_slice::<32>(&[0x11; 32]), Return::Continue);
+    assert_eq!(stack.push_slice::<32>(&[0x22; 32]), Return::Continue);
+    for _ in 0..iterations {
+        stack.dup::<2>();
+        stack.dup::<2>();
+        let a = stack.pop().unwrap();
+        let b = stack.pop().unwrap();
+        stack.push(a.wrapping_add(b)).unwrap();
+        stack.swap::<1>();
+        stack.pop().unwrap();
+    }
+    stack.pop().unwrap().into()
+}
+
+fn main() {
+    const ITERATIONS: usize = 10_000_000;
+
+    // just to spead up processor.
+    run::<U256>(ITERATIONS);
+
+    let timer = Instant::now();
+    let u256 = run::<U256>(ITERATIONS);
+    println!("U256 elapsed time: {:?}", timer.elapsed());
+
+    let timer = Instant::now();
+    let aligned = run::<AlignedWord>(ITERATIONS);
+    println!("AlignedWord elapsed time: {:?}", timer.elapsed());
+
+    assert_eq!(u256, aligned);
+}
@@ -1,4 +1,4 @@
-use crate::{runner, statetest};
+use crate::{disasm, runner, statetest, tracediff};
 use structopt::{clap::AppSettings, StructOpt};
 
 #[derive(StructOpt, Debug)]
@@ -7,14 +7,21 @@ use structopt::{clap::AppSettings, StructOpt};
 pub enum MainCmd {
     Statetest(statetest::Cmd),
     Run(runner::Cmd),
+    Disasm(disasm::Cmd),
+    TraceDiff(tracediff::Cmd),
 }
 
 use thiserror::Error as ThisError;
 
 #[derive(Debug, ThisError)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     #[error("Statetest: {0}")]
     Statetest(statetest::Error),
+    #[error("Disasm: {0}")]
+    Disasm(disasm::Error),
+    #[error("TraceDiff: {0}")]
+    TraceDiff(tracediff::Error),
     #[error("Generic system error")]
     SystemError,
 }
@@ -23,6 +30,8 @@ impl MainCmd {
     pub fn run(&self) -> Result<(), Error> {
         match self {
             Self::Statetest(cmd) => cmd.run().map_err(Error::Statetest),
+            Self::Disasm(cmd) => cmd.run().map_err(Error::Disasm),
+            Self::TraceDiff(cmd) => cmd.run().map_err(Error::TraceDiff),
             _ => Ok(()),
         }
     }
@@ -0,0 +1,84 @@
+use revm::{disassemble, split_deploy_code};
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+use structopt::StructOpt;
+use thiserror::Error as ThisError;
+
+#[derive(StructOpt, Debug)]
+pub struct Cmd {
+    /// File with hex encoded bytecode.
+    #[structopt(long)]
+    code: PathBuf,
+    /// Strip the constructor and print only the runtime code it returns.
+    #[structopt(long)]
+    runtime: bool,
+}
+
+#[derive(Debug, ThisError)]
+pub enum Error {
+    #[error("Can't read code file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Code is not valid hex: {0}")]
+    Hex(#[from] hex::FromHexError),
+    #[error("Deploy pattern not found, code does not look like init code")]
+    RuntimeNotFound,
+}
+
+impl Cmd {
+    pub fn run(&self) -> Result<(), Error> {
+        let code = read_code::<Error>(&self.code)?;
+        let code = if self.runtime {
+            split_deploy_code(&code).ok_or(Error::RuntimeNotFound)?.1
+        } else {
+            &code
+        };
+        print!("{}", listing(code));
+        Ok(())
+    }
+}
+
+/// Read hex encoded code from a file, surrounding whitespace and `0x` prefix are allowed.
+pub fn read_code<E>(path: &Path) -> Result<Vec<u8>, E>
+where
+    E: From<std::io::Error> + From<hex::FromHexError>,
+{
+    let text = std::fs::read_to_string(path)?;
+    let text = text.trim();
+    Ok(hex::decode(text.strip_prefix("0x").unwrap_or(text))?)
+}
+
+/// Format disassembly of `code` as aligned `pc mnemonic immediate` columns.
+fn listing(code: &[u8]) -> String {
+    let ops = disassemble(code);
+    let pc_width = ops
+        .last()
+        .map(|(pc, _, _)| format!("{:x}", pc).len())
+        .unwrap_or_default()
+        .max(4);
+    let mut out = String::new();
+    for (pc, op, immediate) in ops {
+        let immediate = immediate
+            .map(|data| format!("0x{}", hex::encode(data)))
+            .unwrap_or_default();
+        let line = format!("0x{:0pc_width$x}  {:<14} {}", pc, op.as_str(), immediate);
+        writeln!(out, "{}", line.trim_end()).unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::listing;
+    use revm::opcode;
+
+    #[test]
+    fn aligned_listing() {
+        let code = [opcode::PUSH2, 0x01, 0x02, opcode::JUMPDEST, opcode::STOP];
+        assert_eq!(
+            listing(&code),
+            "0x0000  PUSH2          0x0102\n0x0003  JUMPDEST\n0x0004  STOP\n"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use crate::statetest::models::deserializer::{
+    deserialize_str_as_bytes, deserialize_str_as_u256, deserialize_str_as_u64,
+};
+use bytes::Bytes;
+use primitive_types::{H160, U256};
+use revm::{AccountInfo, Bytecode, InMemoryDB};
+use serde_derive::Deserialize;
+use std::{collections::HashMap, path::Path};
+use thiserror::Error as ThisError;
+
+/// Geth `genesis.json`. Only the `alloc` section is read.
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+pub struct Genesis {
+    pub alloc: HashMap<H160, GenesisAccount>,
+}
+
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+pub struct GenesisAccount {
+    /// Balance as decimal or 0x prefixed hex string.
+    #[serde(deserialize_with = "deserialize_str_as_u256")]
+    pub balance: U256,
+    #[serde(default, deserialize_with = "deserialize_str_as_u64")]
+    pub nonce: u64,
+    #[serde(default, deserialize_with = "deserialize_str_as_bytes")]
+    pub code: Bytes,
+    #[serde(default)]
+    pub storage: HashMap<U256, U256>,
+}
+
+#[derive(Debug, ThisError)]
+pub enum Error {
+    #[error("Can't read genesis file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid genesis json: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl Genesis {
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// Database with all accounts from the alloc section.
+    pub fn into_db(self) -> InMemoryDB {
+        let mut db = InMemoryDB::default();
+        for (address, account) in self.alloc {
+            let info = AccountInfo::new(
+                account.balance,
+                account.nonce,
+                Bytecode::new_raw(account.code),
+            );
+            db.insert_account_info(address, info);
+            for (slot, value) in account.storage {
+                db.insert_account_storage(address, slot, value).unwrap();
+            }
+        }
+        db
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Genesis;
+    use primitive_types::{H160, U256};
+    use revm::{Database, KECCAK_EMPTY};
+
+    fn address(first_byte: u8) -> H160 {
+        let mut address = H160::zero();
+        address.0[0] = first_byte;
+        address
+    }
+
+    #[test]
+    fn load_alloc() {
+        let json = r#"{
+            "config": { "chainId": 1337 },
+            "difficulty": "1",
+            "gasLimit": "8000000",
+            "alloc": {
+                "0x1000000000000000000000000000000000000000": {
+                    "balance": "1000000000000000000"
+                },
+                "2000000000000000000000000000000000000000": {
+                    "balance": "0x10",
+                    "nonce": "0x2",
+                    "code": "0x600160005500",
+                    "storage": {
+                        "0x0000000000000000000000000000000000000000000000000000000000000001": "0x2a"
+                    }
+                }
+            }
+        }"#;
+        let mut db = serde_json::from_str::<Genesis>(json).unwrap().into_db();
+
+        let eoa = db.basic(address(0x10)).unwrap().unwrap();
+        assert_eq!(eoa.balance, U256::exp10(18));
+        assert_eq!(eoa.nonce, 0);
+        assert_eq!(eoa.code_hash, KECCAK_EMPTY);
+
+        let contract_address = address(0x20);
+        let contract = db.basic(contract_address).unwrap().unwrap();
+        assert_eq!(contract.balance, U256::from(0x10));
+        assert_eq!(contract.nonce, 2);
+        assert_eq!(
+            contract.code.unwrap().bytes().as_ref(),
+            &[0x60, 0x01, 0x60, 0x00, 0x55, 0x00]
+        );
+        assert_eq!(
+            db.storage(contract_address, U256::one()).unwrap(),
+            U256::from(0x2a)
+        );
+    }
+}
@@ -1,7 +1,9 @@
 mod cmd;
+mod disasm;
 mod exec;
 mod runner;
 mod statetest;
+mod tracediff;
 use cmd::Error;
 use structopt::StructOpt;
 mod cli_env;
@@ -7,6 +7,9 @@ use structopt::StructOpt;
 pub struct Cmd {
     #[structopt(required = true)]
     path: Vec<PathBuf>,
+    /// Trace a failing test as EIP-3155 JSON lines instead of the plain text trace.
+    #[structopt(long)]
+    json: bool,
 }
 
 impl Cmd {
@@ -14,7 +17,7 @@ impl Cmd {
         for path in &self.path {
             println!("Start running tests on: {:?}", path);
             let test_files = find_all_json_tests(path);
-            run(test_files)?
+            run(test_files, self.json)?
         }
         Ok(())
     }
@@ -0,0 +1,224 @@
+use bytes::Bytes;
+use primitive_types::{H160, U256};
+use revm::{
+    return_ok, CallInputs, CreateInputs, Database, EVMData, ExecutionResult, Gas, GasInspector,
+    Inspector, Interpreter, OpCode, Return, TransactOut,
+};
+use serde_derive::Serialize;
+use std::io::{self, Write};
+
+/// One line of an EIP-3155 trace, written before the opcode executes.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StepLine {
+    pc: usize,
+    op: u8,
+    gas: String,
+    /// Static gas of the opcode, dynamic costs like memory expansion are not included.
+    gas_cost: String,
+    mem_size: usize,
+    stack: Vec<String>,
+    depth: u64,
+    refund: i64,
+    op_name: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SummaryLine {
+    output: String,
+    gas_used: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Inspector writing an EIP-3155 JSON line per step to `writer`, see
+/// [JsonStepTracer::write_summary] for the closing line.
+///
+/// Write errors do not stop execution, the first one is kept and returned by
+/// [JsonStepTracer::finish].
+pub struct JsonStepTracer<W: Write> {
+    gas_inspector: GasInspector,
+    writer: W,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> JsonStepTracer<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            gas_inspector: GasInspector::default(),
+            writer,
+            error: None,
+        }
+    }
+
+    /// Write the summary line with the output, used gas and error of `result`.
+    pub fn write_summary(&mut self, result: &ExecutionResult) {
+        let output = match &result.out {
+            TransactOut::None => Bytes::new(),
+            TransactOut::Call(out) | TransactOut::Create(out, _) => out.clone(),
+        };
+        let summary = SummaryLine {
+            output: format!("0x{}", hex::encode(output)),
+            gas_used: format!("{:#x}", result.gas_used),
+            error: (!matches!(result.exit_reason, return_ok!()))
+                .then(|| format!("{:?}", result.exit_reason)),
+        };
+        self.write_line(&summary);
+    }
+
+    /// Flush and return the writer, or the first write error.
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_line<T: serde::Serialize>(&mut self, line: &T) {
+        if self.error.is_some() {
+            return;
+        }
+        let res = serde_json::to_writer(&mut self.writer, line)
+            .map_err(io::Error::from)
+            .and_then(|_| self.writer.write_all(b"\n"));
+        if let Err(error) = res {
+            self.error = Some(error);
+        }
+    }
+}
+
+impl<W: Write, DB: Database> Inspector<DB> for JsonStepTracer<W> {
+    fn initialize_interp(
+        &mut self,
+        interp: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        is_static: bool,
+    ) -> Return {
+        self.gas_inspector
+            .initialize_interp(interp, data, is_static);
+        Return::Continue
+    }
+
+    fn step(
+        &mut self,
+        interp: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        is_static: bool,
+    ) -> Return {
+        let op = interp.current_opcode();
+        let info = OpCode::info(op, data.env.cfg.spec_id);
+        let line = StepLine {
+            pc: interp.program_counter(),
+            op,
+            gas: format!("{:#x}", self.gas_inspector.gas_remaining()),
+            gas_cost: format!("{:#x}", info.map_or(0, |info| info.base_gas)),
+            mem_size: interp.memory.len(),
+            stack: interp
+                .stack
+                .data()
+                .iter()
+                .map(|value: &U256| format!("{:#x}", value))
+                .collect(),
+            depth: data.journaled_state.depth(),
+            refund: interp.gas.refunded(),
+            op_name: info.map_or("INVALID", |info| info.name),
+        };
+        self.write_line(&line);
+
+        self.gas_inspector.step(interp, data, is_static);
+        Return::Continue
+    }
+
+    fn step_end(
+        &mut self,
+        interp: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        is_static: bool,
+        eval: Return,
+    ) -> Return {
+        self.gas_inspector.step_end(interp, data, is_static, eval);
+        Return::Continue
+    }
+
+    fn call_end(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        inputs: &CallInputs,
+        remaining_gas: Gas,
+        ret: Return,
+        out: Bytes,
+        is_static: bool,
+    ) -> (Return, Gas, Bytes) {
+        self.gas_inspector
+            .call_end(data, inputs, remaining_gas, ret, out.clone(), is_static);
+        (ret, remaining_gas, out)
+    }
+
+    fn create_end(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        inputs: &CreateInputs,
+        ret: Return,
+        address: Option<H160>,
+        remaining_gas: Gas,
+        out: Bytes,
+    ) -> (Return, Option<H160>, Gas, Bytes) {
+        self.gas_inspector
+            .create_end(data, inputs, ret, address, remaining_gas, out.clone());
+        (ret, address, remaining_gas, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::JsonStepTracer;
+    use bytes::Bytes;
+    use primitive_types::H160;
+    use revm::{db::BenchmarkDB, opcode, Bytecode, TransactTo};
+
+    #[test]
+    fn test_json_trace() {
+        // returns the word 0x2a stored at memory 0.
+        let code = vec![
+            opcode::PUSH1,
+            0x2a,
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x20,
+            opcode::PUSH1,
+            0x00,
+            opcode::RETURN,
+        ];
+        let mut evm = revm::new();
+        evm.database(BenchmarkDB::new_bytecode(Bytecode::new_raw(Bytes::from(
+            code,
+        ))));
+        evm.env.tx.caller = H160::repeat_byte(0x10);
+        evm.env.tx.transact_to = TransactTo::Call(H160::zero());
+        evm.env.tx.gas_limit = 100_000;
+
+        let mut tracer = JsonStepTracer::new(Vec::new());
+        let (result, _) = evm.inspect(&mut tracer);
+        tracer.write_summary(&result);
+        let trace = String::from_utf8(tracer.finish().unwrap()).unwrap();
+
+        let expected = [
+            r#"{"pc":0,"op":96,"gas":"0x13498","gasCost":"0x3","memSize":0,"stack":[],"depth":1,"refund":0,"opName":"PUSH1"}"#,
+            r#"{"pc":2,"op":96,"gas":"0x13495","gasCost":"0x3","memSize":0,"stack":["0x2a"],"depth":1,"refund":0,"opName":"PUSH1"}"#,
+            r#"{"pc":4,"op":82,"gas":"0x13492","gasCost":"0x3","memSize":0,"stack":["0x2a","0x0"],"depth":1,"refund":0,"opName":"MSTORE"}"#,
+            r#"{"pc":5,"op":96,"gas":"0x1348c","gasCost":"0x3","memSize":32,"stack":[],"depth":1,"refund":0,"opName":"PUSH1"}"#,
+            r#"{"pc":7,"op":96,"gas":"0x13489","gasCost":"0x3","memSize":32,"stack":["0x20"],"depth":1,"refund":0,"opName":"PUSH1"}"#,
+            r#"{"pc":9,"op":243,"gas":"0x13486","gasCost":"0x0","memSize":32,"stack":["0x20","0x0"],"depth":1,"refund":0,"opName":"RETURN"}"#,
+            r#"{"output":"0x000000000000000000000000000000000000000000000000000000000000002a","gasUsed":"0x521a"}"#,
+        ];
+        let lines: Vec<&str> = trace.lines().collect();
+        assert_eq!(lines, expected);
+        for line in lines {
+            serde_json::from_str::<serde_json::Value>(line).unwrap();
+        }
+    }
+}
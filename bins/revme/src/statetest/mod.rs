@@ -1,8 +1,10 @@
 mod cmd;
+mod json_trace;
 pub mod merkle_trie;
 pub mod models;
 mod runner;
 mod trace;
 
 pub use cmd::Cmd;
+pub use json_trace::JsonStepTracer;
 pub use runner::TestError as Error;
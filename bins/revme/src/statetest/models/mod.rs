@@ -1,7 +1,7 @@
 use bytes::Bytes;
 use primitive_types::{H160, H256, U256};
 use std::collections::{BTreeMap, HashMap};
-mod deserializer;
+pub(crate) mod deserializer;
 mod spec;
 
 use deserializer::*;
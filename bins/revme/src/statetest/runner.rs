@@ -19,6 +19,7 @@ use super::{
     merkle_trie::{log_rlp_hash, state_merkle_trie_root},
     models::{SpecName, TestSuit},
     trace::CustomPrintTracer,
+    JsonStepTracer,
 };
 use thiserror::Error;
 
@@ -48,7 +49,11 @@ pub fn find_all_json_tests(path: &Path) -> Vec<PathBuf> {
         .collect::<Vec<PathBuf>>()
 }
 
-pub fn execute_test_suit(path: &Path, elapsed: &Arc<Mutex<Duration>>) -> Result<(), TestError> {
+pub fn execute_test_suit(
+    path: &Path,
+    elapsed: &Arc<Mutex<Duration>>,
+    json: bool,
+) -> Result<(), TestError> {
     // funky test with `bigint 0x00` value in json :) not possible to happen on mainnet and require custom json parser.
     // https://github.com/ethereum/tests/issues/971
     if path.file_name() == Some(OsStr::new("ValueOverflow.json")) {
@@ -258,7 +263,14 @@ pub fn execute_test_suit(path: &Path, elapsed: &Arc<Mutex<Duration>>) -> Result<
                     );
                     let mut database_cloned = database.clone();
                     evm.database(&mut database_cloned);
-                    evm.inspect_commit(CustomPrintTracer::new());
+                    if json {
+                        let mut tracer = JsonStepTracer::new(std::io::stdout());
+                        let result = evm.inspect_commit(&mut tracer);
+                        tracer.write_summary(&result);
+                        tracer.finish().map_err(|_| TestError::SystemError)?;
+                    } else {
+                        evm.inspect_commit(CustomPrintTracer::new());
+                    }
                     let db = evm.db().unwrap();
                     println!("{:?} UNIT_TEST:{}\n", path, name);
                     println!(
@@ -280,7 +292,7 @@ pub fn execute_test_suit(path: &Path, elapsed: &Arc<Mutex<Duration>>) -> Result<
     Ok(())
 }
 
-pub fn run(test_files: Vec<PathBuf>) -> Result<(), TestError> {
+pub fn run(test_files: Vec<PathBuf>, json: bool) -> Result<(), TestError> {
     let endjob = Arc::new(AtomicBool::new(false));
     let console_bar = Arc::new(ProgressBar::new(test_files.len() as u64));
     let mut joins: Vec<std::thread::JoinHandle<Result<(), TestError>>> = Vec::new();
@@ -309,7 +321,7 @@ pub fn run(test_files: Vec<PathBuf>) -> Result<(), TestError> {
                         return Ok(());
                     }
                     //println!("Test:{:?}\n",test_path);
-                    if let Err(err) = execute_test_suit(&test_path, &elapsed) {
+                    if let Err(err) = execute_test_suit(&test_path, &elapsed, json) {
                         endjob.store(true, Ordering::SeqCst);
                         println!("Test[{}] named:\n{:?} failed: {}\n", index, test_path, err);
                         return Err(err);
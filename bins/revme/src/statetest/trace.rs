@@ -1,5 +1,5 @@
 use bytes::Bytes;
-use primitive_types::H160;
+use primitive_types::{H160, H256, U256};
 pub use revm::Inspector;
 use revm::{
     opcode::{self},
@@ -135,8 +135,26 @@ impl<DB: Database> Inspector<DB> for CustomPrintTracer {
         (Return::Continue, None, Gas::new(0), Bytes::new())
     }
 
-    fn selfdestruct(&mut self) {
-        //, address: H160, target: H160) {
-        println!("SELFDESTRUCT on "); //{:?} target: {:?}", address, target);
+    fn log(
+        &mut self,
+        _evm_data: &mut EVMData<'_, DB>,
+        address: &H160,
+        topics: &[H256],
+        data: &Bytes,
+    ) {
+        println!(
+            "LOG{} from {:?} topics: {:?} data size: {}",
+            topics.len(),
+            address,
+            topics,
+            data.len()
+        );
+    }
+
+    fn selfdestruct(&mut self, contract: H160, target: H160, value: U256) {
+        println!(
+            "SELFDESTRUCT on {:?} target: {:?} value: {:?}",
+            contract, target, value
+        );
     }
 }
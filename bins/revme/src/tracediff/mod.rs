@@ -0,0 +1,215 @@
+use crate::disasm::read_code;
+use bytes::Bytes;
+use primitive_types::{H160, U256};
+use revm::{
+    db::BenchmarkDB, opcode, Bytecode, CallInputs, CreateInputs, Database, EVMData, Gas,
+    GasInspector, Inspector, Interpreter, Return, SpecId, TransactTo,
+};
+use std::path::PathBuf;
+use structopt::StructOpt;
+use thiserror::Error as ThisError;
+
+/// Run the same code under two specs and report where their step traces diverge.
+#[derive(StructOpt, Debug)]
+pub struct Cmd {
+    /// File with hex encoded bytecode, it is called at the zero address.
+    #[structopt(long)]
+    code: PathBuf,
+    /// Spec of the first run, named as in state tests (e.g. Berlin).
+    #[structopt(long, parse(from_str))]
+    spec_a: SpecId,
+    /// Spec of the second run.
+    #[structopt(long, parse(from_str))]
+    spec_b: SpecId,
+    #[structopt(long, default_value = "1000000")]
+    gas_limit: u64,
+    /// Number of equal steps printed before the divergence.
+    #[structopt(long, default_value = "3")]
+    context: usize,
+}
+
+#[derive(Debug, ThisError)]
+pub enum Error {
+    #[error("Can't read code file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Code is not valid hex: {0}")]
+    Hex(#[from] hex::FromHexError),
+}
+
+impl Cmd {
+    pub fn run(&self) -> Result<(), Error> {
+        let code = Bytes::from(read_code::<Error>(&self.code)?);
+        let a = trace(code.clone(), self.spec_a, self.gas_limit);
+        let b = trace(code, self.spec_b, self.gas_limit);
+        match first_divergence(&a, &b) {
+            None => println!("Traces are equal, {} steps", a.len()),
+            Some(index) => {
+                for step in &a[index.saturating_sub(self.context)..index] {
+                    println!("   {}", step);
+                }
+                println!("Divergence at step {}:", index);
+                for (spec, trace) in [(self.spec_a, &a), (self.spec_b, &b)] {
+                    match trace.get(index) {
+                        Some(step) => println!("{:?}: {}", spec, step),
+                        None => println!("{:?}: trace ended", spec),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One executed opcode with the EIP-3155 fields needed to compare runs.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Step {
+    pub depth: u64,
+    pub pc: usize,
+    pub opcode: u8,
+    pub gas: u64,
+    pub stack: Vec<U256>,
+}
+
+impl std::fmt::Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "depth:{} pc:{} op:{} gas:{} stack:{:?}",
+            self.depth,
+            self.pc,
+            opcode::OPCODE_JUMPMAP[self.opcode as usize].unwrap_or("unknown"),
+            self.gas,
+            self.stack,
+        )
+    }
+}
+
+/// Inspector that records every step.
+#[derive(Clone, Default)]
+pub struct StepRecorder {
+    gas_inspector: GasInspector,
+    pub steps: Vec<Step>,
+}
+
+impl<DB: Database> Inspector<DB> for StepRecorder {
+    fn initialize_interp(
+        &mut self,
+        interp: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        is_static: bool,
+    ) -> Return {
+        self.gas_inspector
+            .initialize_interp(interp, data, is_static);
+        Return::Continue
+    }
+
+    fn step(
+        &mut self,
+        interp: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        is_static: bool,
+    ) -> Return {
+        self.steps.push(Step {
+            depth: data.journaled_state.depth(),
+            pc: interp.program_counter(),
+            opcode: interp.current_opcode(),
+            gas: self.gas_inspector.gas_remaining(),
+            stack: interp.stack.data().clone(),
+        });
+        self.gas_inspector.step(interp, data, is_static);
+        Return::Continue
+    }
+
+    fn step_end(
+        &mut self,
+        interp: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        is_static: bool,
+        eval: Return,
+    ) -> Return {
+        self.gas_inspector.step_end(interp, data, is_static, eval);
+        Return::Continue
+    }
+
+    fn call_end(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        inputs: &CallInputs,
+        remaining_gas: Gas,
+        ret: Return,
+        out: Bytes,
+        is_static: bool,
+    ) -> (Return, Gas, Bytes) {
+        self.gas_inspector
+            .call_end(data, inputs, remaining_gas, ret, out.clone(), is_static);
+        (ret, remaining_gas, out)
+    }
+
+    fn create_end(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        inputs: &CreateInputs,
+        ret: Return,
+        address: Option<H160>,
+        remaining_gas: Gas,
+        out: Bytes,
+    ) -> (Return, Option<H160>, Gas, Bytes) {
+        self.gas_inspector
+            .create_end(data, inputs, ret, address, remaining_gas, out.clone());
+        (ret, address, remaining_gas, out)
+    }
+}
+
+/// Call `code` at the zero address under `spec` and return its steps.
+pub fn trace(code: Bytes, spec: SpecId, gas_limit: u64) -> Vec<Step> {
+    let mut evm = revm::new();
+    evm.database(BenchmarkDB::new_bytecode(Bytecode::new_raw(code)));
+    evm.env.cfg.spec_id = spec;
+    evm.env.tx.caller = H160::repeat_byte(0x10);
+    evm.env.tx.transact_to = TransactTo::Call(H160::zero());
+    evm.env.tx.gas_limit = gas_limit;
+
+    let mut recorder = StepRecorder::default();
+    evm.inspect(&mut recorder);
+    recorder.steps
+}
+
+/// Index of the first step that differs. A trace that ends earlier diverges where it ends.
+pub fn first_divergence(a: &[Step], b: &[Step]) -> Option<usize> {
+    a.iter()
+        .zip(b)
+        .position(|(a, b)| a != b)
+        .or_else(|| (a.len() != b.len()).then(|| a.len().min(b.len())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{first_divergence, trace};
+    use bytes::Bytes;
+    use revm::{opcode, SpecId};
+
+    #[test]
+    fn shl_diverges_before_constantinople() {
+        let code = Bytes::from(vec![
+            opcode::PUSH1,
+            0x01,
+            opcode::PUSH1,
+            0x01,
+            opcode::SHL,
+            opcode::STOP,
+        ]);
+        let byzantium = trace(code.clone(), SpecId::BYZANTIUM, 100_000);
+        let petersburg = trace(code.clone(), SpecId::PETERSBURG, 100_000);
+        assert_eq!(first_divergence(&petersburg, &petersburg), None);
+
+        // SHL is the last step on Byzantium as it is not activated there.
+        assert_eq!(first_divergence(&byzantium, &petersburg), Some(3));
+        assert_eq!(byzantium[2].opcode, opcode::SHL);
+        assert_eq!(petersburg[3].opcode, opcode::STOP);
+        assert_eq!(petersburg[3].stack, vec![2.into()]);
+
+        // with equal gas rules traces match.
+        let london = trace(code, SpecId::LONDON, 100_000);
+        assert_eq!(first_divergence(&petersburg, &london), None);
+    }
+}
@@ -0,0 +1,109 @@
+//! Print the `tracing` spans and events that revm emits for call frames.
+//!
+//! Run with `cargo run -p revm --example tracing_spans --features tracing`.
+
+use bytes::Bytes;
+use primitive_types::H160;
+use revm::{db::BenchmarkDB, opcode, Bytecode, TransactTo};
+use std::{
+    fmt::Write,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    Event, Metadata, Subscriber,
+};
+
+/// Collects the fields of a span or event as `name=value` pairs.
+#[derive(Default)]
+struct Fields(String);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        let _ = write!(self.0, " {}={:?}", field.name(), value);
+    }
+}
+
+/// Subscriber that prints every span and event to stdout.
+#[derive(Default)]
+struct PrintSubscriber {
+    next_id: AtomicU64,
+}
+
+impl Subscriber for PrintSubscriber {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut fields = Fields::default();
+        span.record(&mut fields);
+        println!("span {} #{}:{}", span.metadata().name(), id, fields.0);
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut fields = Fields::default();
+        values.record(&mut fields);
+        println!("record #{}:{}", span.into_u64(), fields.0);
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        println!("event{}", fields.0);
+    }
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, span: &Id) {
+        println!("exit #{}", span.into_u64());
+    }
+}
+
+fn main() {
+    // Calls itself once, the inner call reverts: `CALLDATASIZE ? REVERT : CALL(self, input)`.
+    let code = vec![
+        opcode::CALLDATASIZE,
+        opcode::PUSH1,
+        0x13,
+        opcode::JUMPI,
+        opcode::PUSH1,
+        0x00, // out len
+        opcode::PUSH1,
+        0x00, // out offset
+        opcode::PUSH1,
+        0x01, // in len
+        opcode::PUSH1,
+        0x00, // in offset
+        opcode::PUSH1,
+        0x00, // value
+        opcode::ADDRESS,
+        opcode::GAS,
+        opcode::CALL,
+        opcode::POP,
+        opcode::STOP,
+        opcode::JUMPDEST,
+        opcode::PUSH1,
+        0x00,
+        opcode::PUSH1,
+        0x00,
+        opcode::REVERT,
+    ];
+
+    let mut evm = revm::new();
+    evm.database(BenchmarkDB::new_bytecode(Bytecode::new_raw(Bytes::from(
+        code,
+    ))));
+    evm.env.tx.caller = H160::repeat_byte(0x10);
+    evm.env.tx.transact_to = TransactTo::Call(H160::zero());
+    evm.env.tx.gas_limit = 100_000;
+
+    let (result, _) =
+        tracing::subscriber::with_default(PrintSubscriber::default(), || evm.transact());
+    println!("exit reason: {:?}", result.exit_reason);
+}
@@ -0,0 +1,328 @@
+use crate::{
+    alloc::vec::Vec, interpreter::bytecode::Bytecode, CallInputs, CreateInputs, Database, Env, Gas,
+    Host, Interpreter, Return, SelfDestructResult, Spec,
+};
+use bytes::Bytes;
+use primitive_types::{H160, H256, U256};
+
+/// Default [Host] implementation for custom hosts.
+///
+/// Every type implementing [BasicHost] is a [Host] where environment and state are read straight
+/// from [BasicHost::db]. There is no journaling, all accesses are reported as warm and state
+/// changing operations are routed to the `override_*` methods. By default `sstore`, `tstore`,
+/// `log` and `selfdestruct` are dropped while sub calls and creates revert, so only the parts that matter
+/// need to be implemented.
+pub trait BasicHost {
+    /// Yield sub calls and creates to the embedder instead of calling `override_call` and
+    /// `override_create`, see [Host::ITERATIVE_CALLS].
+    const ITERATIVE_CALLS: bool = false;
+
+    type DB: Database;
+
+    fn db(&mut self) -> &mut Self::DB;
+
+    fn env(&mut self) -> &mut Env;
+
+    /// Called on `sstore`, returns `(original, present, new, is_cold)`.
+    fn override_sstore(
+        &mut self,
+        address: H160,
+        index: U256,
+        value: U256,
+    ) -> Option<(U256, U256, U256, bool)> {
+        let present = self.db().storage(address, index).ok()?;
+        Some((present, present, value, false))
+    }
+
+    /// Called on `TLOAD`. There is no transient storage by default so it is always zero.
+    fn override_tload(&mut self, _address: H160, _index: U256) -> U256 {
+        U256::zero()
+    }
+
+    /// Called on `TSTORE`.
+    fn override_tstore(&mut self, _address: H160, _index: U256, _value: U256) {}
+
+    /// Called on `LOG*`.
+    fn override_log(&mut self, _address: H160, _topics: Vec<H256>, _data: Bytes) {}
+
+    /// Called on `SELFDESTRUCT`.
+    fn override_selfdestruct(
+        &mut self,
+        _address: H160,
+        _target: H160,
+    ) -> Option<SelfDestructResult> {
+        Some(SelfDestructResult::default())
+    }
+
+    /// Called on `CREATE` and `CREATE2`. Reverts and gives back all gas by default.
+    fn override_create(&mut self, inputs: &mut CreateInputs) -> (Return, Option<H160>, Gas, Bytes) {
+        (
+            Return::Revert,
+            None,
+            Gas::new(inputs.gas_limit),
+            Bytes::new(),
+        )
+    }
+
+    /// Called on the `CALL` family. Reverts and gives back all gas by default.
+    fn override_call(&mut self, inputs: &mut CallInputs) -> (Return, Gas, Bytes) {
+        (Return::Revert, Gas::new(inputs.gas_limit), Bytes::new())
+    }
+}
+
+impl<T: BasicHost> Host for T {
+    const INSPECT: bool = false;
+    const ITERATIVE_CALLS: bool = T::ITERATIVE_CALLS;
+
+    type DB = T::DB;
+
+    fn step(&mut self, _interp: &mut Interpreter, _is_static: bool) -> Return {
+        Return::Continue
+    }
+
+    fn step_end(&mut self, _interp: &mut Interpreter, _is_static: bool, _ret: Return) -> Return {
+        Return::Continue
+    }
+
+    fn env(&mut self) -> &mut Env {
+        BasicHost::env(self)
+    }
+
+    fn load_account(&mut self, address: H160) -> Option<(bool, bool)> {
+        let is_new = self.db().basic(address).ok()?.is_none();
+        Some((false, is_new))
+    }
+
+    fn block_hash(&mut self, number: U256) -> Option<H256> {
+        self.db().block_hash(number).ok()
+    }
+
+    fn balance(&mut self, address: H160) -> Option<(U256, bool)> {
+        let info = self.db().basic(address).ok()?;
+        Some((info.map(|info| info.balance).unwrap_or_default(), false))
+    }
+
+    fn code(&mut self, address: H160) -> Option<(Bytecode, bool)> {
+        let info = match self.db().basic(address).ok()? {
+            Some(info) => info,
+            None => return Some((Bytecode::new(), false)),
+        };
+        let code = match info.code {
+            Some(code) => code,
+            None => self.db().code_by_hash(info.code_hash).ok()?,
+        };
+        Some((code, false))
+    }
+
+    fn code_hash(&mut self, address: H160) -> Option<(H256, bool)> {
+        let hash = match self.db().basic(address).ok()? {
+            Some(info) if !info.is_empty() => info.code_hash,
+            _ => H256::zero(),
+        };
+        Some((hash, false))
+    }
+
+    fn sload(&mut self, address: H160, index: U256) -> Option<(U256, bool)> {
+        Some((self.db().storage(address, index).ok()?, false))
+    }
+
+    fn sstore(
+        &mut self,
+        address: H160,
+        index: U256,
+        value: U256,
+    ) -> Option<(U256, U256, U256, bool)> {
+        self.override_sstore(address, index, value)
+    }
+
+    fn tload(&mut self, address: H160, index: U256) -> U256 {
+        self.override_tload(address, index)
+    }
+
+    fn tstore(&mut self, address: H160, index: U256, value: U256) {
+        self.override_tstore(address, index, value)
+    }
+
+    fn log(&mut self, address: H160, topics: Vec<H256>, data: Bytes) {
+        self.override_log(address, topics, data)
+    }
+
+    fn selfdestruct(&mut self, address: H160, target: H160) -> Option<SelfDestructResult> {
+        self.override_selfdestruct(address, target)
+    }
+
+    fn create<SPEC: Spec>(
+        &mut self,
+        inputs: &mut CreateInputs,
+    ) -> (Return, Option<H160>, Gas, Bytes) {
+        self.override_create(inputs)
+    }
+
+    fn call<SPEC: Spec>(&mut self, inputs: &mut CallInputs) -> (Return, Gas, Bytes) {
+        self.override_call(inputs)
+    }
+
+    fn memory_grow(&mut self, _additional: usize) -> bool {
+        true
+    }
+
+    fn jump(&mut self, _from_pc: usize, _to_pc: usize, _taken: bool) {}
+
+    fn gas_block(&mut self, _pc: usize, _block_cost: u64, _remaining: u64) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BasicHost;
+    use crate::{
+        opcode, test_utils::interpreter, CallInputs, Env, Gas, InMemoryDB, LatestSpec, Return,
+        SubCall, SubCallResult,
+    };
+    use bytes::Bytes;
+    use primitive_types::H160;
+
+    /// Host that answers every call with a fixed word.
+    struct MockCallHost {
+        db: InMemoryDB,
+        env: Env,
+        calls: Vec<H160>,
+    }
+
+    impl BasicHost for MockCallHost {
+        type DB = InMemoryDB;
+
+        fn db(&mut self) -> &mut InMemoryDB {
+            &mut self.db
+        }
+
+        fn env(&mut self) -> &mut Env {
+            &mut self.env
+        }
+
+        fn override_call(&mut self, inputs: &mut CallInputs) -> (Return, Gas, Bytes) {
+            self.calls.push(inputs.contract);
+            (
+                Return::Return,
+                Gas::new(inputs.gas_limit),
+                Bytes::from(vec![0x11; 32]),
+            )
+        }
+    }
+
+    #[test]
+    fn test_override_call() {
+        let code = vec![
+            opcode::PUSH1,
+            0x20, // out len
+            opcode::PUSH1,
+            0x00, // out offset
+            opcode::PUSH1,
+            0x00, // in len
+            opcode::PUSH1,
+            0x00, // in offset
+            opcode::PUSH1,
+            0x00, // value
+            opcode::PUSH1,
+            0x42, // address
+            opcode::PUSH2,
+            0xff,
+            0xff, // gas
+            opcode::CALL,
+            opcode::POP,
+            opcode::PUSH1,
+            0x20,
+            opcode::PUSH1,
+            0x00,
+            opcode::RETURN,
+        ];
+        let mut interp = interpreter(code, 1_000_000);
+
+        let mut host = MockCallHost {
+            db: InMemoryDB::default(),
+            env: Env::default(),
+            calls: Vec::new(),
+        };
+        assert_eq!(interp.run::<_, LatestSpec>(&mut host), Return::Return);
+        assert_eq!(host.calls, vec![H160::from_low_u64_be(0x42)]);
+        assert_eq!(interp.return_value(), Bytes::from(vec![0x11; 32]));
+    }
+
+    /// Host that hands calls back to the embedder.
+    struct YieldingHost {
+        db: InMemoryDB,
+        env: Env,
+    }
+
+    impl BasicHost for YieldingHost {
+        const ITERATIVE_CALLS: bool = true;
+        type DB = InMemoryDB;
+
+        fn db(&mut self) -> &mut InMemoryDB {
+            &mut self.db
+        }
+
+        fn env(&mut self) -> &mut Env {
+            &mut self.env
+        }
+    }
+
+    #[test]
+    fn test_yield_call() {
+        let code = vec![
+            opcode::PUSH1,
+            0x20, // out len
+            opcode::PUSH1,
+            0x00, // out offset
+            opcode::PUSH1,
+            0x00, // in len
+            opcode::PUSH1,
+            0x00, // in offset
+            opcode::PUSH1,
+            0x00, // value
+            opcode::PUSH1,
+            0x42, // address
+            opcode::PUSH2,
+            0xff,
+            0xff, // gas
+            opcode::CALL,
+            // success flag after the output.
+            opcode::PUSH1,
+            0x20,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x40,
+            opcode::PUSH1,
+            0x00,
+            opcode::RETURN,
+        ];
+        let mut interp = interpreter(code, 1_000_000);
+
+        let mut host = YieldingHost {
+            db: InMemoryDB::default(),
+            env: Env::default(),
+        };
+        assert_eq!(interp.run::<_, LatestSpec>(&mut host), Return::SubCall);
+        let inputs = match interp.take_sub_call() {
+            Some(SubCall::Call { inputs, is_static }) => {
+                assert!(!is_static);
+                inputs
+            }
+            sub_call => panic!("expected a call, got {:?}", sub_call),
+        };
+        assert_eq!(inputs.contract, H160::from_low_u64_be(0x42));
+        assert_eq!(inputs.gas_limit, 0xffff);
+
+        // the call returns synthetic data. Without the yield the default `override_call` reverts.
+        interp.resume(SubCallResult::Call {
+            ret: Return::Return,
+            gas: Gas::new(inputs.gas_limit),
+            out: Bytes::from(vec![0x11; 32]),
+        });
+        assert_eq!(interp.run::<_, LatestSpec>(&mut host), Return::Return);
+        assert!(interp.take_sub_call().is_none());
+        let mut expected = vec![0x11; 32];
+        expected.extend_from_slice(&[0; 31]);
+        expected.push(1);
+        assert_eq!(interp.return_value(), Bytes::from(expected));
+    }
+}
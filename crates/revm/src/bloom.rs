@@ -0,0 +1,123 @@
+use crate::Log;
+use sha3::{Digest, Keccak256};
+
+/// 2048 bit logs bloom filter of receipts and block headers.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Bloom(pub [u8; 256]);
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Self([0; 256])
+    }
+}
+
+impl Bloom {
+    /// Receipt bloom of the addresses and topics of `logs`.
+    pub fn from_logs(logs: &[Log]) -> Self {
+        let mut bloom = Self::default();
+        for log in logs {
+            bloom.accrue_log(log);
+        }
+        bloom
+    }
+
+    /// Add the address and topics of `log`.
+    pub fn accrue_log(&mut self, log: &Log) {
+        self.accrue(log.address.as_bytes());
+        for topic in &log.topics {
+            self.accrue(topic.as_bytes());
+        }
+    }
+
+    /// Add `input`, setting the three bits selected by its keccak hash.
+    pub fn accrue(&mut self, input: &[u8]) {
+        for (byte, mask) in Self::bits(input) {
+            self.0[byte] |= mask;
+        }
+    }
+
+    /// Union with `other`, used to build the block bloom from the receipt blooms.
+    pub fn accumulate(&mut self, other: &Bloom) {
+        for (byte, other) in self.0.iter_mut().zip(other.0.iter()) {
+            *byte |= other;
+        }
+    }
+
+    /// Whether `input` may have been added. False positives are possible.
+    pub fn contains_input(&self, input: &[u8]) -> bool {
+        Self::bits(input).all(|(byte, mask)| self.0[byte] & mask == mask)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == [0; 256]
+    }
+
+    /// Byte index and mask of the bits selected by `input`.
+    fn bits(input: &[u8]) -> impl Iterator<Item = (usize, u8)> {
+        let hash = Keccak256::digest(input);
+        (0..3).map(move |i| {
+            let bit = (((hash[2 * i] as usize) << 8) | hash[2 * i + 1] as usize) & 2047;
+            (255 - bit / 8, 1 << (bit % 8))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Bloom;
+    use crate::Log;
+    use bytes::Bytes;
+    use primitive_types::{H160, H256};
+
+    #[test]
+    fn test_log_bloom() {
+        let address =
+            H160::from_slice(&hex::decode("ef2d6d194084c2de36e0dabfce45d046b37d1106").unwrap());
+        let topic = H256::from_slice(
+            &hex::decode("02c69be41d0b7e40352fc85be1cd65eb03d40ef8427a0ca4596b1ead9a00e9fc")
+                .unwrap(),
+        );
+        let bloom = Bloom::from_logs(&[Log {
+            address,
+            topics: vec![topic],
+            data: Bytes::new(),
+        }]);
+
+        let mut expected = [0u8; 256];
+        // set bytes of the bloom in the ethbloom test vector.
+        for (byte, value) in [
+            (20, 0x10),
+            (99, 0x02),
+            (100, 0x02),
+            (123, 0x08),
+            (128, 0x10),
+            (155, 0x10),
+        ] {
+            expected[byte] = value;
+        }
+        assert_eq!(bloom, Bloom(expected));
+        assert!(bloom.contains_input(address.as_bytes()));
+        assert!(bloom.contains_input(topic.as_bytes()));
+    }
+
+    #[test]
+    fn test_accumulate() {
+        let log = |address| Log {
+            address: H160::repeat_byte(address),
+            topics: Vec::new(),
+            data: Bytes::new(),
+        };
+        let first = Bloom::from_logs(&[log(0x01)]);
+        let second = Bloom::from_logs(&[log(0x02)]);
+
+        let mut block = Bloom::default();
+        block.accumulate(&first);
+        block.accumulate(&second);
+        assert_eq!(block, Bloom::from_logs(&[log(0x01), log(0x02)]));
+        for (i, byte) in block.0.iter().enumerate() {
+            assert_eq!(*byte, first.0[i] | second.0[i]);
+        }
+        assert!(block.contains_input(H160::repeat_byte(0x01).as_bytes()));
+        assert!(block.contains_input(H160::repeat_byte(0x02).as_bytes()));
+    }
+}
@@ -3,7 +3,7 @@ mod in_memory_db;
 #[cfg(feature = "web3db")]
 pub mod web3db;
 #[cfg(feature = "web3db")]
-pub use web3db::Web3DB;
+pub use web3db::{CachedWeb3DB, Web3DB};
 
 pub use in_memory_db::{AccountState, BenchmarkDB, CacheDB, DbAccount, EmptyDB, InMemoryDB};
 
@@ -28,9 +28,67 @@ pub trait Database {
     fn block_hash(&mut self, number: U256) -> Result<H256, Self::Error>;
 }
 
+/// Changes of one account at the end of a transaction.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AccountChanges {
+    /// New balance, nonce and code. `None` if the account was destroyed together with its storage.
+    pub info: Option<AccountInfo>,
+    /// If account is newly created, its storage has to be cleared before `storage` is written.
+    pub storage_cleared: bool,
+    /// Slots whose present value differs from the original one.
+    pub storage: Map<U256, U256>,
+}
+
+impl From<Account> for AccountChanges {
+    fn from(account: Account) -> Self {
+        if account.is_destroyed {
+            return Self::default();
+        }
+        Self {
+            info: Some(account.info),
+            storage_cleared: account.storage_cleared,
+            storage: account
+                .storage
+                .into_iter()
+                .filter(|(_, slot)| slot.is_changed())
+                .map(|(index, slot)| (index, slot.present_value()))
+                .collect(),
+        }
+    }
+}
+
 #[auto_impl(& mut, Box)]
 pub trait DatabaseCommit {
-    fn commit(&mut self, changes: Map<H160, Account>);
+    /// Commit state changes of a transaction. Changes are batched per account and every account is
+    /// handed to [DatabaseCommit::commit_account] once.
+    fn commit(&mut self, changes: Map<H160, Account>) {
+        for (address, account) in changes {
+            self.commit_account(address, account.into());
+        }
+    }
+
+    /// Apply balance, nonce, code and storage of one account together. Backends that can write
+    /// them in a single batch should override this, by default every field is written separately.
+    fn commit_account(&mut self, address: H160, changes: AccountChanges) {
+        let info = match changes.info {
+            Some(info) => info,
+            None => return self.commit_info(address, None),
+        };
+        self.commit_info(address, Some(info));
+        if changes.storage_cleared {
+            self.commit_storage_cleared(address);
+        }
+        for (index, value) in changes.storage {
+            self.commit_storage(address, index, value);
+        }
+    }
+
+    /// Set account info, `None` removes the account together with its storage.
+    fn commit_info(&mut self, address: H160, info: Option<AccountInfo>);
+    /// Remove all storage of the account.
+    fn commit_storage_cleared(&mut self, address: H160);
+    /// Set one storage slot.
+    fn commit_storage(&mut self, address: H160, index: U256, value: U256);
 }
 
 #[auto_impl(&, Box)]
@@ -79,3 +137,73 @@ impl<'a, Error> Database for RefDBWrapper<'a, Error> {
         self.db.block_hash(number)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{AccountChanges, DatabaseCommit};
+    use crate::{opcode, test_utils::evm_with_code, AccountInfo, Return};
+
+    use primitive_types::{H160, U256};
+
+    /// Records batched commits and counts writes that went through the per field fallback.
+    #[derive(Default)]
+    struct BatchRecorder {
+        batches: Vec<(H160, AccountChanges)>,
+        field_writes: usize,
+    }
+
+    impl DatabaseCommit for BatchRecorder {
+        fn commit_account(&mut self, address: H160, changes: AccountChanges) {
+            self.batches.push((address, changes));
+        }
+
+        fn commit_info(&mut self, _address: H160, _info: Option<AccountInfo>) {
+            self.field_writes += 1;
+        }
+
+        fn commit_storage_cleared(&mut self, _address: H160) {
+            self.field_writes += 1;
+        }
+
+        fn commit_storage(&mut self, _address: H160, _index: U256, _value: U256) {
+            self.field_writes += 1;
+        }
+    }
+
+    #[test]
+    fn test_commit_batched_per_account() {
+        let mut code = Vec::new();
+        for slot in 1..=3 {
+            code.extend([
+                opcode::PUSH1,
+                0x10 + slot,
+                opcode::PUSH1,
+                slot,
+                opcode::SSTORE,
+            ]);
+        }
+        code.push(opcode::STOP);
+
+        let mut evm = evm_with_code(code);
+
+        let (result, state) = evm.transact();
+        assert_eq!(result.exit_reason, Return::Stop);
+
+        let mut db = BatchRecorder::default();
+        db.commit(state);
+        assert_eq!(db.field_writes, 0);
+
+        let contract: Vec<_> = db
+            .batches
+            .iter()
+            .filter(|(address, _)| *address == H160::zero())
+            .collect();
+        assert_eq!(contract.len(), 1);
+        let changes = &contract[0].1;
+        assert!(changes.info.is_some());
+        assert_eq!(changes.storage.len(), 3);
+        for slot in 1..=3u64 {
+            assert_eq!(changes.storage[&U256::from(slot)], U256::from(0x10 + slot));
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use super::{DatabaseCommit, DatabaseRef};
 use crate::{interpreter::bytecode::Bytecode, Database, KECCAK_EMPTY};
-use crate::{Account, AccountInfo, Log};
+use crate::{AccountInfo, Log};
 use alloc::vec::Vec;
 use core::convert::Infallible;
 use hashbrown::{hash_map::Entry, HashMap as Map};
@@ -166,33 +166,36 @@ impl<ExtDB: DatabaseRef> CacheDB<ExtDB> {
 }
 
 impl<ExtDB: DatabaseRef> DatabaseCommit for CacheDB<ExtDB> {
-    fn commit(&mut self, changes: Map<H160, Account>) {
-        for (address, mut account) in changes {
-            if account.is_destroyed {
+    fn commit_info(&mut self, address: H160, info: Option<AccountInfo>) {
+        let mut info = match info {
+            Some(info) => info,
+            None => {
                 let db_account = self.accounts.entry(address).or_default();
                 db_account.storage.clear();
                 db_account.account_state = AccountState::NotExisting;
                 db_account.info = AccountInfo::default();
-                continue;
+                return;
             }
-            self.insert_contract(&mut account.info);
+        };
+        self.insert_contract(&mut info);
 
-            let db_account = self.accounts.entry(address).or_default();
-            db_account.info = account.info;
+        let db_account = self.accounts.entry(address).or_default();
+        db_account.info = info;
+        db_account.account_state = AccountState::Touched;
+    }
 
-            db_account.account_state = if account.storage_cleared {
-                db_account.storage.clear();
-                AccountState::StorageCleared
-            } else {
-                AccountState::Touched
-            };
-            db_account.storage.extend(
-                account
-                    .storage
-                    .into_iter()
-                    .map(|(key, value)| (key, value.present_value())),
-            );
-        }
+    fn commit_storage_cleared(&mut self, address: H160) {
+        let db_account = self.accounts.entry(address).or_default();
+        db_account.storage.clear();
+        db_account.account_state = AccountState::StorageCleared;
+    }
+
+    fn commit_storage(&mut self, address: H160, index: U256, value: U256) {
+        self.accounts
+            .entry(address)
+            .or_default()
+            .storage
+            .insert(index, value);
     }
 }
 
@@ -1,3 +1,4 @@
+use super::{CacheDB, DatabaseRef};
 use crate::{interpreter::bytecode::Bytecode, AccountInfo, Database, KECCAK_EMPTY};
 use bytes::Bytes;
 use primitive_types::{H160, H256, U256};
@@ -5,37 +6,54 @@ use tokio::runtime::{Handle, Runtime};
 use web3::{
     transports::Http,
     types::{BlockId, BlockNumber, H160 as wH160, U256 as wU256, U64 as wU64},
-    Web3,
+    Error, Transport, Web3,
 };
 
-pub struct Web3DB {
-    web3: Web3<Http>,
+/// [Web3DB] that caches every account, storage slot and block hash it loaded.
+pub type CachedWeb3DB<T = Http> = CacheDB<Web3DB<T>>;
+
+/// Read-through database over an Ethereum JSON-RPC endpoint, pinned to one block.
+///
+/// Code is loaded together with the account. RPC failures are returned as [Error] and end the
+/// transaction with [crate::Return::FatalExternalError]. Nothing is cached, wrap it in a
+/// [CacheDB] with [Web3DB::cached] to fork a chain.
+pub struct Web3DB<T: Transport = Http> {
+    web3: Web3<T>,
     runtime: Option<Runtime>,
-    block_number: Option<BlockNumber>,
+    block_number: BlockNumber,
 }
 
 impl Web3DB {
     /// create web3 db connector inputs are url and block on what we are basing our database (None for latest)
     pub fn new(url: &str, block_number: Option<u64>) -> Option<Self> {
+        let transport = Http::new(url).ok()?;
+        Self::with_transport(transport, block_number).ok()
+    }
+}
+
+impl<T: Transport> Web3DB<T> {
+    /// Database over `transport` at `block_number`, or at the latest block if None.
+    pub fn with_transport(transport: T, block_number: Option<u64>) -> Result<Self, Error> {
         let runtime = Handle::try_current()
             .is_err()
             .then(|| Runtime::new().unwrap());
-        let transport = web3::transports::Http::new(url).ok()?;
-        let web3 = Web3::new(transport);
 
         let mut out = Self {
-            web3,
+            web3: Web3::new(transport),
             runtime,
-            block_number: None,
+            block_number: BlockNumber::Latest,
         };
-        let bnum = if let Some(block_number) = block_number {
-            block_number.into()
-        } else {
-            out.block_on(out.web3.eth().block_number()).ok()?
+        let bnum = match block_number {
+            Some(block_number) => block_number.into(),
+            None => out.block_on(out.web3.eth().block_number())?,
         };
+        out.block_number = BlockNumber::Number(bnum);
+        Ok(out)
+    }
 
-        out.block_number = Some(BlockNumber::Number(bnum));
-        Some(out)
+    /// Cache everything loaded from the endpoint in memory.
+    pub fn cached(self) -> CachedWeb3DB<T> {
+        CacheDB::new(self)
     }
 
     /// internal utility function to call tokio feature and wait for output
@@ -47,82 +65,161 @@ impl Web3DB {
     }
 }
 
-impl Database for Web3DB {
-    type Error = ();
+impl<T: Transport> DatabaseRef for Web3DB<T> {
+    type Error = Error;
 
-    fn basic(&mut self, address: H160) -> Result<Option<AccountInfo>, Self::Error> {
+    fn basic(&self, address: H160) -> Result<Option<AccountInfo>, Self::Error> {
         let add = wH160(address.0);
+        let block_number = Some(self.block_number);
         let f = async {
-            let nonce = self.web3.eth().transaction_count(add, self.block_number);
-            let balance = self.web3.eth().balance(add, self.block_number);
-            let code = self.web3.eth().code(add, self.block_number);
+            let nonce = self.web3.eth().transaction_count(add, block_number);
+            let balance = self.web3.eth().balance(add, block_number);
+            let code = self.web3.eth().code(add, block_number);
             tokio::join!(nonce, balance, code)
         };
         let (nonce, balance, code) = self.block_on(f);
-        // panic on not getting data?
         Ok(Some(AccountInfo::new(
-            U256(
-                balance
-                    .unwrap_or_else(|e| panic!("web3 get balance error:{:?}", e))
-                    .0,
-            ),
-            nonce
-                .unwrap_or_else(|e| panic!("web3 get nonce error:{:?}", e))
-                .as_u64(),
-            Bytecode::new_raw(Bytes::from(
-                code.unwrap_or_else(|e| panic!("web3 get node error:{:?}", e))
-                    .0,
-            )),
+            U256(balance?.0),
+            nonce?.as_u64(),
+            Bytecode::new_raw(Bytes::from(code?.0)),
         )))
     }
 
-    fn code_by_hash(&mut self, _code_hash: primitive_types::H256) -> Result<Bytecode, Self::Error> {
-        panic!("Should not be called. Code is already loaded");
+    fn code_by_hash(&self, _code_hash: H256) -> Result<Bytecode, Self::Error> {
         // not needed because we already load code with basic info
+        Err(Error::Internal)
     }
 
-    fn storage(
-        &mut self,
-        address: primitive_types::H160,
-        index: primitive_types::U256,
-    ) -> Result<primitive_types::U256, Self::Error> {
+    fn storage(&self, address: H160, index: U256) -> Result<U256, Self::Error> {
         let add = wH160(address.0);
         let index = wU256(index.0);
-        let f = async {
-            let storage = self
-                .web3
-                .eth()
-                .storage(add, index, self.block_number)
-                .await
-                .unwrap();
-            U256::from_big_endian(storage.as_bytes())
-        };
-        Ok(self.block_on(f))
+        let f = self.web3.eth().storage(add, index, Some(self.block_number));
+        let storage = self.block_on(f)?;
+        Ok(U256::from_big_endian(storage.as_bytes()))
     }
 
-    fn block_hash(
-        &mut self,
-        number: primitive_types::U256,
-    ) -> Result<primitive_types::H256, Self::Error> {
+    fn block_hash(&self, number: U256) -> Result<H256, Self::Error> {
         if number > U256::from(u64::MAX) {
             return Ok(KECCAK_EMPTY);
         }
         let number = number.as_u64();
-        if let Some(block_num) = self.block_number {
-            match block_num {
-                BlockNumber::Number(t) if t.as_u64() > number => return Ok(KECCAK_EMPTY),
-                _ => (),
-            }
+        // blocks after the pinned one are unknown.
+        match self.block_number {
+            BlockNumber::Number(t) if t.as_u64() < number => return Ok(KECCAK_EMPTY),
+            _ => (),
         }
         let number = wU64::from(number);
-        let f = async {
-            self.web3
-                .eth()
-                .block(BlockId::Number(BlockNumber::Number(number)))
-                .await
-                .ok()
-                .flatten()
-        };
-        Ok(H256(self.block_on(f).unwrap().hash.unwrap().0))
+        let f = self
+            .web3
+            .eth()
+            .block(BlockId::Number(BlockNumber::Number(number)));
+        let hash = self.block_on(f)?.and_then(|block| block.hash);
+        Ok(hash.map(|hash| H256(hash.0)).unwrap_or(KECCAK_EMPTY))
+    }
+}
+
+impl<T: Transport> Database for Web3DB<T> {
+    type Error = Error;
+
+    fn basic(&mut self, address: H160) -> Result<Option<AccountInfo>, Self::Error> {
+        DatabaseRef::basic(self, address)
+    }
+
+    fn code_by_hash(&mut self, code_hash: H256) -> Result<Bytecode, Self::Error> {
+        DatabaseRef::code_by_hash(self, code_hash)
+    }
+
+    fn storage(&mut self, address: H160, index: U256) -> Result<U256, Self::Error> {
+        DatabaseRef::storage(self, address, index)
+    }
+
+    fn block_hash(&mut self, number: U256) -> Result<H256, Self::Error> {
+        DatabaseRef::block_hash(self, number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Web3DB;
+    use crate::{test_utils::evm_with_db, Database, Return, TransactTo};
+    use jsonrpc_core::{Call, Params, Value};
+    use primitive_types::{H160, U256};
+    use std::{cell::RefCell, rc::Rc};
+    use web3::{helpers::build_request, Error, RequestId, Transport};
+
+    const CONTRACT: &str = "0x4242424242424242424242424242424242424242";
+
+    /// Transport answering from fixed values, storage requests fail.
+    #[derive(Clone, Debug, Default)]
+    struct MockTransport {
+        methods: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl Transport for MockTransport {
+        type Out = futures::future::Ready<web3::Result<Value>>;
+
+        fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, Call) {
+            (0, build_request(0, method, params))
+        }
+
+        fn send(&self, _id: RequestId, request: Call) -> Self::Out {
+            let (method, params) = match request {
+                Call::MethodCall(call) => (call.method, call.params),
+                _ => unreachable!("only method calls are sent"),
+            };
+            self.methods.borrow_mut().push(method.clone());
+            // only CONTRACT has code, it reads storage.
+            let code = match params {
+                Params::Array(params) if params.first() == Some(&Value::from(CONTRACT)) => {
+                    "0x60005400"
+                }
+                _ => "0x",
+            };
+            futures::future::ready(match method.as_str() {
+                "eth_blockNumber" => Ok(Value::from("0x10")),
+                "eth_getTransactionCount" => Ok(Value::from("0x5")),
+                "eth_getBalance" => Ok(Value::from("0x64")),
+                "eth_getCode" => Ok(Value::from(code)),
+                _ => Err(Error::Rpc(jsonrpc_core::Error::internal_error())),
+            })
+        }
+    }
+
+    #[test]
+    fn test_read_through_cache() {
+        let transport = MockTransport::default();
+        let mut db = Web3DB::with_transport(transport.clone(), None)
+            .unwrap()
+            .cached();
+
+        let address = H160::repeat_byte(0x42);
+        let info = db.basic(address).unwrap().unwrap();
+        assert_eq!((info.balance, info.nonce), (U256::from(100), 5));
+        assert_eq!(info.code.unwrap().len(), 4);
+        db.basic(address).unwrap();
+        assert_eq!(
+            *transport.methods.borrow(),
+            [
+                "eth_blockNumber",
+                "eth_getTransactionCount",
+                "eth_getBalance",
+                "eth_getCode"
+            ]
+        );
+        assert!(matches!(
+            db.storage(address, U256::zero()),
+            Err(Error::Rpc(_))
+        ));
+    }
+
+    #[test]
+    fn test_rpc_error_is_fatal() {
+        let db = Web3DB::with_transport(MockTransport::default(), Some(0x10)).unwrap();
+        let mut evm = evm_with_db(db.cached());
+        evm.env.tx.transact_to = TransactTo::Call(H160::repeat_byte(0x42));
+
+        // SLOAD reaches the failing storage request.
+        let (result, _) = evm.transact();
+        assert_eq!(result.exit_reason, Return::FatalExternalError);
     }
 }
@@ -1,11 +1,18 @@
 use crate::{
     db::{Database, DatabaseCommit, DatabaseRef, RefDBWrapper},
     evm_impl::{EVMImpl, Transact},
-    journaled_state::State,
-    specification, Env, ExecutionResult, Inspector, NoOpInspector,
+    inspector::oog::OogLocator,
+    journaled_state::{AccountOverride, State},
+    return_ok, specification, Env, ExecutionResult, GasBlockGranularity, Inspector, NoOpInspector,
+    Return,
 };
-use alloc::boxed::Box;
-use revm_precompiles::Precompiles;
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use hashbrown::HashMap as Map;
+use primitive_types::H160;
+use revm_precompiles::{CustomPrecompileFn, PrecompileResult, Precompiles};
+
+/// Percent added on top of the smallest succeeding gas limit by [EVM::estimate_gas].
+pub const ESTIMATE_GAS_BUFFER: u64 = 1;
 
 /// Struct that takes Database and enabled transact to update state directly to database.
 /// additionally it allows user to set all environment parameters.
@@ -28,6 +35,16 @@ use revm_precompiles::Precompiles;
 pub struct EVM<DB> {
     pub env: Env,
     pub db: Option<DB>,
+    options: EVMOptions,
+}
+
+/// Additions to the built-in behavior of a spec that an [EVM] passes to [evm_inner_with_options].
+#[derive(Clone, Default)]
+pub struct EVMOptions {
+    /// Precompiles added with [EVM::register_precompile].
+    pub custom_precompiles: Vec<(H160, CustomPrecompileFn)>,
+    /// Overrides added with [EVM::apply_overrides].
+    pub state_overrides: Map<H160, AccountOverride>,
 }
 
 pub fn new<DB>() -> EVM<DB> {
@@ -60,7 +77,9 @@ impl<DB: Database> EVM<DB> {
     pub fn transact(&mut self) -> (ExecutionResult, State) {
         if let Some(db) = self.db.as_mut() {
             let mut noop = NoOpInspector {};
-            let out = evm_inner::<DB, false>(&mut self.env, db, &mut noop).transact();
+            let out =
+                evm_inner_with_options::<DB, false>(&mut self.env, db, &mut noop, &self.options)
+                    .transact();
             out
         } else {
             panic!("Database needs to be set");
@@ -73,11 +92,114 @@ impl<DB: Database> EVM<DB> {
         mut inspector: INSP,
     ) -> (ExecutionResult, State) {
         if let Some(db) = self.db.as_mut() {
-            evm_inner::<DB, true>(&mut self.env, db, &mut inspector).transact()
+            evm_inner_with_options::<DB, true>(&mut self.env, db, &mut inspector, &self.options)
+                .transact()
         } else {
             panic!("Database needs to be set");
         }
     }
+
+    /// Run the transaction and drop its state changes, for simulations that only want the result.
+    ///
+    /// Sub calls and storage writes run in the journal as usual, but nothing is committed to the
+    /// database. It is only asked for the accounts and slots the transaction loads, which a
+    /// caching database like [crate::InMemoryDB] keeps.
+    pub fn transact_dry_run(&mut self) -> ExecutionResult {
+        self.transact().0
+    }
+
+    /// [EVM::transact_dry_run] with an inspector, read its output after the run.
+    pub fn inspect_dry_run<INSP: Inspector<DB>>(&mut self, inspector: INSP) -> ExecutionResult {
+        self.inspect(inspector).0
+    }
+
+    /// Find where the transaction runs out of gas and how much more gas it needs to get past it.
+    ///
+    /// Returns `(pc, opcode, extra gas)` for the first frame that runs out of gas, or None if no
+    /// frame does. The extra gas is found by rerunning the transaction with an increased gas
+    /// limit until it gets past that opcode. State is never committed, so every run starts from the
+    /// same database state.
+    pub fn oog_location(&mut self) -> Option<(usize, u8, u64)> {
+        let gas_limit = self.env.tx.gas_limit;
+        let granularity = self.env.cfg.gas_block_granularity;
+        self.env.cfg.gas_block_granularity = GasBlockGranularity::PerOpcode;
+
+        let location_with = |evm: &mut Self, gas_limit: u64| {
+            evm.env.tx.gas_limit = gas_limit;
+            let mut locator = OogLocator::default();
+            evm.inspect(&mut locator);
+            locator.location
+        };
+
+        let result = location_with(self, gas_limit).and_then(|location| {
+            // find an extra amount of gas that gets past the location, then bisect down to the
+            // smallest one.
+            let mut low = 0;
+            let mut high = 1u64;
+            while location_with(self, gas_limit.checked_add(high)?) == Some(location) {
+                low = high;
+                high = high.checked_mul(2)?;
+            }
+            while high - low > 1 {
+                let mid = low + (high - low) / 2;
+                if location_with(self, gas_limit + mid) == Some(location) {
+                    low = mid;
+                } else {
+                    high = mid;
+                }
+            }
+            Some((location.1, location.2, high))
+        });
+
+        self.env.tx.gas_limit = gas_limit;
+        self.env.cfg.gas_block_granularity = granularity;
+        result
+    }
+
+    /// Gas limit to use for the transaction, like `eth_estimateGas`.
+    ///
+    /// The smallest gas limit at which the transaction succeeds plus [ESTIMATE_GAS_BUFFER]
+    /// percent, capped at `env.tx.gas_limit`. The transaction is run at `env.tx.gas_limit` first
+    /// and its result is returned as error if it does not succeed there. State is never
+    /// committed, so every run starts from the same database state.
+    #[allow(clippy::result_large_err)]
+    pub fn estimate_gas(&mut self) -> Result<u64, ExecutionResult> {
+        let gas_limit = self.env.tx.gas_limit;
+        let min_gas_limit = self.min_gas_limit()?;
+        let buffer = min_gas_limit * ESTIMATE_GAS_BUFFER / 100;
+        Ok(min_gas_limit.saturating_add(buffer).min(gas_limit))
+    }
+
+    /// Smallest gas limit at which the transaction succeeds, see [EVM::estimate_gas].
+    #[allow(clippy::result_large_err)]
+    fn min_gas_limit(&mut self) -> Result<u64, ExecutionResult> {
+        let gas_limit = self.env.tx.gas_limit;
+        let run_with = |evm: &mut Self, gas_limit: u64| {
+            evm.env.tx.gas_limit = gas_limit;
+            evm.transact().0
+        };
+
+        let result = run_with(self, gas_limit);
+        if !matches!(result.exit_reason, return_ok!()) {
+            self.env.tx.gas_limit = gas_limit;
+            return Err(result);
+        }
+        // gas used is a lower bound, more is needed for gas that is refunded at the end or
+        // withheld from calls by the 63/64 rule.
+        let mut low = result.gas_used.saturating_sub(1);
+        let mut high = gas_limit;
+        while high - low > 1 {
+            let mid = low + (high - low) / 2;
+            if matches!(run_with(self, mid).exit_reason, return_ok!()) {
+                high = mid;
+            } else {
+                low = mid;
+            }
+        }
+
+        self.env.tx.gas_limit = gas_limit;
+        Ok(high)
+    }
 }
 
 impl<'a, DB: DatabaseRef> EVM<DB> {
@@ -87,9 +209,13 @@ impl<'a, DB: DatabaseRef> EVM<DB> {
             let mut noop = NoOpInspector {};
             let mut db = RefDBWrapper::new(db);
             let db = &mut db;
-            let out =
-                evm_inner::<RefDBWrapper<DB::Error>, false>(&mut self.env.clone(), db, &mut noop)
-                    .transact();
+            let out = evm_inner_with_options::<RefDBWrapper<DB::Error>, false>(
+                &mut self.env.clone(),
+                db,
+                &mut noop,
+                &self.options,
+            )
+            .transact();
             out
         } else {
             panic!("Database needs to be set");
@@ -104,10 +230,11 @@ impl<'a, DB: DatabaseRef> EVM<DB> {
         if let Some(db) = self.db.as_ref() {
             let mut db = RefDBWrapper::new(db);
             let db = &mut db;
-            let out = evm_inner::<RefDBWrapper<DB::Error>, true>(
+            let out = evm_inner_with_options::<RefDBWrapper<DB::Error>, true>(
                 &mut self.env.clone(),
                 db,
                 &mut inspector,
+                &self.options,
             )
             .transact();
             out
@@ -122,9 +249,39 @@ impl<DB> EVM<DB> {
         Self {
             env: Env::default(),
             db: None,
+            options: EVMOptions::default(),
         }
     }
 
+    /// Add a precompile at `address` for all following transactions. It replaces a built-in
+    /// precompile at the same address and is warm and charged like one: `fun` gets the input and
+    /// gas limit of the call and an error, or a cost above the gas limit, fails the call.
+    pub fn register_precompile(
+        &mut self,
+        address: H160,
+        fun: impl Fn(&[u8], u64) -> PrecompileResult + Send + Sync + 'static,
+    ) {
+        self.options
+            .custom_precompiles
+            .retain(|(a, _)| *a != address);
+        self.options
+            .custom_precompiles
+            .push((address, Arc::new(fun)));
+    }
+
+    /// Use `overrides` instead of the database state of their accounts in all following
+    /// transactions, see [crate::JournaledState::apply_overrides]. The database is never changed
+    /// by them, but the state returned by a transaction includes the overridden values of the
+    /// accounts it touches, so commit it only if that is wanted.
+    pub fn apply_overrides(&mut self, overrides: Map<H160, AccountOverride>) {
+        self.options.state_overrides.extend(overrides);
+    }
+
+    /// Remove the overrides added with [EVM::apply_overrides].
+    pub fn clear_overrides(&mut self) {
+        self.options.state_overrides.clear();
+    }
+
     pub fn database(&mut self, db: DB) {
         self.db = Some(db);
     }
@@ -138,36 +295,388 @@ impl<DB> EVM<DB> {
     }
 }
 
-macro_rules! create_evm {
-    ($spec:ident, $db:ident,$env:ident,$inspector:ident) => {
-        Box::new(EVMImpl::<'a, $spec, DB, INSPECT>::new(
-            $db,
-            $env,
-            $inspector,
-            Precompiles::new(SpecId::to_precompile_id($spec::SPEC_ID)).clone(),
-        )) as Box<dyn Transact + 'a>
-    };
+pub fn evm_inner<'a, DB: Database, const INSPECT: bool>(
+    env: &'a mut Env,
+    db: &'a mut DB,
+    insp: &'a mut dyn Inspector<DB>,
+) -> Box<dyn Transact + 'a> {
+    evm_inner_with_options::<DB, INSPECT>(env, db, insp, &EVMOptions::default())
 }
 
-pub fn evm_inner<'a, DB: Database, const INSPECT: bool>(
+/// [evm_inner] with the precompiles of `options` added to the built-in ones of the spec and its
+/// account state overrides, see [crate::JournaledState::apply_overrides].
+pub fn evm_inner_with_options<'a, DB: Database, const INSPECT: bool>(
     env: &'a mut Env,
     db: &'a mut DB,
     insp: &'a mut dyn Inspector<DB>,
+    options: &EVMOptions,
 ) -> Box<dyn Transact + 'a> {
+    // defined here so that it sees the arguments of the function.
+    macro_rules! create_evm {
+        ($spec:ident) => {{
+            let mut precompiles =
+                Precompiles::new(SpecId::to_precompile_id($spec::SPEC_ID)).clone();
+            for (address, fun) in &options.custom_precompiles {
+                precompiles.insert_custom(*address, fun.clone());
+            }
+            Box::new(EVMImpl::<'a, $spec, DB, INSPECT>::new(
+                db,
+                env,
+                insp,
+                precompiles,
+                &options.state_overrides,
+            )) as Box<dyn Transact + 'a>
+        }};
+    }
+
     use specification::*;
     match env.cfg.spec_id {
-        SpecId::FRONTIER | SpecId::FRONTIER_THAWING => create_evm!(FrontierSpec, db, env, insp),
-        SpecId::HOMESTEAD | SpecId::DAO_FORK => create_evm!(HomesteadSpec, db, env, insp),
-        SpecId::TANGERINE => create_evm!(TangerineSpec, db, env, insp),
-        SpecId::SPURIOUS_DRAGON => create_evm!(SpuriousDragonSpec, db, env, insp),
-        SpecId::BYZANTIUM => create_evm!(ByzantiumSpec, db, env, insp),
-        SpecId::PETERSBURG | SpecId::CONSTANTINOPLE => create_evm!(PetersburgSpec, db, env, insp),
-        SpecId::ISTANBUL | SpecId::MUIR_GLACIER => create_evm!(IstanbulSpec, db, env, insp),
-        SpecId::BERLIN => create_evm!(BerlinSpec, db, env, insp),
-        SpecId::LONDON | SpecId::ARROW_GLACIER | SpecId::GRAY_GLACIER => {
-            create_evm!(LondonSpec, db, env, insp)
+        SpecId::FRONTIER | SpecId::FRONTIER_THAWING => create_evm!(FrontierSpec),
+        SpecId::HOMESTEAD | SpecId::DAO_FORK => create_evm!(HomesteadSpec),
+        SpecId::TANGERINE => create_evm!(TangerineSpec),
+        SpecId::SPURIOUS_DRAGON => create_evm!(SpuriousDragonSpec),
+        SpecId::BYZANTIUM => create_evm!(ByzantiumSpec),
+        SpecId::CONSTANTINOPLE => create_evm!(ConstantinopleSpec),
+        SpecId::PETERSBURG => create_evm!(PetersburgSpec),
+        SpecId::ISTANBUL | SpecId::MUIR_GLACIER => create_evm!(IstanbulSpec),
+        SpecId::BERLIN => create_evm!(BerlinSpec),
+        SpecId::LONDON | SpecId::ARROW_GLACIER | SpecId::GRAY_GLACIER => create_evm!(LondonSpec),
+        SpecId::MERGE => create_evm!(MergeSpec),
+        SpecId::SHANGHAI => create_evm!(ShanghaiSpec),
+        SpecId::CANCUN => create_evm!(CancunSpec),
+        SpecId::PRAGUE => create_evm!(PragueSpec),
+        SpecId::LATEST => create_evm!(LatestSpec),
+        SpecId::EXPERIMENTAL_EOF => create_evm!(ExperimentalEofSpec),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![cfg_attr(feature = "no_gas_measuring", allow(unused_imports))]
+
+    use crate::{
+        opcode,
+        precompiles::{gas_query, PrecompileOutput, Return as PrecompileReturn},
+        test_utils::evm_with_db,
+        AccountInfo, AccountOverride, Bytecode, CallTraceInspector, Database, InMemoryDB, Return,
+        StorageOverride, TransactOut, TransactTo, EVM,
+    };
+    use bytes::Bytes;
+    use hashbrown::HashMap as Map;
+    use primitive_types::{H160, H256, U256};
+
+    fn evm_with(accounts: &[(H160, Vec<u8>)]) -> EVM<InMemoryDB> {
+        let mut db = InMemoryDB::default();
+        for (address, code) in accounts {
+            db.insert_account_info(
+                *address,
+                AccountInfo {
+                    code: Some(Bytecode::new_raw(Bytes::from(code.clone()))),
+                    ..Default::default()
+                },
+            );
+        }
+        let mut evm = evm_with_db(db);
+        evm.env.tx.gas_limit = 1_000_000;
+        evm
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_estimate_transfer() {
+        let mut evm = evm_with(&[]);
+        evm.env.tx.transact_to = TransactTo::Call(H160::repeat_byte(0x20));
+        assert_eq!(evm.min_gas_limit().unwrap(), 21_000);
+        assert_eq!(evm.estimate_gas().unwrap(), 21_210);
+        assert_eq!(evm.env.tx.gas_limit, 1_000_000);
+
+        // the buffer is capped at the given gas limit.
+        evm.env.tx.gas_limit = 21_100;
+        assert_eq!(evm.estimate_gas().unwrap(), 21_100);
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_estimate_call_reverting_at_low_gas() {
+        let callee = H160::repeat_byte(0xbb);
+        // calls the callee with all gas, reverts if the call failed.
+        let mut caller_code = vec![opcode::PUSH1, 0x00];
+        caller_code.extend_from_slice(&caller_code.repeat(4));
+        caller_code.push(opcode::PUSH20);
+        caller_code.extend_from_slice(callee.as_bytes());
+        caller_code.extend_from_slice(&[
+            opcode::GAS,
+            opcode::CALL,
+            opcode::PUSH1,
+            41,
+            opcode::JUMPI,
+            opcode::PUSH1,
+            0x00,
+            opcode::PUSH1,
+            0x00,
+            opcode::REVERT,
+            opcode::JUMPDEST,
+            opcode::STOP,
+        ]);
+        assert_eq!(caller_code[41], opcode::JUMPDEST);
+        // stores 1 in slot 0.
+        let callee_code = vec![
+            opcode::PUSH1,
+            0x01,
+            opcode::PUSH1,
+            0x00,
+            opcode::SSTORE,
+            opcode::STOP,
+        ];
+        let caller = H160::repeat_byte(0xaa);
+        let mut evm = evm_with(&[(caller, caller_code), (callee, callee_code)]);
+        evm.env.tx.transact_to = TransactTo::Call(caller);
+
+        let gas_used = evm.transact().0.gas_used;
+        let min_gas_limit = evm.min_gas_limit().unwrap();
+        // 1/64 of the gas at the CALL is kept by the caller and not used.
+        assert!(min_gas_limit > gas_used);
+
+        evm.env.tx.gas_limit = min_gas_limit;
+        assert_eq!(evm.transact().0.exit_reason, Return::Stop);
+        evm.env.tx.gas_limit = min_gas_limit - 1;
+        assert_eq!(evm.transact().0.exit_reason, Return::Revert);
+        assert_eq!(
+            evm.estimate_gas().map_err(|result| result.exit_reason),
+            Err(Return::Revert)
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_estimate_storage_clearing_refund() {
+        // clears slot 0 that is set to 1.
+        let code = vec![
+            opcode::PUSH1,
+            0x00,
+            opcode::PUSH1,
+            0x00,
+            opcode::SSTORE,
+            opcode::STOP,
+        ];
+        let address = H160::repeat_byte(0xcc);
+        let mut evm = evm_with(&[(address, code)]);
+        evm.db()
+            .unwrap()
+            .insert_account_storage(address, U256::zero(), U256::one())
+            .unwrap();
+        evm.env.tx.transact_to = TransactTo::Call(address);
+
+        let result = evm.transact().0;
+        assert!(result.gas_refunded > 0);
+        // the refund is only paid back at the end, the whole gas has to be available.
+        assert_eq!(
+            evm.min_gas_limit().unwrap(),
+            result.gas_used + result.gas_refunded
+        );
+    }
+
+    /// Code that calls `precompile` with the word 0x2a and returns its output and the success
+    /// flag of the call.
+    fn call_precompile(precompile: H160) -> Vec<u8> {
+        let mut code = vec![opcode::PUSH1, 0x2a, opcode::PUSH1, 0x00, opcode::MSTORE];
+        code.extend([
+            opcode::PUSH1,
+            0x20,
+            opcode::PUSH1,
+            0x00,
+            opcode::PUSH1,
+            0x20,
+        ]);
+        code.extend([opcode::PUSH1, 0x00, opcode::PUSH1, 0x00, opcode::PUSH20]);
+        code.extend_from_slice(precompile.as_bytes());
+        code.extend([
+            opcode::GAS,
+            opcode::CALL,
+            opcode::PUSH1,
+            0x20,
+            opcode::MSTORE,
+        ]);
+        code.extend([opcode::PUSH1, 0x40, opcode::PUSH1, 0x00, opcode::RETURN]);
+        code
+    }
+
+    #[test]
+    fn test_register_precompile() {
+        let contract = H160::repeat_byte(0xcc);
+        let custom = H160::from_low_u64_be(0x100);
+        let identity = H160::from_low_u64_be(4);
+        // reverses the input at the cost of the identity precompile for one word.
+        let reverse = |input: &[u8], gas_limit: u64| {
+            let cost = gas_query(18, gas_limit)?;
+            Ok(PrecompileOutput::without_logs(
+                cost,
+                input.iter().rev().copied().collect(),
+            ))
+        };
+        let run = |evm: &mut EVM<InMemoryDB>, precompile| {
+            evm.db().unwrap().insert_account_info(
+                contract,
+                AccountInfo::new(
+                    U256::zero(),
+                    0,
+                    Bytecode::new_raw(Bytes::from(call_precompile(precompile))),
+                ),
+            );
+            let (result, _) = evm.transact();
+            match result.out {
+                TransactOut::Call(out) => (result.gas_used, out),
+                _ => panic!("expected call output"),
+            }
+        };
+
+        let mut evm = evm_with(&[]);
+        evm.env.tx.transact_to = TransactTo::Call(contract);
+        evm.register_precompile(custom, reverse);
+        let (gas_used, out) = run(&mut evm, custom);
+        assert_eq!((out[0], out[31], out[63]), (0x2a, 0, 1));
+
+        // warm and charged the same as a built-in precompile of the same cost.
+        let (identity_gas_used, out) = run(&mut evm, identity);
+        assert_eq!((out[0], out[31], out[63]), (0, 0x2a, 1));
+        assert_eq!(gas_used, identity_gas_used);
+
+        // a failing precompile fails the call.
+        evm.register_precompile(custom, |_: &[u8], _| Err(PrecompileReturn::OutOfGas));
+        let (_, out) = run(&mut evm, custom);
+        assert_eq!(out[63], 0);
+
+        // replaces a built-in precompile.
+        evm.register_precompile(identity, reverse);
+        let (_, out) = run(&mut evm, identity);
+        assert_eq!((out[0], out[31], out[63]), (0x2a, 0, 1));
+    }
+
+    /// [InMemoryDB] that fails to load the account at `failing`.
+    struct FailingDB {
+        db: InMemoryDB,
+        failing: H160,
+    }
+
+    impl Database for FailingDB {
+        type Error = ();
+
+        fn basic(&mut self, address: H160) -> Result<Option<AccountInfo>, ()> {
+            if address == self.failing {
+                return Err(());
+            }
+            Ok(self.db.basic(address).unwrap())
+        }
+
+        fn code_by_hash(&mut self, code_hash: H256) -> Result<Bytecode, ()> {
+            Ok(self.db.code_by_hash(code_hash).unwrap())
+        }
+
+        fn storage(&mut self, address: H160, index: U256) -> Result<U256, ()> {
+            Ok(self.db.storage(address, index).unwrap())
         }
-        SpecId::MERGE => create_evm!(MergeSpec, db, env, insp),
-        SpecId::LATEST => create_evm!(LatestSpec, db, env, insp),
+
+        fn block_hash(&mut self, number: U256) -> Result<H256, ()> {
+            Ok(self.db.block_hash(number).unwrap())
+        }
+    }
+
+    #[test]
+    fn test_register_precompile_load_error() {
+        let custom = H160::from_low_u64_be(0x100);
+        let mut evm = evm_with_db(FailingDB {
+            db: InMemoryDB::default(),
+            failing: custom,
+        });
+        evm.register_precompile(custom, |_: &[u8], _| Err(PrecompileReturn::OutOfGas));
+        let (result, state) = evm.transact();
+        assert_eq!(result.exit_reason, Return::FatalExternalError);
+        assert!(state.is_empty());
+    }
+
+    #[test]
+    fn test_state_overrides() {
+        let contract = H160::repeat_byte(0xcc);
+        let mut evm = evm_with(&[(contract, vec![opcode::STOP])]);
+        evm.env.tx.transact_to = TransactTo::Call(contract);
+        let db = evm.db().unwrap();
+        db.insert_account_storage(contract, U256::from(1), U256::from(5))
+            .unwrap();
+        db.insert_account_storage(contract, U256::from(2), U256::from(7))
+            .unwrap();
+        let output = |evm: &mut EVM<InMemoryDB>| match evm.transact().0.out {
+            TransactOut::Call(out) => out,
+            _ => panic!("expected call output"),
+        };
+
+        // returns sload(1) and sload(2).
+        let code = vec![
+            opcode::PUSH1,
+            0x01,
+            opcode::SLOAD,
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x02,
+            opcode::SLOAD,
+            opcode::PUSH1,
+            0x20,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x40,
+            opcode::PUSH1,
+            0x00,
+            opcode::RETURN,
+        ];
+        let with_storage = |storage| {
+            Map::from([(
+                contract,
+                AccountOverride {
+                    code: Some(Bytecode::new_raw(Bytes::from(code.clone()))),
+                    storage: Some(storage),
+                    ..Default::default()
+                },
+            )])
+        };
+        let slot_one = Map::from([(U256::from(1), U256::from(42))]);
+
+        evm.apply_overrides(with_storage(StorageOverride::Merge(slot_one.clone())));
+        let out = output(&mut evm);
+        assert_eq!((out[31], out[63]), (42, 7));
+
+        // replaces the earlier override, slot 2 is not in the database anymore.
+        evm.apply_overrides(with_storage(StorageOverride::Replace(slot_one)));
+        let out = output(&mut evm);
+        assert_eq!((out[31], out[63]), (42, 0));
+
+        // the database is unchanged.
+        evm.clear_overrides();
+        assert!(output(&mut evm).is_empty());
+    }
+
+    #[test]
+    fn test_dry_run() {
+        let contract = H160::repeat_byte(0xcc);
+        // SSTORE(1, 9)
+        let code = vec![opcode::PUSH1, 0x09, opcode::PUSH1, 0x01, opcode::SSTORE];
+        let mut evm = evm_with(&[(contract, code)]);
+        evm.env.tx.transact_to = TransactTo::Call(contract);
+        evm.db()
+            .unwrap()
+            .insert_account_storage(contract, U256::one(), U256::from(5))
+            .unwrap();
+        let slot = |evm: &mut EVM<InMemoryDB>| evm.db().unwrap().storage(contract, U256::one());
+
+        let mut tracer = CallTraceInspector::new();
+        let result = evm.inspect_dry_run(&mut tracer);
+        assert_eq!(result.exit_reason, Return::Stop);
+        assert_eq!(tracer.into_trace().error, None);
+        assert_eq!(slot(&mut evm), Ok(U256::from(5)));
+        assert_eq!(evm.transact_dry_run().gas_used, result.gas_used);
+        assert_eq!(slot(&mut evm), Ok(U256::from(5)));
+
+        evm.transact_commit();
+        assert_eq!(slot(&mut evm), Ok(U256::from(9)));
     }
 }
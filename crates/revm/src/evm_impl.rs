@@ -1,16 +1,18 @@
 use crate::{
     db::Database,
     gas,
-    interpreter::{self, bytecode::Bytecode},
-    interpreter::{Contract, Interpreter},
-    journaled_state::{Account, JournaledState, State},
+    interpreter::bytecode::Bytecode,
+    interpreter::{validate_bytecode, Contract, Interpreter, SubCall, SubCallResult},
+    journaled_state::{
+        is_precompile, Account, AccountOverride, JournalCheckpoint, JournaledState, State,
+    },
     models::SelfDestructResult,
-    return_ok, return_revert, AnalysisKind, CallContext, CallInputs, CallScheme, CreateInputs,
-    CreateScheme, Env, ExecutionResult, Gas, Inspector, Log, Return, Spec,
+    return_ok, return_revert, AnalysisKind, CallInputs, CallScheme, CreateInputs, CreateScheme,
+    Env, ExecutionResult, Gas, GasBreakdown, Inspector, Log, Return, Spec,
     SpecId::{self, *},
-    TransactOut, TransactTo, Transfer, KECCAK_EMPTY,
+    TransactOut, TransactTo, KECCAK_EMPTY,
 };
-use alloc::vec::Vec;
+use alloc::{boxed::Box, vec::Vec};
 use bytes::Bytes;
 use core::{cmp::min, marker::PhantomData};
 use hashbrown::HashMap as Map;
@@ -29,9 +31,22 @@ pub struct EVMImpl<'a, GSPEC: Spec, DB: Database, const INSPECT: bool> {
     data: EVMData<'a, DB>,
     precompiles: Precompiles,
     inspector: &'a mut dyn Inspector<DB>,
+    /// Memory in bytes of all active call frames.
+    memory_used: usize,
+    /// Results of STATICCALLs, see [crate::CfgEnv::static_call_cache].
+    static_calls: StaticCallCache,
     _phantomdata: PhantomData<GSPEC>,
 }
 
+/// `(caller, callee, keccak(input))` of a STATICCALL.
+type StaticCallKey = (H160, H160, H256);
+
+/// [StaticCallKey] to the exit reason, gas spent and output of the call.
+type StaticCallCache = Map<StaticCallKey, (Return, u64, Bytes)>;
+
+/// Exit reason, created address, remaining gas and output of a create frame.
+type CreateResult = (Return, Option<H160>, Gas, Bytes);
+
 pub trait Transact {
     /// Do transaction.
     /// Return Return, Output for call or Address if we are creating contract, gas spend, gas refunded, State that needs to be applied.
@@ -46,8 +61,16 @@ impl<'a, GSPEC: Spec, DB: Database, const INSPECT: bool> Transact
         let value = self.data.env.tx.value;
         let data = self.data.env.tx.data.clone();
         let gas_limit = self.data.env.tx.gas_limit;
-        let exit = |reason: Return| (ExecutionResult::new_with_reason(reason), State::new());
+        let exit = |reason: Return| {
+            let mut result = ExecutionResult::new_with_reason(reason);
+            result.gas_limit = gas_limit;
+            (result, State::new())
+        };
 
+        // warming the precompiles in [EVMImpl::new] failed.
+        if self.data.error.is_some() {
+            return exit(Return::FatalExternalError);
+        }
         if GSPEC::enabled(LONDON) {
             if let Some(priority_fee) = self.data.env.tx.gas_priority_fee {
                 if priority_fee > self.data.env.tx.gas_price {
@@ -55,25 +78,43 @@ impl<'a, GSPEC: Spec, DB: Database, const INSPECT: bool> Transact
                     return exit(Return::GasMaxFeeGreaterThanPriorityFee);
                 }
             }
-            let effective_gas_price = self.data.env.effective_gas_price();
-            let basefee = self.data.env.block.basefee;
-
-            // check minimal cost against basefee
-            // TODO maybe do this checks when creating evm. We already have all data there
-            // or should be move effective_gas_price inside transact fn
-            if effective_gas_price < basefee {
+            // the max fee, or the gas price of legacy transactions, has to cover the base fee.
+            if self.data.env.tx.gas_price < self.data.env.block.basefee {
                 return exit(Return::GasPriceLessThenBasefee);
             }
-            // check if priority fee is lower then max fee
+        }
+        if matches!(self.data.env.cfg.max_input_size, Some(limit) if data.len() > limit) {
+            return exit(Return::InputSizeLimit);
+        }
+        if matches!(self.data.env.tx.transact_to, TransactTo::Create(_))
+            && self.data.env.cfg.exceeds_executed_code_size(data.len())
+        {
+            return exit(Return::CodeSizeLimit);
         }
         // unusual to be found here, but check if gas_limit is more then block_gas_limit
         if U256::from(gas_limit) > self.data.env.block.gas_limit {
             return exit(Return::CallerGasLimitMoreThenBlock);
         }
+        // EIP-7702: authorizations come with set code transactions, those can not create.
+        if !self.data.env.tx.authorization_list.is_empty()
+            && (!GSPEC::enabled(PRAGUE)
+                || matches!(self.data.env.tx.transact_to, TransactTo::Create(_)))
+        {
+            return exit(Return::InvalidAuthorizationList);
+        }
+
+        // EIP-3860: Limit and meter initcode
+        if GSPEC::enabled(SHANGHAI)
+            && matches!(self.data.env.tx.transact_to, TransactTo::Create(_))
+            && data.len() > self.data.env.cfg.max_initcode_size()
+        {
+            return exit(Return::CreateInitcodeSizeLimit);
+        }
 
         let mut gas = Gas::new(gas_limit);
         // record initial gas cost. if not using gas metering init will return 0
-        if !gas.record_cost(self.initialization::<GSPEC>()) {
+        let intrinsic_gas = self.initialization::<GSPEC>();
+        if !gas.record_cost(intrinsic_gas) {
             return exit(Return::OutOfGas);
         }
 
@@ -81,7 +122,7 @@ impl<'a, GSPEC: Spec, DB: Database, const INSPECT: bool> Transact
         if self
             .data
             .journaled_state
-            .load_account(caller, self.data.db)
+            .load_code(caller, self.data.db)
             .is_err()
         {
             return exit(Return::FatalExternalError);
@@ -90,7 +131,11 @@ impl<'a, GSPEC: Spec, DB: Database, const INSPECT: bool> Transact
         // EIP-3607: Reject transactions from senders with deployed code
         // This EIP is introduced after london but there was no colision in past
         // so we can leave it enabled always
-        if self.data.journaled_state.account(caller).info.code_hash != KECCAK_EMPTY {
+        // EIP-7702: Accounts that delegate their code still send transactions.
+        let caller_info = &self.data.journaled_state.account(caller).info;
+        let is_delegated = GSPEC::enabled(PRAGUE)
+            && matches!(&caller_info.code, Some(code) if code.delegation().is_some());
+        if caller_info.code_hash != KECCAK_EMPTY && !is_delegated {
             return exit(Return::RejectCallerWithCode);
         }
 
@@ -121,9 +166,9 @@ impl<'a, GSPEC: Spec, DB: Database, const INSPECT: bool> Transact
         }
 
         // record all as cost;
-        let gas_limit = gas.remaining();
-        if crate::USE_GAS {
-            gas.record_cost(gas_limit);
+        let frame_gas_limit = gas.remaining();
+        if self.use_gas() {
+            gas.record_cost(frame_gas_limit);
         }
 
         // call inner handling of call/create
@@ -133,24 +178,16 @@ impl<'a, GSPEC: Spec, DB: Database, const INSPECT: bool> Transact
                     // overflow
                     return exit(Return::NonceOverflow);
                 }
-                let context = CallContext {
-                    caller,
-                    address,
-                    code_address: address,
-                    apparent_value: value,
-                    scheme: CallScheme::Call,
-                };
-                let mut call_input = CallInputs {
-                    contract: address,
-                    transfer: Transfer {
-                        source: caller,
-                        target: address,
-                        value,
-                    },
-                    input: data,
-                    gas_limit,
-                    context,
-                };
+                if let Err(reason) = self.apply_authorizations(&mut gas) {
+                    return exit(reason);
+                }
+                let mut call_input = CallInputs::builder()
+                    .caller(caller)
+                    .contract(address)
+                    .value(value)
+                    .input(data)
+                    .gas_limit(frame_gas_limit)
+                    .to_call();
                 let (exit, gas, bytes) = self.call_inner::<GSPEC>(&mut call_input);
                 (exit, gas, TransactOut::Call(bytes))
             }
@@ -160,14 +197,14 @@ impl<'a, GSPEC: Spec, DB: Database, const INSPECT: bool> Transact
                     scheme,
                     value,
                     init_code: data,
-                    gas_limit,
+                    gas_limit: frame_gas_limit,
                 };
                 let (exit, address, ret_gas, bytes) = self.create_inner::<GSPEC>(&mut create_input);
                 (exit, ret_gas, TransactOut::Create(bytes, address))
             }
         };
 
-        if crate::USE_GAS {
+        if self.use_gas() {
             match exit_reason {
                 return_ok!() => {
                     gas.erase_cost(ret_gas.remaining());
@@ -181,13 +218,26 @@ impl<'a, GSPEC: Spec, DB: Database, const INSPECT: bool> Transact
         }
 
         let (state, logs, gas_used, gas_refunded) = self.finalize::<GSPEC>(caller, &gas);
+        let gas_breakdown = self.use_gas().then(|| GasBreakdown {
+            intrinsic: intrinsic_gas,
+            execution: gas.spend() - intrinsic_gas,
+            refunded: gas_refunded,
+            blob: if GSPEC::enabled(SpecId::CANCUN) {
+                self.data.env.tx.blob_hashes.len() as u64 * gas::GAS_PER_BLOB
+            } else {
+                0
+            },
+        });
         (
             ExecutionResult {
                 exit_reason,
                 out,
                 gas_used,
                 gas_refunded,
+                gas_limit,
+                gas_breakdown,
                 logs,
+                change_log: core::mem::take(&mut self.data.journaled_state.change_log),
             },
             state,
         )
@@ -199,22 +249,45 @@ impl<'a, GSPEC: Spec, DB: Database, const INSPECT: bool> EVMImpl<'a, GSPEC, DB,
         db: &'a mut DB,
         env: &'a mut Env,
         inspector: &'a mut dyn Inspector<DB>,
-        precompiles: Precompiles,
+        mut precompiles: Precompiles,
+        state_overrides: &Map<H160, AccountOverride>,
     ) -> Self {
-        let journaled_state = if GSPEC::enabled(SpecId::SPURIOUS_DRAGON) {
-            JournaledState::new(precompiles.len())
+        for (address, multiplier) in &env.cfg.precompile_gas_multipliers {
+            precompiles.set_gas_multiplier(*address, *multiplier);
+        }
+        // precompiles at 0x01.. are known to be warm, others are loaded up front to warm them.
+        let num_of_precompiles = (1..)
+            .take_while(|i| precompiles.contains(&H160::from_low_u64_be(*i)))
+            .count();
+        let mut journaled_state = if GSPEC::enabled(SpecId::SPURIOUS_DRAGON) {
+            JournaledState::new(num_of_precompiles)
         } else {
-            JournaledState::new_legacy(precompiles.len())
+            JournaledState::new_legacy(num_of_precompiles)
         };
+        journaled_state.keep_change_log = env.cfg.keep_change_log;
+        journaled_state.is_cancun = GSPEC::enabled(SpecId::CANCUN);
+        journaled_state.apply_overrides(state_overrides.clone());
+        // a failed load is kept in `error` and fails the transaction in [Transact::transact].
+        let mut error = None;
+        for address in precompiles.addresses() {
+            if !is_precompile(*address, num_of_precompiles) {
+                if let Err(e) = journaled_state.load_account(*address, db) {
+                    error = Some(e);
+                    break;
+                }
+            }
+        }
         Self {
             data: EVMData {
                 env,
                 journaled_state,
                 db,
-                error: None,
+                error,
             },
             precompiles,
             inspector,
+            memory_used: 0,
+            static_calls: Map::new(),
             _phantomdata: PhantomData {},
         }
     }
@@ -225,12 +298,16 @@ impl<'a, GSPEC: Spec, DB: Database, const INSPECT: bool> EVMImpl<'a, GSPEC, DB,
         gas: &Gas,
     ) -> (Map<H160, Account>, Vec<Log>, u64, u64) {
         let coinbase = self.data.env.block.coinbase;
-        let (gas_used, gas_refunded) = if crate::USE_GAS {
+        let (gas_used, gas_refunded) = if self.use_gas() {
             let effective_gas_price = self.data.env.effective_gas_price();
             let basefee = self.data.env.block.basefee;
             let max_refund_quotient = if SPEC::enabled(LONDON) { 5 } else { 2 }; // EIP-3529: Reduction in refunds
 
-            let gas_refunded = min(gas.refunded() as u64, gas.spend() / max_refund_quotient);
+            // refund is capped once for the whole transaction, a negative counter refunds nothing.
+            let gas_refunded = min(
+                gas.refunded().max(0) as u64,
+                gas.spend() / max_refund_quotient,
+            );
             let acc_caller = self.data.journaled_state.state().get_mut(&caller).unwrap();
             acc_caller.info.balance = acc_caller
                 .info
@@ -298,7 +375,7 @@ impl<'a, GSPEC: Spec, DB: Database, const INSPECT: bool> EVMImpl<'a, GSPEC, DB,
         let is_create = matches!(self.data.env.tx.transact_to, TransactTo::Create(_));
         let input = &self.data.env.tx.data;
 
-        if crate::USE_GAS {
+        if self.use_gas() {
             let zero_data_len = input.iter().filter(|v| **v == 0).count() as u64;
             let non_zero_data_len = input.len() as u64 - zero_data_len;
             let (accessed_accounts, accessed_slots) = {
@@ -320,6 +397,13 @@ impl<'a, GSPEC: Spec, DB: Database, const INSPECT: bool> EVMImpl<'a, GSPEC, DB,
                                 .sload(*address, *slot, self.data.db);
                         }
                     }
+                    // EIP-3651: Warm COINBASE
+                    if SPEC::enabled(SHANGHAI) {
+                        let _ = self
+                            .data
+                            .journaled_state
+                            .load_account(self.data.env.block.coinbase, self.data.db);
+                    }
                     (self.data.env.tx.access_list.len() as u64, accessed_slots)
                 } else {
                     (0, 0)
@@ -340,50 +424,339 @@ impl<'a, GSPEC: Spec, DB: Database, const INSPECT: bool> EVMImpl<'a, GSPEC, DB,
             // EIP-2028: Transaction data gas cost reduction
             let gas_transaction_non_zero_data = if SPEC::enabled(ISTANBUL) { 16 } else { 68 };
 
+            // EIP-7702: every authorization is charged as if it creates its authority.
+            let authorizations = if SPEC::enabled(PRAGUE) {
+                self.data.env.tx.authorization_list.len() as u64
+            } else {
+                0
+            };
+
+            // EIP-3860: Limit and meter initcode
+            let initcode_cost = if SPEC::enabled(SHANGHAI) && is_create {
+                gas::initcode_cost(input.len())
+            } else {
+                0
+            };
+
             transact
+                + initcode_cost
                 + zero_data_len * gas::TRANSACTION_ZERO_DATA
                 + non_zero_data_len * gas_transaction_non_zero_data
                 + accessed_accounts * gas::ACCESS_LIST_ADDRESS
                 + accessed_slots * gas::ACCESS_LIST_STORAGE_KEY
+                + authorizations * gas::PER_EMPTY_ACCOUNT_COST
         } else {
             0
         }
     }
 
+    /// EIP-7702: set the code of every authority to a delegation to the authorized address, in
+    /// list order. Authorizations for another chain, with a stale nonce or signed by an account
+    /// with code are skipped. Authorities that already exist get part of the intrinsic cost back.
+    fn apply_authorizations(&mut self, gas: &mut Gas) -> Result<(), Return> {
+        let chain_id = self.data.env.cfg.chain_id;
+        for i in 0..self.data.env.tx.authorization_list.len() {
+            let authorization = self.data.env.tx.authorization_list[i];
+            let authority = match authorization.authority {
+                Some(authority) => authority,
+                None => continue,
+            };
+            if !(authorization.chain_id.is_zero() || authorization.chain_id == chain_id)
+                || authorization.nonce == u64::MAX
+            {
+                continue;
+            }
+
+            // authority is warm from here on, even if the authorization is skipped.
+            let journal = &mut self.data.journaled_state;
+            let error = &mut self.data.error;
+            let (acc, _) = journal.load_code(authority, self.data.db).map_err(|e| {
+                *error = Some(e);
+                Return::FatalExternalError
+            })?;
+            let code = acc.info.code.as_ref().unwrap();
+            if !(code.is_empty() || code.delegation().is_some())
+                || acc.info.nonce != authorization.nonce
+            {
+                continue;
+            }
+            if !acc.is_empty() {
+                gas.record_refund((gas::PER_EMPTY_ACCOUNT_COST - gas::PER_AUTH_BASE_COST) as i64);
+            }
+
+            let code = if authorization.address.is_zero() {
+                Bytecode::new()
+            } else {
+                Bytecode::new_delegation(authorization.address)
+            };
+            journal.set_code(authority, code);
+            journal.inc_nonce(authority);
+        }
+        Ok(())
+    }
+
+    /// Run a create frame and every frame nested in it, see [EVMImpl::run_frames].
+    #[inline]
     fn create_inner<SPEC: Spec>(
         &mut self,
         inputs: &mut CreateInputs,
     ) -> (Return, Option<H160>, Gas, Bytes) {
+        let sub_call = SubCall::Create {
+            inputs: inputs.clone(),
+        };
+        match self.run_frames(sub_call, SPEC::IS_STATIC_CALL) {
+            SubCallResult::Create {
+                ret,
+                address,
+                gas,
+                out,
+            } => (ret, address, gas, out),
+            SubCallResult::Call { .. } => unreachable!("create frame returned a call result"),
+        }
+    }
+
+    /// Run a call frame and every frame nested in it, see [EVMImpl::run_frames].
+    #[inline]
+    fn call_inner<SPEC: Spec>(&mut self, inputs: &mut CallInputs) -> (Return, Gas, Bytes) {
+        let sub_call = SubCall::Call {
+            inputs: inputs.clone(),
+            is_static: false,
+        };
+        match self.run_frames(sub_call, SPEC::IS_STATIC_CALL) {
+            SubCallResult::Call { ret, gas, out } => (ret, gas, out),
+            SubCallResult::Create { .. } => unreachable!("call frame returned a create result"),
+        }
+    }
+
+    /// Run `sub_call` and all of its nested calls and creates.
+    ///
+    /// Suspended frames are kept on a heap allocated stack instead of recursing through
+    /// [Host::call], so reaching [crate::CfgEnv::call_stack_limit] does not overflow the native
+    /// stack.
+    fn run_frames(&mut self, sub_call: SubCall, is_static: bool) -> SubCallResult {
+        let mut frames: Vec<Frame> = Vec::new();
+        let mut frame = match self.start_frame(sub_call, is_static) {
+            FrameStart::Done(result) => return result,
+            FrameStart::Run(frame) => *frame,
+        };
+        loop {
+            let exit_reason = if frame.is_static {
+                frame.interp.run::<Self, GSPEC::STATIC>(self)
+            } else {
+                frame.interp.run::<Self, GSPEC>(self)
+            };
+            if exit_reason == Return::SubCall {
+                if let Some(sub_call) = frame.interp.take_sub_call() {
+                    // a STATICCALL makes everything below it static.
+                    let is_static = frame.is_static
+                        || matches!(
+                            sub_call,
+                            SubCall::Call {
+                                is_static: true,
+                                ..
+                            }
+                        );
+                    match self.start_frame(sub_call, is_static) {
+                        FrameStart::Done(result) => frame.interp.resume(result),
+                        FrameStart::Run(child) => {
+                            frames.push(core::mem::replace(&mut frame, *child))
+                        }
+                    }
+                    continue;
+                }
+            }
+            let result = self.end_frame(frame, exit_reason);
+            match frames.pop() {
+                Some(parent) => {
+                    frame = parent;
+                    frame.interp.resume(result);
+                }
+                None => return result,
+            }
+        }
+    }
+
+    /// Start a frame with the spec of its static context, inside of a `call` or `create` span if
+    /// the `tracing` feature is enabled.
+    fn start_frame(&mut self, sub_call: SubCall, is_static: bool) -> FrameStart {
+        if is_static {
+            self.start_frame_with::<GSPEC::STATIC>(sub_call)
+        } else {
+            self.start_frame_with::<GSPEC>(sub_call)
+        }
+    }
+
+    fn start_frame_with<SPEC: Spec>(&mut self, sub_call: SubCall) -> FrameStart {
+        #[cfg(feature = "tracing")]
+        let span = match &sub_call {
+            SubCall::Call { inputs, .. } => tracing::debug_span!(
+                "call",
+                address = ?inputs.contract,
+                depth = self.data.journaled_state.depth() + 1,
+                gas = inputs.gas_limit,
+            ),
+            SubCall::Create { inputs } => tracing::debug_span!(
+                "create",
+                caller = ?inputs.caller,
+                address = tracing::field::Empty,
+                depth = self.data.journaled_state.depth() + 1,
+                gas = inputs.gas_limit,
+            ),
+        }
+        .entered();
+
+        let start = match sub_call {
+            SubCall::Call { inputs, .. } => match self.start_call::<SPEC>(inputs) {
+                Ok(frame) => Ok(frame),
+                Err((ret, gas, out)) => Err(SubCallResult::Call { ret, gas, out }),
+            },
+            SubCall::Create { inputs } => match self.start_create::<SPEC>(inputs) {
+                Ok(frame) => Ok(frame),
+                Err((ret, address, gas, out)) => Err(SubCallResult::Create {
+                    ret,
+                    address,
+                    gas,
+                    out,
+                }),
+            },
+        };
+        match start {
+            Ok(mut frame) => {
+                frame.is_static = SPEC::IS_STATIC_CALL;
+                #[cfg(feature = "tracing")]
+                {
+                    frame.span = Some(span);
+                }
+                FrameStart::Run(Box::new(frame))
+            }
+            Err(result) => {
+                #[cfg(feature = "tracing")]
+                trace_result(&span, &result);
+                FrameStart::Done(result)
+            }
+        }
+    }
+
+    /// Finish a frame with the spec it was started with, see [EVMImpl::start_frame].
+    fn end_frame(&mut self, frame: Frame, exit_reason: Return) -> SubCallResult {
+        #[cfg(feature = "tracing")]
+        let span = frame.span;
+        let result = if frame.is_static {
+            self.end_frame_with::<GSPEC::STATIC>(
+                frame.interp,
+                frame.checkpoint,
+                frame.kind,
+                exit_reason,
+            )
+        } else {
+            self.end_frame_with::<GSPEC>(frame.interp, frame.checkpoint, frame.kind, exit_reason)
+        };
+        #[cfg(feature = "tracing")]
+        if let Some(span) = span {
+            trace_result(&span, &result);
+        }
+        result
+    }
+
+    fn end_frame_with<SPEC: Spec>(
+        &mut self,
+        interp: Interpreter,
+        checkpoint: JournalCheckpoint,
+        kind: FrameKind,
+        exit_reason: Return,
+    ) -> SubCallResult {
+        match kind {
+            FrameKind::Call { inputs, cache_key } => {
+                let (ret, gas, out) =
+                    self.end_call::<SPEC>(interp, checkpoint, inputs, cache_key, exit_reason);
+                SubCallResult::Call { ret, gas, out }
+            }
+            FrameKind::Create { inputs, address } => {
+                let (ret, address, gas, out) =
+                    self.end_create::<SPEC>(interp, checkpoint, inputs, address, exit_reason);
+                SubCallResult::Create {
+                    ret,
+                    address,
+                    gas,
+                    out,
+                }
+            }
+        }
+    }
+
+    /// First half of a create frame, up to running the init code. Returns the result right away
+    /// if the frame stops before that.
+    fn start_create<SPEC: Spec>(
+        &mut self,
+        mut inputs: CreateInputs,
+    ) -> Result<Frame, (Return, Option<H160>, Gas, Bytes)> {
         // Call inspector
         if INSPECT {
-            let (ret, address, gas, out) = self.inspector.create(&mut self.data, inputs);
+            let (ret, address, gas, out) = self.inspector.create(&mut self.data, &mut inputs);
             if ret != Return::Continue {
-                return self
+                return Err(self.inspector.create_end(
+                    &mut self.data,
+                    &inputs,
+                    ret,
+                    address,
+                    gas,
+                    out,
+                ));
+            }
+        }
+
+        match self.enter_create::<SPEC>(&inputs) {
+            Ok((interp, checkpoint, address)) => Ok(Frame::new(
+                interp,
+                checkpoint,
+                FrameKind::Create { inputs, address },
+            )),
+            Err((ret, address, gas, out)) if INSPECT => {
+                Err(self
                     .inspector
-                    .create_end(&mut self.data, inputs, ret, address, gas, out);
+                    .create_end(&mut self.data, &inputs, ret, address, gas, out))
             }
+            Err(result) => Err(result),
         }
+    }
 
+    /// Checks, nonce increase, address derivation and value transfer of a create frame. Returns
+    /// the interpreter for the init code with its checkpoint and the created address.
+    fn enter_create<SPEC: Spec>(
+        &mut self,
+        inputs: &CreateInputs,
+    ) -> Result<(Interpreter, JournalCheckpoint, H160), CreateResult> {
         let gas = Gas::new(inputs.gas_limit);
         self.load_account(inputs.caller);
 
         // Check depth of calls
-        if self.data.journaled_state.depth() > interpreter::CALL_STACK_LIMIT {
-            return (Return::CallTooDeep, None, gas, Bytes::new());
+        if self.data.journaled_state.depth() > self.data.env.cfg.call_stack_limit {
+            return Err((Return::CallTooDeep, None, gas, Bytes::new()));
+        }
+        if self
+            .data
+            .env
+            .cfg
+            .exceeds_executed_code_size(inputs.init_code.len())
+        {
+            return Err((Return::CodeSizeLimit, None, gas, Bytes::new()));
         }
         // Check balance of caller and value. Do this before increasing nonce
         match self.balance(inputs.caller) {
-            Some(i) if i.0 < inputs.value => return (Return::OutOfFund, None, gas, Bytes::new()),
+            Some(i) if i.0 < inputs.value => {
+                return Err((Return::OutOfFund, None, gas, Bytes::new()))
+            }
             Some(_) => (),
-            _ => return (Return::FatalExternalError, None, gas, Bytes::new()),
+            _ => return Err((Return::FatalExternalError, None, gas, Bytes::new())),
         }
 
         // Increase nonce of caller and check if it overflows
+        self.static_calls.clear();
         let old_nonce;
         if let Some(nonce) = self.data.journaled_state.inc_nonce(inputs.caller) {
             old_nonce = nonce - 1;
         } else {
-            return (Return::Return, None, gas, Bytes::new());
+            return Err((Return::Return, None, gas, Bytes::new()));
         }
 
         // Create address
@@ -408,11 +781,11 @@ impl<'a, GSPEC: Spec, DB: Database, const INSPECT: bool> EVMImpl<'a, GSPEC, DB,
         ) {
             Ok(false) => {
                 self.data.journaled_state.checkpoint_revert(checkpoint);
-                return (Return::CreateCollision, ret, gas, Bytes::new());
+                return Err((Return::CreateCollision, ret, gas, Bytes::new()));
             }
             Err(err) => {
                 self.data.error = Some(err);
-                return (Return::FatalExternalError, ret, gas, Bytes::new());
+                return Err((Return::FatalExternalError, ret, gas, Bytes::new()));
             }
             Ok(true) => (),
         }
@@ -425,7 +798,7 @@ impl<'a, GSPEC: Spec, DB: Database, const INSPECT: bool> EVMImpl<'a, GSPEC, DB,
             self.data.db,
         ) {
             self.data.journaled_state.checkpoint_revert(checkpoint);
-            return (e, ret, gas, Bytes::new());
+            return Err((e, ret, gas, Bytes::new()));
         }
 
         // EIP-161: State trie clearing (invariant-preserving alternative)
@@ -438,7 +811,7 @@ impl<'a, GSPEC: Spec, DB: Database, const INSPECT: bool> EVMImpl<'a, GSPEC, DB,
         {
             // overflow
             self.data.journaled_state.checkpoint_revert(checkpoint);
-            return (Return::Return, None, gas, Bytes::new());
+            return Err((Return::Return, None, gas, Bytes::new()));
         }
 
         // Create new interpreter and execute initcode
@@ -450,44 +823,60 @@ impl<'a, GSPEC: Spec, DB: Database, const INSPECT: bool> EVMImpl<'a, GSPEC, DB,
             inputs.value,
         );
 
-        #[cfg(feature = "memory_limit")]
-        let mut interp = Interpreter::new_with_memory_limit::<SPEC>(
-            contract,
-            gas.limit(),
-            self.data.env.cfg.memory_limit,
-        );
-
-        #[cfg(not(feature = "memory_limit"))]
-        let mut interp = Interpreter::new::<SPEC>(contract, gas.limit());
+        let mut interp = self.new_interpreter::<SPEC>(contract, gas.limit());
+        interp.is_create = true;
 
         if Self::INSPECT {
             self.inspector
                 .initialize_interp(&mut interp, &mut self.data, SPEC::IS_STATIC_CALL);
         }
-        let exit_reason = interp.run::<Self, SPEC>(self);
+        Ok((interp, checkpoint, created_address))
+    }
+
+    /// Second half of a create frame, once the init code stopped with `exit_reason`.
+    fn end_create<SPEC: Spec>(
+        &mut self,
+        mut interp: Interpreter,
+        checkpoint: JournalCheckpoint,
+        inputs: CreateInputs,
+        created_address: H160,
+        exit_reason: Return,
+    ) -> (Return, Option<H160>, Gas, Bytes) {
+        let ret = Some(created_address);
+        self.memory_used = self.memory_used.saturating_sub(interp.memory.len());
+        // code is deployed or the init code reverted, either way state changed.
+        self.static_calls.clear();
 
         // Host error if present on execution\
         let (ret, address, gas, out) = match exit_reason {
+            // Init code returned nothing: the account is created without code. There is no code
+            // to check or pay deposit for, only the empty code hash is stored.
+            return_ok!() if interp.return_range.is_empty() => {
+                self.data.journaled_state.checkpoint_commit();
+                self.data
+                    .journaled_state
+                    .set_code(created_address, Bytecode::new());
+                (Return::Continue, ret, interp.gas, Bytes::new())
+            }
             return_ok!() => {
                 let b = Bytes::new();
                 // if ok, check contract creation limit and calculate gas deduction on output len.
                 let mut bytes = interp.return_value();
 
                 // EIP-3541: Reject new contract code starting with the 0xEF byte
-                if SPEC::enabled(LONDON) && !bytes.is_empty() && bytes.first() == Some(&0xEF) {
+                if validate_bytecode(&bytes, SPEC::SPEC_ID).is_err() {
                     self.data.journaled_state.checkpoint_revert(checkpoint);
                     return (Return::CreateContractWithEF, ret, interp.gas, b);
                 }
 
                 // EIP-170: Contract code size limit
                 // By default limit is 0x6000 (~25kb)
-                if SPEC::enabled(SPURIOUS_DRAGON)
-                    && bytes.len() > self.data.env.cfg.limit_contract_code_size.unwrap_or(0x6000)
+                if SPEC::enabled(SPURIOUS_DRAGON) && bytes.len() > self.data.env.cfg.max_code_size()
                 {
                     self.data.journaled_state.checkpoint_revert(checkpoint);
                     return (Return::CreateContractLimit, ret, interp.gas, b);
                 }
-                if crate::USE_GAS {
+                if self.use_gas() {
                     let gas_for_code = bytes.len() as u64 * crate::gas::CODEDEPOSIT;
                     if !interp.gas.record_cost(gas_for_code) {
                         // record code deposit gas cost and check if we are out of gas.
@@ -524,52 +913,100 @@ impl<'a, GSPEC: Spec, DB: Database, const INSPECT: bool> EVMImpl<'a, GSPEC, DB,
 
         if INSPECT {
             self.inspector
-                .create_end(&mut self.data, inputs, ret, address, gas, out)
+                .create_end(&mut self.data, &inputs, ret, address, gas, out)
         } else {
             (ret, address, gas, out)
         }
     }
 
-    fn call_inner<SPEC: Spec>(&mut self, inputs: &mut CallInputs) -> (Return, Gas, Bytes) {
+    /// First half of a call frame, up to running the callee. Precompiles, cached STATICCALLs and
+    /// frames that fail early return their result right away.
+    fn start_call<SPEC: Spec>(
+        &mut self,
+        mut inputs: CallInputs,
+    ) -> Result<Frame, (Return, Gas, Bytes)> {
         // Call the inspector
         if INSPECT {
-            let (ret, gas, out) = self
-                .inspector
-                .call(&mut self.data, inputs, SPEC::IS_STATIC_CALL);
+            let (ret, gas, out) =
+                self.inspector
+                    .call(&mut self.data, &mut inputs, SPEC::IS_STATIC_CALL);
             if ret != Return::Continue {
-                return self.inspector.call_end(
+                return Err(self.inspector.call_end(
                     &mut self.data,
-                    inputs,
+                    &inputs,
                     gas,
                     ret,
                     out,
                     SPEC::IS_STATIC_CALL,
-                );
+                ));
             }
         }
 
         let mut gas = Gas::new(inputs.gas_limit);
+
         // Load account and get code. Account is now hot.
-        let bytecode = if let Some((bytecode, _)) = self.code(inputs.contract) {
-            bytecode
-        } else {
-            return (Return::FatalExternalError, gas, Bytes::new());
+        let bytecode = match self.analysed_code::<SPEC>(inputs.contract) {
+            Ok(bytecode) => bytecode,
+            Err(Return::FatalExternalError) => {
+                return Err((Return::FatalExternalError, gas, Bytes::new()))
+            }
+            Err(ret) => return Err(self.call_failed::<SPEC>(&inputs, ret, gas)),
+        };
+        // EIP-7702: a delegated account runs the code of the account it delegates to. Only one
+        // level is followed, the code of a delegate that is delegated itself runs as is.
+        let bytecode = match bytecode.delegation() {
+            Some(delegate) if SPEC::enabled(PRAGUE) => match self.analysed_code::<SPEC>(delegate) {
+                Ok(bytecode) => bytecode,
+                Err(Return::FatalExternalError) => {
+                    return Err((Return::FatalExternalError, gas, Bytes::new()))
+                }
+                Err(ret) => return Err(self.call_failed::<SPEC>(&inputs, ret, gas)),
+            },
+            _ => bytecode,
         };
 
         // Check depth
-        if self.data.journaled_state.depth() > interpreter::CALL_STACK_LIMIT {
+        if self.data.journaled_state.depth() > self.data.env.cfg.call_stack_limit {
             let (ret, gas, out) = (Return::CallTooDeep, gas, Bytes::new());
             if Self::INSPECT {
-                return self.inspector.call_end(
+                return Err(self.inspector.call_end(
                     &mut self.data,
-                    inputs,
+                    &inputs,
                     gas,
                     ret,
                     out,
                     SPEC::IS_STATIC_CALL,
-                );
+                ));
             } else {
-                return (ret, gas, out);
+                return Err((ret, gas, out));
+            }
+        }
+
+        // Reuse the result of an identical STATICCALL, see [crate::CfgEnv::static_call_cache].
+        let cache_key = if self.data.env.cfg.static_call_cache
+            && inputs.context.scheme == CallScheme::StaticCall
+            && !self.precompiles.contains(&inputs.contract)
+        {
+            let input_hash = H256::from_slice(Keccak256::digest(&inputs.input).as_slice());
+            Some((inputs.context.caller, inputs.contract, input_hash))
+        } else {
+            None
+        };
+        if let Some((ret, spent, out)) = cache_key.and_then(|key| self.static_calls.get(&key)) {
+            if gas.record_cost(*spent) {
+                let (ret, out) = (*ret, out.clone());
+                if INSPECT {
+                    return Err(self.inspector.call_end(
+                        &mut self.data,
+                        &inputs,
+                        gas,
+                        ret,
+                        out,
+                        SPEC::IS_STATIC_CALL,
+                    ));
+                } else {
+                    return Err((ret, gas, out));
+                }
             }
         }
 
@@ -580,6 +1017,8 @@ impl<'a, GSPEC: Spec, DB: Database, const INSPECT: bool> EVMImpl<'a, GSPEC, DB,
         if inputs.transfer.value.is_zero() {
             self.load_account(inputs.context.address);
             self.data.journaled_state.touch(&inputs.context.address);
+        } else {
+            self.static_calls.clear();
         }
 
         // Transfer value from caller to called account
@@ -592,28 +1031,30 @@ impl<'a, GSPEC: Spec, DB: Database, const INSPECT: bool> EVMImpl<'a, GSPEC, DB,
             self.data.journaled_state.checkpoint_revert(checkpoint);
             let (ret, gas, out) = (e, gas, Bytes::new());
             if Self::INSPECT {
-                return self.inspector.call_end(
+                return Err(self.inspector.call_end(
                     &mut self.data,
-                    inputs,
+                    &inputs,
                     gas,
                     ret,
                     out,
                     SPEC::IS_STATIC_CALL,
-                );
+                ));
             } else {
-                return (ret, gas, out);
+                return Err((ret, gas, out));
             }
         }
 
         // Call precompiles
-        let (ret, gas, out) = if let Some(precompile) = self.precompiles.get(&inputs.contract) {
+        if let Some(precompile) = self.precompiles.get(&inputs.contract) {
             let out = match precompile {
                 Precompile::Standard(fun) => fun(inputs.input.as_ref(), inputs.gas_limit),
                 Precompile::Custom(fun) => fun(inputs.input.as_ref(), inputs.gas_limit),
             };
-            match out {
+            let (ret, gas, out) = match out {
                 Ok(PrecompileOutput { output, cost, logs }) => {
-                    if !crate::USE_GAS || gas.record_cost(cost) {
+                    let cost =
+                        cost.saturating_mul(self.precompiles.gas_multiplier(&inputs.contract));
+                    if !self.use_gas() || gas.record_cost(cost) {
                         logs.into_iter().for_each(|l| {
                             self.data.journaled_state.log(Log {
                                 address: l.address,
@@ -632,50 +1073,186 @@ impl<'a, GSPEC: Spec, DB: Database, const INSPECT: bool> EVMImpl<'a, GSPEC, DB,
                     self.data.journaled_state.checkpoint_revert(checkpoint); //TODO check if we are discarding or reverting
                     (Return::PrecompileError, gas, Bytes::new())
                 }
-            }
-        } else {
-            // Create interpreter and execute subcall
-            let contract =
-                Contract::new_with_context::<SPEC>(inputs.input.clone(), bytecode, &inputs.context);
+            };
+            return Err(if INSPECT {
+                self.inspector.call_end(
+                    &mut self.data,
+                    &inputs,
+                    gas,
+                    ret,
+                    out,
+                    SPEC::IS_STATIC_CALL,
+                )
+            } else {
+                (ret, gas, out)
+            });
+        }
 
-            #[cfg(feature = "memory_limit")]
-            let mut interp = Interpreter::new_with_memory_limit::<SPEC>(
-                contract,
-                gas.limit(),
-                self.data.env.cfg.memory_limit,
-            );
+        // Create interpreter for the subcall. Accounts without code run the empty bytecode that
+        // stops right away, so the call succeeds with empty output, returns all of its gas and is
+        // seen by the inspector like any other call.
+        let contract =
+            Contract::new_with_context::<SPEC>(inputs.input.clone(), bytecode, &inputs.context);
 
-            #[cfg(not(feature = "memory_limit"))]
-            let mut interp = Interpreter::new::<SPEC>(contract, gas.limit());
+        let mut interp = self.new_interpreter::<SPEC>(contract, gas.limit());
 
-            if Self::INSPECT {
-                // create is always no static call.
-                self.inspector
-                    .initialize_interp(&mut interp, &mut self.data, false);
-            }
-            let exit_reason = interp.run::<Self, SPEC>(self);
-            if matches!(exit_reason, return_ok!()) {
-                self.data.journaled_state.checkpoint_commit();
-            } else {
-                self.data.journaled_state.checkpoint_revert(checkpoint);
-            }
+        if Self::INSPECT {
+            // create is always no static call.
+            self.inspector
+                .initialize_interp(&mut interp, &mut self.data, false);
+        }
+        Ok(Frame::new(
+            interp,
+            checkpoint,
+            FrameKind::Call { inputs, cache_key },
+        ))
+    }
 
-            (exit_reason, interp.gas, interp.return_value())
-        };
+    /// Interpreter of a call or create frame with the interpreter settings of [crate::CfgEnv].
+    fn new_interpreter<SPEC: Spec>(&self, contract: Contract, gas_limit: u64) -> Interpreter {
+        let cfg = &self.data.env.cfg;
+        let mut interp = Interpreter::new::<SPEC>(contract, gas_limit);
+        interp.memory_limit = cfg.memory_limit;
+        interp.gas_block_granularity = cfg.gas_block_granularity;
+        interp.disabled_opcodes = cfg.disabled_opcodes;
+        interp.disable_gas = cfg.disable_gas;
+        interp.checked_execution = cfg.checked_execution;
+        interp.fuse_opcodes = cfg.fuse_opcodes;
+        interp
+    }
+
+    /// Whether gas is metered, see [crate::CfgEnv::disable_gas].
+    fn use_gas(&self) -> bool {
+        crate::USE_GAS && !self.data.env.cfg.disable_gas
+    }
+
+    /// Code of `address`, analysed for `SPEC`. Code loaded from the database is raw, the analysed
+    /// code is put back on the loaded account so further calls to it in this transaction skip the
+    /// analysis and share its jump table. Code above [crate::CfgEnv::max_executed_code_size] is
+    /// not analysed.
+    fn analysed_code<SPEC: Spec>(&mut self, address: H160) -> Result<Bytecode, Return> {
+        let journal = &mut self.data.journaled_state;
+        let db = &mut self.data.db;
+        let error = &mut self.data.error;
+
+        let (acc, _) = journal.load_code(address, db).map_err(|e| {
+            *error = Some(e);
+            Return::FatalExternalError
+        })?;
+        let code = acc.info.code.take().unwrap();
+        if self.data.env.cfg.exceeds_executed_code_size(code.len()) {
+            acc.info.code = Some(code);
+            return Err(Return::CodeSizeLimit);
+        }
+        let code = code.to_analysed::<SPEC>();
+        acc.info.code = Some(code.clone());
+        Ok(code)
+    }
+
+    /// Result of a call that failed with `ret` before it started, seen by the inspector.
+    fn call_failed<SPEC: Spec>(
+        &mut self,
+        inputs: &CallInputs,
+        ret: Return,
+        gas: Gas,
+    ) -> (Return, Gas, Bytes) {
+        if Self::INSPECT {
+            self.inspector.call_end(
+                &mut self.data,
+                inputs,
+                gas,
+                ret,
+                Bytes::new(),
+                SPEC::IS_STATIC_CALL,
+            )
+        } else {
+            (ret, gas, Bytes::new())
+        }
+    }
+
+    /// Second half of a call frame, once the callee stopped with `exit_reason`.
+    fn end_call<SPEC: Spec>(
+        &mut self,
+        interp: Interpreter,
+        checkpoint: JournalCheckpoint,
+        inputs: CallInputs,
+        cache_key: Option<StaticCallKey>,
+        exit_reason: Return,
+    ) -> (Return, Gas, Bytes) {
+        self.memory_used = self.memory_used.saturating_sub(interp.memory.len());
+        if matches!(exit_reason, return_ok!()) {
+            self.data.journaled_state.checkpoint_commit();
+        } else {
+            self.data.journaled_state.checkpoint_revert(checkpoint);
+            // writes of the frame are undone, results cached since then are stale.
+            self.static_calls.clear();
+        }
+
+        let out = interp.return_value();
+        if let Some(key) = cache_key {
+            if matches!(exit_reason, return_ok!() | return_revert!()) {
+                self.static_calls
+                    .insert(key, (exit_reason, interp.gas.spend(), out.clone()));
+            }
+        }
+        let (ret, gas) = (exit_reason, interp.gas);
 
         if INSPECT {
             self.inspector
-                .call_end(&mut self.data, inputs, gas, ret, out, SPEC::IS_STATIC_CALL)
+                .call_end(&mut self.data, &inputs, gas, ret, out, SPEC::IS_STATIC_CALL)
         } else {
             (ret, gas, out)
         }
     }
 }
 
+/// Call or create frame whose interpreter is running or suspended in a sub call, see
+/// [EVMImpl::run_frames].
+struct Frame {
+    interp: Interpreter,
+    checkpoint: JournalCheckpoint,
+    /// Frame runs with the static spec, set for STATICCALL and everything below it.
+    is_static: bool,
+    kind: FrameKind,
+    #[cfg(feature = "tracing")]
+    span: Option<tracing::span::EnteredSpan>,
+}
+
+impl Frame {
+    fn new(interp: Interpreter, checkpoint: JournalCheckpoint, kind: FrameKind) -> Self {
+        Self {
+            interp,
+            checkpoint,
+            is_static: false,
+            kind,
+            #[cfg(feature = "tracing")]
+            span: None,
+        }
+    }
+}
+
+enum FrameKind {
+    Call {
+        inputs: CallInputs,
+        cache_key: Option<StaticCallKey>,
+    },
+    Create {
+        inputs: CreateInputs,
+        address: H160,
+    },
+}
+
+enum FrameStart {
+    /// Frame finished without running any code.
+    Done(SubCallResult),
+    Run(Box<Frame>),
+}
+
 impl<'a, GSPEC: Spec, DB: Database + 'a, const INSPECT: bool> Host
     for EVMImpl<'a, GSPEC, DB, INSPECT>
 {
     const INSPECT: bool = INSPECT;
+    const ITERATIVE_CALLS: bool = true;
     type DB = DB;
 
     fn step(&mut self, interp: &mut Interpreter, is_static: bool) -> Return {
@@ -745,8 +1322,9 @@ impl<'a, GSPEC: Spec, DB: Database + 'a, const INSPECT: bool> Host
         if is_precompile && self.data.env.cfg.perf_all_precompiles_have_balance {
             return Some((KECCAK_EMPTY, is_cold));
         }
+        // EIP-1052: non existing and EIP-161 empty accounts have zero hash. Existing accounts
+        // without code (funded EOAs) have KECCAK_EMPTY that is set as their code hash.
         if acc.is_empty() {
-            // TODO check this for pre tangerine fork
             return Some((H256::zero(), is_cold));
         }
 
@@ -755,11 +1333,16 @@ impl<'a, GSPEC: Spec, DB: Database + 'a, const INSPECT: bool> Host
 
     fn sload(&mut self, address: H160, index: U256) -> Option<(U256, bool)> {
         // account is always hot. reference on that statement https://eips.ethereum.org/EIPS/eip-2929 see `Note 2:`
-        self.data
+        let (value, is_cold) = self
+            .data
             .journaled_state
             .sload(address, index, self.data.db)
             .map_err(|e| self.data.error = Some(e))
-            .ok()
+            .ok()?;
+        if INSPECT {
+            self.inspector.sload(address, index, value, is_cold);
+        }
+        Some((value, is_cold))
     }
 
     fn sstore(
@@ -768,11 +1351,27 @@ impl<'a, GSPEC: Spec, DB: Database + 'a, const INSPECT: bool> Host
         index: U256,
         value: U256,
     ) -> Option<(U256, U256, U256, bool)> {
-        self.data
+        self.static_calls.clear();
+        let (original, current, new, is_cold) = self
+            .data
             .journaled_state
             .sstore(address, index, value, self.data.db)
             .map_err(|e| self.data.error = Some(e))
-            .ok()
+            .ok()?;
+        if INSPECT {
+            self.inspector
+                .sstore(address, index, original, current, new, is_cold);
+        }
+        Some((original, current, new, is_cold))
+    }
+
+    fn tload(&mut self, address: H160, index: U256) -> U256 {
+        self.data.journaled_state.tload(address, index)
+    }
+
+    fn tstore(&mut self, address: H160, index: U256, value: U256) {
+        self.static_calls.clear();
+        self.data.journaled_state.tstore(address, index, value)
     }
 
     fn log(&mut self, address: H160, topics: Vec<H256>, data: Bytes) {
@@ -788,14 +1387,19 @@ impl<'a, GSPEC: Spec, DB: Database + 'a, const INSPECT: bool> Host
     }
 
     fn selfdestruct(&mut self, address: H160, target: H160) -> Option<SelfDestructResult> {
-        if INSPECT {
-            self.inspector.selfdestruct();
-        }
-        self.data
+        self.static_calls.clear();
+        // the running contract is loaded.
+        let value = self.data.journaled_state.account(address).info.balance;
+        let res = self
+            .data
             .journaled_state
             .selfdestruct(address, target, self.data.db)
             .map_err(|e| self.data.error = Some(e))
-            .ok()
+            .ok()?;
+        if INSPECT {
+            self.inspector.selfdestruct(address, target, value);
+        }
+        Some(res)
     }
 
     fn create<SPEC: Spec>(
@@ -808,6 +1412,54 @@ impl<'a, GSPEC: Spec, DB: Database + 'a, const INSPECT: bool> Host
     fn call<SPEC: Spec>(&mut self, inputs: &mut CallInputs) -> (Return, Gas, Bytes) {
         self.call_inner::<SPEC>(inputs)
     }
+
+    fn jump(&mut self, from_pc: usize, to_pc: usize, taken: bool) {
+        if INSPECT {
+            self.inspector.jump(from_pc, to_pc, taken);
+        }
+    }
+
+    fn gas_block(&mut self, pc: usize, block_cost: u64, remaining: u64) {
+        if INSPECT {
+            self.inspector.gas_block(pc, block_cost, remaining);
+        }
+    }
+
+    fn memory_grow(&mut self, additional: usize) -> bool {
+        let memory_used = self.memory_used + additional;
+        if matches!(self.data.env.cfg.total_memory_limit, Some(limit) if memory_used > limit) {
+            return false;
+        }
+        self.memory_used = memory_used;
+        true
+    }
+}
+
+/// Emit an event for frames that did not succeed.
+#[cfg(feature = "tracing")]
+fn trace_exit(ret: Return, gas: &Gas) {
+    match ret {
+        return_ok!() => (),
+        Return::Revert => tracing::debug!(gas_spent = gas.spend(), "revert"),
+        Return::OutOfGas => tracing::debug!(gas_spent = gas.spend(), "out of gas"),
+        _ => tracing::debug!(reason = ?ret, gas_spent = gas.spend(), "frame failed"),
+    }
+}
+
+/// Record the created address on the `create` span and emit the exit event of a frame.
+#[cfg(feature = "tracing")]
+fn trace_result(span: &tracing::span::EnteredSpan, result: &SubCallResult) {
+    match result {
+        SubCallResult::Call { ret, gas, .. } => trace_exit(*ret, gas),
+        SubCallResult::Create {
+            ret, address, gas, ..
+        } => {
+            if let Some(address) = address {
+                span.record("address", tracing::field::debug(address));
+            }
+            trace_exit(*ret, gas);
+        }
+    }
 }
 
 /// Returns the address for the legacy `CREATE` scheme: [`CreateScheme::Create`]
@@ -836,6 +1488,10 @@ pub fn create2_address(caller: H160, code_hash: H256, salt: U256) -> H160 {
 /// EVM context host.
 pub trait Host {
     const INSPECT: bool;
+    /// CALL and CREATE opcodes stop the interpreter with [Return::SubCall] instead of calling
+    /// [Host::call] and [Host::create], see [Interpreter::run]. The host then runs nested frames
+    /// from a loop and call depth does not grow the native stack.
+    const ITERATIVE_CALLS: bool = false;
 
     type DB: Database;
 
@@ -863,6 +1519,10 @@ pub trait Host {
         index: U256,
         value: U256,
     ) -> Option<(U256, U256, U256, bool)>;
+    /// Get transient storage value of address at index.
+    fn tload(&mut self, address: H160, index: U256) -> U256;
+    /// Set transient storage value of address at index.
+    fn tstore(&mut self, address: H160, index: U256, value: U256);
     /// Create a log owned by address with given topics and data.
     fn log(&mut self, address: H160, topics: Vec<H256>, data: Bytes);
     /// Mark an address to be deleted, with funds transferred to target.
@@ -874,4 +1534,1353 @@ pub trait Host {
     ) -> (Return, Option<H160>, Gas, Bytes);
     /// Invoke a call operation.
     fn call<SPEC: Spec>(&mut self, input: &mut CallInputs) -> (Return, Gas, Bytes);
+    /// Memory of the current call frame grows by `additional` bytes. Returns false if the total
+    /// memory of all call frames would exceed the host limit.
+    fn memory_grow(&mut self, additional: usize) -> bool;
+    /// JUMP or JUMPI at `from_pc` continues at `to_pc`, see [Inspector::jump].
+    fn jump(&mut self, from_pc: usize, to_pc: usize, taken: bool);
+    /// Gas block starting at `pc` was charged, see [Inspector::gas_block].
+    fn gas_block(&mut self, pc: usize, block_cost: u64, remaining: u64);
+}
+
+#[cfg(test)]
+mod tests {
+    #![cfg_attr(feature = "no_gas_measuring", allow(unused_imports))]
+
+    use crate::{
+        db::BenchmarkDB,
+        gas,
+        journaled_state::State,
+        opcode,
+        test_utils::{evm_with_code, evm_with_db},
+        AccountInfo, Authorization, Bytecode, BytecodeState, CallInputs, CreateScheme, Database,
+        EVMData, ExecutionResult, Gas, GasBlockGranularity, InMemoryDB, Inspector, Interpreter,
+        OpcodeSet, Return, SpecId, TransactOut, TransactTo, KECCAK_EMPTY, MAX_CODE_SIZE,
+        MAX_INITCODE_SIZE,
+    };
+    use bytes::Bytes;
+    use core::str::FromStr;
+    use primitive_types::{H160, H256, U256};
+    use sha3::{Digest, Keccak256};
+    use std::sync::Arc;
+
+    #[derive(Default)]
+    struct DepthInspector {
+        max_depth: u64,
+    }
+
+    impl<DB: Database> Inspector<DB> for DepthInspector {
+        fn step(
+            &mut self,
+            _interp: &mut Interpreter,
+            data: &mut EVMData<'_, DB>,
+            _is_static: bool,
+        ) -> Return {
+            self.max_depth = self.max_depth.max(data.journaled_state.depth());
+            Return::Continue
+        }
+    }
+
+    /// Depth reached by a contract that takes 4KiB of memory and calls itself.
+    fn recursion_depth(total_memory_limit: Option<usize>) -> u64 {
+        let code = vec![
+            opcode::PUSH1,
+            0x00,
+            opcode::PUSH2,
+            0x0f,
+            0xe0,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x00,
+            opcode::PUSH1,
+            0x00,
+            opcode::PUSH1,
+            0x00,
+            opcode::PUSH1,
+            0x00,
+            opcode::PUSH1,
+            0x00,
+            opcode::ADDRESS,
+            opcode::GAS,
+            opcode::CALL,
+            opcode::STOP,
+        ];
+        let mut evm = evm_with_code(code);
+        evm.env.cfg.total_memory_limit = total_memory_limit;
+        evm.env.tx.gas_limit = 60_000;
+
+        let mut inspector = DepthInspector::default();
+        let (result, _) = evm.inspect(&mut inspector);
+        assert_eq!(result.exit_reason, Return::Stop);
+        inspector.max_depth
+    }
+
+    #[test]
+    fn test_total_memory_limit() {
+        assert!(recursion_depth(None) > 20);
+        // ten frames fit, the eleventh fails on its MSTORE.
+        assert_eq!(recursion_depth(Some(10 * 4096)), 11);
+    }
+
+    /// Exit reason of an MSTORE of the word that ends at `memory_end`.
+    fn mstore_up_to(memory_end: u32, memory_limit: Option<u64>) -> Return {
+        let mut code = vec![opcode::PUSH1, 0x00, opcode::PUSH4];
+        code.extend_from_slice(&(memory_end - 32).to_be_bytes());
+        code.extend([opcode::MSTORE, opcode::STOP]);
+        let mut evm = evm_with_code(code);
+        evm.env.cfg.memory_limit = memory_limit;
+        evm.env.tx.gas_limit = 10_000_000;
+        evm.transact().0.exit_reason
+    }
+
+    #[test]
+    fn test_memory_limit() {
+        assert_eq!(mstore_up_to(4096, Some(4096)), Return::Stop);
+        assert_eq!(mstore_up_to(4096, Some(4095)), Return::MemoryLimitOOG);
+        assert_eq!(mstore_up_to(4096 + 32, Some(4096)), Return::MemoryLimitOOG);
+        // a MiB is only bounded by gas without a limit.
+        assert_eq!(mstore_up_to(1 << 20, None), Return::Stop);
+    }
+
+    #[test]
+    fn test_call_depth_limit() {
+        // calls itself with all of its gas until the calls fail with CallTooDeep.
+        let code = vec![
+            opcode::PUSH1,
+            0x00,
+            opcode::DUP1,
+            opcode::DUP1,
+            opcode::DUP1,
+            opcode::DUP1,
+            opcode::ADDRESS,
+            opcode::GAS,
+            opcode::CALL,
+            opcode::STOP,
+        ];
+        let mut evm = evm_with_code(code);
+        evm.env.tx.gas_limit = u64::MAX / 2;
+
+        // frames are not nested on the native stack, the limit is reached without overflowing it.
+        let mut inspector = DepthInspector::default();
+        let (result, _) = evm.inspect(&mut inspector);
+        assert_eq!(result.exit_reason, Return::Stop);
+        assert_eq!(inspector.max_depth, 1025);
+    }
+
+    /// Records the result of every call, in the order they end.
+    #[derive(Default)]
+    struct CallResults {
+        results: Vec<Return>,
+    }
+
+    impl<DB: Database> Inspector<DB> for CallResults {
+        fn call_end(
+            &mut self,
+            _data: &mut EVMData<'_, DB>,
+            _inputs: &CallInputs,
+            remaining_gas: Gas,
+            ret: Return,
+            out: Bytes,
+            _is_static: bool,
+        ) -> (Return, Gas, Bytes) {
+            self.results.push(ret);
+            (ret, remaining_gas, out)
+        }
+    }
+
+    /// Results of the calls of a contract that calls itself `calls` times.
+    fn nested_calls(call_stack_limit: u64, calls: u64) -> Vec<Return> {
+        // calls itself with the calldata word decremented until it is zero.
+        let code = vec![
+            opcode::PUSH1,
+            0x00,
+            opcode::CALLDATALOAD,
+            opcode::DUP1,
+            opcode::ISZERO,
+            opcode::PUSH1,
+            0x1d,
+            opcode::JUMPI,
+            opcode::PUSH1,
+            0x01,
+            opcode::SWAP1,
+            opcode::SUB,
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x00, // out len
+            opcode::PUSH1,
+            0x00, // out offset
+            opcode::PUSH1,
+            0x20, // in len
+            opcode::PUSH1,
+            0x00, // in offset
+            opcode::PUSH1,
+            0x00, // value
+            opcode::ADDRESS,
+            opcode::GAS,
+            opcode::CALL,
+            opcode::STOP,
+            opcode::JUMPDEST,
+            opcode::STOP,
+        ];
+        let mut evm = evm_with_code(code);
+        evm.env.cfg.call_stack_limit = call_stack_limit;
+        let mut data = [0; 32];
+        U256::from(calls).to_big_endian(&mut data);
+        evm.env.tx.data = Bytes::from(data.to_vec());
+        evm.env.tx.gas_limit = 1_000_000;
+
+        let mut inspector = CallResults::default();
+        let (result, _) = evm.inspect(&mut inspector);
+        assert_eq!(result.exit_reason, Return::Stop);
+        inspector.results
+    }
+
+    #[test]
+    fn test_call_stack_limit() {
+        assert_eq!(crate::CfgEnv::default().call_stack_limit, 1024);
+        // the transaction's call and three nested ones, innermost first.
+        assert_eq!(nested_calls(3, 3), vec![Return::Stop; 4]);
+        let mut results = vec![Return::CallTooDeep];
+        results.extend([Return::Stop; 4]);
+        assert_eq!(nested_calls(3, 4), results);
+    }
+
+    /// Counts executed call frames.
+    #[derive(Default)]
+    struct FrameCounter {
+        frames: usize,
+    }
+
+    impl<DB: Database> Inspector<DB> for FrameCounter {
+        fn initialize_interp(
+            &mut self,
+            _interp: &mut Interpreter,
+            _data: &mut EVMData<'_, DB>,
+            _is_static: bool,
+        ) -> Return {
+            self.frames += 1;
+            Return::Continue
+        }
+    }
+
+    /// Records the jump table of every call frame.
+    #[derive(Default)]
+    struct JumpTables {
+        tables: Vec<*const ()>,
+    }
+
+    impl<DB: Database> Inspector<DB> for JumpTables {
+        fn initialize_interp(
+            &mut self,
+            interp: &mut Interpreter,
+            _data: &mut EVMData<'_, DB>,
+            _is_static: bool,
+        ) -> Return {
+            let analysis = &interp.contract.bytecode.jumptable().analysis;
+            self.tables.push(Arc::as_ptr(analysis).cast());
+            Return::Continue
+        }
+    }
+
+    #[test]
+    fn test_code_analysed_once() {
+        // without input calls itself twice with one byte of input, with input it stops.
+        let call = [
+            opcode::PUSH1,
+            0x00, // out len
+            opcode::PUSH1,
+            0x00, // out offset
+            opcode::PUSH1,
+            0x01, // in len
+            opcode::PUSH1,
+            0x00, // in offset
+            opcode::PUSH1,
+            0x00, // value
+            opcode::ADDRESS,
+            opcode::GAS,
+            opcode::CALL,
+            opcode::POP,
+        ];
+        let mut code = vec![opcode::CALLDATASIZE, opcode::PUSH1, 0x00, opcode::JUMPI];
+        code.extend_from_slice(&call);
+        code.extend_from_slice(&call);
+        code.push(opcode::STOP);
+        code[2] = code.len() as u8;
+        code.extend_from_slice(&[opcode::JUMPDEST, opcode::STOP]);
+
+        let mut evm = evm_with_code(code);
+        evm.env.tx.gas_limit = 1_000_000;
+
+        let mut inspector = JumpTables::default();
+        let (result, state) = evm.inspect(&mut inspector);
+        assert_eq!(result.exit_reason, Return::Stop);
+        // all three frames run on the jump table of the first one.
+        assert_eq!(inspector.tables.len(), 3);
+        assert!(inspector.tables.iter().all(|t| *t == inspector.tables[0]));
+        let code = state[&H160::zero()].info.code.as_ref().unwrap();
+        assert!(matches!(code.state(), BytecodeState::Analysed { .. }));
+    }
+
+    /// Contract that STATICCALLs itself twice with the same input and returns the second result,
+    /// optionally with an SSTORE in between. Called with input it returns 42.
+    fn repeated_static_call(cache: bool, write_between: bool) -> (usize, Bytes) {
+        let static_call = [
+            opcode::PUSH1,
+            0x20, // out len
+            opcode::PUSH1,
+            0x20, // out offset
+            opcode::PUSH1,
+            0x01, // in len
+            opcode::PUSH1,
+            0x00, // in offset
+            opcode::ADDRESS,
+            opcode::GAS,
+            opcode::STATICCALL,
+            opcode::POP,
+        ];
+        let sstore = [opcode::PUSH1, 0x01, opcode::PUSH1, 0x00, opcode::SSTORE];
+        let mut code = vec![opcode::CALLDATASIZE, opcode::PUSH1, 0x00, opcode::JUMPI];
+        code.extend_from_slice(&[opcode::PUSH1, 0x01, opcode::PUSH1, 0x00, opcode::MSTORE8]);
+        code.extend_from_slice(&static_call);
+        if write_between {
+            code.extend_from_slice(&sstore);
+        }
+        code.extend_from_slice(&static_call);
+        code.extend_from_slice(&[opcode::PUSH1, 0x20, opcode::PUSH1, 0x20, opcode::RETURN]);
+        code[2] = code.len() as u8;
+        code.extend_from_slice(&[
+            opcode::JUMPDEST,
+            opcode::PUSH1,
+            0x2a,
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x20,
+            opcode::PUSH1,
+            0x00,
+            opcode::RETURN,
+        ]);
+
+        let mut evm = evm_with_code(code);
+        evm.env.cfg.static_call_cache = cache;
+        evm.env.tx.gas_limit = 1_000_000;
+
+        let mut inspector = FrameCounter::default();
+        let (result, _) = evm.inspect(&mut inspector);
+        assert_eq!(result.exit_reason, Return::Return);
+        match result.out {
+            TransactOut::Call(out) => (inspector.frames, out),
+            _ => unreachable!("transaction is a call"),
+        }
+    }
+
+    #[test]
+    fn test_static_call_cache() {
+        let mut expected = [0u8; 32];
+        U256::from(42).to_big_endian(&mut expected);
+        let expected = Bytes::from(expected.to_vec());
+
+        assert_eq!(repeated_static_call(false, false), (3, expected.clone()));
+        // second call is served from the cache.
+        assert_eq!(repeated_static_call(true, false), (2, expected.clone()));
+        // SSTORE in between invalidates the cache.
+        assert_eq!(repeated_static_call(true, true), (3, expected));
+    }
+
+    fn ecrecover_gas_used(multiplier: Option<u64>) -> u64 {
+        let ecrecover = H160::from_low_u64_be(1);
+        let mut evm = evm_with_db(BenchmarkDB::new_bytecode(Bytecode::new()));
+        if let Some(multiplier) = multiplier {
+            evm.env.cfg.precompile_gas_multipliers = vec![(ecrecover, multiplier)];
+        }
+        evm.env.tx.transact_to = TransactTo::Call(ecrecover);
+
+        let (result, _) = evm.transact();
+        assert_eq!(result.exit_reason, Return::Continue);
+        result.gas_used
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_precompile_gas_multiplier() {
+        assert_eq!(ecrecover_gas_used(None), 21_000 + 3_000);
+        assert_eq!(ecrecover_gas_used(Some(2)), 21_000 + 6_000);
+    }
+
+    fn deploy(init_code: Vec<u8>) -> (ExecutionResult, State, H160) {
+        deploy_at(SpecId::LATEST, 100_000, init_code)
+    }
+
+    fn deploy_at(
+        spec_id: SpecId,
+        gas_limit: u64,
+        init_code: Vec<u8>,
+    ) -> (ExecutionResult, State, H160) {
+        let caller = H160::repeat_byte(0x10);
+        let mut evm = evm_with_db(BenchmarkDB::new_bytecode(Bytecode::new()));
+        evm.env.cfg.spec_id = spec_id;
+        evm.env.tx.transact_to = TransactTo::create();
+        evm.env.tx.data = Bytes::from(init_code);
+        evm.env.tx.gas_limit = gas_limit;
+
+        let (result, state) = evm.transact();
+        (result, state, crate::create_address(caller, 0))
+    }
+
+    #[test]
+    fn test_deploy_empty_code() {
+        let (result, state, address) = deploy(vec![
+            opcode::PUSH1,
+            0x00,
+            opcode::PUSH1,
+            0x00,
+            opcode::RETURN,
+        ]);
+        assert_eq!(result.exit_reason, Return::Continue);
+        assert!(
+            matches!(result.out, TransactOut::Create(out, Some(a)) if out.is_empty() && a == address)
+        );
+        let account = &state[&address];
+        assert_eq!(account.info.nonce, 1);
+        assert_eq!(account.info.code_hash, KECCAK_EMPTY);
+        assert!(account.info.code.as_ref().unwrap().is_empty());
+
+        // a reverted deploy is reported as such and leaves no account behind.
+        let (result, state, address) = deploy(vec![
+            opcode::PUSH1,
+            0x00,
+            opcode::PUSH1,
+            0x00,
+            opcode::REVERT,
+        ]);
+        assert_eq!(result.exit_reason, Return::Revert);
+        assert!(!state.contains_key(&address));
+    }
+
+    #[test]
+    fn test_deploy_eof_container() {
+        // returns the minimal container with a code section of one STOP.
+        let container = [0xEF, 0x00, 0x01, 0x01, 0x00, 0x01, 0x00, opcode::STOP];
+        let mut init_code = vec![opcode::PUSH8];
+        init_code.extend_from_slice(&container);
+        init_code.extend([
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x08,
+            opcode::PUSH1,
+            0x18,
+            opcode::RETURN,
+        ]);
+
+        // EIP-3541 keeps rejecting it in every fork.
+        let (result, state, address) = deploy_at(SpecId::LATEST, 100_000, init_code.clone());
+        assert_eq!(result.exit_reason, Return::CreateContractWithEF);
+        assert!(!state.contains_key(&address));
+
+        let (result, state, address) = deploy_at(SpecId::EXPERIMENTAL_EOF, 100_000, init_code);
+        assert_eq!(result.exit_reason, Return::Continue);
+        let code = state[&address].info.code.clone().unwrap();
+        assert_eq!(
+            code.bytes().slice(..code.len()),
+            Bytes::copy_from_slice(&container)
+        );
+
+        // EOF code is not executed, the 0xEF magic byte is an undefined opcode.
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(address, AccountInfo::new(U256::zero(), 1, code));
+        let mut evm = evm_with_db(db);
+        evm.env.cfg.spec_id = SpecId::EXPERIMENTAL_EOF;
+        evm.env.tx.transact_to = TransactTo::Call(address);
+        let (result, _) = evm.transact();
+        assert_eq!(result.exit_reason, Return::OpcodeNotFound);
+    }
+
+    /// Records call results and the gas remaining before every opcode of the top frame.
+    #[derive(Default)]
+    struct CallRecorder {
+        calls: Vec<(H160, Return, u64, u64)>,
+        gas: Vec<(u8, u64)>,
+    }
+
+    impl<DB: Database> Inspector<DB> for CallRecorder {
+        fn step(
+            &mut self,
+            interp: &mut Interpreter,
+            data: &mut EVMData<'_, DB>,
+            _is_static: bool,
+        ) -> Return {
+            if data.journaled_state.depth() == 1 {
+                self.gas
+                    .push((interp.current_opcode(), interp.gas.remaining()));
+            }
+            Return::Continue
+        }
+
+        fn call_end(
+            &mut self,
+            _data: &mut EVMData<'_, DB>,
+            inputs: &CallInputs,
+            remaining_gas: Gas,
+            ret: Return,
+            out: Bytes,
+            _is_static: bool,
+        ) -> (Return, Gas, Bytes) {
+            self.calls.push((
+                inputs.contract,
+                ret,
+                remaining_gas.limit(),
+                remaining_gas.remaining(),
+            ));
+            (ret, remaining_gas, out)
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_call_account_without_code() {
+        let contract = H160::repeat_byte(0xaa);
+        let eoa = H160::repeat_byte(0xbb);
+        let mut code = vec![
+            opcode::PUSH1,
+            0x00, // out len
+            opcode::PUSH1,
+            0x00, // out offset
+            opcode::PUSH1,
+            0x00, // in len
+            opcode::PUSH1,
+            0x00, // in offset
+            opcode::PUSH1,
+            0x01, // value
+            opcode::PUSH20,
+        ];
+        code.extend_from_slice(eoa.as_bytes());
+        code.extend_from_slice(&[
+            opcode::PUSH2,
+            0x10,
+            0x00, // gas
+            opcode::CALL,
+            opcode::RETURNDATASIZE,
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x20,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x40,
+            opcode::PUSH1,
+            0x00,
+            opcode::RETURN,
+        ]);
+
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            contract,
+            AccountInfo {
+                balance: U256::from(1),
+                code: Some(Bytecode::new_raw(code.into())),
+                ..Default::default()
+            },
+        );
+        db.insert_account_info(
+            eoa,
+            AccountInfo {
+                balance: U256::from(1),
+                ..Default::default()
+            },
+        );
+        let mut evm = evm_with_db(db);
+        evm.env.cfg.gas_block_granularity = GasBlockGranularity::PerOpcode;
+        evm.env.tx.transact_to = TransactTo::Call(contract);
+
+        let mut recorder = CallRecorder::default();
+        let (result, state) = evm.inspect(&mut recorder);
+        assert_eq!(result.exit_reason, Return::Return);
+
+        // returndatasize is zero and the call succeeded.
+        let mut expected = vec![0u8; 64];
+        expected[63] = 1;
+        assert!(matches!(result.out, TransactOut::Call(out) if out.to_vec() == expected));
+        assert_eq!(state[&eoa].info.balance, U256::from(2));
+
+        // the call is seen by the inspector and gives back all gas it got, including the stipend.
+        assert_eq!(
+            recorder.calls[0],
+            (eoa, Return::Stop, 0x1000 + 2300, 0x1000 + 2300)
+        );
+
+        // cold account access and value transfer, minus the unused stipend.
+        let call = recorder
+            .gas
+            .iter()
+            .position(|(opcode, _)| *opcode == opcode::CALL)
+            .unwrap();
+        let spent = recorder.gas[call].1 - recorder.gas[call + 1].1;
+        assert_eq!(spent, 2600 + 9000 - 2300);
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_gas_breakdown() {
+        // clears slot 0 for a refund.
+        let code = vec![opcode::PUSH1, 0x00, opcode::PUSH1, 0x00, opcode::SSTORE];
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            H160::zero(),
+            AccountInfo {
+                code: Some(Bytecode::new_raw(Bytes::from(code))),
+                ..Default::default()
+            },
+        );
+        db.insert_account_storage(H160::zero(), U256::zero(), U256::one())
+            .unwrap();
+
+        let mut evm = evm_with_db(db);
+        evm.env.tx.data = Bytes::from(vec![0x00, 0x01]);
+
+        let (result, _) = evm.transact();
+        assert_eq!(result.exit_reason, Return::Stop);
+        let breakdown = result.gas_breakdown.unwrap();
+        assert_eq!(breakdown.intrinsic, 21_000 + 4 + 16);
+        assert_eq!(breakdown.refunded, result.gas_refunded);
+        assert!(breakdown.refunded > 0);
+        assert_eq!(breakdown.total(), result.gas_used);
+        assert_eq!(breakdown.blob, 0);
+
+        // blob gas is reported apart from the charged gas.
+        evm.env.tx.blob_hashes = vec![H256::repeat_byte(0x01), H256::repeat_byte(0x02)];
+        let (result, _) = evm.transact();
+        let breakdown = result.gas_breakdown.unwrap();
+        assert_eq!(breakdown.blob, 2 * 131072);
+        assert_eq!(breakdown.total(), result.gas_used);
+        evm.env.cfg.spec_id = SpecId::SHANGHAI;
+        let (result, _) = evm.transact();
+        assert_eq!(result.gas_breakdown.unwrap().blob, 0);
+
+        // rejected before execution.
+        evm.env.tx.gas_limit = 21_000;
+        let (result, _) = evm.transact();
+        assert_eq!(result.exit_reason, Return::OutOfGas);
+        assert_eq!(result.gas_breakdown, None);
+    }
+
+    /// Transaction clearing storage slots `0..slots`, all set to one before.
+    fn clear_slots(spec_id: SpecId, slots: u8) -> ExecutionResult {
+        let mut code = Vec::new();
+        for slot in 0..slots {
+            code.extend([opcode::PUSH1, 0x00, opcode::PUSH1, slot, opcode::SSTORE]);
+        }
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            H160::zero(),
+            AccountInfo {
+                code: Some(Bytecode::new_raw(Bytes::from(code))),
+                ..Default::default()
+            },
+        );
+        for slot in 0..slots {
+            db.insert_account_storage(H160::zero(), U256::from(slot), U256::one())
+                .unwrap();
+        }
+
+        let mut evm = evm_with_db(db);
+        evm.env.cfg.spec_id = spec_id;
+
+        let (result, _) = evm.transact();
+        assert_eq!(result.exit_reason, Return::Stop);
+        result
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_refund_cap() {
+        // two pushes and a cold SSTORE reset per slot.
+        let spent = |slots: u64| 21_000 + slots * (3 + 3 + 5000);
+
+        // London refunds 4800 per cleared slot, capped at a fifth of the spent gas.
+        let result = clear_slots(SpecId::LONDON, 1);
+        assert_eq!(result.gas_refunded, 4800);
+        assert_eq!(result.gas_used, spent(1) - 4800);
+        let result = clear_slots(SpecId::LONDON, 3);
+        assert_eq!(result.gas_refunded, spent(3) / 5);
+        assert_eq!(result.gas_used, spent(3) - spent(3) / 5);
+
+        // Berlin refunds 15000 per cleared slot, capped at half of the spent gas.
+        let result = clear_slots(SpecId::BERLIN, 1);
+        assert_eq!(result.gas_refunded, spent(1) / 2);
+        assert_eq!(result.gas_used, spent(1) - spent(1) / 2);
+    }
+
+    #[test]
+    fn test_set_refund() {
+        let mut gas = Gas::new(100);
+        gas.record_refund(4800);
+        gas.record_refund(-4800 - 2800);
+        assert_eq!(gas.refunded(), -2800);
+        gas.set_refund(100);
+        assert_eq!(gas.refunded(), 100);
+    }
+
+    #[test]
+    fn test_disabled_opcodes() {
+        let (caller, callee) = (H160::from_low_u64_be(0xa0), H160::from_low_u64_be(0xb0));
+        let calling = vec![
+            opcode::PUSH1,
+            0x00, // out len
+            opcode::PUSH1,
+            0x00, // out offset
+            opcode::PUSH1,
+            0x00, // in len
+            opcode::PUSH1,
+            0x00, // in offset
+            opcode::PUSH1,
+            0x00, // value
+            opcode::PUSH1,
+            0xb0, // address
+            opcode::GAS,
+            opcode::CALL,
+            opcode::STOP,
+        ];
+        let mut db = InMemoryDB::default();
+        for (address, code) in [
+            (caller, calling),
+            (callee, vec![opcode::PUSH1, 0x01, opcode::POP, opcode::STOP]),
+        ] {
+            db.insert_account_info(
+                address,
+                AccountInfo {
+                    code: Some(Bytecode::new_raw(Bytes::from(code))),
+                    ..Default::default()
+                },
+            );
+        }
+
+        let mut evm = evm_with_db(db);
+        evm.env.cfg.disabled_opcodes = OpcodeSet::new(&[opcode::CALL]);
+
+        evm.env.tx.transact_to = TransactTo::Call(caller);
+        let (result, _) = evm.transact();
+        assert_eq!(result.exit_reason, Return::OpcodeDisabled);
+
+        evm.env.tx.transact_to = TransactTo::Call(callee);
+        let (result, _) = evm.transact();
+        assert_eq!(result.exit_reason, Return::Stop);
+
+        evm.env.cfg.disabled_opcodes = OpcodeSet::default();
+        evm.env.tx.transact_to = TransactTo::Call(caller);
+        let (result, _) = evm.transact();
+        assert_eq!(result.exit_reason, Return::Stop);
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_opcode_handlers() {
+        fn push_answer(interp: &mut Interpreter) -> Return {
+            match interp.stack.push(U256::from(42)) {
+                Ok(()) if interp.gas.record_cost(gas::BASE) => Return::Continue,
+                Ok(()) => Return::OutOfGas,
+                Err(ret) => ret,
+            }
+        }
+
+        // 0xb0 then return the top of the stack.
+        let code = vec![
+            0xb0,
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x20,
+            opcode::PUSH1,
+            0x00,
+            opcode::RETURN,
+        ];
+        let mut evm = evm_with_code(code);
+
+        let (result, _) = evm.transact();
+        assert_eq!(result.exit_reason, Return::OpcodeNotFound);
+
+        evm.env.cfg.opcode_handlers = vec![(0xb0, push_answer)];
+        let (result, _) = evm.transact();
+        assert_eq!(result.exit_reason, Return::Return);
+        let TransactOut::Call(out) = result.out else {
+            panic!("not a call")
+        };
+        assert_eq!(U256::from_big_endian(&out), U256::from(42));
+        // 0xb0, three PUSH1, MSTORE and a word of memory.
+        assert_eq!(result.gas_used, 21_000 + 2 + 3 * 3 + 3 + 3);
+
+        // handlers of defined opcodes are not used.
+        evm.env.cfg.opcode_handlers = vec![(opcode::MSTORE, |_| Return::InvalidOpcode)];
+        let (result, _) = evm.transact();
+        assert_eq!(result.exit_reason, Return::OpcodeNotFound);
+    }
+
+    /// Call `authority` with one authorization to delegate it to a contract that stores 42.
+    fn delegated_call(spec_id: SpecId, authorization_nonce: u64) -> (ExecutionResult, State, H160) {
+        let authority = H160::repeat_byte(0xa0);
+        let delegate = H160::repeat_byte(0xd0);
+        let code = vec![opcode::PUSH1, 0x2a, opcode::PUSH1, 0x00, opcode::SSTORE];
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            delegate,
+            AccountInfo {
+                code: Some(Bytecode::new_raw(code.into())),
+                ..Default::default()
+            },
+        );
+        db.insert_account_info(
+            authority,
+            AccountInfo {
+                balance: U256::from(1),
+                ..Default::default()
+            },
+        );
+
+        let mut evm = evm_with_db(db);
+        evm.env.cfg.spec_id = spec_id;
+        evm.env.tx.transact_to = TransactTo::Call(authority);
+        evm.env.tx.authorization_list = vec![Authorization {
+            chain_id: U256::zero(),
+            address: delegate,
+            nonce: authorization_nonce,
+            authority: Some(authority),
+        }];
+        let (result, state) = evm.transact();
+        (result, state, authority)
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_authorization_delegates_code() {
+        let (result, state, authority) = delegated_call(SpecId::PRAGUE, 0);
+        assert_eq!(result.exit_reason, Return::Stop);
+        let breakdown = result.gas_breakdown.unwrap();
+        assert_eq!(breakdown.intrinsic, 21000 + 25000);
+
+        // delegated code writes to the storage of the authority.
+        let account = &state[&authority];
+        assert_eq!(
+            account.storage[&U256::zero()].present_value(),
+            U256::from(42)
+        );
+        assert_eq!(account.info.nonce, 1);
+        let code = account.info.code.as_ref().unwrap();
+        assert_eq!(code.delegation(), Some(H160::repeat_byte(0xd0)));
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_authorization_with_wrong_nonce_is_skipped() {
+        let (result, state, authority) = delegated_call(SpecId::PRAGUE, 1);
+        assert_eq!(result.exit_reason, Return::Stop);
+        // still paid for.
+        assert_eq!(result.gas_breakdown.unwrap().intrinsic, 21000 + 25000);
+        let account = &state[&authority];
+        assert!(account.storage.is_empty());
+        assert_eq!(account.info.nonce, 0);
+        assert_eq!(account.info.code_hash, KECCAK_EMPTY);
+
+        let (result, _, _) = delegated_call(SpecId::MERGE, 0);
+        assert_eq!(result.exit_reason, Return::InvalidAuthorizationList);
+    }
+
+    /// Call `target` from a contract that stores the CALL result at slot 0. `target` delegates
+    /// to `delegate`, which stores 42 at slot 0 unless `delegate_code` is given.
+    fn call_delegated(delegate_code: Option<Bytecode>) -> (State, H160, H160, H160) {
+        let caller = H160::repeat_byte(0xc0);
+        let target = H160::repeat_byte(0xa0);
+        let delegate = H160::repeat_byte(0xd0);
+        let mut code = vec![
+            opcode::PUSH1,
+            0x00,
+            opcode::DUP1,
+            opcode::DUP1,
+            opcode::DUP1,
+        ];
+        code.extend([opcode::DUP1, opcode::PUSH20]);
+        code.extend(target.as_bytes());
+        code.extend([
+            opcode::GAS,
+            opcode::CALL,
+            opcode::PUSH1,
+            0x00,
+            opcode::SSTORE,
+        ]);
+        let delegate_code = delegate_code.unwrap_or_else(|| {
+            let code = vec![opcode::PUSH1, 0x2a, opcode::PUSH1, 0x00, opcode::SSTORE];
+            Bytecode::new_raw(code.into())
+        });
+
+        let mut db = InMemoryDB::default();
+        for (address, code) in [
+            (caller, Bytecode::new_raw(code.into())),
+            (target, Bytecode::new_delegation(delegate)),
+            (delegate, delegate_code),
+        ] {
+            db.insert_account_info(
+                address,
+                AccountInfo {
+                    code: Some(code),
+                    ..Default::default()
+                },
+            );
+        }
+
+        let mut evm = evm_with_db(db);
+        evm.env.cfg.spec_id = SpecId::PRAGUE;
+        evm.env.tx.transact_to = TransactTo::Call(caller);
+        // enough for the SSTORE after a failed call consumed all gas it was given.
+        evm.env.tx.gas_limit = 2_000_000;
+        let (result, state) = evm.transact();
+        assert_eq!(result.exit_reason, Return::Stop);
+        (state, caller, target, delegate)
+    }
+
+    #[test]
+    fn test_call_delegated_account() {
+        let (state, caller, target, delegate) = call_delegated(None);
+        let slot = |address: H160| state[&address].storage[&U256::zero()].present_value();
+        assert_eq!(slot(caller), U256::one());
+        // delegate code ran against the storage of the delegated account.
+        assert_eq!(slot(target), U256::from(42));
+        assert!(!state.contains_key(&delegate));
+    }
+
+    #[test]
+    fn test_call_delegation_chain_is_not_followed() {
+        let end = H160::repeat_byte(0xe0);
+        let (state, caller, _, _) = call_delegated(Some(Bytecode::new_delegation(end)));
+        // the designator of the delegate is executed as code and fails on 0xef.
+        let caller_slot = state[&caller].storage[&U256::zero()].present_value();
+        assert_eq!(caller_slot, U256::zero());
+    }
+
+    /// Run `code` at address zero under `spec_id`.
+    fn transient_storage(spec_id: SpecId, code: Vec<u8>) -> (ExecutionResult, State) {
+        let mut evm = evm_with_code(code);
+        evm.env.cfg.spec_id = spec_id;
+        evm.transact()
+    }
+
+    #[test]
+    fn test_transient_storage() {
+        // sstore(1, tload(1)), tstore(1, 42), sstore(0, tload(1))
+        let code = vec![
+            opcode::PUSH1,
+            0x01,
+            opcode::TLOAD,
+            opcode::PUSH1,
+            0x01,
+            opcode::SSTORE,
+            opcode::PUSH1,
+            0x2a,
+            opcode::PUSH1,
+            0x01,
+            opcode::TSTORE,
+            opcode::PUSH1,
+            0x01,
+            opcode::TLOAD,
+            opcode::PUSH1,
+            0x00,
+            opcode::SSTORE,
+            opcode::STOP,
+        ];
+        // transient storage of the first transaction is gone in the second one.
+        for _ in 0..2 {
+            let (result, state) = transient_storage(SpecId::CANCUN, code.clone());
+            assert_eq!(result.exit_reason, Return::Stop);
+            let storage = &state[&H160::zero()].storage;
+            assert_eq!(storage[&U256::zero()].present_value(), U256::from(42));
+            assert_eq!(storage[&U256::one()].present_value(), U256::zero());
+        }
+
+        let (result, _) = transient_storage(SpecId::MERGE, code);
+        assert_eq!(result.exit_reason, Return::NotActivated);
+    }
+
+    #[test]
+    fn test_transient_storage_reverted_call() {
+        // tstore(1, 7) and call itself with one byte of calldata, the call does tstore(1, 42)
+        // and reverts. Then sstore(0, tload(1)).
+        let code = vec![
+            opcode::CALLDATASIZE,
+            opcode::PUSH1,
+            0x1e,
+            opcode::JUMPI,
+            opcode::PUSH1,
+            0x07,
+            opcode::PUSH1,
+            0x01,
+            opcode::TSTORE,
+            opcode::PUSH1,
+            0x00, // out len
+            opcode::PUSH1,
+            0x00, // out offset
+            opcode::PUSH1,
+            0x01, // in len
+            opcode::PUSH1,
+            0x00, // in offset
+            opcode::PUSH1,
+            0x00, // value
+            opcode::ADDRESS,
+            opcode::GAS,
+            opcode::CALL,
+            opcode::POP,
+            opcode::PUSH1,
+            0x01,
+            opcode::TLOAD,
+            opcode::PUSH1,
+            0x00,
+            opcode::SSTORE,
+            opcode::STOP,
+            opcode::JUMPDEST,
+            opcode::PUSH1,
+            0x2a,
+            opcode::PUSH1,
+            0x01,
+            opcode::TSTORE,
+            opcode::PUSH1,
+            0x00,
+            opcode::DUP1,
+            opcode::REVERT,
+        ];
+        let (result, state) = transient_storage(SpecId::CANCUN, code);
+        assert_eq!(result.exit_reason, Return::Stop);
+        let slot = state[&H160::zero()].storage[&U256::zero()].present_value();
+        assert_eq!(slot, U256::from(7));
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_disable_gas() {
+        // count down from 1000, then CALL an empty account and store its result at slot 0.
+        let code = vec![
+            opcode::PUSH2,
+            0x03,
+            0xe8,
+            opcode::JUMPDEST,
+            opcode::PUSH1,
+            0x01,
+            opcode::SWAP1,
+            opcode::SUB,
+            opcode::DUP1,
+            opcode::PUSH1,
+            0x03,
+            opcode::JUMPI,
+            opcode::POP,
+            opcode::PUSH1,
+            0x00, // out len
+            opcode::PUSH1,
+            0x00, // out offset
+            opcode::PUSH1,
+            0x00, // in len
+            opcode::PUSH1,
+            0x00, // in offset
+            opcode::PUSH1,
+            0x00, // value
+            opcode::PUSH1,
+            0xff,
+            opcode::GAS,
+            opcode::CALL,
+            opcode::PUSH1,
+            0x00,
+            opcode::SSTORE,
+            opcode::STOP,
+        ];
+        let mut evm = evm_with_code(code);
+        evm.env.tx.gas_limit = 21_000 + 1_000;
+        let (result, _) = evm.transact();
+        assert_eq!(result.exit_reason, Return::OutOfGas);
+
+        evm.env.cfg.disable_gas = true;
+        let (result, state) = evm.transact();
+        assert_eq!(result.exit_reason, Return::Stop);
+        assert_eq!(result.gas_used, 0);
+        let slot = state[&H160::zero()].storage[&U256::zero()].present_value();
+        assert_eq!(slot, U256::one());
+    }
+
+    #[test]
+    fn test_create_address() {
+        let address = |hex: &str| H160::from_str(hex).unwrap();
+        let deployer = address("6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0");
+        for (nonce, expected) in [
+            (0, "cd234a471b72ba2f1ccf0a70fcaba648a5eecd8d"),
+            (1, "343c43a37d37dff08ae8c4a11544c718abb4fcf8"),
+            (2, "f778b86fa74e846c4f0a1fbd1335fe81c00a0c91"),
+        ] {
+            assert_eq!(crate::create_address(deployer, nonce), address(expected));
+        }
+    }
+
+    #[test]
+    fn test_create2_address() {
+        // examples of EIP-1014.
+        let address = |hex: &str| H160::from_str(hex).unwrap();
+        let salt = |hex: &str| U256::from_str(hex).unwrap();
+        let code_hash = |code: &[u8]| H256::from_slice(&Keccak256::digest(code));
+        for (deployer, salt, code, expected) in [
+            (
+                "0000000000000000000000000000000000000000",
+                salt("0"),
+                vec![0x00],
+                "4d1a2e2bb4f88f0250f26ffff098b0b30b26bf38",
+            ),
+            (
+                "deadbeef00000000000000000000000000000000",
+                salt("0"),
+                vec![0x00],
+                "b928f69bb1d91cd65274e3c79d8986362984fda3",
+            ),
+            (
+                "deadbeef00000000000000000000000000000000",
+                salt("000000000000000000000000feed000000000000000000000000000000000000"),
+                vec![0x00],
+                "d04116cdd17bebe565eb2422f2497e06cc1c9833",
+            ),
+            (
+                "0000000000000000000000000000000000000000",
+                salt("0"),
+                vec![0xde, 0xad, 0xbe, 0xef],
+                "70f2b2914a2a4b783faefb75f459a580616fcb5e",
+            ),
+            (
+                "00000000000000000000000000000000deadbeef",
+                salt("cafebabe"),
+                vec![0xde, 0xad, 0xbe, 0xef],
+                "60f3f640a8508fc6a86d45df051962668e1e8ac7",
+            ),
+            (
+                "00000000000000000000000000000000deadbeef",
+                salt("cafebabe"),
+                [0xde, 0xad, 0xbe, 0xef].repeat(11),
+                "1d8bfdc5d46dc4f61d6b6115972536ebe6a8854c",
+            ),
+            (
+                "0000000000000000000000000000000000000000",
+                salt("0"),
+                Vec::new(),
+                "e33c0c7f7df4809055c3eba6c09cfe4baf1bd9e0",
+            ),
+        ] {
+            assert_eq!(
+                crate::create2_address(address(deployer), code_hash(&code), salt),
+                address(expected)
+            );
+        }
+    }
+
+    /// Init code returning `len` zero bytes as code.
+    fn return_zeros(len: usize) -> Vec<u8> {
+        vec![
+            opcode::PUSH2,
+            (len >> 8) as u8,
+            len as u8,
+            opcode::PUSH1,
+            0x00,
+            opcode::RETURN,
+        ]
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_code_size_limit() {
+        let (result, state, address) =
+            deploy_at(SpecId::SHANGHAI, 10_000_000, return_zeros(MAX_CODE_SIZE));
+        assert_eq!(result.exit_reason, Return::Continue);
+        let code = state[&address].info.code.as_ref().unwrap();
+        assert_eq!(code.len(), MAX_CODE_SIZE);
+
+        // one byte over fails and consumes all gas.
+        let (result, state, address) = deploy_at(
+            SpecId::SHANGHAI,
+            10_000_000,
+            return_zeros(MAX_CODE_SIZE + 1),
+        );
+        assert_eq!(result.exit_reason, Return::CreateContractLimit);
+        assert_eq!(result.gas_used, 10_000_000);
+        assert!(!state.contains_key(&address));
+
+        // there is no limit before EIP-170.
+        let (result, state, address) = deploy_at(
+            SpecId::TANGERINE,
+            10_000_000,
+            return_zeros(MAX_CODE_SIZE + 1),
+        );
+        assert_eq!(result.exit_reason, Return::Continue);
+        let code = state[&address].info.code.as_ref().unwrap();
+        assert_eq!(code.len(), MAX_CODE_SIZE + 1);
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_initcode_size_limit() {
+        // zero bytes are STOP, init code deploys nothing.
+        let init_code = vec![0; MAX_INITCODE_SIZE + 1];
+        let (result, _, _) = deploy_at(SpecId::SHANGHAI, 10_000_000, init_code.clone());
+        assert_eq!(result.exit_reason, Return::CreateInitcodeSizeLimit);
+        assert_eq!(result.gas_used, 0);
+        let (result, _, _) = deploy_at(SpecId::MERGE, 10_000_000, init_code);
+        assert_eq!(result.exit_reason, Return::Continue);
+
+        // init code at the limit is charged 2 gas per word.
+        let init_code = vec![0; MAX_INITCODE_SIZE];
+        let (shanghai, _, _) = deploy_at(SpecId::SHANGHAI, 10_000_000, init_code.clone());
+        let (merge, _, _) = deploy_at(SpecId::MERGE, 10_000_000, init_code);
+        assert_eq!(shanghai.exit_reason, Return::Continue);
+        assert_eq!(
+            shanghai.gas_used - merge.gas_used,
+            gas::INITCODE_WORD_COST * (MAX_INITCODE_SIZE as u64 / 32)
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_input_and_code_size_limits() {
+        // CREATE of 32 zero bytes of memory, returns the created address.
+        let code = vec![
+            opcode::PUSH1,
+            0x20,
+            opcode::PUSH1,
+            0x00,
+            opcode::PUSH1,
+            0x00,
+            opcode::CREATE,
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x20,
+            opcode::PUSH1,
+            0x00,
+            opcode::RETURN,
+        ];
+        let run = |max_input_size, max_executed_code_size, transact_to, data: &[u8]| {
+            let mut evm = evm_with_code(code.clone());
+            evm.env.cfg.max_input_size = max_input_size;
+            evm.env.cfg.max_executed_code_size = max_executed_code_size;
+            evm.env.tx.transact_to = transact_to;
+            evm.env.tx.data = Bytes::copy_from_slice(data);
+            evm.transact().0
+        };
+        let call = TransactTo::Call(H160::zero());
+        let created = |result: &ExecutionResult| match &result.out {
+            TransactOut::Call(out) => out.iter().any(|byte| *byte != 0),
+            out => panic!("unexpected output {out:?}"),
+        };
+
+        // no limits, as before.
+        let result = run(None, None, call.clone(), &[1; 64]);
+        assert_eq!(result.exit_reason, Return::Return);
+        assert!(created(&result));
+
+        // call data is rejected before any gas is charged.
+        let result = run(Some(32), None, call.clone(), &[1; 33]);
+        assert_eq!(result.exit_reason, Return::InputSizeLimit);
+        assert_eq!(result.gas_used, 0);
+        let result = run(Some(32), None, call.clone(), &[1; 32]);
+        assert_eq!(result.exit_reason, Return::Return);
+
+        // code of the called account is not run.
+        let result = run(None, Some(code.len() - 1), call.clone(), &[]);
+        assert_eq!(result.exit_reason, Return::CodeSizeLimit);
+        assert_eq!(result.gas_used, 100_000);
+
+        // the code runs, the init code of its CREATE does not.
+        let result = run(None, Some(code.len()), call.clone(), &[]);
+        assert_eq!(result.exit_reason, Return::Return);
+        assert!(!created(&result));
+        assert!(created(&run(None, Some(32), call, &[])));
+
+        // init code of a create transaction is rejected up front.
+        let create = TransactTo::Create(CreateScheme::Create);
+        let result = run(None, Some(31), create.clone(), &[0; 32]);
+        assert_eq!(result.exit_reason, Return::CodeSizeLimit);
+        assert_eq!(result.gas_used, 0);
+        let result = run(None, Some(32), create, &[0; 32]);
+        assert_eq!(result.exit_reason, Return::Continue);
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_create_opcode_initcode_size_limit() {
+        // CREATE of `len` zero bytes of memory.
+        let create = |spec_id, len: usize| {
+            let code = vec![
+                opcode::PUSH3,
+                (len >> 16) as u8,
+                (len >> 8) as u8,
+                len as u8,
+                opcode::PUSH1,
+                0x00,
+                opcode::PUSH1,
+                0x00,
+                opcode::CREATE,
+                opcode::STOP,
+            ];
+            let mut evm = evm_with_code(code);
+            evm.env.cfg.spec_id = spec_id;
+            evm.env.tx.gas_limit = 1_000_000;
+            evm.transact().0
+        };
+        assert_eq!(
+            create(SpecId::SHANGHAI, MAX_INITCODE_SIZE).exit_reason,
+            Return::Stop
+        );
+        let result = create(SpecId::SHANGHAI, MAX_INITCODE_SIZE + 1);
+        assert_eq!(result.exit_reason, Return::CreateInitcodeSizeLimit);
+        assert_eq!(result.gas_used, 1_000_000);
+        assert_eq!(
+            create(SpecId::MERGE, MAX_INITCODE_SIZE + 1).exit_reason,
+            Return::Stop
+        );
+    }
+
+    #[test]
+    fn test_selfdestruct_eip6780() {
+        let target = H160::repeat_byte(0x30);
+        let mut selfdestruct = vec![opcode::PUSH20];
+        selfdestruct.extend_from_slice(target.as_bytes());
+        selfdestruct.push(opcode::SELFDESTRUCT);
+
+        // created in the same transaction, deleted before and after Cancun.
+        for spec_id in [SpecId::SHANGHAI, SpecId::CANCUN] {
+            let (result, state, address) = deploy_at(spec_id, 100_000, selfdestruct.clone());
+            assert_eq!(result.exit_reason, Return::Continue);
+            assert!(state[&address].is_destroyed);
+        }
+
+        let contract = H160::repeat_byte(0x20);
+        let destroy_existing = |spec_id| {
+            let mut db = InMemoryDB::default();
+            db.insert_account_info(
+                contract,
+                AccountInfo::new(
+                    U256::from(100),
+                    1,
+                    Bytecode::new_raw(selfdestruct.clone().into()),
+                ),
+            );
+            let mut evm = evm_with_db(db);
+            evm.env.cfg.spec_id = spec_id;
+            evm.env.tx.transact_to = TransactTo::Call(contract);
+            let (result, state) = evm.transact();
+            assert_eq!(result.exit_reason, Return::SelfDestruct);
+            assert_eq!(state[&target].info.balance, U256::from(100));
+            state[&contract].clone()
+        };
+        assert!(destroy_existing(SpecId::SHANGHAI).is_destroyed);
+        // only the balance moves from Cancun on.
+        let account = destroy_existing(SpecId::CANCUN);
+        assert!(!account.is_destroyed);
+        assert_eq!(account.info.balance, U256::zero());
+        let code = account.info.code.unwrap();
+        assert_eq!(&code.bytes()[..code.len()], &selfdestruct[..]);
+    }
 }
@@ -47,10 +47,18 @@ impl Gas {
         self.all_used_gas -= returned;
     }
 
+    /// Add `refund` to the refund counter, negative to take back an earlier refund as when a
+    /// cleared slot is written again.
     pub fn record_refund(&mut self, refund: i64) {
         self.refunded += refund;
     }
 
+    /// Overwrite the refund counter. The counter is capped only when the transaction is
+    /// finalized, so it may exceed the cap or be negative in between.
+    pub fn set_refund(&mut self, refund: i64) {
+        self.refunded = refund;
+    }
+
     /// Record an explict cost.
     #[inline(always)]
     pub fn record_cost(&mut self, cost: u64) -> bool {
@@ -81,4 +89,17 @@ impl Gas {
     pub fn gas_refund(&mut self, refund: i64) {
         self.refunded += refund;
     }
+
+    /// Gas with `used` static/dynamic and `memory` expansion gas already spent. None if more than
+    /// `limit` is spent.
+    pub(crate) fn with_usage(limit: u64, used: u64, memory: u64, refunded: i64) -> Option<Self> {
+        let all_used_gas = used.checked_add(memory).filter(|all| *all <= limit)?;
+        Some(Self {
+            limit,
+            used,
+            memory,
+            refunded,
+            all_used_gas,
+        })
+    }
 }
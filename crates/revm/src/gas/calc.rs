@@ -2,9 +2,16 @@ use super::constants::*;
 use crate::{models::SelfDestructResult, Spec, SpecId::*};
 use primitive_types::U256;
 
+/// Net gas metering for SSTORE was introduced in Constantinople (EIP-1283), removed again in
+/// Petersburg and reintroduced in Istanbul (EIP-2200).
+#[inline(always)]
+fn sstore_net_gas_metering<SPEC: Spec>() -> bool {
+    SPEC::enabled(ISTANBUL) || SPEC::SPEC_ID == CONSTANTINOPLE
+}
+
 #[allow(clippy::collapsible_else_if)]
 pub fn sstore_refund<SPEC: Spec>(original: U256, current: U256, new: U256) -> i64 {
-    if SPEC::enabled(ISTANBUL) {
+    if sstore_net_gas_metering::<SPEC>() {
         // EIP-3529: Reduction in refunds
         let sstore_clears_schedule = if SPEC::enabled(LONDON) {
             (SSTORE_RESET - COLD_SLOAD_COST + ACCESS_LIST_STORAGE_KEY) as i64
@@ -63,6 +70,11 @@ pub fn create2_cost(len: usize) -> Option<u64> {
     Some(gas)
 }
 
+/// EIP-3860 init code cost, charged per started word.
+pub fn initcode_cost(len: usize) -> u64 {
+    INITCODE_WORD_COST * (len as u64).div_ceil(32)
+}
+
 fn log2floor(value: U256) -> u64 {
     assert!(!value.is_zero());
     let mut l: u64 = 256;
@@ -111,23 +123,44 @@ pub fn verylowcopy_cost(len: u64) -> Option<u64> {
 pub fn extcodecopy_cost<SPEC: Spec>(len: u64, is_cold: bool) -> Option<u64> {
     let wordd = len / 32;
     let wordr = len % 32;
+    cold_account_access_surcharge::<SPEC>(is_cold).checked_add(COPY.checked_mul(if wordr == 0 {
+        wordd
+    } else {
+        wordd + 1
+    })?)
+}
+
+/// EIP-2929 cost of accessing an account from Berlin on.
+pub const fn account_access_cost(is_cold: bool) -> u64 {
+    if is_cold {
+        COLD_ACCOUNT_ACCESS_COST
+    } else {
+        WARM_STORAGE_READ_COST
+    }
+}
+
+/// EIP-2929 cost of accessing a storage slot from Berlin on.
+pub const fn storage_access_cost(is_cold: bool) -> u64 {
+    if is_cold {
+        COLD_SLOAD_COST
+    } else {
+        WARM_STORAGE_READ_COST
+    }
+}
 
-    let base_gas: u64 = if SPEC::enabled(BERLIN) && is_cold {
-        // WARM_STORAGE_READ_COST is already calculated
+/// Dynamic gas of an account access by an opcode whose static gas is the warm cost, like
+/// EXTCODESIZE. Only a cold access from Berlin on costs extra.
+pub fn cold_account_access_surcharge<SPEC: Spec>(is_cold: bool) -> u64 {
+    if SPEC::enabled(BERLIN) && is_cold {
         COLD_ACCOUNT_ACCESS_COST - WARM_STORAGE_READ_COST
     } else {
         0
-    };
-    base_gas.checked_add(COPY.checked_mul(if wordr == 0 { wordd } else { wordd + 1 })?)
+    }
 }
 
 pub fn account_access_gas<SPEC: Spec>(is_cold: bool) -> u64 {
     if SPEC::enabled(BERLIN) {
-        if is_cold {
-            COLD_ACCOUNT_ACCESS_COST
-        } else {
-            WARM_STORAGE_READ_COST
-        }
+        account_access_cost(is_cold)
     } else if SPEC::enabled(ISTANBUL) {
         700
     } else {
@@ -148,11 +181,7 @@ pub fn sha3_cost(len: u64) -> Option<u64> {
 
 pub fn sload_cost<SPEC: Spec>(is_cold: bool) -> u64 {
     if SPEC::enabled(BERLIN) {
-        if is_cold {
-            COLD_SLOAD_COST
-        } else {
-            WARM_STORAGE_READ_COST
-        }
+        storage_access_cost(is_cold)
     } else if SPEC::enabled(ISTANBUL) {
         // EIP-1884: Repricing for trie-size-dependent opcodes
         800
@@ -181,9 +210,9 @@ pub fn sstore_cost<SPEC: Spec>(
 
     // https://eips.ethereum.org/EIPS/eip-2200
     // It’s a combined version of EIP-1283 and EIP-1706
-    let gas_cost = if SPEC::enabled(ISTANBUL) {
+    let gas_cost = if sstore_net_gas_metering::<SPEC>() {
         // EIP-1706
-        if gas <= CALL_STIPEND {
+        if SPEC::enabled(ISTANBUL) && gas <= CALL_STIPEND {
             return None;
         }
 
@@ -250,11 +279,7 @@ pub fn call_cost<SPEC: Spec>(
     let transfers_value = value != U256::default();
 
     let call_gas = if SPEC::enabled(BERLIN) {
-        if is_cold {
-            COLD_ACCOUNT_ACCESS_COST
-        } else {
-            WARM_STORAGE_READ_COST
-        }
+        account_access_cost(is_cold)
     } else if SPEC::enabled(TANGERINE) {
         // EIP-150: Gas cost changes for IO-heavy operations
         700
@@ -269,11 +294,7 @@ pub fn call_cost<SPEC: Spec>(
 
 pub fn hot_cold_cost<SPEC: Spec>(is_cold: bool, regular_value: u64) -> u64 {
     if SPEC::enabled(BERLIN) {
-        if is_cold {
-            COLD_ACCOUNT_ACCESS_COST
-        } else {
-            WARM_STORAGE_READ_COST
-        }
+        account_access_cost(is_cold)
     } else {
         regular_value
     }
@@ -306,9 +327,140 @@ fn new_cost<SPEC: Spec>(is_call_or_staticcall: bool, is_new: bool, transfers_val
     }
 }
 
+/// Gas of growing memory from `current_len` to `new_len` bytes, both rounded up to words. Zero if
+/// memory does not grow.
+pub fn memory_expansion_cost(current_len: usize, new_len: usize) -> u64 {
+    memory_gas(new_len.div_ceil(32)).saturating_sub(memory_gas(current_len.div_ceil(32)))
+}
+
+/// Total memory gas of `a` words, quadratic in memory size.
 pub fn memory_gas(a: usize) -> u64 {
     let a = a as u64;
     MEMORY
         .saturating_mul(a)
         .saturating_add(a.saturating_mul(a) / 512)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        account_access_gas, cold_account_access_surcharge, memory_expansion_cost, sload_cost,
+        sstore_cost, sstore_refund,
+    };
+    use crate::{
+        BerlinSpec, CancunSpec, ConstantinopleSpec, IstanbulSpec, LondonSpec, PetersburgSpec, Spec,
+    };
+    use primitive_types::U256;
+
+    #[test]
+    fn test_access_costs() {
+        /// Account, slot and surcharge cost of a cold, then a warm access.
+        fn costs<SPEC: Spec>() -> [[u64; 3]; 2] {
+            [true, false].map(|is_cold| {
+                [
+                    account_access_gas::<SPEC>(is_cold),
+                    sload_cost::<SPEC>(is_cold),
+                    cold_account_access_surcharge::<SPEC>(is_cold),
+                ]
+            })
+        }
+        let eip2929 = [[2600, 2100, 2500], [100, 100, 0]];
+        assert_eq!(costs::<BerlinSpec>(), eip2929);
+        assert_eq!(costs::<LondonSpec>(), eip2929);
+        assert_eq!(costs::<CancunSpec>(), eip2929);
+        // EIP-1884 prices are flat.
+        assert_eq!(costs::<IstanbulSpec>(), [[700, 800, 0], [700, 800, 0]]);
+    }
+
+    /// Gas used and refund of `PUSH1 new PUSH1 0 SSTORE` for every write, as in the EIP vectors.
+    fn writes<SPEC: Spec>(original: u64, writes: &[u64]) -> (u64, i64) {
+        let original = U256::from(original);
+        let mut current = original;
+        let (mut used, mut refund) = (0, 0);
+        for new in writes.iter().map(|new| U256::from(*new)) {
+            used += 6 + sstore_cost::<SPEC>(original, current, new, u64::MAX, false).unwrap();
+            refund += sstore_refund::<SPEC>(original, current, new);
+            current = new;
+        }
+        (used, refund)
+    }
+
+    /// (original, writes, Constantinople vector from EIP-1283, Istanbul vector from EIP-2200)
+    type Vector = (u64, &'static [u64], (u64, i64), (u64, i64));
+
+    const VECTORS: &[Vector] = &[
+        (0, &[0, 0], (412, 0), (1612, 0)),
+        (0, &[0, 1], (20212, 0), (20812, 0)),
+        (0, &[1, 0], (20212, 19800), (20812, 19200)),
+        (0, &[1, 2], (20212, 0), (20812, 0)),
+        (0, &[1, 1], (20212, 0), (20812, 0)),
+        (1, &[0, 0], (5212, 15000), (5812, 15000)),
+        (1, &[0, 1], (5212, 4800), (5812, 4200)),
+        (1, &[0, 2], (5212, 0), (5812, 0)),
+        (1, &[2, 0], (5212, 15000), (5812, 15000)),
+        (1, &[2, 3], (5212, 0), (5812, 0)),
+        (1, &[2, 1], (5212, 4800), (5812, 4200)),
+        (1, &[2, 2], (5212, 0), (5812, 0)),
+        (1, &[1, 0], (5212, 15000), (5812, 15000)),
+        (1, &[1, 2], (5212, 0), (5812, 0)),
+        (1, &[1, 1], (412, 0), (1612, 0)),
+        (0, &[1, 0, 1], (40218, 19800), (40818, 19200)),
+        (1, &[0, 1, 0], (10218, 19800), (10818, 19200)),
+    ];
+
+    #[test]
+    fn test_sstore_net_gas_metering() {
+        for (original, code, constantinople, istanbul) in VECTORS {
+            assert_eq!(
+                writes::<ConstantinopleSpec>(*original, code),
+                *constantinople
+            );
+            assert_eq!(writes::<IstanbulSpec>(*original, code), *istanbul);
+        }
+    }
+
+    #[test]
+    fn test_sstore_petersburg_vs_istanbul() {
+        // without net gas metering every write is charged in full.
+        assert_eq!(writes::<PetersburgSpec>(0, &[1, 0]), (25012, 15000));
+        assert_eq!(writes::<PetersburgSpec>(1, &[1, 1]), (10012, 0));
+        assert_eq!(writes::<IstanbulSpec>(0, &[1, 0]), (20812, 19200));
+        assert_eq!(writes::<IstanbulSpec>(1, &[1, 1]), (1612, 0));
+    }
+
+    #[test]
+    fn test_sstore_sentry() {
+        // EIP-1706 is only part of Istanbul, EIP-1283 has no stipend check.
+        let (zero, one) = (U256::zero(), U256::one());
+        assert_eq!(
+            sstore_cost::<IstanbulSpec>(zero, zero, one, 2300, false),
+            None
+        );
+        assert_eq!(
+            sstore_cost::<ConstantinopleSpec>(zero, zero, one, 2300, false),
+            Some(20000)
+        );
+        assert_eq!(
+            sstore_cost::<IstanbulSpec>(zero, zero, one, 2301, false),
+            Some(20000)
+        );
+    }
+
+    #[test]
+    fn test_memory_expansion_cost() {
+        // 3 gas per word plus words^2 / 512.
+        assert_eq!(memory_expansion_cost(0, 32), 3);
+        assert_eq!(memory_expansion_cost(0, 1024), 32 * 3 + 2);
+        assert_eq!(
+            memory_expansion_cost(0, 1 << 20),
+            32768 * 3 + 32768 * 32768 / 512
+        );
+        // partial words are paid as whole words.
+        assert_eq!(memory_expansion_cost(0, 1), 3);
+        assert_eq!(memory_expansion_cost(0, 33), 6);
+        // marginal cost of growing, nothing when not growing.
+        assert_eq!(memory_expansion_cost(32, 1024), 32 * 3 + 2 - 3);
+        assert_eq!(memory_expansion_cost(1024, 32), 0);
+        assert_eq!(memory_expansion_cost(1024, 1000), 0);
+    }
+}
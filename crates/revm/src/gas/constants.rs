@@ -19,6 +19,8 @@ pub const SHA3WORD: u64 = 6;
 pub const COPY: u64 = 3;
 pub const BLOCKHASH: u64 = 20;
 pub const CODEDEPOSIT: u64 = 200;
+/// EIP-3860 cost per word of init code.
+pub const INITCODE_WORD_COST: u64 = 2;
 
 pub const SSTORE_SET: u64 = 20000;
 pub const SSTORE_RESET: u64 = 5000;
@@ -35,4 +37,11 @@ pub const COLD_SLOAD_COST: u64 = 2100;
 pub const COLD_ACCOUNT_ACCESS_COST: u64 = 2600;
 pub const WARM_STORAGE_READ_COST: u64 = 100;
 
+// cancun eip4844 constants
+pub const GAS_PER_BLOB: u64 = 131072;
+
+// prague eip7702 constants
+pub const PER_EMPTY_ACCOUNT_COST: u64 = 25000;
+pub const PER_AUTH_BASE_COST: u64 = 12500;
+
 pub const CALL_STIPEND: u64 = 2300;
@@ -1,12 +1,28 @@
 use bytes::Bytes;
-use primitive_types::{H160, H256};
+use primitive_types::{H160, H256, U256};
 
 use crate::{
     evm_impl::EVMData, opcode, spec_opcode_gas, CallInputs, CreateInputs, Database, Gas,
-    Interpreter, Return,
+    GasBlockGranularity, Interpreter, Return,
 };
 use auto_impl::auto_impl;
 
+pub mod access_list;
+pub mod balance_transfer;
+#[cfg(feature = "std")]
+pub mod binary_trace;
+pub mod call_depth;
+pub mod call_output;
+pub mod call_trace;
+pub mod memory_profile;
+pub mod multi;
+pub(crate) mod oog;
+pub mod opcode_gas;
+pub mod revert_context;
+pub mod storage_access;
+#[cfg(feature = "tracing")]
+pub mod tracing;
+
 #[auto_impl(&mut, Box)]
 pub trait Inspector<DB: Database> {
     /// Called Before the interpreter is initialized.
@@ -30,6 +46,12 @@ pub trait Inspector<DB: Database> {
     /// # Example
     ///
     /// To get the current opcode, use `interp.current_opcode()`.
+    ///
+    /// Returning [Return::SkipOpcode] moves the pc past the opcode and its immediate bytes
+    /// without executing it, so its stack inputs stay on the stack. A skipped opcode charges no
+    /// gas, its static gas is given back if it was charged ahead with its gas block.
+    /// `step_end` is still called with [Return::Continue]. Anything other than
+    /// [Return::Continue] or [Return::SkipOpcode] halts the interpreter with that result.
     fn step(
         &mut self,
         _interp: &mut Interpreter,
@@ -77,7 +99,9 @@ pub trait Inspector<DB: Database> {
     /// Called when a call to a contract has concluded.
     ///
     /// Returning anything other than the values passed to this function (`(ret, remaining_gas,
-    /// out)`) will alter the result of the call.
+    /// out)`) will alter the result of the call. The returned values are what the caller sees:
+    /// `out` becomes its return data buffer and is copied to its output memory, so a call can be
+    /// mocked by replacing `out`. For the top level call it is the transaction output.
     fn call_end(
         &mut self,
         _data: &mut EVMData<'_, DB>,
@@ -117,8 +141,35 @@ pub trait Inspector<DB: Database> {
         (ret, address, remaining_gas, out)
     }
 
-    /// Called when a contract has been self-destructed.
-    fn selfdestruct(&mut self) {}
+    /// Called on SLOAD with the loaded `value` and whether `slot` was cold before.
+    fn sload(&mut self, _address: H160, _slot: U256, _value: U256, _is_cold: bool) {}
+
+    /// Called on SSTORE with the value of `slot` at the start of the transaction, its value before
+    /// the write and the written value, see [crate::gas::sstore_cost].
+    fn sstore(
+        &mut self,
+        _address: H160,
+        _slot: U256,
+        _original: U256,
+        _current: U256,
+        _new: U256,
+        _is_cold: bool,
+    ) {
+    }
+
+    /// Called when `contract` has been self-destructed and its balance `value` sent to `target`.
+    fn selfdestruct(&mut self, _contract: H160, _target: H160, _value: U256) {}
+
+    /// Called on JUMP and JUMPI with the pc of the jump and where execution continues. `taken` is
+    /// false for a JUMPI that falls through to `to_pc` right after it. Invalid jumps are not
+    /// reported.
+    fn jump(&mut self, _from_pc: usize, _to_pc: usize, _taken: bool) {}
+
+    /// Called when the static gas of the gas block starting at `pc` is charged, with the summed
+    /// cost of its opcodes and the gas remaining after it. The gas block is charged before its
+    /// first opcode runs, so [Inspector::step] sees it as one lump. Only called when gas is
+    /// charged per [GasBlockGranularity::WholeBlock].
+    fn gas_block(&mut self, _pc: usize, _block_cost: u64, _remaining: u64) {}
 }
 
 #[derive(Clone, Copy)]
@@ -207,7 +258,11 @@ impl<DB: Database> Inspector<DB> for GasInspector {
             self.was_return = false;
         }
 
-        self.gas_remaining = interp.gas.remaining() + self.full_gas_block - self.reduced_gas_block;
+        self.gas_remaining = if interp.gas_block_granularity == GasBlockGranularity::WholeBlock {
+            interp.gas.remaining() + self.full_gas_block - self.reduced_gas_block
+        } else {
+            interp.gas.remaining() + interp.prepaid_gas()
+        };
 
         Return::Continue
     }
@@ -241,14 +296,18 @@ impl<DB: Database> Inspector<DB> for GasInspector {
 
 #[cfg(test)]
 mod tests {
+    #![cfg_attr(feature = "no_gas_measuring", allow(unused_imports))]
+
     use crate::db::BenchmarkDB;
     use crate::{
-        opcode, Bytecode, CallInputs, CreateInputs, Database, EVMData, Gas, GasInspector,
-        Inspector, Interpreter, OpCode, Return, TransactTo,
+        opcode,
+        test_utils::{evm_with_code, evm_with_db},
+        Bytecode, CallInputs, CreateInputs, Database, EVMData, Gas, GasBlockGranularity,
+        GasInspector, Inspector, Interpreter, OpCode, Return, TransactTo,
     };
     use bytes::Bytes;
     use core::str::FromStr;
-    use primitive_types::{H160, H256};
+    use primitive_types::{H160, H256, U256};
 
     #[derive(Default, Debug)]
     struct StackInspector {
@@ -395,4 +454,416 @@ mod tests {
             );
         }
     }
+
+    /// Replaces the output of every call to `target`.
+    struct MockOutput {
+        target: H160,
+        out: Bytes,
+    }
+
+    impl<DB: Database> Inspector<DB> for MockOutput {
+        fn call_end(
+            &mut self,
+            _data: &mut EVMData<'_, DB>,
+            inputs: &CallInputs,
+            remaining_gas: Gas,
+            ret: Return,
+            out: Bytes,
+            _is_static: bool,
+        ) -> (Return, Gas, Bytes) {
+            if inputs.contract == self.target {
+                return (ret, remaining_gas, self.out.clone());
+            }
+            (ret, remaining_gas, out)
+        }
+    }
+
+    #[derive(Default)]
+    struct JumpRecorder {
+        jumps: Vec<(usize, usize, bool)>,
+    }
+
+    impl<DB: Database> Inspector<DB> for JumpRecorder {
+        fn jump(&mut self, from_pc: usize, to_pc: usize, taken: bool) {
+            self.jumps.push((from_pc, to_pc, taken));
+        }
+    }
+
+    #[test]
+    fn test_jump() {
+        let code = vec![
+            opcode::PUSH1,
+            0x00,
+            opcode::PUSH1,
+            0x08,
+            opcode::JUMPI, // 4: falls through
+            opcode::PUSH1,
+            0x01,
+            opcode::PUSH1,
+            0x0b,
+            opcode::JUMPI, // 9: jumps to 11
+            opcode::STOP,
+            opcode::JUMPDEST,
+            opcode::PUSH1,
+            0x0f,
+            opcode::JUMP, // 14: jumps to 15
+            opcode::JUMPDEST,
+            opcode::STOP,
+        ];
+        let mut evm = evm_with_code(code);
+
+        let mut inspector = JumpRecorder::default();
+        let (result, _) = evm.inspect(&mut inspector);
+        assert_eq!(result.exit_reason, Return::Stop);
+        assert_eq!(
+            inspector.jumps,
+            vec![(4, 5, false), (9, 11, true), (14, 15, true)]
+        );
+    }
+
+    #[derive(Default)]
+    struct GasBlockRecorder {
+        blocks: Vec<(usize, u64, u64)>,
+    }
+
+    impl<DB: Database> Inspector<DB> for GasBlockRecorder {
+        fn gas_block(&mut self, pc: usize, block_cost: u64, remaining: u64) {
+            self.blocks.push((pc, block_cost, remaining));
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_gas_block() {
+        let code = vec![
+            opcode::PUSH1,
+            0x01,
+            opcode::PUSH1,
+            0x02,
+            opcode::ADD,
+            opcode::PUSH1,
+            0x09,
+            opcode::JUMP, // 7: jumps to 9
+            opcode::STOP,
+            opcode::JUMPDEST,
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE,
+            opcode::STOP,
+        ];
+        let mut evm = evm_with_code(code);
+
+        let mut inspector = GasBlockRecorder::default();
+        let (result, _) = evm.inspect(&mut inspector);
+        assert_eq!(result.exit_reason, Return::Stop);
+        // three PUSH1, ADD and JUMP, then JUMPDEST charges PUSH1, MSTORE and STOP. Memory
+        // expansion is charged by MSTORE itself.
+        let first = 3 * 3 + 3 + 8;
+        let remaining = 100_000 - 21_000 - first;
+        assert_eq!(
+            inspector.blocks,
+            vec![(0, first, remaining), (10, 3 + 3, remaining - 1 - 6)]
+        );
+        assert_eq!(result.gas_used, 21_000 + first + 1 + 6 + 3);
+    }
+
+    #[derive(Default)]
+    struct LogRecorder {
+        logs: Vec<(H160, Vec<H256>, Bytes)>,
+    }
+
+    impl<DB: Database> Inspector<DB> for LogRecorder {
+        fn log(
+            &mut self,
+            _evm_data: &mut EVMData<'_, DB>,
+            address: &H160,
+            topics: &[H256],
+            data: &Bytes,
+        ) {
+            self.logs.push((*address, topics.to_vec(), data.clone()));
+        }
+    }
+
+    #[test]
+    fn test_log() {
+        // mstore8(0, 0xaa), log2(0, 1, 0x22, 0x11)
+        let code = vec![
+            opcode::PUSH1,
+            0xaa,
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE8,
+            opcode::PUSH1,
+            0x11,
+            opcode::PUSH1,
+            0x22,
+            opcode::PUSH1,
+            0x01,
+            opcode::PUSH1,
+            0x00,
+            opcode::LOG2,
+        ];
+        let mut evm = evm_with_code(code);
+
+        let mut inspector = LogRecorder::default();
+        let (result, _) = evm.inspect(&mut inspector);
+        assert_eq!(result.exit_reason, Return::Stop);
+        let topics = vec![H256::from_low_u64_be(0x22), H256::from_low_u64_be(0x11)];
+        assert_eq!(
+            inspector.logs,
+            vec![(H160::zero(), topics, Bytes::from(vec![0xaa]))]
+        );
+        assert_eq!(result.logs.len(), 1);
+    }
+
+    #[derive(Default)]
+    struct SelfdestructRecorder {
+        destroyed: Vec<(H160, H160, U256)>,
+    }
+
+    impl<DB: Database> Inspector<DB> for SelfdestructRecorder {
+        fn selfdestruct(&mut self, contract: H160, target: H160, value: U256) {
+            self.destroyed.push((contract, target, value));
+        }
+    }
+
+    #[test]
+    fn test_selfdestruct() {
+        use crate::{AccountInfo, InMemoryDB};
+
+        let contract = H160::repeat_byte(0xcc);
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            contract,
+            AccountInfo {
+                balance: U256::from(5),
+                code: Some(Bytecode::new_raw(Bytes::from(vec![
+                    opcode::PUSH1,
+                    0xbe,
+                    opcode::SELFDESTRUCT,
+                ]))),
+                ..Default::default()
+            },
+        );
+        let mut evm = evm_with_db(db);
+        evm.env.tx.transact_to = TransactTo::Call(contract);
+
+        let mut inspector = SelfdestructRecorder::default();
+        let (result, _) = evm.inspect(&mut inspector);
+        assert_eq!(result.exit_reason, Return::SelfDestruct);
+        assert_eq!(
+            inspector.destroyed,
+            vec![(contract, H160::from_low_u64_be(0xbe), U256::from(5))]
+        );
+    }
+
+    #[test]
+    fn test_call_end_mocks_output() {
+        use crate::{AccountInfo, InMemoryDB, TransactOut};
+
+        // callee returns a single 0xaa byte.
+        let callee = Bytecode::new_raw(Bytes::from(vec![
+            opcode::PUSH1,
+            0xaa,
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE8,
+            opcode::PUSH1,
+            0x01,
+            opcode::PUSH1,
+            0x00,
+            opcode::RETURN,
+        ]));
+        // caller copies 2 output bytes to memory 0 and returns them with RETURNDATASIZE at 0x20.
+        let caller = Bytecode::new_raw(Bytes::from(vec![
+            opcode::PUSH1,
+            0x02, // out len
+            opcode::PUSH1,
+            0x00, // out offset
+            opcode::PUSH1,
+            0x00, // in len
+            opcode::PUSH1,
+            0x00, // in offset
+            opcode::PUSH1,
+            0x00, // value
+            opcode::PUSH1,
+            0x42,
+            opcode::GAS,
+            opcode::CALL,
+            opcode::POP,
+            opcode::RETURNDATASIZE,
+            opcode::PUSH1,
+            0x20,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x40,
+            opcode::PUSH1,
+            0x00,
+            opcode::RETURN,
+        ]));
+
+        let mut db = InMemoryDB::default();
+        for (address, code) in [
+            (H160::zero(), caller),
+            (H160::from_low_u64_be(0x42), callee),
+        ] {
+            db.insert_account_info(
+                address,
+                AccountInfo {
+                    code: Some(code),
+                    ..Default::default()
+                },
+            );
+        }
+        let mut evm = evm_with_db(db);
+
+        let mut inspector = MockOutput {
+            target: H160::from_low_u64_be(0x42),
+            out: Bytes::from(vec![0xbb, 0xcc]),
+        };
+        let (result, _) = evm.inspect(&mut inspector);
+        assert_eq!(result.exit_reason, Return::Return);
+        let out = match result.out {
+            TransactOut::Call(out) => out,
+            out => panic!("unexpected output {out:?}"),
+        };
+        assert_eq!(out[..2], [0xbb, 0xcc]);
+        assert_eq!(out[63], 2);
+    }
+
+    #[derive(Default)]
+    struct StorageHookInspector {
+        sloads: Vec<(U256, U256, bool)>,
+        sstores: Vec<(U256, U256, U256, U256, bool)>,
+    }
+
+    impl<DB: Database> Inspector<DB> for StorageHookInspector {
+        fn sload(&mut self, _address: H160, slot: U256, value: U256, is_cold: bool) {
+            self.sloads.push((slot, value, is_cold));
+        }
+
+        fn sstore(
+            &mut self,
+            _address: H160,
+            slot: U256,
+            original: U256,
+            current: U256,
+            new: U256,
+            is_cold: bool,
+        ) {
+            self.sstores.push((slot, original, current, new, is_cold));
+        }
+    }
+
+    #[test]
+    fn test_storage_hooks() {
+        use crate::{AccountInfo, InMemoryDB};
+
+        // SLOAD(0), then SSTORE(0, 2) and SSTORE(0, 3).
+        let code = vec![
+            opcode::PUSH1,
+            0x00,
+            opcode::SLOAD,
+            opcode::POP,
+            opcode::PUSH1,
+            0x02,
+            opcode::PUSH1,
+            0x00,
+            opcode::SSTORE,
+            opcode::PUSH1,
+            0x03,
+            opcode::PUSH1,
+            0x00,
+            opcode::SSTORE,
+        ];
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            H160::zero(),
+            AccountInfo {
+                code: Some(Bytecode::new_raw(Bytes::from(code))),
+                ..Default::default()
+            },
+        );
+        db.insert_account_storage(H160::zero(), U256::zero(), U256::one())
+            .unwrap();
+
+        let mut evm = evm_with_db(db);
+
+        let mut inspector = StorageHookInspector::default();
+        let (result, _) = evm.inspect(&mut inspector);
+        assert_eq!(result.exit_reason, Return::Stop);
+
+        let (zero, one) = (U256::zero(), U256::one());
+        assert_eq!(inspector.sloads, vec![(zero, one, true)]);
+        // original value stays the one of the transaction start.
+        assert_eq!(
+            inspector.sstores,
+            vec![
+                (zero, one, one, U256::from(2), false),
+                (zero, one, U256::from(2), U256::from(3), false),
+            ]
+        );
+    }
+
+    /// Skips every `SSTORE`.
+    struct SkipSstore;
+
+    impl<DB: Database> Inspector<DB> for SkipSstore {
+        fn step(
+            &mut self,
+            interp: &mut Interpreter,
+            _data: &mut EVMData<'_, DB>,
+            _is_static: bool,
+        ) -> Return {
+            if interp.current_opcode() == opcode::SSTORE {
+                return Return::SkipOpcode;
+            }
+            Return::Continue
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_skip_opcode() {
+        // store 9 at slot 1, then return slot 1.
+        let code = |store: u8| {
+            vec![
+                opcode::PUSH1,
+                0x09,
+                opcode::PUSH1,
+                0x01,
+                store,
+                opcode::PUSH1,
+                0x01,
+                opcode::SLOAD,
+                opcode::PUSH1,
+                0x00,
+                opcode::MSTORE,
+                opcode::PUSH1,
+                0x20,
+                opcode::PUSH1,
+                0x00,
+                opcode::RETURN,
+            ]
+        };
+        let run = |store: u8, granularity: GasBlockGranularity| {
+            let mut evm = evm_with_code(code(store));
+            evm.env.cfg.gas_block_granularity = granularity;
+            evm.inspect(SkipSstore)
+        };
+        for granularity in [
+            GasBlockGranularity::WholeBlock,
+            GasBlockGranularity::PerOpcode,
+            GasBlockGranularity::PerMemoryOp,
+        ] {
+            let (result, state) = run(opcode::SSTORE, granularity);
+            assert_eq!(result.exit_reason, Return::Return, "{granularity:?}");
+            let slot = &state[&H160::zero()].storage[&U256::one()];
+            assert_eq!(slot.present_value(), U256::zero());
+
+            // the skipped SSTORE charges nothing, a JUMPDEST charges 1.
+            let (jumpdest, _) = run(opcode::JUMPDEST, granularity);
+            assert_eq!(result.gas_used, jumpdest.gas_used - 1, "{granularity:?}");
+        }
+    }
 }
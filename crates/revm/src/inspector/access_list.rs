@@ -0,0 +1,144 @@
+use crate::{
+    alloc::{
+        collections::{BTreeMap, BTreeSet},
+        vec::Vec,
+    },
+    opcode, Database, EVMData, Inspector, Interpreter, Return,
+};
+use primitive_types::{H160, H256, U256};
+
+/// Inspector that collects the accessed addresses and storage slots of an execution as an
+/// EIP-2930 access list.
+///
+/// Slots are collected for every address, including the transaction target. Addresses accessed
+/// without slots are left out if they are excluded, usually the sender, the target and the
+/// precompiles, which are warm anyway.
+#[derive(Clone, Debug, Default)]
+pub struct AccessListInspector {
+    excluded: BTreeSet<H160>,
+    access_list: BTreeMap<H160, BTreeSet<U256>>,
+}
+
+impl AccessListInspector {
+    pub fn new(from: H160, to: H160, precompiles: impl IntoIterator<Item = H160>) -> Self {
+        let mut excluded: BTreeSet<H160> = precompiles.into_iter().collect();
+        excluded.insert(from);
+        excluded.insert(to);
+        Self {
+            excluded,
+            access_list: BTreeMap::new(),
+        }
+    }
+
+    /// Accessed addresses with their slots, both in ascending order.
+    pub fn into_access_list(self) -> Vec<(H160, Vec<U256>)> {
+        self.access_list
+            .into_iter()
+            .map(|(address, slots)| (address, slots.into_iter().collect()))
+            .collect()
+    }
+
+    fn add_address(&mut self, address: H160) {
+        if !self.excluded.contains(&address) {
+            self.access_list.entry(address).or_default();
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for AccessListInspector {
+    fn step(
+        &mut self,
+        interp: &mut Interpreter,
+        _data: &mut EVMData<'_, DB>,
+        _is_static: bool,
+    ) -> Return {
+        let address_at = |index| {
+            interp.stack.peek(index).ok().map(|value| {
+                let mut word = H256::zero();
+                value.to_big_endian(word.as_bytes_mut());
+                H160::from(word)
+            })
+        };
+        match interp.current_opcode() {
+            opcode::SLOAD | opcode::SSTORE => {
+                if let Ok(slot) = interp.stack.peek(0) {
+                    self.access_list
+                        .entry(interp.contract.address)
+                        .or_default()
+                        .insert(slot);
+                }
+            }
+            opcode::BALANCE
+            | opcode::EXTCODESIZE
+            | opcode::EXTCODECOPY
+            | opcode::EXTCODEHASH
+            | opcode::SELFDESTRUCT => {
+                if let Some(address) = address_at(0) {
+                    self.add_address(address);
+                }
+            }
+            opcode::CALL | opcode::CALLCODE | opcode::DELEGATECALL | opcode::STATICCALL => {
+                if let Some(address) = address_at(1) {
+                    self.add_address(address);
+                }
+            }
+            _ => (),
+        }
+        Return::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AccessListInspector;
+    use crate::{
+        opcode, test_utils::evm_with_db, AccountInfo, Bytecode, InMemoryDB, Return, TransactTo,
+    };
+    use bytes::Bytes;
+    use primitive_types::{H160, U256};
+
+    #[test]
+    fn test_access_list() {
+        let (caller, contract, other) = (
+            H160::repeat_byte(0x10),
+            H160::repeat_byte(0x20),
+            H160::repeat_byte(0x30),
+        );
+        let ecrecover = H160::from_low_u64_be(1);
+        // SLOAD(2) and SLOAD(1), then CALL other, the caller and ecrecover with no gas.
+        let mut code = vec![
+            opcode::PUSH1,
+            0x02,
+            opcode::SLOAD,
+            opcode::PUSH1,
+            0x01,
+            opcode::SLOAD,
+        ];
+        for address in [other, caller, ecrecover] {
+            // five zero arguments, no gas.
+            code.extend([opcode::PUSH1, 0x00, opcode::DUP1, opcode::DUP1]);
+            code.extend([opcode::DUP1, opcode::DUP1, opcode::PUSH20]);
+            code.extend_from_slice(address.as_bytes());
+            code.extend([opcode::DUP2, opcode::CALL, opcode::POP]);
+        }
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            contract,
+            AccountInfo::new(U256::zero(), 1, Bytecode::new_raw(Bytes::from(code))),
+        );
+
+        let mut evm = evm_with_db(db);
+        evm.env.tx.transact_to = TransactTo::Call(contract);
+
+        let mut inspector = AccessListInspector::new(caller, contract, [ecrecover]);
+        let (result, _) = evm.inspect(&mut inspector);
+        assert_eq!(result.exit_reason, Return::Stop);
+        assert_eq!(
+            inspector.into_access_list(),
+            vec![
+                (contract, vec![U256::from(1), U256::from(2)]),
+                (other, Vec::new()),
+            ]
+        );
+    }
+}
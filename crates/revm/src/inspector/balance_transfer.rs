@@ -0,0 +1,160 @@
+use crate::{alloc::vec::Vec, Database, Inspector};
+use primitive_types::{H160, U256};
+
+/// Two SSTOREs of one contract that move `amount` from one slot to another, see
+/// [BalanceTransferInspector].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BalanceTransfer {
+    pub address: H160,
+    pub from_slot: U256,
+    pub to_slot: U256,
+    pub amount: U256,
+    /// Both slots were warm when they were written, usually because they were loaded before.
+    pub warm: bool,
+}
+
+/// One SSTORE that changed its slot, waiting for the SSTORE that balances it.
+#[derive(Clone, Copy, Debug)]
+struct Write {
+    address: H160,
+    slot: U256,
+    amount: U256,
+    is_decrement: bool,
+    is_cold: bool,
+}
+
+/// Inspector that recognizes the storage pattern of an ERC20 `transfer`: an SSTORE that
+/// decrements a balance slot directly followed by one that increments another slot of the same
+/// contract by the same amount, in either order.
+///
+/// A `transfer` loads both balances before it writes them, so both writes are charged as warm. A
+/// transfer with [BalanceTransfer::warm] unset paid the cold slot surcharge on a write. Writes of
+/// reverted frames are recognized too.
+#[derive(Clone, Debug, Default)]
+pub struct BalanceTransferInspector {
+    transfers: Vec<BalanceTransfer>,
+    pending: Option<Write>,
+}
+
+impl BalanceTransferInspector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn transfers(&self) -> &[BalanceTransfer] {
+        &self.transfers
+    }
+}
+
+impl<DB: Database> Inspector<DB> for BalanceTransferInspector {
+    fn sstore(
+        &mut self,
+        address: H160,
+        slot: U256,
+        _original: U256,
+        current: U256,
+        new: U256,
+        is_cold: bool,
+    ) {
+        if new == current {
+            return;
+        }
+        let write = Write {
+            address,
+            slot,
+            amount: if new < current {
+                current - new
+            } else {
+                new - current
+            },
+            is_decrement: new < current,
+            is_cold,
+        };
+        match self.pending.take() {
+            Some(pending)
+                if pending.address == write.address
+                    && pending.slot != write.slot
+                    && pending.amount == write.amount
+                    && pending.is_decrement != write.is_decrement =>
+            {
+                let (from, to) = if pending.is_decrement {
+                    (pending, write)
+                } else {
+                    (write, pending)
+                };
+                self.transfers.push(BalanceTransfer {
+                    address,
+                    from_slot: from.slot,
+                    to_slot: to.slot,
+                    amount: write.amount,
+                    warm: !pending.is_cold && !write.is_cold,
+                });
+            }
+            _ => self.pending = Some(write),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BalanceTransfer, BalanceTransferInspector};
+    use crate::{opcode, test_utils::evm_with_db, AccountInfo, Bytecode, InMemoryDB, Return};
+    use bytes::Bytes;
+    use primitive_types::{H160, U256};
+
+    /// Transfers recognized in `code` run at the zero address with 10 in slot 1.
+    fn transfers(code: Vec<u8>) -> Vec<BalanceTransfer> {
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            H160::zero(),
+            AccountInfo::new(U256::zero(), 1, Bytecode::new_raw(Bytes::from(code))),
+        );
+        db.insert_account_storage(H160::zero(), U256::one(), U256::from(10))
+            .unwrap();
+        let mut evm = evm_with_db(db);
+
+        let mut inspector = BalanceTransferInspector::new();
+        let (result, _) = evm.inspect(&mut inspector);
+        assert_eq!(result.exit_reason, Return::Stop);
+        inspector.transfers().to_vec()
+    }
+
+    /// `slot = SLOAD(slot) op amount`, without the SLOAD if `load` is false.
+    fn update(slot: u8, op: u8, amount: u8, load: bool) -> Vec<u8> {
+        let mut code = vec![opcode::PUSH1, amount];
+        if load {
+            code.extend([opcode::PUSH1, slot, opcode::SLOAD, op]);
+        }
+        code.extend([opcode::PUSH1, slot, opcode::SSTORE]);
+        code
+    }
+
+    #[test]
+    fn test_balance_transfer() {
+        let transfer = |amount: u64, warm| BalanceTransfer {
+            address: H160::zero(),
+            from_slot: U256::from(1),
+            to_slot: U256::from(2),
+            amount: U256::from(amount),
+            warm,
+        };
+
+        // slot 1 -= 3, slot 2 += 3.
+        let mut code = update(1, opcode::SUB, 3, true);
+        code.extend(update(2, opcode::ADD, 3, true));
+        code.push(opcode::STOP);
+        assert_eq!(transfers(code), vec![transfer(3, true)]);
+
+        // the increment comes first and slot 2 is written without loading it.
+        let mut code = update(2, opcode::ADD, 4, false);
+        code.extend(update(1, opcode::SUB, 4, true));
+        code.push(opcode::STOP);
+        assert_eq!(transfers(code), vec![transfer(4, false)]);
+
+        // the amounts differ.
+        let mut code = update(1, opcode::SUB, 3, true);
+        code.extend(update(2, opcode::ADD, 2, true));
+        code.push(opcode::STOP);
+        assert_eq!(transfers(code), Vec::new());
+    }
+}
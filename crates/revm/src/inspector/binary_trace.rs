@@ -0,0 +1,273 @@
+use std::io::{self, Read, Write};
+
+use bytes::Bytes;
+use primitive_types::H160;
+
+use crate::{
+    CallInputs, CreateInputs, Database, EVMData, Gas, GasInspector, Inspector, Interpreter, Return,
+};
+
+/// One interpreter step in the compact binary trace format.
+///
+/// Records are encoded little endian with a fixed size of [TraceRecord::SIZE] bytes:
+/// `pc: u32, opcode: u8, gas: u64, depth: u16, stack_len: u16`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TraceRecord {
+    pub pc: u32,
+    pub opcode: u8,
+    pub gas: u64,
+    pub depth: u16,
+    pub stack_len: u16,
+}
+
+impl TraceRecord {
+    pub const SIZE: usize = 4 + 1 + 8 + 2 + 2;
+
+    pub fn encode(&self) -> [u8; Self::SIZE] {
+        let mut out = [0u8; Self::SIZE];
+        out[0..4].copy_from_slice(&self.pc.to_le_bytes());
+        out[4] = self.opcode;
+        out[5..13].copy_from_slice(&self.gas.to_le_bytes());
+        out[13..15].copy_from_slice(&self.depth.to_le_bytes());
+        out[15..17].copy_from_slice(&self.stack_len.to_le_bytes());
+        out
+    }
+
+    pub fn decode(bytes: &[u8; Self::SIZE]) -> Self {
+        Self {
+            pc: u32::from_le_bytes(bytes[0..4].try_into().unwrap()),
+            opcode: bytes[4],
+            gas: u64::from_le_bytes(bytes[5..13].try_into().unwrap()),
+            depth: u16::from_le_bytes(bytes[13..15].try_into().unwrap()),
+            stack_len: u16::from_le_bytes(bytes[15..17].try_into().unwrap()),
+        }
+    }
+}
+
+/// Inspector that writes every step as a [TraceRecord] into `W`.
+///
+/// Inspector hooks can't return io errors, so the first error is kept and writing stops. It is
+/// reported by [BinaryTraceWriter::finish].
+pub struct BinaryTraceWriter<W: Write> {
+    gas_inspector: GasInspector,
+    writer: W,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> BinaryTraceWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            gas_inspector: GasInspector::default(),
+            writer,
+            error: None,
+        }
+    }
+
+    pub fn write_record(&mut self, record: &TraceRecord) -> io::Result<()> {
+        self.writer.write_all(&record.encode())
+    }
+
+    /// Flush the writer and return it, or the first error that happened while tracing.
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<DB: Database, W: Write> Inspector<DB> for BinaryTraceWriter<W> {
+    fn initialize_interp(
+        &mut self,
+        interp: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        is_static: bool,
+    ) -> Return {
+        self.gas_inspector
+            .initialize_interp(interp, data, is_static);
+        Return::Continue
+    }
+
+    fn step(
+        &mut self,
+        interp: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        is_static: bool,
+    ) -> Return {
+        if self.error.is_none() {
+            let record = TraceRecord {
+                pc: interp.program_counter() as u32,
+                opcode: interp.current_opcode(),
+                gas: self.gas_inspector.gas_remaining(),
+                depth: data.journaled_state.depth() as u16,
+                stack_len: interp.stack.len() as u16,
+            };
+            if let Err(error) = self.write_record(&record) {
+                self.error = Some(error);
+            }
+        }
+
+        self.gas_inspector.step(interp, data, is_static);
+        Return::Continue
+    }
+
+    fn step_end(
+        &mut self,
+        interp: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        is_static: bool,
+        eval: Return,
+    ) -> Return {
+        self.gas_inspector.step_end(interp, data, is_static, eval);
+        Return::Continue
+    }
+
+    fn call_end(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        inputs: &CallInputs,
+        remaining_gas: Gas,
+        ret: Return,
+        out: Bytes,
+        is_static: bool,
+    ) -> (Return, Gas, Bytes) {
+        self.gas_inspector
+            .call_end(data, inputs, remaining_gas, ret, out.clone(), is_static);
+        (ret, remaining_gas, out)
+    }
+
+    fn create_end(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        inputs: &CreateInputs,
+        ret: Return,
+        address: Option<H160>,
+        remaining_gas: Gas,
+        out: Bytes,
+    ) -> (Return, Option<H160>, Gas, Bytes) {
+        self.gas_inspector
+            .create_end(data, inputs, ret, address, remaining_gas, out.clone());
+        (ret, address, remaining_gas, out)
+    }
+}
+
+/// Reads back [TraceRecord]s written by [BinaryTraceWriter].
+pub struct BinaryTraceReader<R: Read> {
+    reader: R,
+}
+
+impl<R: Read> BinaryTraceReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader }
+    }
+
+    /// Read next record. Returns `Ok(None)` on a clean end of input and an
+    /// [io::ErrorKind::UnexpectedEof] error if the input ends in the middle of a record.
+    pub fn read_record(&mut self) -> io::Result<Option<TraceRecord>> {
+        let mut buf = [0u8; TraceRecord::SIZE];
+        let mut filled = 0;
+        while filled < buf.len() {
+            match self.reader.read(&mut buf[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        match filled {
+            0 => Ok(None),
+            TraceRecord::SIZE => Ok(Some(TraceRecord::decode(&buf))),
+            _ => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "truncated trace record",
+            )),
+        }
+    }
+}
+
+impl<R: Read> Iterator for BinaryTraceReader<R> {
+    type Item = io::Result<TraceRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![cfg_attr(feature = "no_gas_measuring", allow(unused_imports))]
+
+    use super::*;
+    use crate::{opcode, test_utils::evm_with_code};
+
+    #[test]
+    fn round_trip_records() {
+        let records: Vec<TraceRecord> = (0..300u32)
+            .map(|i| TraceRecord {
+                pc: i * 3,
+                opcode: (i % 256) as u8,
+                gas: u64::MAX - i as u64,
+                depth: (i % 1025) as u16,
+                stack_len: (i % 1024) as u16,
+            })
+            .collect();
+
+        let mut writer = BinaryTraceWriter::new(Vec::new());
+        for record in &records {
+            writer.write_record(record).unwrap();
+        }
+        let buf = writer.finish().unwrap();
+        assert_eq!(buf.len(), records.len() * TraceRecord::SIZE);
+
+        let read = BinaryTraceReader::new(buf.as_slice())
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(read, records);
+
+        let truncated = &buf[..buf.len() - 1];
+        let last = BinaryTraceReader::new(truncated).last().unwrap();
+        assert_eq!(last.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn trace_loop() {
+        // counts down from 100 to zero.
+        let code = vec![
+            opcode::PUSH1,
+            100,
+            opcode::JUMPDEST,
+            opcode::PUSH1,
+            1,
+            opcode::SWAP1,
+            opcode::SUB,
+            opcode::DUP1,
+            opcode::PUSH1,
+            2,
+            opcode::JUMPI,
+            opcode::STOP,
+        ];
+
+        let mut evm = evm_with_code(code);
+
+        let mut writer = BinaryTraceWriter::new(Vec::new());
+        let (result, _) = evm.inspect(&mut writer);
+        let buf = writer.finish().unwrap();
+        assert_eq!(result.exit_reason, Return::Stop);
+
+        let records = BinaryTraceReader::new(buf.as_slice())
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        // PUSH1, 100 iterations of 7 opcodes and final STOP.
+        assert_eq!(records.len(), 1 + 100 * 7 + 1);
+        assert_eq!(records[0].pc, 0);
+        assert_eq!(records[0].opcode, opcode::PUSH1);
+        assert_eq!(records[0].stack_len, 0);
+        assert_eq!(records[1].opcode, opcode::JUMPDEST);
+        assert_eq!(records[1].stack_len, 1);
+        assert_eq!(records.last().unwrap().opcode, opcode::STOP);
+        assert!(records.iter().all(|r| r.depth == 1));
+        assert!(records.windows(2).all(|w| w[0].gas >= w[1].gas));
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{alloc::vec::Vec, Database, EVMData, Inspector, Interpreter, Return};
+
+/// Inspector that counts executed steps per call depth.
+///
+/// The top level frame is at depth 1, so index 0 of [CallDepthInspector::depth_histogram] stays
+/// zero. Useful to find deeply nested or recursive execution.
+#[derive(Clone, Debug, Default)]
+pub struct CallDepthInspector {
+    steps: Vec<u64>,
+}
+
+impl CallDepthInspector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Deepest call depth that executed at least one step.
+    pub fn max_depth(&self) -> u64 {
+        self.steps.len().saturating_sub(1) as u64
+    }
+
+    /// Number of steps executed at every depth, indexed by depth.
+    pub fn depth_histogram(&self) -> Vec<u64> {
+        self.steps.clone()
+    }
+}
+
+impl<DB: Database> Inspector<DB> for CallDepthInspector {
+    fn step(
+        &mut self,
+        _interp: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        _is_static: bool,
+    ) -> Return {
+        let depth = data.journaled_state.depth() as usize;
+        if self.steps.len() <= depth {
+            self.steps.resize(depth + 1, 0);
+        }
+        self.steps[depth] += 1;
+        Return::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CallDepthInspector;
+    use crate::{opcode, test_utils::evm_with_code, Return};
+    use bytes::Bytes;
+    use primitive_types::U256;
+
+    #[test]
+    fn test_recursion_depth() {
+        // Calls itself with the calldata word decremented until it is zero.
+        let code = vec![
+            opcode::PUSH1,
+            0x00,
+            opcode::CALLDATALOAD,
+            opcode::DUP1,
+            opcode::ISZERO,
+            opcode::PUSH1,
+            0x1d,
+            opcode::JUMPI,
+            opcode::PUSH1,
+            0x01,
+            opcode::SWAP1,
+            opcode::SUB,
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x00, // out len
+            opcode::PUSH1,
+            0x00, // out offset
+            opcode::PUSH1,
+            0x20, // in len
+            opcode::PUSH1,
+            0x00, // in offset
+            opcode::PUSH1,
+            0x00, // value
+            opcode::ADDRESS,
+            opcode::GAS,
+            opcode::CALL,
+            opcode::STOP,
+            opcode::JUMPDEST,
+            opcode::STOP,
+        ];
+        let mut evm = evm_with_code(code);
+        let mut data = [0; 32];
+        U256::from(2).to_big_endian(&mut data);
+        evm.env.tx.data = Bytes::from(data.to_vec());
+        evm.env.tx.gas_limit = 1_000_000;
+
+        let mut inspector = CallDepthInspector::new();
+        let (result, _) = evm.inspect(&mut inspector);
+        assert_eq!(result.exit_reason, Return::Stop);
+        assert_eq!(inspector.max_depth(), 3);
+        // two recursing frames of 20 steps and the innermost one that jumps to the end.
+        assert_eq!(inspector.depth_histogram(), vec![0, 20, 20, 8]);
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{CallInputs, CreateInputs, Database, EVMData, Gas, Inspector, Return};
+use bytes::Bytes;
+use hashbrown::HashMap as Map;
+use primitive_types::H160;
+
+/// Inspector that keeps the output of the last call to every address.
+///
+/// Outputs are recorded in `call_end` for the call target and in `create_end` for the created
+/// address, reverted frames included. Useful to read back what each contract of a multicall
+/// returned.
+#[derive(Clone, Debug, Default)]
+pub struct CallOutputInspector {
+    outputs: Map<H160, Bytes>,
+}
+
+impl CallOutputInspector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Output of the last call that returned from `address`.
+    pub fn last_output(&self, address: H160) -> Option<Bytes> {
+        self.outputs.get(&address).cloned()
+    }
+}
+
+impl<DB: Database> Inspector<DB> for CallOutputInspector {
+    fn call_end(
+        &mut self,
+        _data: &mut EVMData<'_, DB>,
+        inputs: &CallInputs,
+        remaining_gas: Gas,
+        ret: Return,
+        out: Bytes,
+        _is_static: bool,
+    ) -> (Return, Gas, Bytes) {
+        self.outputs.insert(inputs.contract, out.clone());
+        (ret, remaining_gas, out)
+    }
+
+    fn create_end(
+        &mut self,
+        _data: &mut EVMData<'_, DB>,
+        _inputs: &CreateInputs,
+        ret: Return,
+        address: Option<H160>,
+        remaining_gas: Gas,
+        out: Bytes,
+    ) -> (Return, Option<H160>, Gas, Bytes) {
+        if let Some(address) = address {
+            self.outputs.insert(address, out.clone());
+        }
+        (ret, address, remaining_gas, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CallOutputInspector;
+    use crate::{opcode, test_utils::evm_with_db, AccountInfo, Bytecode, InMemoryDB, Return};
+    use bytes::Bytes;
+    use primitive_types::H160;
+
+    /// Code that returns the last `len` bytes of the word `value`.
+    fn returning(value: u8, len: u8) -> Bytecode {
+        Bytecode::new_raw(Bytes::from(vec![
+            opcode::PUSH1,
+            value,
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            len,
+            opcode::PUSH1,
+            0x20 - len,
+            opcode::RETURN,
+        ]))
+    }
+
+    #[test]
+    fn test_last_output() {
+        let (first, second) = (H160::from_low_u64_be(0xa0), H160::from_low_u64_be(0xb0));
+        let mut code = Vec::new();
+        for callee in [0xa0, 0xb0] {
+            code.extend([
+                opcode::PUSH1,
+                0x00, // out len
+                opcode::PUSH1,
+                0x00, // out offset
+                opcode::PUSH1,
+                0x00, // in len
+                opcode::PUSH1,
+                0x00, // in offset
+                opcode::PUSH1,
+                0x00, // value
+                opcode::PUSH1,
+                callee,
+                opcode::GAS,
+                opcode::CALL,
+                opcode::POP,
+            ]);
+        }
+        code.push(opcode::STOP);
+
+        let mut db = InMemoryDB::default();
+        for (address, code) in [
+            (H160::zero(), Bytecode::new_raw(Bytes::from(code))),
+            (first, returning(0xaa, 0x20)),
+            (second, returning(0xbb, 0x01)),
+        ] {
+            db.insert_account_info(
+                address,
+                AccountInfo {
+                    code: Some(code),
+                    ..Default::default()
+                },
+            );
+        }
+
+        let mut evm = evm_with_db(db);
+
+        let mut inspector = CallOutputInspector::new();
+        let (result, _) = evm.inspect(&mut inspector);
+        assert_eq!(result.exit_reason, Return::Stop);
+
+        let mut word = vec![0; 32];
+        word[31] = 0xaa;
+        assert_eq!(inspector.last_output(first), Some(Bytes::from(word)));
+        assert_eq!(inspector.last_output(second), Some(Bytes::from(vec![0xbb])));
+        assert_eq!(inspector.last_output(H160::zero()), Some(Bytes::new()));
+        assert_eq!(inspector.last_output(H160::repeat_byte(0x10)), None);
+    }
+}
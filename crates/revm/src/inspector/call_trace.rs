@@ -0,0 +1,299 @@
+use crate::{
+    alloc::vec::Vec, CallInputs, CallScheme, CreateInputs, CreateScheme, Database, EVMData, Gas,
+    Inspector, Return,
+};
+use bytes::Bytes;
+use primitive_types::{H160, U256};
+
+/// Kind of a [CallFrame].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CallKind {
+    Call,
+    CallCode,
+    DelegateCall,
+    StaticCall,
+    Create,
+    Create2,
+}
+
+impl From<CallScheme> for CallKind {
+    fn from(scheme: CallScheme) -> Self {
+        match scheme {
+            CallScheme::Call => Self::Call,
+            CallScheme::CallCode => Self::CallCode,
+            CallScheme::DelegateCall => Self::DelegateCall,
+            CallScheme::StaticCall => Self::StaticCall,
+        }
+    }
+}
+
+impl From<CreateScheme> for CallKind {
+    fn from(scheme: CreateScheme) -> Self {
+        match scheme {
+            CreateScheme::Create => Self::Create,
+            CreateScheme::Create2 { .. } => Self::Create2,
+        }
+    }
+}
+
+/// Call or create frame of a [CallTraceInspector] trace with the frames it called.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CallFrame {
+    pub kind: CallKind,
+    pub from: H160,
+    /// Callee, or the created address. `None` for a create that failed before the address was
+    /// derived.
+    pub to: Option<H160>,
+    /// Apparent value, the value of the parent for a `DELEGATECALL`.
+    pub value: U256,
+    /// Gas limit of the frame.
+    pub gas: u64,
+    /// Gas used by the frame, all of it if it failed with an error other than a revert.
+    pub gas_used: u64,
+    /// Call data or init code.
+    pub input: Bytes,
+    /// Return data, or the revert data if the frame reverted.
+    pub output: Bytes,
+    /// Result of a failed frame, `None` if it succeeded.
+    pub error: Option<Return>,
+    /// Frames called from this one in execution order, failed ones included.
+    pub calls: Vec<CallFrame>,
+}
+
+impl CallFrame {
+    fn new(
+        kind: CallKind,
+        from: H160,
+        to: Option<H160>,
+        value: U256,
+        gas: u64,
+        input: Bytes,
+    ) -> Self {
+        Self {
+            kind,
+            from,
+            to,
+            value,
+            gas,
+            gas_used: 0,
+            input,
+            output: Bytes::new(),
+            error: None,
+            calls: Vec::new(),
+        }
+    }
+}
+
+/// Inspector that assembles the calls and creates of a transaction into a tree of
+/// [CallFrame]s, like the `callTracer` of Geth.
+///
+/// Frames are opened in `call` and `create` and closed in the matching end hook, which are
+/// called for every frame, including the ones that fail before running code.
+#[derive(Clone, Debug, Default)]
+pub struct CallTraceInspector {
+    /// Open frames, the transaction frame first.
+    stack: Vec<CallFrame>,
+    root: Option<CallFrame>,
+}
+
+impl CallTraceInspector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Frame of the transaction. Panics if nothing was inspected or the transaction did not
+    /// finish.
+    pub fn into_trace(self) -> CallFrame {
+        assert!(self.stack.is_empty(), "transaction did not finish");
+        self.root.expect("no transaction was inspected")
+    }
+
+    fn close_frame(&mut self, to: Option<H160>, remaining_gas: Gas, ret: Return, out: &Bytes) {
+        let mut frame = match self.stack.pop() {
+            Some(frame) => frame,
+            None => return,
+        };
+        if to.is_some() {
+            frame.to = to;
+        }
+        frame.output = out.clone();
+        frame.gas_used = if ret.is_error() {
+            frame.gas
+        } else {
+            frame.gas.saturating_sub(remaining_gas.remaining())
+        };
+        if !ret.is_ok() {
+            frame.error = Some(ret);
+        }
+        match self.stack.last_mut() {
+            Some(parent) => parent.calls.push(frame),
+            None => self.root = Some(frame),
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for CallTraceInspector {
+    fn call(
+        &mut self,
+        _data: &mut EVMData<'_, DB>,
+        inputs: &mut CallInputs,
+        _is_static: bool,
+    ) -> (Return, Gas, Bytes) {
+        self.stack.push(CallFrame::new(
+            inputs.context.scheme.into(),
+            inputs.context.caller,
+            Some(inputs.contract),
+            inputs.context.apparent_value,
+            inputs.gas_limit,
+            inputs.input.clone(),
+        ));
+        (Return::Continue, Gas::new(0), Bytes::new())
+    }
+
+    fn call_end(
+        &mut self,
+        _data: &mut EVMData<'_, DB>,
+        _inputs: &CallInputs,
+        remaining_gas: Gas,
+        ret: Return,
+        out: Bytes,
+        _is_static: bool,
+    ) -> (Return, Gas, Bytes) {
+        self.close_frame(None, remaining_gas, ret, &out);
+        (ret, remaining_gas, out)
+    }
+
+    fn create(
+        &mut self,
+        _data: &mut EVMData<'_, DB>,
+        inputs: &mut CreateInputs,
+    ) -> (Return, Option<H160>, Gas, Bytes) {
+        self.stack.push(CallFrame::new(
+            inputs.scheme.into(),
+            inputs.caller,
+            None,
+            inputs.value,
+            inputs.gas_limit,
+            inputs.init_code.clone(),
+        ));
+        (Return::Continue, None, Gas::new(0), Bytes::default())
+    }
+
+    fn create_end(
+        &mut self,
+        _data: &mut EVMData<'_, DB>,
+        _inputs: &CreateInputs,
+        ret: Return,
+        address: Option<H160>,
+        remaining_gas: Gas,
+        out: Bytes,
+    ) -> (Return, Option<H160>, Gas, Bytes) {
+        self.close_frame(address, remaining_gas, ret, &out);
+        (ret, address, remaining_gas, out)
+    }
+}
+
+#[cfg(all(test, not(feature = "no_gas_measuring")))]
+mod tests {
+    use super::{CallKind, CallTraceInspector};
+    use crate::{
+        opcode, test_utils::evm_with_db, AccountInfo, Bytecode, InMemoryDB, Return, TransactTo,
+    };
+    use bytes::Bytes;
+    use primitive_types::{H160, U256};
+
+    /// Code that stores `value` at memory 0 and ends with `end` over the first byte.
+    fn ending(value: u8, end: u8) -> Vec<u8> {
+        vec![
+            opcode::PUSH1,
+            value,
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE8,
+            opcode::PUSH1,
+            0x01,
+            opcode::PUSH1,
+            0x00,
+            end,
+        ]
+    }
+
+    #[test]
+    fn test_call_tree() {
+        let (caller, contract) = (H160::repeat_byte(0x10), H160::repeat_byte(0x20));
+        let children = [
+            (H160::repeat_byte(0x30), ending(0x01, opcode::RETURN)),
+            (H160::repeat_byte(0x40), ending(0x02, opcode::REVERT)),
+            // fails and consumes all of its gas.
+            (H160::repeat_byte(0x50), vec![opcode::INVALID]),
+        ];
+        let mut db = InMemoryDB::default();
+        // call every child with 0x1000 gas and no call data.
+        let mut code = Vec::new();
+        for (address, child) in &children {
+            code.extend([opcode::PUSH1, 0x00, opcode::DUP1, opcode::DUP1]);
+            code.extend([opcode::DUP1, opcode::DUP1, opcode::PUSH20]);
+            code.extend_from_slice(address.as_bytes());
+            code.extend([opcode::PUSH2, 0x10, 0x00, opcode::CALL, opcode::POP]);
+            db.insert_account_info(
+                *address,
+                AccountInfo::new(
+                    U256::zero(),
+                    1,
+                    Bytecode::new_raw(Bytes::from(child.clone())),
+                ),
+            );
+        }
+        // create with a value the contract does not have.
+        code.extend([opcode::PUSH1, 0x00, opcode::DUP1, opcode::PUSH1, 0x01]);
+        code.extend([opcode::CREATE, opcode::POP, opcode::STOP]);
+        db.insert_account_info(
+            contract,
+            AccountInfo::new(U256::zero(), 1, Bytecode::new_raw(Bytes::from(code))),
+        );
+
+        let mut evm = evm_with_db(db);
+        evm.env.tx.transact_to = TransactTo::Call(contract);
+        evm.env.tx.data = Bytes::from_static(&[0xab]);
+
+        let mut inspector = CallTraceInspector::new();
+        let (result, _) = evm.inspect(&mut inspector);
+        assert_eq!(result.exit_reason, Return::Stop);
+        let trace = inspector.into_trace();
+
+        assert_eq!(trace.kind, CallKind::Call);
+        assert_eq!((trace.from, trace.to), (caller, Some(contract)));
+        assert_eq!(trace.input, Bytes::from_static(&[0xab]));
+        assert_eq!(trace.error, None);
+        assert_eq!(trace.gas, 100_000 - 21_016);
+        assert_eq!(trace.gas_used, result.gas_used - 21_016);
+        assert_eq!(trace.calls.len(), 4);
+
+        let [returned, reverted, invalid, create] = &trace.calls[..] else {
+            unreachable!()
+        };
+        for (frame, (address, _)) in trace.calls.iter().zip(&children) {
+            assert_eq!(frame.kind, CallKind::Call);
+            assert_eq!((frame.from, frame.to), (contract, Some(*address)));
+            assert_eq!(frame.gas, 0x1000);
+            assert!(frame.calls.is_empty());
+        }
+        assert_eq!(returned.error, None);
+        assert_eq!(returned.output, Bytes::from_static(&[0x01]));
+        assert_eq!(returned.gas_used, 18);
+
+        assert_eq!(reverted.error, Some(Return::Revert));
+        assert_eq!(reverted.output, Bytes::from_static(&[0x02]));
+        assert_eq!(reverted.gas_used, 18);
+
+        assert_eq!(invalid.error, Some(Return::InvalidOpcode));
+        assert_eq!(invalid.gas_used, 0x1000);
+
+        // fails before it starts, still has a frame.
+        assert_eq!(create.kind, CallKind::Create);
+        assert_eq!((create.from, create.to), (contract, None));
+        assert_eq!(create.value, U256::one());
+        assert_eq!(create.error, Some(Return::OutOfFund));
+        assert_eq!(create.gas_used, 0);
+    }
+}
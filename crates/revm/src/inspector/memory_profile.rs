@@ -0,0 +1,153 @@
+use crate::{
+    alloc::{collections::BTreeMap, vec::Vec},
+    opcode, Database, EVMData, Inspector, Interpreter, Return, Stack,
+};
+use core::ops::Range;
+
+/// Inspector that counts how often each memory word is accessed.
+///
+/// Memory ranges are taken from the stack operands in `step` and recorded in `step_end` once the
+/// instruction has succeeded, so only memory that was really expanded is counted. This makes it
+/// easy to spot contracts that pay expansion gas for sparse, high offsets.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryProfileInspector {
+    /// Word offset (byte offset / 32) to number of accesses.
+    accesses: BTreeMap<usize, u64>,
+    pending: Vec<Range<usize>>,
+}
+
+impl MemoryProfileInspector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accessed word offsets and their access count, sorted by offset.
+    pub fn access_histogram(&self) -> Vec<(usize, u64)> {
+        self.accesses
+            .iter()
+            .map(|(offset, count)| (*offset, *count))
+            .collect()
+    }
+}
+
+/// Length of a memory access, either read from the stack or fixed by the opcode.
+#[derive(Clone, Copy)]
+enum Len {
+    Stack(usize),
+    Fixed(usize),
+}
+
+/// Memory byte ranges that `opcode` is going to read or write.
+fn memory_ranges(opcode: u8, stack: &Stack) -> Vec<Range<usize>> {
+    use Len::*;
+    // stack position of the offset and the length of every memory operand.
+    let operands: &[(usize, Len)] = match opcode {
+        opcode::MLOAD | opcode::MSTORE => &[(0, Fixed(32))],
+        opcode::MSTORE8 => &[(0, Fixed(1))],
+        opcode::SHA3
+        | opcode::RETURN
+        | opcode::REVERT
+        | opcode::LOG0
+        | opcode::LOG1
+        | opcode::LOG2
+        | opcode::LOG3
+        | opcode::LOG4 => &[(0, Stack(1))],
+        opcode::CALLDATACOPY | opcode::CODECOPY | opcode::RETURNDATACOPY => &[(0, Stack(2))],
+        opcode::EXTCODECOPY => &[(1, Stack(3))],
+        opcode::CREATE | opcode::CREATE2 => &[(1, Stack(2))],
+        opcode::CALL | opcode::CALLCODE => &[(3, Stack(4)), (5, Stack(6))],
+        opcode::DELEGATECALL | opcode::STATICCALL => &[(2, Stack(3)), (4, Stack(5))],
+        _ => &[],
+    };
+
+    let peek = |index: usize| {
+        stack
+            .peek(index)
+            .ok()
+            .filter(|value| *value <= usize::MAX.into())
+            .map(|value| value.as_usize())
+    };
+
+    operands
+        .iter()
+        .filter_map(|(offset, len)| {
+            let offset = peek(*offset)?;
+            let len = match len {
+                Stack(index) => peek(*index)?,
+                Fixed(len) => *len,
+            };
+            if len == 0 {
+                return None;
+            }
+            Some(offset..offset.checked_add(len)?)
+        })
+        .collect()
+}
+
+impl<DB: Database> Inspector<DB> for MemoryProfileInspector {
+    fn step(
+        &mut self,
+        interp: &mut Interpreter,
+        _data: &mut EVMData<'_, DB>,
+        _is_static: bool,
+    ) -> Return {
+        self.pending = memory_ranges(interp.current_opcode(), &interp.stack);
+        Return::Continue
+    }
+
+    fn step_end(
+        &mut self,
+        interp: &mut Interpreter,
+        _data: &mut EVMData<'_, DB>,
+        _is_static: bool,
+        eval: Return,
+    ) -> Return {
+        let ok = matches!(
+            eval,
+            Return::Continue | Return::Return | Return::Stop | Return::Revert
+        );
+        for range in self.pending.drain(..) {
+            if !ok || range.end > interp.memory.len() {
+                continue;
+            }
+            for word in range.start / 32..=(range.end - 1) / 32 {
+                *self.accesses.entry(word).or_default() += 1;
+            }
+        }
+        Return::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MemoryProfileInspector;
+    use crate::{opcode, test_utils::evm_with_code, Return};
+
+    #[test]
+    fn test_distant_offsets() {
+        let code = vec![
+            opcode::PUSH1,
+            0x01,
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x02,
+            opcode::PUSH2,
+            0x10,
+            0x00,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x00,
+            opcode::MLOAD,
+            opcode::STOP,
+        ];
+
+        let mut evm = evm_with_code(code);
+
+        let mut inspector = MemoryProfileInspector::new();
+        let (result, _) = evm.inspect(&mut inspector);
+        assert_eq!(result.exit_reason, Return::Stop);
+        assert_eq!(inspector.access_histogram(), vec![(0, 2), (0x1000 / 32, 1)]);
+    }
+}
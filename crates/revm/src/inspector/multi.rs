@@ -0,0 +1,356 @@
+use crate::{
+    alloc::{boxed::Box, vec::Vec},
+    CallInputs, CreateInputs, Database, EVMData, Gas, Inspector, Interpreter, Return,
+};
+use bytes::Bytes;
+use primitive_types::{H160, H256, U256};
+
+/// Inspector that runs several inspectors in the order they were added.
+///
+/// Every hook is called on every inspector. Hooks that return a [Return] give the first one
+/// that is not [Return::Continue], so any inspector can halt or override. The end hooks of calls
+/// and creates thread their result through the inspectors, each one sees the result the one
+/// before it returned.
+pub struct MultiInspector<'a, DB: Database> {
+    inspectors: Vec<Box<dyn Inspector<DB> + 'a>>,
+}
+
+impl<DB: Database> Default for MultiInspector<'_, DB> {
+    fn default() -> Self {
+        Self {
+            inspectors: Vec::new(),
+        }
+    }
+}
+
+impl<'a, DB: Database> MultiInspector<'a, DB> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `inspector` after the ones added before.
+    pub fn with(mut self, inspector: impl Inspector<DB> + 'a) -> Self {
+        self.push(inspector);
+        self
+    }
+
+    /// Run `inspector` after the ones added before.
+    pub fn push(&mut self, inspector: impl Inspector<DB> + 'a) {
+        self.inspectors.push(Box::new(inspector));
+    }
+
+    pub fn len(&self) -> usize {
+        self.inspectors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inspectors.is_empty()
+    }
+
+    /// Call `hook` on every inspector, the first result other than [Return::Continue] wins.
+    fn first_halt(
+        &mut self,
+        mut hook: impl FnMut(&mut (dyn Inspector<DB> + 'a)) -> Return,
+    ) -> Return {
+        let mut ret = Return::Continue;
+        for inspector in &mut self.inspectors {
+            let inspector_ret = hook(inspector.as_mut());
+            if ret == Return::Continue {
+                ret = inspector_ret;
+            }
+        }
+        ret
+    }
+}
+
+impl<DB: Database> Inspector<DB> for MultiInspector<'_, DB> {
+    fn initialize_interp(
+        &mut self,
+        interp: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        is_static: bool,
+    ) -> Return {
+        self.first_halt(|inspector| inspector.initialize_interp(interp, data, is_static))
+    }
+
+    fn step(
+        &mut self,
+        interp: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        is_static: bool,
+    ) -> Return {
+        self.first_halt(|inspector| inspector.step(interp, data, is_static))
+    }
+
+    fn log(
+        &mut self,
+        evm_data: &mut EVMData<'_, DB>,
+        address: &H160,
+        topics: &[H256],
+        data: &Bytes,
+    ) {
+        for inspector in &mut self.inspectors {
+            inspector.log(evm_data, address, topics, data);
+        }
+    }
+
+    fn step_end(
+        &mut self,
+        interp: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        is_static: bool,
+        eval: Return,
+    ) -> Return {
+        self.first_halt(|inspector| inspector.step_end(interp, data, is_static, eval))
+    }
+
+    fn call(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        inputs: &mut CallInputs,
+        is_static: bool,
+    ) -> (Return, Gas, Bytes) {
+        let mut result = (Return::Continue, Gas::new(0), Bytes::new());
+        for inspector in &mut self.inspectors {
+            let ret = inspector.call(data, inputs, is_static);
+            if result.0 == Return::Continue {
+                result = ret;
+            }
+        }
+        result
+    }
+
+    fn call_end(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        inputs: &CallInputs,
+        remaining_gas: Gas,
+        ret: Return,
+        out: Bytes,
+        is_static: bool,
+    ) -> (Return, Gas, Bytes) {
+        let mut result = (ret, remaining_gas, out);
+        for inspector in &mut self.inspectors {
+            let (ret, remaining_gas, out) = result;
+            result = inspector.call_end(data, inputs, remaining_gas, ret, out, is_static);
+        }
+        result
+    }
+
+    fn create(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        inputs: &mut CreateInputs,
+    ) -> (Return, Option<H160>, Gas, Bytes) {
+        let mut result = (Return::Continue, None, Gas::new(0), Bytes::default());
+        for inspector in &mut self.inspectors {
+            let ret = inspector.create(data, inputs);
+            if result.0 == Return::Continue {
+                result = ret;
+            }
+        }
+        result
+    }
+
+    fn create_end(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        inputs: &CreateInputs,
+        ret: Return,
+        address: Option<H160>,
+        remaining_gas: Gas,
+        out: Bytes,
+    ) -> (Return, Option<H160>, Gas, Bytes) {
+        let mut result = (ret, address, remaining_gas, out);
+        for inspector in &mut self.inspectors {
+            let (ret, address, remaining_gas, out) = result;
+            result = inspector.create_end(data, inputs, ret, address, remaining_gas, out);
+        }
+        result
+    }
+
+    fn sload(&mut self, address: H160, slot: U256, value: U256, is_cold: bool) {
+        for inspector in &mut self.inspectors {
+            inspector.sload(address, slot, value, is_cold);
+        }
+    }
+
+    fn sstore(
+        &mut self,
+        address: H160,
+        slot: U256,
+        original: U256,
+        current: U256,
+        new: U256,
+        is_cold: bool,
+    ) {
+        for inspector in &mut self.inspectors {
+            inspector.sstore(address, slot, original, current, new, is_cold);
+        }
+    }
+
+    fn selfdestruct(&mut self, contract: H160, target: H160, value: U256) {
+        for inspector in &mut self.inspectors {
+            inspector.selfdestruct(contract, target, value);
+        }
+    }
+
+    fn jump(&mut self, from_pc: usize, to_pc: usize, taken: bool) {
+        for inspector in &mut self.inspectors {
+            inspector.jump(from_pc, to_pc, taken);
+        }
+    }
+
+    fn gas_block(&mut self, pc: usize, block_cost: u64, remaining: u64) {
+        for inspector in &mut self.inspectors {
+            inspector.gas_block(pc, block_cost, remaining);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MultiInspector;
+    use crate::{
+        db::BenchmarkDB, opcode, test_utils::evm_with_code, CallInputs, CallTraceInspector,
+        Database, EVMData, Gas, GasInspector, Inspector, Interpreter, Return,
+    };
+    use bytes::Bytes;
+
+    /// Counts steps and calls.
+    #[derive(Default)]
+    struct Counter {
+        steps: usize,
+        step_ends: usize,
+        calls: usize,
+        /// Output of every call_end.
+        outputs: Vec<Bytes>,
+    }
+
+    impl<DB: Database> Inspector<DB> for Counter {
+        fn step(
+            &mut self,
+            _interp: &mut Interpreter,
+            _data: &mut EVMData<'_, DB>,
+            _is_static: bool,
+        ) -> Return {
+            self.steps += 1;
+            Return::Continue
+        }
+
+        fn step_end(
+            &mut self,
+            _interp: &mut Interpreter,
+            _data: &mut EVMData<'_, DB>,
+            _is_static: bool,
+            _eval: Return,
+        ) -> Return {
+            self.step_ends += 1;
+            Return::Continue
+        }
+
+        fn call(
+            &mut self,
+            _data: &mut EVMData<'_, DB>,
+            _inputs: &mut CallInputs,
+            _is_static: bool,
+        ) -> (Return, Gas, Bytes) {
+            self.calls += 1;
+            (Return::Continue, Gas::new(0), Bytes::new())
+        }
+
+        fn call_end(
+            &mut self,
+            _data: &mut EVMData<'_, DB>,
+            _inputs: &CallInputs,
+            remaining_gas: Gas,
+            ret: Return,
+            out: Bytes,
+            _is_static: bool,
+        ) -> (Return, Gas, Bytes) {
+            self.outputs.push(out.clone());
+            (ret, remaining_gas, Bytes::from_static(&[0x01]))
+        }
+    }
+
+    /// Halts with `Return::Revert` on the first `ADD`.
+    struct HaltOnAdd;
+
+    impl<DB: Database> Inspector<DB> for HaltOnAdd {
+        fn step(
+            &mut self,
+            interp: &mut Interpreter,
+            _data: &mut EVMData<'_, DB>,
+            _is_static: bool,
+        ) -> Return {
+            if interp.current_opcode() == opcode::ADD {
+                return Return::Revert;
+            }
+            Return::Continue
+        }
+    }
+
+    fn evm() -> crate::EVM<BenchmarkDB> {
+        // CALL of ecrecover with no gas and no input, then an ADD.
+        let code = vec![
+            opcode::PUSH1,
+            0x00,
+            opcode::DUP1,
+            opcode::DUP1,
+            opcode::DUP1,
+            opcode::DUP1,
+            opcode::PUSH1,
+            0x01,
+            opcode::DUP2,
+            opcode::CALL,
+            opcode::DUP1,
+            opcode::ADD,
+            opcode::STOP,
+        ];
+        evm_with_code(code)
+    }
+
+    #[test]
+    fn test_every_inspector_observes() {
+        let mut single = Counter::default();
+        let (result, _) = evm().inspect(&mut single);
+        assert_eq!(result.exit_reason, Return::Stop);
+        assert_eq!(single.steps, 11);
+        assert_eq!(single.calls, 2);
+
+        let (mut first, mut second) = (Counter::default(), Counter::default());
+        let mut trace = CallTraceInspector::new();
+        let multi = MultiInspector::new()
+            .with(GasInspector::default())
+            .with(&mut first)
+            .with(&mut trace)
+            .with(&mut second);
+        assert_eq!(multi.len(), 4);
+        let (multi_result, _) = evm().inspect(multi);
+        assert_eq!(multi_result.gas_used, result.gas_used);
+
+        for counter in [&first, &second] {
+            assert_eq!(counter.steps, single.steps);
+            assert_eq!(counter.step_ends, single.step_ends);
+            assert_eq!(counter.calls, single.calls);
+        }
+        // the output each counter replaces is what the next one sees.
+        assert_eq!(first.outputs, vec![Bytes::new(), Bytes::new()]);
+        assert_eq!(second.outputs, vec![Bytes::from_static(&[0x01]); 2]);
+        let trace = trace.into_trace();
+        assert_eq!(trace.calls.len(), 1);
+        assert_eq!(trace.calls[0].output, Bytes::from_static(&[0x01]));
+        assert_eq!(trace.output, Bytes::from_static(&[0x01]));
+    }
+
+    #[test]
+    fn test_any_inspector_halts() {
+        let mut counter = Counter::default();
+        let multi = MultiInspector::new().with(&mut counter).with(HaltOnAdd);
+        let (result, _) = evm().inspect(multi);
+        assert_eq!(result.exit_reason, Return::Revert);
+        // the counter still saw the step of the ADD, which never ended.
+        assert_eq!(counter.steps, 10);
+        assert_eq!(counter.step_ends, 9);
+    }
+}
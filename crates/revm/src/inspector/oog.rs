@@ -0,0 +1,125 @@
+use crate::{
+    alloc::vec::Vec, CallInputs, CreateInputs, Database, EVMData, Gas, Inspector, Interpreter,
+    Return,
+};
+use bytes::Bytes;
+use primitive_types::H160;
+
+/// Finds the first frame that ran out of gas and the opcode it failed at.
+///
+/// Needs [crate::GasBlockGranularity::PerOpcode] to point at the exact opcode, with whole gas
+/// blocks the failure is reported at the start of the block. A precompile that runs out of gas is
+/// reported at the CALL that invoked it.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct OogLocator {
+    /// Last `(pc, opcode)` stepped at every depth.
+    last_step: Vec<Option<(usize, u8)>>,
+    /// `(depth, pc, opcode)` of the first out of gas.
+    pub(crate) location: Option<(u64, usize, u8)>,
+}
+
+impl OogLocator {
+    fn enter(&mut self, data: &EVMData<'_, impl Database>) {
+        let depth = data.journaled_state.depth() as usize + 1;
+        self.last_step.resize(depth + 1, None);
+        self.last_step[depth] = None;
+    }
+
+    fn exit(&mut self, data: &EVMData<'_, impl Database>, ret: Return) {
+        if ret != Return::OutOfGas || self.location.is_some() {
+            return;
+        }
+        let depth = data.journaled_state.depth() as usize + 1;
+        self.location = self.last_step[depth]
+            .map(|step| (depth, step))
+            .or_else(|| self.last_step[depth - 1].map(|step| (depth - 1, step)))
+            .map(|(depth, (pc, opcode))| (depth as u64, pc, opcode));
+    }
+}
+
+impl<DB: Database> Inspector<DB> for OogLocator {
+    fn step(
+        &mut self,
+        interp: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        _is_static: bool,
+    ) -> Return {
+        let depth = data.journaled_state.depth() as usize;
+        self.last_step[depth] = Some((interp.program_counter(), interp.current_opcode()));
+        Return::Continue
+    }
+
+    fn call(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        _inputs: &mut CallInputs,
+        _is_static: bool,
+    ) -> (Return, Gas, Bytes) {
+        self.enter(data);
+        (Return::Continue, Gas::new(0), Bytes::new())
+    }
+
+    fn call_end(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        _inputs: &CallInputs,
+        remaining_gas: Gas,
+        ret: Return,
+        out: Bytes,
+        _is_static: bool,
+    ) -> (Return, Gas, Bytes) {
+        self.exit(data, ret);
+        (ret, remaining_gas, out)
+    }
+
+    fn create(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        _inputs: &mut CreateInputs,
+    ) -> (Return, Option<H160>, Gas, Bytes) {
+        self.enter(data);
+        (Return::Continue, None, Gas::new(0), Bytes::default())
+    }
+
+    fn create_end(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        _inputs: &CreateInputs,
+        ret: Return,
+        address: Option<H160>,
+        remaining_gas: Gas,
+        out: Bytes,
+    ) -> (Return, Option<H160>, Gas, Bytes) {
+        self.exit(data, ret);
+        (ret, address, remaining_gas, out)
+    }
+}
+
+#[cfg(all(test, not(feature = "no_gas_measuring")))]
+mod tests {
+    use crate::{opcode, test_utils::evm_with_code};
+
+    #[test]
+    fn test_oog_location() {
+        let code = vec![
+            opcode::PUSH1,
+            0x01,
+            opcode::PUSH1,
+            0x00,
+            opcode::SSTORE,
+            opcode::STOP,
+        ];
+        let mut evm = evm_with_code(code);
+        evm.env.tx.gas_limit = 21_000 + 1_000;
+
+        // two PUSH1 and a cold SSTORE that sets a slot need 6 + 2100 + 20000 gas.
+        assert_eq!(
+            evm.oog_location(),
+            Some((4, opcode::SSTORE, 6 + 22_100 - 1_000))
+        );
+        assert_eq!(evm.env.tx.gas_limit, 22_000);
+
+        evm.env.tx.gas_limit = 21_000 + 22_106;
+        assert_eq!(evm.oog_location(), None);
+    }
+}
@@ -0,0 +1,272 @@
+use crate::{
+    alloc::{collections::BTreeMap, vec::Vec},
+    CallInputs, CreateInputs, Database, EVMData, Gas, GasInspector, Inspector, Interpreter, OpCode,
+    Return,
+};
+use bytes::Bytes;
+use primitive_types::H160;
+
+/// Gas statistics of one opcode.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct OpcodeGasStats {
+    pub count: u64,
+    pub total: u64,
+    pub min: u64,
+    pub max: u64,
+}
+
+impl OpcodeGasStats {
+    pub fn average(&self) -> u64 {
+        self.total.checked_div(self.count).unwrap_or_default()
+    }
+
+    fn record(&mut self, gas: u64) {
+        if self.count == 0 {
+            self.min = gas;
+            self.max = gas;
+        } else {
+            self.min = self.min.min(gas);
+            self.max = self.max.max(gas);
+        }
+        self.count += 1;
+        self.total += gas;
+    }
+}
+
+#[derive(Clone, Default)]
+struct Frame {
+    gas_inspector: GasInspector,
+    /// Opcode in execution and gas remaining before it.
+    pending: Option<(u8, u64)>,
+}
+
+/// Inspector that collects min, max, average and total gas of every executed opcode, the gas
+/// profiler of revm.
+///
+/// Gas of a step is the difference of [GasInspector::gas_remaining] before and after it, so gas of
+/// sub calls is included in the CALL and CREATE opcodes. Gas is tracked separately for every call
+/// frame.
+#[derive(Clone, Default)]
+pub struct OpcodeGasInspector {
+    frames: Vec<Frame>,
+    stats: BTreeMap<u8, OpcodeGasStats>,
+}
+
+/// Per opcode gas profiler, see [OpcodeGasInspector].
+pub type GasProfiler = OpcodeGasInspector;
+
+impl OpcodeGasInspector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Statistics of executed opcodes, sorted by total gas from most expensive.
+    pub fn table(&self) -> Vec<(OpCode, OpcodeGasStats)> {
+        let mut table: Vec<_> = self
+            .stats
+            .iter()
+            .map(|(opcode, stats)| (OpCode::new_unchecked(*opcode), *stats))
+            .collect();
+        table.sort_by(|(a, a_stats), (b, b_stats)| {
+            b_stats.total.cmp(&a_stats.total).then(a.u8().cmp(&b.u8()))
+        });
+        table
+    }
+
+    /// Count and total gas of executed opcodes, in the order of [OpcodeGasInspector::table].
+    pub fn report(&self) -> Vec<(OpCode, (u64, u64))> {
+        self.table()
+            .into_iter()
+            .map(|(opcode, stats)| (opcode, (stats.count, stats.total)))
+            .collect()
+    }
+
+    fn frame<DB: Database>(&mut self, data: &EVMData<'_, DB>) -> Option<&mut Frame> {
+        let depth = data.journaled_state.depth() as usize;
+        self.frames.truncate(depth);
+        self.frames.last_mut()
+    }
+}
+
+impl<DB: Database> Inspector<DB> for OpcodeGasInspector {
+    fn initialize_interp(
+        &mut self,
+        interp: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        is_static: bool,
+    ) -> Return {
+        let depth = data.journaled_state.depth() as usize;
+        self.frames.truncate(depth.saturating_sub(1));
+        let mut frame = Frame::default();
+        frame
+            .gas_inspector
+            .initialize_interp(interp, data, is_static);
+        self.frames.push(frame);
+        Return::Continue
+    }
+
+    fn step(
+        &mut self,
+        interp: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        is_static: bool,
+    ) -> Return {
+        if let Some(frame) = self.frame(data) {
+            frame.pending = Some((interp.current_opcode(), frame.gas_inspector.gas_remaining()));
+            frame.gas_inspector.step(interp, data, is_static);
+        }
+        Return::Continue
+    }
+
+    fn step_end(
+        &mut self,
+        interp: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        is_static: bool,
+        eval: Return,
+    ) -> Return {
+        let frame = match self.frame(data) {
+            Some(frame) => frame,
+            None => return Return::Continue,
+        };
+        frame.gas_inspector.step_end(interp, data, is_static, eval);
+        if let Some((opcode, gas_before)) = frame.pending.take() {
+            let gas = gas_before.saturating_sub(frame.gas_inspector.gas_remaining());
+            self.stats.entry(opcode).or_default().record(gas);
+        }
+        Return::Continue
+    }
+
+    fn call_end(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        inputs: &CallInputs,
+        remaining_gas: Gas,
+        ret: Return,
+        out: Bytes,
+        is_static: bool,
+    ) -> (Return, Gas, Bytes) {
+        if let Some(frame) = self.frame(data) {
+            frame
+                .gas_inspector
+                .call_end(data, inputs, remaining_gas, ret, out.clone(), is_static);
+        }
+        (ret, remaining_gas, out)
+    }
+
+    fn create_end(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        inputs: &CreateInputs,
+        ret: Return,
+        address: Option<H160>,
+        remaining_gas: Gas,
+        out: Bytes,
+    ) -> (Return, Option<H160>, Gas, Bytes) {
+        if let Some(frame) = self.frame(data) {
+            frame
+                .gas_inspector
+                .create_end(data, inputs, ret, address, remaining_gas, out.clone());
+        }
+        (ret, address, remaining_gas, out)
+    }
+}
+
+#[cfg(all(test, not(feature = "no_gas_measuring")))]
+mod tests {
+    use super::{OpcodeGasInspector, OpcodeGasStats};
+    use crate::{opcode, test_utils::evm_with_code, OpCode, Return};
+
+    #[test]
+    fn test_sload_warmth() {
+        let code = vec![
+            opcode::PUSH1,
+            0x00,
+            opcode::SLOAD,
+            opcode::POP,
+            opcode::PUSH1,
+            0x00,
+            opcode::SLOAD,
+            opcode::POP,
+            opcode::PUSH1,
+            0x01,
+            opcode::SLOAD,
+            opcode::POP,
+            opcode::STOP,
+        ];
+
+        let mut evm = evm_with_code(code);
+
+        let mut inspector = OpcodeGasInspector::new();
+        let (result, _) = evm.inspect(&mut inspector);
+        assert_eq!(result.exit_reason, Return::Stop);
+
+        let table: Vec<_> = inspector
+            .table()
+            .into_iter()
+            .map(|(op, stats)| (op.as_str(), stats))
+            .collect();
+        let stats = |count, total, min, max| OpcodeGasStats {
+            count,
+            total,
+            min,
+            max,
+        };
+        assert_eq!(
+            table,
+            vec![
+                // two cold and one warm access.
+                ("SLOAD", stats(3, 4300, 100, 2100)),
+                ("PUSH1", stats(3, 9, 3, 3)),
+                ("POP", stats(3, 6, 2, 2)),
+                ("STOP", stats(1, 0, 0, 0)),
+            ]
+        );
+        assert_eq!(table[0].1.average(), 1433);
+    }
+
+    #[test]
+    fn test_totals_across_gas_blocks() {
+        // JUMPI and JUMPDEST split the code in gas blocks, MSTORE adds memory expansion.
+        let code = vec![
+            opcode::PUSH1,
+            0x01,
+            opcode::PUSH1,
+            0x07,
+            opcode::JUMPI,
+            opcode::INVALID,
+            opcode::INVALID,
+            opcode::JUMPDEST,
+            opcode::PUSH1,
+            0x2a,
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE,
+            opcode::STOP,
+        ];
+
+        let mut evm = evm_with_code(code);
+
+        let mut inspector = OpcodeGasInspector::new();
+        let (result, _) = evm.inspect(&mut inspector);
+        assert_eq!(result.exit_reason, Return::Stop);
+
+        let table = inspector.table();
+        let total = |name| {
+            table
+                .iter()
+                .find(|(op, _)| op.as_str() == name)
+                .map(|(_, stats)| stats.total)
+        };
+        assert_eq!(total("JUMPI"), Some(10));
+        assert_eq!(total("JUMPDEST"), Some(1));
+        assert_eq!(total("MSTORE"), Some(6));
+        assert_eq!(total("PUSH1"), Some(12));
+        assert_eq!(total("INVALID"), None);
+
+        let report = inspector.report();
+        assert_eq!(report[0], (OpCode::new_unchecked(opcode::PUSH1), (4, 12)));
+        let sum: u64 = report.iter().map(|(_, (_, total))| total).sum();
+        assert_eq!(sum, result.gas_used - 21000);
+    }
+}
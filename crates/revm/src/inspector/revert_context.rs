@@ -0,0 +1,95 @@
+use crate::{
+    alloc::collections::BTreeMap, opcode, Database, EVMData, Inspector, Interpreter, Memory,
+    Return, Stack,
+};
+
+/// Inspector that keeps the memory and stack of frames that executed `REVERT`.
+///
+/// Both are snapshotted in `step` just before `REVERT` runs and kept in `step_end` if the
+/// instruction really reverted. Frames are keyed by call depth and a later revert at the same
+/// depth replaces the earlier one.
+#[derive(Clone, Debug, Default)]
+pub struct RevertInspector {
+    contexts: BTreeMap<u64, (Memory, Stack)>,
+    pending: Option<(Memory, Stack)>,
+}
+
+impl RevertInspector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Memory and stack of the last frame at `depth` that reverted.
+    pub fn revert_context(&self, depth: u64) -> Option<(Memory, Stack)> {
+        self.contexts.get(&depth).cloned()
+    }
+}
+
+impl<DB: Database> Inspector<DB> for RevertInspector {
+    fn step(
+        &mut self,
+        interp: &mut Interpreter,
+        _data: &mut EVMData<'_, DB>,
+        _is_static: bool,
+    ) -> Return {
+        if interp.current_opcode() == opcode::REVERT {
+            self.pending = Some((interp.memory.clone(), interp.stack.clone()));
+        }
+        Return::Continue
+    }
+
+    fn step_end(
+        &mut self,
+        _interp: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        _is_static: bool,
+        eval: Return,
+    ) -> Return {
+        if let Some(context) = self.pending.take() {
+            if eval == Return::Revert {
+                self.contexts.insert(data.journaled_state.depth(), context);
+            }
+        }
+        Return::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RevertInspector;
+    use crate::{opcode, test_utils::evm_with_code, Return};
+
+    use primitive_types::U256;
+
+    #[test]
+    fn test_revert_context() {
+        let code = vec![
+            opcode::PUSH4,
+            0xde,
+            0xad,
+            0xbe,
+            0xef,
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x04,
+            opcode::PUSH1,
+            0x1c,
+            opcode::REVERT,
+        ];
+
+        let mut evm = evm_with_code(code);
+
+        let mut inspector = RevertInspector::new();
+        let (result, _) = evm.inspect(&mut inspector);
+        assert_eq!(result.exit_reason, Return::Revert);
+
+        let (memory, stack) = inspector.revert_context(1).unwrap();
+        let mut expected = vec![0u8; 32];
+        expected[28..].copy_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(memory.get_slice(0, 32), expected.as_slice());
+        assert_eq!(stack.data(), &vec![U256::from(4), U256::from(0x1c)]);
+        assert!(inspector.revert_context(2).is_none());
+    }
+}
@@ -0,0 +1,110 @@
+use crate::{alloc::vec::Vec, opcode, Database, EVMData, Inspector, Interpreter, Return};
+use primitive_types::{H160, U256};
+
+/// One SLOAD or SSTORE, see [StorageAccessInspector].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StorageAccess {
+    pub address: H160,
+    pub slot: U256,
+    pub is_write: bool,
+}
+
+/// Inspector that records every storage access in execution order.
+///
+/// Unlike an access list every SLOAD and SSTORE is kept, duplicates included, so a prover can
+/// replay the reads in sequence. Accesses of reverted frames are kept too, only opcodes that
+/// failed themselves are left out.
+#[derive(Clone, Debug, Default)]
+pub struct StorageAccessInspector {
+    accesses: Vec<StorageAccess>,
+    pending: Option<StorageAccess>,
+}
+
+impl StorageAccessInspector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn access_sequence(&self) -> &[StorageAccess] {
+        &self.accesses
+    }
+}
+
+impl<DB: Database> Inspector<DB> for StorageAccessInspector {
+    fn step(
+        &mut self,
+        interp: &mut Interpreter,
+        _data: &mut EVMData<'_, DB>,
+        _is_static: bool,
+    ) -> Return {
+        let is_write = match interp.current_opcode() {
+            opcode::SLOAD => false,
+            opcode::SSTORE => true,
+            _ => return Return::Continue,
+        };
+        if let Ok(slot) = interp.stack.peek(0) {
+            self.pending = Some(StorageAccess {
+                address: interp.contract.address,
+                slot,
+                is_write,
+            });
+        }
+        Return::Continue
+    }
+
+    fn step_end(
+        &mut self,
+        _interp: &mut Interpreter,
+        _data: &mut EVMData<'_, DB>,
+        _is_static: bool,
+        eval: Return,
+    ) -> Return {
+        if let Some(access) = self.pending.take() {
+            if eval == Return::Continue {
+                self.accesses.push(access);
+            }
+        }
+        Return::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StorageAccess, StorageAccessInspector};
+    use crate::{opcode, test_utils::evm_with_code, Return};
+
+    use primitive_types::{H160, U256};
+
+    #[test]
+    fn test_access_sequence() {
+        // SLOAD(1) twice, then SSTORE(2, sum).
+        let code = vec![
+            opcode::PUSH1,
+            0x01,
+            opcode::SLOAD,
+            opcode::PUSH1,
+            0x01,
+            opcode::SLOAD,
+            opcode::ADD,
+            opcode::PUSH1,
+            0x02,
+            opcode::SSTORE,
+            opcode::STOP,
+        ];
+        let mut evm = evm_with_code(code);
+
+        let mut inspector = StorageAccessInspector::new();
+        let (result, _) = evm.inspect(&mut inspector);
+        assert_eq!(result.exit_reason, Return::Stop);
+
+        let access = |slot: u64, is_write| StorageAccess {
+            address: H160::zero(),
+            slot: U256::from(slot),
+            is_write,
+        };
+        assert_eq!(
+            inspector.access_sequence(),
+            &[access(1, false), access(1, false), access(2, true)]
+        );
+    }
+}
@@ -0,0 +1,269 @@
+use crate::{
+    alloc::vec::Vec, CallInputs, CreateInputs, Database, EVMData, Gas, Inspector, Interpreter,
+    OpCode, Return,
+};
+use ::tracing::{field, span::EnteredSpan, Level};
+use bytes::Bytes;
+use primitive_types::H160;
+
+/// Span or event at a level only known at runtime, the `tracing` macros take constant levels.
+macro_rules! at_level {
+    ($macro:ident, $level:expr, $($args:tt)*) => {
+        match $level {
+            Level::ERROR => ::tracing::$macro!(Level::ERROR, $($args)*),
+            Level::WARN => ::tracing::$macro!(Level::WARN, $($args)*),
+            Level::INFO => ::tracing::$macro!(Level::INFO, $($args)*),
+            Level::DEBUG => ::tracing::$macro!(Level::DEBUG, $($args)*),
+            _ => ::tracing::$macro!(Level::TRACE, $($args)*),
+        }
+    };
+}
+
+/// Inspector that emits every call frame as a `tracing` span and every opcode as an event.
+///
+/// Call spans are named `call` with the fields `depth`, `caller`, `address`, `gas` (the gas
+/// limit of the frame) and `is_static`, create spans are named `create` and record `address`
+/// once the contract is created. Both record `result` when the frame ends. Opcode events are
+/// emitted in the span of their frame with the fields `pc`, `opcode`, `gas` (remaining) and
+/// `depth`. Frames are at [Level::INFO] and opcodes at [Level::TRACE] unless configured
+/// otherwise.
+#[derive(Debug)]
+pub struct TracingInspector {
+    frame_level: Level,
+    opcode_level: Level,
+    /// Entered spans of the running frames with their depth.
+    spans: Vec<(u64, EnteredSpan)>,
+}
+
+impl Default for TracingInspector {
+    fn default() -> Self {
+        Self {
+            frame_level: Level::INFO,
+            opcode_level: Level::TRACE,
+            spans: Vec::new(),
+        }
+    }
+}
+
+impl TracingInspector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Level of the call and create spans.
+    pub fn with_frame_level(mut self, level: Level) -> Self {
+        self.frame_level = level;
+        self
+    }
+
+    /// Level of the opcode events.
+    pub fn with_opcode_level(mut self, level: Level) -> Self {
+        self.opcode_level = level;
+        self
+    }
+
+    /// Exit the spans of frames deeper than `depth`, in case a frame ended without its end hook.
+    fn exit_below(&mut self, depth: u64) {
+        while matches!(self.spans.last(), Some((span_depth, _)) if *span_depth > depth) {
+            self.spans.pop();
+        }
+    }
+
+    fn exit_frame(&mut self, ret: Return) {
+        if let Some((_, span)) = self.spans.pop() {
+            span.record("result", field::debug(ret));
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for TracingInspector {
+    fn step(
+        &mut self,
+        interp: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        _is_static: bool,
+    ) -> Return {
+        let depth = data.journaled_state.depth();
+        self.exit_below(depth);
+        let opcode = OpCode::new_unchecked(interp.current_opcode());
+        at_level!(
+            event,
+            self.opcode_level,
+            pc = interp.program_counter(),
+            opcode = opcode.as_str(),
+            gas = interp.gas.remaining(),
+            depth,
+        );
+        Return::Continue
+    }
+
+    fn call(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        inputs: &mut CallInputs,
+        is_static: bool,
+    ) -> (Return, Gas, Bytes) {
+        let depth = data.journaled_state.depth();
+        self.exit_below(depth);
+        let span = at_level!(
+            span,
+            self.frame_level,
+            "call",
+            depth = depth + 1,
+            caller = ?inputs.context.caller,
+            address = ?inputs.contract,
+            gas = inputs.gas_limit,
+            is_static,
+            result = field::Empty,
+        );
+        self.spans.push((depth + 1, span.entered()));
+        (Return::Continue, Gas::new(0), Bytes::new())
+    }
+
+    fn call_end(
+        &mut self,
+        _data: &mut EVMData<'_, DB>,
+        _inputs: &CallInputs,
+        remaining_gas: Gas,
+        ret: Return,
+        out: Bytes,
+        _is_static: bool,
+    ) -> (Return, Gas, Bytes) {
+        self.exit_frame(ret);
+        (ret, remaining_gas, out)
+    }
+
+    fn create(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        inputs: &mut CreateInputs,
+    ) -> (Return, Option<H160>, Gas, Bytes) {
+        let depth = data.journaled_state.depth();
+        self.exit_below(depth);
+        let span = at_level!(
+            span,
+            self.frame_level,
+            "create",
+            depth = depth + 1,
+            caller = ?inputs.caller,
+            address = field::Empty,
+            gas = inputs.gas_limit,
+            result = field::Empty,
+        );
+        self.spans.push((depth + 1, span.entered()));
+        (Return::Continue, None, Gas::new(0), Bytes::default())
+    }
+
+    fn create_end(
+        &mut self,
+        _data: &mut EVMData<'_, DB>,
+        _inputs: &CreateInputs,
+        ret: Return,
+        address: Option<H160>,
+        remaining_gas: Gas,
+        out: Bytes,
+    ) -> (Return, Option<H160>, Gas, Bytes) {
+        if let (Some((_, span)), Some(address)) = (self.spans.last(), address) {
+            span.record("address", field::debug(address));
+        }
+        self.exit_frame(ret);
+        (ret, address, remaining_gas, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TracingInspector;
+    use crate::{opcode, test_utils::evm_with_code, Return};
+    use ::tracing::Level;
+    use primitive_types::H160;
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+    };
+    use tracing_subscriber::{filter::Targets, fmt::format::FmtSpan, prelude::*};
+
+    /// Output of the fmt subscriber, kept for the test to read.
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_call_span() {
+        // CALL of ecrecover with no gas and no input.
+        let code = vec![
+            opcode::PUSH1,
+            0x00,
+            opcode::DUP1,
+            opcode::DUP1,
+            opcode::DUP1,
+            opcode::DUP1,
+            opcode::PUSH1,
+            0x01,
+            opcode::DUP2,
+            opcode::CALL,
+            opcode::STOP,
+        ];
+        let mut evm = evm_with_code(code);
+
+        // spans and events of other targets, like the frame spans of revm itself, are skipped.
+        let output = Output::default();
+        let writer = output.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .without_time()
+            .with_target(false)
+            .with_max_level(Level::TRACE)
+            .with_span_events(FmtSpan::NEW | FmtSpan::CLOSE)
+            .finish()
+            .with(Targets::new().with_target("revm::inspector::tracing", Level::TRACE));
+        let mut inspector = TracingInspector::new().with_opcode_level(Level::DEBUG);
+        let (result, _) =
+            ::tracing::subscriber::with_default(subscriber, || evm.inspect(&mut inspector));
+        assert_eq!(result.exit_reason, Return::Stop);
+
+        let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        // every line starts with the level and the fields of the spans it is in.
+        let top = format!(
+            "call{{depth=1 caller={:?} address={:?} gas=79000 is_static=false",
+            H160::repeat_byte(0x10),
+            H160::zero()
+        );
+        assert_eq!(lines[0], format!(" INFO {top}}}: new"));
+        assert_eq!(
+            lines[1],
+            format!("DEBUG {top}}}: pc=0 opcode=\"PUSH1\" gas=78979 depth=1")
+        );
+        let inner = format!(
+            "call{{depth=2 caller={:?} address={:?} gas=0 is_static=false",
+            H160::zero(),
+            H160::from_low_u64_be(1)
+        );
+        let call = lines
+            .iter()
+            .position(|line| line.contains("call{depth=2"))
+            .unwrap();
+        assert_eq!(lines[call], format!(" INFO {top}}}:{inner}}}: new"));
+        // ecrecover fails without gas, the top level call stops.
+        assert_eq!(
+            lines[call + 1],
+            format!(" INFO {top}}}:{inner} result=PrecompileError}}: close")
+        );
+        assert!(lines[call + 2].contains("opcode=\"STOP\""));
+        assert_eq!(
+            *lines.last().unwrap(),
+            format!(" INFO {top} result=Stop}}: close")
+        );
+    }
+}
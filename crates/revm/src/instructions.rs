@@ -3,7 +3,9 @@ mod macros;
 mod arithmetic;
 mod bitwise;
 mod control;
-mod host;
+pub mod disassembler;
+mod fused;
+pub(crate) mod host;
 mod host_env;
 mod i256;
 mod memory;
@@ -13,7 +15,11 @@ mod system;
 
 pub use opcode::{OpCode, OPCODE_JUMPMAP};
 
-use crate::{interpreter::Interpreter, CallScheme, Host, Spec, SpecId::*};
+use crate::{
+    interpreter::{fusion, Interpreter},
+    CallScheme, Host, Spec,
+    SpecId::*,
+};
 use core::ops::{BitAnd, BitOr, BitXor};
 use primitive_types::U256;
 
@@ -54,7 +60,9 @@ pub enum Return {
     InvalidJump,
     InvalidMemoryRange,
     NotActivated,
+    /// Opcode needs more items than the stack holds. The pc is left at the opcode.
     StackUnderflow,
+    /// Opcode pushes past the limit of 1024 stack items. The pc is left at the opcode.
     StackOverflow,
     OutOfOffset,
     FatalExternalError,
@@ -72,10 +80,100 @@ pub enum Return {
     CreateContractLimit,
     /// Error on created contract that begins with EF
     CreateContractWithEF,
+    /// Opcode is in [crate::CfgEnv::disabled_opcodes].
+    OpcodeDisabled,
+    /// CALL or CREATE handed its sub call back to the host, see [crate::Host::ITERATIVE_CALLS].
+    SubCall,
+    /// EIP-7702 authorizations before Prague or on a create transaction.
+    InvalidAuthorizationList,
+    /// [crate::Interpreter::step_limit] opcodes were executed.
+    StepLimitReached,
+    /// EIP-3860 init code of CREATE, CREATE2 or a create transaction exceeds
+    /// [crate::CfgEnv::max_initcode_size].
+    CreateInitcodeSizeLimit,
+    /// Memory would grow above [crate::CfgEnv::memory_limit].
+    MemoryLimitOOG,
+    /// Returned by [crate::Inspector::step] to step over the opcode without executing it.
+    SkipOpcode,
+    /// Transaction data exceeds [crate::CfgEnv::max_input_size].
+    InputSizeLimit,
+    /// Code of a frame exceeds [crate::CfgEnv::max_executed_code_size].
+    CodeSizeLimit,
+}
+
+impl Return {
+    /// Successful end of execution, see [crate::return_ok].
+    pub fn is_ok(&self) -> bool {
+        matches!(self, return_ok!())
+    }
+
+    /// Execution ended without success but gas that was not spent is returned, see
+    /// [crate::return_revert].
+    pub fn is_revert(&self) -> bool {
+        matches!(self, return_revert!())
+    }
+
+    /// Execution halted and consumed all of its gas, or the transaction was invalid.
+    pub fn is_error(&self) -> bool {
+        !self.is_ok() && !self.is_revert()
+    }
+
+    /// Name of the variant, stable for logging.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Return::Continue => "Continue",
+            Return::Stop => "Stop",
+            Return::Return => "Return",
+            Return::SelfDestruct => "SelfDestruct",
+            Return::Revert => "Revert",
+            Return::CallTooDeep => "CallTooDeep",
+            Return::OutOfFund => "OutOfFund",
+            Return::OutOfGas => "OutOfGas",
+            Return::OpcodeNotFound => "OpcodeNotFound",
+            Return::CallNotAllowedInsideStatic => "CallNotAllowedInsideStatic",
+            Return::InvalidOpcode => "InvalidOpcode",
+            Return::InvalidJump => "InvalidJump",
+            Return::InvalidMemoryRange => "InvalidMemoryRange",
+            Return::NotActivated => "NotActivated",
+            Return::StackUnderflow => "StackUnderflow",
+            Return::StackOverflow => "StackOverflow",
+            Return::OutOfOffset => "OutOfOffset",
+            Return::FatalExternalError => "FatalExternalError",
+            Return::GasMaxFeeGreaterThanPriorityFee => "GasMaxFeeGreaterThanPriorityFee",
+            Return::GasPriceLessThenBasefee => "GasPriceLessThenBasefee",
+            Return::CallerGasLimitMoreThenBlock => "CallerGasLimitMoreThenBlock",
+            Return::RejectCallerWithCode => "RejectCallerWithCode",
+            Return::LackOfFundForGasLimit => "LackOfFundForGasLimit",
+            Return::CreateCollision => "CreateCollision",
+            Return::OverflowPayment => "OverflowPayment",
+            Return::PrecompileError => "PrecompileError",
+            Return::NonceOverflow => "NonceOverflow",
+            Return::CreateContractLimit => "CreateContractLimit",
+            Return::CreateContractWithEF => "CreateContractWithEF",
+            Return::OpcodeDisabled => "OpcodeDisabled",
+            Return::SubCall => "SubCall",
+            Return::InvalidAuthorizationList => "InvalidAuthorizationList",
+            Return::StepLimitReached => "StepLimitReached",
+            Return::CreateInitcodeSizeLimit => "CreateInitcodeSizeLimit",
+            Return::MemoryLimitOOG => "MemoryLimitOOG",
+            Return::SkipOpcode => "SkipOpcode",
+            Return::InputSizeLimit => "InputSizeLimit",
+            Return::CodeSizeLimit => "CodeSizeLimit",
+        }
+    }
+}
+
+impl core::fmt::Display for Return {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 #[inline(always)]
 pub fn eval<H: Host, S: Spec>(opcode: u8, interp: &mut Interpreter, host: &mut H) -> Return {
+    if interp.disabled_opcodes.contains(opcode) {
+        return Return::OpcodeDisabled;
+    }
     match opcode {
         /*12_u8..=15_u8 => Return::OpcodeNotFound,
         30_u8..=31_u8 => Return::OpcodeNotFound,
@@ -123,25 +221,27 @@ pub fn eval<H: Host, S: Spec>(opcode: u8, interp: &mut Interpreter, host: &mut H
             bitwise::sar,
             S::enabled(CONSTANTINOPLE) // EIP-145: Bitwise shifting instructions in EVM
         ),
-        opcode::SHA3 => system::sha3(interp),
+        opcode::SHA3 => system::sha3(interp, host),
 
         opcode::ADDRESS => system::address(interp),
         opcode::BALANCE => host::balance::<H, S>(interp, host),
         opcode::SELFBALANCE => host::selfbalance::<H, S>(interp, host),
         opcode::CODESIZE => system::codesize(interp),
-        opcode::CODECOPY => system::codecopy(interp),
+        opcode::CODECOPY => system::codecopy(interp, host),
         opcode::CALLDATALOAD => system::calldataload(interp),
         opcode::CALLDATASIZE => system::calldatasize(interp),
-        opcode::CALLDATACOPY => system::calldatacopy(interp),
+        opcode::CALLDATACOPY => system::calldatacopy(interp, host),
         opcode::POP => stack::pop(interp),
-        opcode::MLOAD => memory::mload(interp),
-        opcode::MSTORE => memory::mstore(interp),
-        opcode::MSTORE8 => memory::mstore8(interp),
-        opcode::JUMP => control::jump(interp),
-        opcode::JUMPI => control::jumpi(interp),
+        opcode::MLOAD => memory::mload(interp, host),
+        opcode::MSTORE => memory::mstore(interp, host),
+        opcode::MSTORE8 => memory::mstore8(interp, host),
+        opcode::MCOPY => memory::mcopy::<H, S>(interp, host),
+        opcode::JUMP => control::jump(interp, host),
+        opcode::JUMPI => control::jumpi(interp, host),
         opcode::PC => control::pc(interp),
         opcode::MSIZE => memory::msize(interp),
-        opcode::JUMPDEST => control::jumpdest(interp),
+        opcode::JUMPDEST => control::jumpdest(interp, host),
+        opcode::PUSH0 => stack::push0::<S>(interp),
         opcode::PUSH1 => stack::push::<1>(interp),
         opcode::PUSH2 => stack::push::<2>(interp),
         opcode::PUSH3 => stack::push::<3>(interp),
@@ -208,10 +308,12 @@ pub fn eval<H: Host, S: Spec>(opcode: u8, interp: &mut Interpreter, host: &mut H
         opcode::SWAP15 => stack::swap::<15>(interp),
         opcode::SWAP16 => stack::swap::<16>(interp),
 
-        opcode::RETURN => control::ret(interp),
-        opcode::REVERT => control::revert::<S>(interp),
+        opcode::RETURN => control::ret(interp, host),
+        opcode::REVERT => control::revert::<H, S>(interp, host),
         opcode::INVALID => Return::InvalidOpcode,
         opcode::BASEFEE => host_env::basefee::<H, S>(interp, host),
+        opcode::BLOBHASH => host_env::blobhash::<H, S>(interp, host),
+        opcode::BLOBBASEFEE => host_env::blobbasefee::<H, S>(interp, host),
         opcode::ORIGIN => host_env::origin(interp, host),
         opcode::CALLER => system::caller(interp),
         opcode::CALLVALUE => system::callvalue(interp),
@@ -220,7 +322,7 @@ pub fn eval<H: Host, S: Spec>(opcode: u8, interp: &mut Interpreter, host: &mut H
         opcode::EXTCODEHASH => host::extcodehash::<H, S>(interp, host),
         opcode::EXTCODECOPY => host::extcodecopy::<H, S>(interp, host),
         opcode::RETURNDATASIZE => system::returndatasize::<S>(interp),
-        opcode::RETURNDATACOPY => system::returndatacopy::<S>(interp),
+        opcode::RETURNDATACOPY => system::returndatacopy::<H, S>(interp, host),
         opcode::BLOCKHASH => host::blockhash(interp, host),
         opcode::COINBASE => host_env::coinbase(interp, host),
         opcode::TIMESTAMP => host_env::timestamp(interp, host),
@@ -229,7 +331,9 @@ pub fn eval<H: Host, S: Spec>(opcode: u8, interp: &mut Interpreter, host: &mut H
         opcode::GASLIMIT => host_env::gaslimit(interp, host),
         opcode::SLOAD => host::sload::<H, S>(interp, host),
         opcode::SSTORE => host::sstore::<H, S>(interp, host),
-        opcode::GAS => system::gas(interp),
+        opcode::TLOAD => host::tload::<H, S>(interp, host),
+        opcode::TSTORE => host::tstore::<H, S>(interp, host),
+        opcode::GAS => system::gas(interp, host),
         opcode::LOG0 => host::log::<H, S>(interp, 0, host),
         opcode::LOG1 => host::log::<H, S>(interp, 1, host),
         opcode::LOG2 => host::log::<H, S>(interp, 2, host),
@@ -243,6 +347,42 @@ pub fn eval<H: Host, S: Spec>(opcode: u8, interp: &mut Interpreter, host: &mut H
         opcode::DELEGATECALL => host::call::<H, S>(interp, CallScheme::DelegateCall, host), //check
         opcode::STATICCALL => host::call::<H, S>(interp, CallScheme::StaticCall, host), //check
         opcode::CHAINID => host_env::chainid::<H, S>(interp, host),
-        _ => Return::OpcodeNotFound,
+        _ => match host.env().cfg.opcode_handler(opcode) {
+            Some(handler) => handler(interp),
+            None => Return::OpcodeNotFound,
+        },
+    }
+}
+
+/// Run the fused opcode `fused` of [crate::interpreter::fusion], None if the first opcode of
+/// the pair has to run on its own. Not inlined, so the step of code without fusion stays small.
+#[inline(never)]
+pub fn eval_fused<H: Host>(fused: u8, interp: &mut Interpreter, host: &mut H) -> Option<Return> {
+    match fused {
+        fusion::PUSH1_ADD => fused::push1_add(interp),
+        fusion::DUP1_PUSH1 => fused::dup1_push1(interp),
+        fusion::PUSH1_JUMP => fused::push1_jump(interp),
+        fusion::PUSH2_JUMPI => fused::push2_jumpi(interp, host),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Return;
+    use crate::alloc::string::ToString;
+
+    #[test]
+    fn test_return_classification() {
+        assert!(Return::Stop.is_ok());
+        assert!(!Return::Stop.is_revert() && !Return::Stop.is_error());
+        assert!(Return::Revert.is_revert());
+        assert!(!Return::Revert.is_ok() && !Return::Revert.is_error());
+        assert!(Return::OutOfGas.is_error());
+        assert!(!Return::OutOfGas.is_ok() && !Return::OutOfGas.is_revert());
+        assert!(Return::LackOfFundForGasLimit.is_error());
+
+        assert_eq!(Return::OutOfGas.as_str(), "OutOfGas");
+        assert_eq!(Return::StackUnderflow.to_string(), "StackUnderflow");
     }
 }
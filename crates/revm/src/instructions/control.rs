@@ -1,11 +1,14 @@
-use crate::{gas, interpreter::Interpreter, Return, Spec, SpecId::*};
+use crate::{gas, interpreter::Interpreter, Host, Return, Spec, SpecId::*};
 use primitive_types::U256;
 
-pub fn jump(interp: &mut Interpreter) -> Return {
+pub fn jump<H: Host>(interp: &mut Interpreter, host: &mut H) -> Return {
     // gas!(interp, gas::MID);
     pop!(interp, dest);
     let dest = as_usize_or_fail!(dest, Return::InvalidJump);
     if interp.contract.is_valid_jump(dest) {
+        if H::INSPECT {
+            host.jump(interp.program_counter() - 1, dest, true);
+        }
         // Safety: In analysis we are checking create our jump table and we do check above to be
         // sure that jump is safe to execute.
         interp.instruction_pointer = unsafe { interp.contract.bytecode.as_ptr().add(dest) };
@@ -15,12 +18,15 @@ pub fn jump(interp: &mut Interpreter) -> Return {
     }
 }
 
-pub fn jumpi(interp: &mut Interpreter) -> Return {
+pub fn jumpi<H: Host>(interp: &mut Interpreter, host: &mut H) -> Return {
     // gas!(interp, gas::HIGH);
     pop!(interp, dest, value);
     if !value.is_zero() {
         let dest = as_usize_or_fail!(dest, Return::InvalidJump);
         if interp.contract.is_valid_jump(dest) {
+            if H::INSPECT {
+                host.jump(interp.program_counter() - 1, dest, true);
+            }
             // Safety: In analysis we are checking if jump is valid destination and
             // this `if` makes this unsafe block safe.
             interp.instruction_pointer = unsafe { interp.contract.bytecode.as_ptr().add(dest) };
@@ -29,14 +35,18 @@ pub fn jumpi(interp: &mut Interpreter) -> Return {
             Return::InvalidJump
         }
     } else {
+        if H::INSPECT {
+            let pc = interp.program_counter();
+            host.jump(pc - 1, pc, false);
+        }
         // if we are not doing jump, add next gas block.
-        interp.add_next_gas_block(interp.program_counter() - 1)
+        interp.add_next_gas_block(host, interp.program_counter() - 1)
     }
 }
 
-pub fn jumpdest(interp: &mut Interpreter) -> Return {
+pub fn jumpdest<H: Host>(interp: &mut Interpreter, host: &mut H) -> Return {
     gas!(interp, gas::JUMPDEST);
-    interp.add_next_gas_block(interp.program_counter() - 1)
+    interp.add_next_gas_block(host, interp.program_counter() - 1)
 }
 
 pub fn pc(interp: &mut Interpreter) -> Return {
@@ -45,7 +55,7 @@ pub fn pc(interp: &mut Interpreter) -> Return {
     Return::Continue
 }
 
-pub fn ret(interp: &mut Interpreter) -> Return {
+pub fn ret<H: Host>(interp: &mut Interpreter, host: &mut H) -> Return {
     // zero gas cost gas!(interp,gas::ZERO);
     pop!(interp, start, len);
     let len = as_usize_or_fail!(len, Return::OutOfGas);
@@ -53,13 +63,13 @@ pub fn ret(interp: &mut Interpreter) -> Return {
         interp.return_range = usize::MAX..usize::MAX;
     } else {
         let offset = as_usize_or_fail!(start, Return::OutOfGas);
-        memory_resize!(interp, offset, len);
+        memory_resize!(interp, host, offset, len);
         interp.return_range = offset..(offset + len);
     }
     Return::Return
 }
 
-pub fn revert<SPEC: Spec>(interp: &mut Interpreter) -> Return {
+pub fn revert<H: Host, SPEC: Spec>(interp: &mut Interpreter, host: &mut H) -> Return {
     // zero gas cost gas!(interp,gas::ZERO);
     // EIP-140: REVERT instruction
     check!(SPEC::enabled(BYZANTIUM));
@@ -69,7 +79,7 @@ pub fn revert<SPEC: Spec>(interp: &mut Interpreter) -> Return {
         interp.return_range = usize::MAX..usize::MAX;
     } else {
         let offset = as_usize_or_fail!(start, Return::OutOfGas);
-        memory_resize!(interp, offset, len);
+        memory_resize!(interp, host, offset, len);
         interp.return_range = offset..(offset + len);
     }
     Return::Revert
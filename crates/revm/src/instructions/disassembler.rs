@@ -0,0 +1,333 @@
+use super::opcode::{self, OpCode, OPCODE_JUMPMAP};
+use crate::alloc::{collections::BTreeSet, vec::Vec};
+use bytes::Bytes;
+use core::ops::Range;
+use primitive_types::U256;
+
+/// Decode `code` into a list of `(pc, opcode, push data)`.
+///
+/// PUSH immediates are skipped so they are not decoded as opcodes. If code ends in the middle of
+/// PUSH data, the returned immediate is shorter than the PUSH size. Undefined opcodes are returned
+/// as they are and their name is "unknown".
+pub fn disassemble(code: &[u8]) -> Vec<(usize, OpCode, Option<Bytes>)> {
+    InstructionIter::new(code)
+        .map(|(pc, opcode, immediate)| (pc, opcode, immediate.map(Bytes::copy_from_slice)))
+        .collect()
+}
+
+/// Lazy [disassemble] that borrows the push data from the code instead of copying it.
+#[derive(Clone, Debug)]
+pub struct InstructionIter<'a> {
+    code: &'a [u8],
+    pc: usize,
+}
+
+impl<'a> InstructionIter<'a> {
+    pub fn new(code: &'a [u8]) -> Self {
+        Self { code, pc: 0 }
+    }
+}
+
+impl<'a> Iterator for InstructionIter<'a> {
+    type Item = (usize, OpCode, Option<&'a [u8]>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let pc = self.pc;
+        let opcode = OpCode::new_unchecked(*self.code.get(pc)?);
+        let size = opcode.immediate_size();
+        let immediate = if size == 0 {
+            None
+        } else {
+            let end = core::cmp::min(pc + 1 + size, self.code.len());
+            Some(&self.code[pc + 1..end])
+        };
+        self.pc = pc + 1 + size;
+        Some((pc, opcode, immediate))
+    }
+}
+
+impl core::iter::FusedIterator for InstructionIter<'_> {}
+
+/// Split deploy code into `(constructor, runtime)` code.
+///
+/// This is a best effort heuristic and returns None if the split can't be determined statically.
+/// It looks for the runtime code that the constructor copies to memory with CODECOPY and then
+/// RETURNs. Constants pushed on the stack are followed through DUP, SWAP and POP so the usual
+/// `PUSH size DUP1 PUSH offset PUSH 0 CODECOPY PUSH 0 RETURN` sequence emitted by compilers is
+/// recognized, any other opcode makes all stack values unknown. Constructor arguments appended
+/// after the runtime code are not part of either slice.
+pub fn split_deploy_code(code: &[u8]) -> Option<(&[u8], &[u8])> {
+    let mut stack: Vec<Option<U256>> = Vec::new();
+    let mut copied = None;
+    let peek = |stack: &Vec<Option<U256>>, index: usize| {
+        stack
+            .len()
+            .checked_sub(index + 1)
+            .and_then(|i| stack[i])
+            .filter(|value| *value <= U256::from(usize::MAX))
+            .map(|value| value.as_usize())
+    };
+    for (_, op, immediate) in disassemble(code) {
+        match op.u8() {
+            opcode::PUSH1..=opcode::PUSH32 => {
+                stack.push(immediate.map(|data| U256::from_big_endian(&data)))
+            }
+            opcode::DUP1..=opcode::DUP16 => {
+                let n = (op.u8() - opcode::DUP1) as usize;
+                let value = stack.len().checked_sub(n + 1).and_then(|i| stack[i]);
+                stack.push(value);
+            }
+            opcode::SWAP1..=opcode::SWAP16 => {
+                let n = (op.u8() - opcode::SWAP1 + 1) as usize;
+                match stack.len().checked_sub(n + 1) {
+                    Some(i) => {
+                        let top = stack.len() - 1;
+                        stack.swap(i, top)
+                    }
+                    None => stack.clear(),
+                }
+            }
+            opcode::POP => {
+                stack.pop();
+            }
+            opcode::CODECOPY => {
+                copied = peek(&stack, 1).zip(peek(&stack, 2));
+                stack.clear();
+            }
+            opcode::RETURN => {
+                if let Some((offset, size)) = copied {
+                    if let Some(runtime) = code.get(offset..offset.checked_add(size)?) {
+                        return Some((&code[..offset], runtime));
+                    }
+                }
+                stack.clear();
+            }
+            _ => stack.clear(),
+        }
+    }
+    None
+}
+
+/// Program counters of the opcodes reachable from `entry` and the byte ranges of code that is not.
+///
+/// Execution is followed through fall-through and through JUMP and JUMPI whose target is pushed
+/// by the PUSH right before them. For any other jump the target is unknown, so every valid
+/// JUMPDEST is treated as reachable. STOP, RETURN, REVERT, INVALID, SELFDESTRUCT and undefined
+/// opcodes end a path. Unreachable ranges include PUSH data and are sorted by offset.
+pub fn reachable_code(code: &[u8], entry: usize) -> (BTreeSet<usize>, Vec<Range<usize>>) {
+    let instructions = disassemble(code);
+    let index_of = |pc: usize| {
+        instructions
+            .binary_search_by_key(&pc, |(pc, _, _)| *pc)
+            .ok()
+    };
+    let jumpdest =
+        |pc: usize| index_of(pc).filter(|index| instructions[*index].1.u8() == opcode::JUMPDEST);
+
+    let mut reachable = BTreeSet::new();
+    let mut pending: Vec<usize> = index_of(entry).into_iter().collect();
+    let mut dynamic_jump = false;
+    while let Some(index) = pending.pop() {
+        let (pc, op, _) = &instructions[index];
+        if !reachable.insert(*pc) {
+            continue;
+        }
+        let op = op.u8();
+        if matches!(op, opcode::JUMP | opcode::JUMPI) {
+            let target = index
+                .checked_sub(1)
+                .map(|prev| &instructions[prev])
+                .filter(|(_, prev, _)| prev.immediate_size() != 0)
+                .and_then(|(_, _, data)| data.as_ref())
+                .map(|data| U256::from_big_endian(data));
+            match target {
+                Some(target) if target <= U256::from(usize::MAX) => {
+                    pending.extend(jumpdest(target.as_usize()));
+                }
+                // out of range target is an invalid jump.
+                Some(_) => (),
+                None => dynamic_jump = true,
+            }
+        }
+        let ends = matches!(
+            op,
+            opcode::STOP
+                | opcode::JUMP
+                | opcode::RETURN
+                | opcode::REVERT
+                | opcode::INVALID
+                | opcode::SELFDESTRUCT
+        ) || OPCODE_JUMPMAP[op as usize].is_none();
+        if !ends && index + 1 < instructions.len() {
+            pending.push(index + 1);
+        }
+        if dynamic_jump && pending.is_empty() {
+            pending.extend(
+                instructions
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (pc, op, _))| {
+                        op.u8() == opcode::JUMPDEST && !reachable.contains(pc)
+                    })
+                    .map(|(index, _)| index),
+            );
+        }
+    }
+
+    let mut unreachable: Vec<Range<usize>> = Vec::new();
+    for (index, (pc, _, _)) in instructions.iter().enumerate() {
+        if reachable.contains(pc) {
+            continue;
+        }
+        let end = instructions
+            .get(index + 1)
+            .map_or(code.len(), |(pc, _, _)| *pc);
+        match unreachable.last_mut() {
+            Some(range) if range.end == *pc => range.end = end,
+            _ => unreachable.push(*pc..end),
+        }
+    }
+    (reachable, unreachable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{disassemble, reachable_code, split_deploy_code, InstructionIter};
+    use crate::opcode;
+
+    #[test]
+    fn test_disassemble() {
+        let code = [
+            opcode::PUSH1,
+            0x5b,
+            opcode::JUMPDEST,
+            opcode::PUSH2,
+            0x01,
+            0x02,
+            0x0c,
+            opcode::PUSH4,
+            0xaa,
+        ];
+        let listing: Vec<_> = disassemble(&code)
+            .into_iter()
+            .map(|(pc, op, data)| (pc, op.as_str(), data.map(|d| d.to_vec())))
+            .collect();
+        assert_eq!(
+            listing,
+            vec![
+                (0, "PUSH1", Some(vec![0x5b])),
+                (2, "JUMPDEST", None),
+                (3, "PUSH2", Some(vec![0x01, 0x02])),
+                (6, "unknown", None),
+                (7, "PUSH4", Some(vec![0xaa])),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_disassemble_push32() {
+        // PUSH32 data holds opcode bytes that must not be decoded, then a truncated PUSH2.
+        let mut code = vec![opcode::PUSH32];
+        code.extend_from_slice(&[opcode::JUMPDEST; 32]);
+        code.extend_from_slice(&[opcode::PUSH1, 0x01, opcode::ADD, opcode::PUSH2, 0xff]);
+        let listing = disassemble(&code);
+        let pcs: Vec<_> = listing
+            .iter()
+            .map(|(pc, op, _)| (*pc, op.as_str()))
+            .collect();
+        assert_eq!(
+            pcs,
+            vec![(0, "PUSH32"), (33, "PUSH1"), (35, "ADD"), (36, "PUSH2")]
+        );
+        assert_eq!(listing[0].2.as_deref(), Some(&[opcode::JUMPDEST; 32][..]));
+        assert_eq!(listing[3].2.as_deref(), Some(&[0xff][..]));
+
+        // PUSH as the last byte has empty data.
+        let listing = disassemble(&[opcode::PUSH32]);
+        assert_eq!(listing.len(), 1);
+        assert_eq!(listing[0].2.as_deref(), Some(&[][..]));
+    }
+
+    #[test]
+    fn test_instruction_iter() {
+        let mut code = vec![opcode::PUSH1, 0x80, opcode::PUSH1, 0x40, opcode::MSTORE];
+        code.extend([opcode::CALLVALUE, opcode::DUP1, opcode::ISZERO, 0x0c]);
+        code.extend([
+            opcode::PUSH3,
+            0x5b,
+            0x5b,
+            0x5b,
+            opcode::JUMPI,
+            opcode::PUSH32,
+        ]);
+        code.extend_from_slice(&[0xff; 20]);
+        let mut iter = InstructionIter::new(&code);
+        let listing: Vec<_> = iter.by_ref().collect();
+        assert_eq!(listing.len(), 10);
+        for (lazy, eager) in listing.iter().zip(disassemble(&code)) {
+            assert_eq!(lazy.0, eager.0);
+            assert_eq!(lazy.1, eager.1);
+            assert_eq!(lazy.2, eager.2.as_deref());
+        }
+        // truncated PUSH32 data ends the code.
+        let (pc, op, data) = listing[9];
+        assert_eq!(
+            (pc, op.u8(), data),
+            (14, opcode::PUSH32, Some(&[0xff; 20][..]))
+        );
+        assert_eq!(iter.next(), None);
+        assert_eq!(InstructionIter::new(&[]).next(), None);
+    }
+
+    #[test]
+    fn test_split_deploy_code() {
+        // solc 0.8.17 output for `contract C {}`, the metadata hash is zeroed.
+        let constructor = "6080604052348015600f57600080fd5b50603f80601d6000396000f3fe";
+        let runtime = "6080604052600080fdfea2646970667358221220\
+            0000000000000000000000000000000000000000000000000000000000000000\
+            64736f6c63430008110033";
+        let code = hex::decode(format!("{constructor}{runtime}")).unwrap();
+
+        let (split_constructor, split_runtime) = split_deploy_code(&code).unwrap();
+        assert_eq!(split_constructor, hex::decode(constructor).unwrap());
+        assert_eq!(split_runtime, hex::decode(runtime).unwrap());
+
+        assert_eq!(split_deploy_code(split_runtime), None);
+    }
+
+    #[test]
+    fn test_reachable_code() {
+        let code = [
+            opcode::PUSH1,
+            0x05,
+            opcode::JUMP,
+            opcode::ADD,
+            opcode::ADD,
+            opcode::JUMPDEST,
+            opcode::STOP,
+            opcode::ADD,
+            opcode::MUL,
+        ];
+        let (reachable, unreachable) = reachable_code(&code, 0);
+        assert_eq!(reachable.into_iter().collect::<Vec<_>>(), vec![0, 2, 5, 6]);
+        assert_eq!(unreachable, vec![3..5, 7..9]);
+
+        // target of a dynamic jump is unknown, all JUMPDESTs are reachable.
+        let code = [
+            opcode::CALLDATASIZE,
+            opcode::JUMP,
+            opcode::JUMPDEST,
+            opcode::STOP,
+            opcode::PUSH1,
+            opcode::JUMPDEST,
+            opcode::JUMPDEST,
+            opcode::STOP,
+        ];
+        let (reachable, unreachable) = reachable_code(&code, 0);
+        assert_eq!(
+            reachable.into_iter().collect::<Vec<_>>(),
+            vec![0, 1, 2, 3, 6, 7]
+        );
+        assert_eq!(unreachable, vec![4..6]);
+    }
+}
@@ -0,0 +1,69 @@
+//! Handlers of the fused opcodes of [crate::interpreter::fusion]. They run with the instruction
+//! pointer at the first opcode of the pair and move it past the second one.
+//!
+//! Static gas of both opcodes is part of the gas block, none of them has dynamic gas. A handler
+//! returns None if one of the opcodes would fail on the stack, the first opcode then runs on its
+//! own so the error is reported at the same opcode as without fusion.
+use crate::{interpreter::stack::STACK_LIMIT, interpreter::Interpreter, Host, Return};
+use primitive_types::U256;
+
+/// Immediate byte `n` after the first opcode.
+fn immediate(interp: &Interpreter, n: usize) -> u8 {
+    // Safety: analysis pads the code past the immediate of its last opcode.
+    unsafe { *interp.instruction_pointer.add(1 + n) }
+}
+
+/// `PUSH1 x ADD`.
+pub fn push1_add(interp: &mut Interpreter) -> Option<Return> {
+    if !(1..STACK_LIMIT).contains(&interp.stack.len()) {
+        return None;
+    }
+    let value = U256::from(immediate(interp, 0));
+    // Safety: the stack is not empty.
+    let top = unsafe { interp.stack.top_unsafe() };
+    *top = value.overflowing_add(*top).0;
+    interp.instruction_pointer = unsafe { interp.instruction_pointer.add(3) };
+    Some(Return::Continue)
+}
+
+/// `DUP1 PUSH1 x`.
+pub fn dup1_push1(interp: &mut Interpreter) -> Option<Return> {
+    if !(1..STACK_LIMIT - 1).contains(&interp.stack.len()) {
+        return None;
+    }
+    let value = U256::from(immediate(interp, 1));
+    // both pushes fit, checked above.
+    let _ = interp.stack.dup::<1>();
+    let _ = interp.stack.push(value);
+    interp.instruction_pointer = unsafe { interp.instruction_pointer.add(3) };
+    Some(Return::Continue)
+}
+
+/// `PUSH1 x JUMP`, x is a valid jump destination.
+pub fn push1_jump(interp: &mut Interpreter) -> Option<Return> {
+    if interp.stack.len() >= STACK_LIMIT {
+        return None;
+    }
+    let dest = immediate(interp, 0) as usize;
+    // Safety: fusion checks that the destination is a valid jump.
+    interp.instruction_pointer = unsafe { interp.contract.bytecode.as_ptr().add(dest) };
+    Some(Return::Continue)
+}
+
+/// `PUSH2 x JUMPI`, x is a valid jump destination.
+pub fn push2_jumpi<H: Host>(interp: &mut Interpreter, host: &mut H) -> Option<Return> {
+    if !(1..STACK_LIMIT).contains(&interp.stack.len()) {
+        return None;
+    }
+    let dest = u16::from_be_bytes([immediate(interp, 0), immediate(interp, 1)]) as usize;
+    // Safety: the stack is not empty.
+    let value = unsafe { interp.stack.pop_unsafe() };
+    if !value.is_zero() {
+        // Safety: fusion checks that the destination is a valid jump.
+        interp.instruction_pointer = unsafe { interp.contract.bytecode.as_ptr().add(dest) };
+        Some(Return::Continue)
+    } else {
+        interp.instruction_pointer = unsafe { interp.instruction_pointer.add(4) };
+        Some(interp.add_next_gas_block(host, interp.program_counter() - 1))
+    }
+}
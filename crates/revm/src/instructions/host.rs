@@ -1,8 +1,8 @@
 use crate::{
     alloc::vec::Vec,
-    gas::{self, COLD_ACCOUNT_ACCESS_COST, WARM_STORAGE_READ_COST},
-    interpreter::Interpreter,
-    return_ok, return_revert, CallContext, CallInputs, CallScheme, CreateInputs, CreateScheme,
+    gas,
+    interpreter::{Interpreter, SubCall},
+    return_ok, return_revert, CallContext, CallInputs, CallScheme, CreateInputs, CreateScheme, Gas,
     Host, Return, Spec,
     SpecId::*,
     Transfer,
@@ -55,10 +55,8 @@ pub fn extcodesize<H: Host, SPEC: Spec>(interp: &mut Interpreter, host: &mut H)
         return Return::FatalExternalError;
     }
     let (code, is_cold) = ret.unwrap();
-    if SPEC::enabled(BERLIN) && is_cold {
-        // WARM_STORAGE_READ_COST is already calculated in gas block
-        gas!(interp, COLD_ACCOUNT_ACCESS_COST - WARM_STORAGE_READ_COST);
-    }
+    // WARM_STORAGE_READ_COST is already calculated in gas block
+    gas!(interp, gas::cold_account_access_surcharge::<SPEC>(is_cold));
 
     push!(interp, U256::from(code.len()));
 
@@ -73,10 +71,8 @@ pub fn extcodehash<H: Host, SPEC: Spec>(interp: &mut Interpreter, host: &mut H)
         return Return::FatalExternalError;
     }
     let (code_hash, is_cold) = ret.unwrap();
-    if SPEC::enabled(BERLIN) && is_cold {
-        // WARM_STORAGE_READ_COST is already calculated in gas block
-        gas!(interp, COLD_ACCOUNT_ACCESS_COST - WARM_STORAGE_READ_COST);
-    }
+    // WARM_STORAGE_READ_COST is already calculated in gas block
+    gas!(interp, gas::cold_account_access_surcharge::<SPEC>(is_cold));
     push_h256!(interp, code_hash);
 
     Return::Continue
@@ -99,7 +95,7 @@ pub fn extcodecopy<H: Host, SPEC: Spec>(interp: &mut Interpreter, host: &mut H)
     }
     let memory_offset = as_usize_or_fail!(memory_offset, Return::OutOfGas);
     let code_offset = min(as_usize_saturated!(code_offset), code.len());
-    memory_resize!(interp, memory_offset, len);
+    memory_resize!(interp, host, memory_offset, len);
 
     // Safety: set_data is unsafe function and memory_resize ensures us that it is safe to call it
     interp
@@ -155,7 +151,27 @@ pub fn sstore<H: Host, SPEC: Spec>(interp: &mut Interpreter, host: &mut H) -> Re
         gas::sstore_cost::<SPEC>(original, old, new, remaining_gas, is_cold)
     });
     refund!(interp, gas::sstore_refund::<SPEC>(original, old, new));
-    interp.add_next_gas_block(interp.program_counter() - 1)
+    interp.add_next_gas_block(host, interp.program_counter() - 1)
+}
+
+pub fn tload<H: Host, SPEC: Spec>(interp: &mut Interpreter, host: &mut H) -> Return {
+    // EIP-1153: Transient storage opcodes
+    check!(SPEC::enabled(CANCUN));
+
+    pop!(interp, index);
+    let value = host.tload(interp.contract.address, index);
+    push!(interp, value);
+    Return::Continue
+}
+
+pub fn tstore<H: Host, SPEC: Spec>(interp: &mut Interpreter, host: &mut H) -> Return {
+    // EIP-1153: Transient storage opcodes
+    check!(SPEC::enabled(CANCUN));
+    check!(!SPEC::IS_STATIC_CALL);
+
+    pop!(interp, index, value);
+    host.tstore(interp.contract.address, index, value);
+    Return::Continue
 }
 
 pub fn log<H: Host, SPEC: Spec>(interp: &mut Interpreter, n: u8, host: &mut H) -> Return {
@@ -168,7 +184,7 @@ pub fn log<H: Host, SPEC: Spec>(interp: &mut Interpreter, n: u8, host: &mut H) -
         Bytes::new()
     } else {
         let offset = as_usize_or_fail!(offset, Return::OutOfGas);
-        memory_resize!(interp, offset, len);
+        memory_resize!(interp, host, offset, len);
         Bytes::copy_from_slice(interp.memory.get_slice(offset, len))
     };
     let n = n as usize;
@@ -215,7 +231,7 @@ pub fn create<H: Host, SPEC: Spec>(
     check!(!SPEC::IS_STATIC_CALL);
     if is_create2 {
         // EIP-1014: Skinny CREATE2
-        check!(SPEC::enabled(PETERSBURG));
+        check!(SPEC::enabled(CONSTANTINOPLE));
     }
 
     interp.return_data_buffer = Bytes::new();
@@ -223,11 +239,19 @@ pub fn create<H: Host, SPEC: Spec>(
     pop!(interp, value, code_offset, len);
     let len = as_usize_or_fail!(len, Return::OutOfGas);
 
+    // EIP-3860: Limit and meter initcode
+    if SPEC::enabled(SHANGHAI) {
+        if len > host.env().cfg.max_initcode_size() {
+            return Return::CreateInitcodeSizeLimit;
+        }
+        gas!(interp, gas::initcode_cost(len));
+    }
+
     let code = if len == 0 {
         Bytes::new()
     } else {
         let code_offset = as_usize_or_fail!(code_offset, Return::OutOfGas);
-        memory_resize!(interp, code_offset, len);
+        memory_resize!(interp, host, code_offset, len);
         Bytes::copy_from_slice(interp.memory.get_slice(code_offset, len))
     };
 
@@ -257,25 +281,47 @@ pub fn create<H: Host, SPEC: Spec>(
         gas_limit,
     };
 
+    if H::ITERATIVE_CALLS {
+        interp.sub_call = Some(SubCall::Create {
+            inputs: create_input,
+        });
+        return Return::SubCall;
+    }
     let (return_reason, address, gas, return_data) = host.create::<SPEC>(&mut create_input);
+    create_return(interp, host, return_reason, address, gas, return_data)
+}
+
+/// Second half of CREATE and CREATE2, once the create frame returned.
+pub(crate) fn create_return<H: Host>(
+    interp: &mut Interpreter,
+    host: &mut H,
+    return_reason: Return,
+    address: Option<H160>,
+    gas: Gas,
+    return_data: Bytes,
+) -> Return {
     interp.return_data_buffer = return_data;
 
     match return_reason {
         return_ok!() => {
             push_h256!(interp, address.map(|a| a.into()).unwrap_or_default());
-            interp.gas.erase_cost(gas.remaining());
-            interp.gas.record_refund(gas.refunded());
+            if interp.use_gas() {
+                interp.gas.erase_cost(gas.remaining());
+                interp.gas.record_refund(gas.refunded());
+            }
         }
         return_revert!() => {
             push_h256!(interp, H256::default());
-            interp.gas.erase_cost(gas.remaining());
+            if interp.use_gas() {
+                interp.gas.erase_cost(gas.remaining());
+            }
         }
         Return::FatalExternalError => return Return::FatalExternalError,
         _ => {
             push_h256!(interp, H256::default());
         }
     }
-    interp.add_next_gas_block(interp.program_counter() - 1)
+    interp.add_next_gas_block(host, interp.program_counter() - 1)
 }
 
 pub fn call<H: Host, SPEC: Spec>(
@@ -315,10 +361,12 @@ pub fn call<H: Host, SPEC: Spec>(
 
     pop!(interp, in_offset, in_len, out_offset, out_len);
 
+    // Regions that do not fit in usize or whose end overflows fail with OutOfGas before any
+    // memory is read or allocated.
     let in_len = as_usize_or_fail!(in_len, Return::OutOfGas);
     let input = if in_len != 0 {
         let in_offset = as_usize_or_fail!(in_offset, Return::OutOfGas);
-        memory_resize!(interp, in_offset, in_len);
+        memory_resize!(interp, host, in_offset, in_len);
         Bytes::copy_from_slice(interp.memory.get_slice(in_offset, in_len))
     } else {
         Bytes::new()
@@ -327,7 +375,7 @@ pub fn call<H: Host, SPEC: Spec>(
     let out_len = as_usize_or_fail!(out_len, Return::OutOfGas);
     let out_offset = if out_len != 0 {
         let out_offset = as_usize_or_fail!(out_offset, Return::OutOfGas);
-        memory_resize!(interp, out_offset, out_len);
+        memory_resize!(interp, host, out_offset, out_len);
         out_offset
     } else {
         usize::MAX //unrealistic value so we are sure it is not used
@@ -397,6 +445,21 @@ pub fn call<H: Host, SPEC: Spec>(
         )
     );
 
+    // EIP-7702: calling a delegated account also accesses the account it delegates to.
+    if SPEC::enabled(PRAGUE) {
+        let delegate = match host.code(to) {
+            Some((code, _)) => code.delegation(),
+            None => return Return::FatalExternalError,
+        };
+        if let Some(delegate) = delegate {
+            let is_cold = match host.load_account(delegate) {
+                Some((is_cold, _)) => is_cold,
+                None => return Return::FatalExternalError,
+            };
+            gas!(interp, gas::account_access_cost(is_cold));
+        }
+    }
+
     // take l64 part of gas_limit
     let mut gas_limit = if SPEC::enabled(TANGERINE) {
         //EIP-150: Gas cost changes for IO-heavy operations
@@ -421,12 +484,33 @@ pub fn call<H: Host, SPEC: Spec>(
         gas_limit,
         context,
     };
+    if H::ITERATIVE_CALLS {
+        interp.sub_call_out = (out_offset, out_len);
+        interp.sub_call = Some(SubCall::Call {
+            inputs: call_input,
+            is_static,
+        });
+        return Return::SubCall;
+    }
     // CALL CONTRACT, with static or ordinary spec.
     let (reason, gas, return_data) = if is_static {
         host.call::<SPEC::STATIC>(&mut call_input)
     } else {
         host.call::<SPEC>(&mut call_input)
     };
+    call_return(interp, host, reason, gas, return_data, out_offset, out_len)
+}
+
+/// Second half of the CALL family, once the call frame returned.
+pub(crate) fn call_return<H: Host>(
+    interp: &mut Interpreter,
+    host: &mut H,
+    reason: Return,
+    gas: Gas,
+    return_data: Bytes,
+    out_offset: usize,
+    out_len: usize,
+) -> Return {
     interp.return_data_buffer = return_data;
 
     let target_len = min(out_len, interp.return_data_buffer.len());
@@ -434,15 +518,19 @@ pub fn call<H: Host, SPEC: Spec>(
     match reason {
         return_ok!() => {
             // return unspend gas.
-            interp.gas.erase_cost(gas.remaining());
-            interp.gas.record_refund(gas.refunded());
+            if interp.use_gas() {
+                interp.gas.erase_cost(gas.remaining());
+                interp.gas.record_refund(gas.refunded());
+            }
             interp
                 .memory
                 .set(out_offset, &interp.return_data_buffer[..target_len]);
             push!(interp, U256::one());
         }
         return_revert!() => {
-            interp.gas.erase_cost(gas.remaining());
+            if interp.use_gas() {
+                interp.gas.erase_cost(gas.remaining());
+            }
             interp
                 .memory
                 .set(out_offset, &interp.return_data_buffer[..target_len]);
@@ -453,5 +541,416 @@ pub fn call<H: Host, SPEC: Spec>(
             push!(interp, U256::zero());
         }
     }
-    interp.add_next_gas_block(interp.program_counter() - 1)
+    interp.add_next_gas_block(host, interp.program_counter() - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    #![cfg_attr(feature = "no_gas_measuring", allow(unused_imports))]
+
+    use crate::{
+        gas::{self, COLD_ACCOUNT_ACCESS_COST, COLD_SLOAD_COST, WARM_STORAGE_READ_COST},
+        opcode,
+        test_utils::{evm_with_code, evm_with_db, interpreter},
+        AccountInfo, Bytecode, InMemoryDB, LatestSpec, MockHost, Return, SpecId, TransactOut,
+        TransactTo, KECCAK_EMPTY,
+    };
+    use bytes::Bytes;
+    use primitive_types::{H160, H256, U256};
+
+    const CONTRACT: u64 = 0x1000;
+    const TARGET: u64 = 0x3000;
+
+    /// `PUSH20 TARGET` followed by `opcode`.
+    fn touch(code: &mut Vec<u8>, opcode: u8) {
+        code.push(opcode::PUSH20);
+        code.extend_from_slice(H160::from_low_u64_be(TARGET).as_bytes());
+        code.push(opcode);
+    }
+
+    fn push0(code: &mut Vec<u8>, n: usize) {
+        for _ in 0..n {
+            code.extend_from_slice(&[opcode::PUSH1, 0x00]);
+        }
+    }
+
+    /// Runs `code` twice followed by STOP and returns gas spent after intrinsic gas.
+    fn gas_used_twice(spec_id: SpecId, code: &[u8], access_list: Vec<(H160, Vec<U256>)>) -> u64 {
+        let mut bytecode = code.to_vec();
+        bytecode.extend_from_slice(code);
+        gas_used(spec_id, bytecode, access_list)
+    }
+
+    /// Runs `bytecode` followed by STOP and returns gas spent after intrinsic gas.
+    fn gas_used(
+        spec_id: SpecId,
+        mut bytecode: Vec<u8>,
+        access_list: Vec<(H160, Vec<U256>)>,
+    ) -> u64 {
+        bytecode.push(opcode::STOP);
+
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            H160::from_low_u64_be(CONTRACT),
+            AccountInfo::new(U256::zero(), 1, Bytecode::new_raw(Bytes::from(bytecode))),
+        );
+
+        let mut evm = evm_with_db(db);
+        evm.env.cfg.spec_id = spec_id;
+        evm.env.tx.caller = H160::from_low_u64_be(0x2000);
+        evm.env.tx.transact_to = TransactTo::Call(H160::from_low_u64_be(CONTRACT));
+        evm.env.tx.gas_limit = 1_000_000;
+        let access_list_gas = access_list.len() as u64 * 2400
+            + access_list.iter().map(|(_, s)| s.len() as u64).sum::<u64>() * 1900;
+        evm.env.tx.access_list = access_list;
+
+        let (result, _) = evm.transact();
+        assert_eq!(result.exit_reason, Return::Stop);
+        result.gas_used - 21000 - access_list_gas
+    }
+
+    /// Cold and warm access gas with the fixed cost of the surrounding opcodes removed.
+    fn access_gas(spec_id: SpecId, code: &[u8], fixed: u64) -> u64 {
+        gas_used_twice(spec_id, code, Vec::new()) - 2 * fixed
+    }
+
+    fn account_opcodes() -> Vec<(&'static str, Vec<u8>, u64)> {
+        let mut balance = Vec::new();
+        touch(&mut balance, opcode::BALANCE);
+        balance.push(opcode::POP);
+
+        let mut extcodesize = Vec::new();
+        touch(&mut extcodesize, opcode::EXTCODESIZE);
+        extcodesize.push(opcode::POP);
+
+        let mut extcodehash = Vec::new();
+        touch(&mut extcodehash, opcode::EXTCODEHASH);
+        extcodehash.push(opcode::POP);
+
+        // len, code offset, memory offset
+        let mut extcodecopy = Vec::new();
+        push0(&mut extcodecopy, 3);
+        touch(&mut extcodecopy, opcode::EXTCODECOPY);
+
+        let mut calls = Vec::new();
+        for (name, call, args) in [
+            ("CALL", opcode::CALL, 5),
+            ("CALLCODE", opcode::CALLCODE, 5),
+            ("DELEGATECALL", opcode::DELEGATECALL, 4),
+            ("STATICCALL", opcode::STATICCALL, 4),
+        ] {
+            // out len, out offset, in len, in offset, (value), address, gas limit zero.
+            let mut code = Vec::new();
+            push0(&mut code, args);
+            code.push(opcode::PUSH20);
+            code.extend_from_slice(H160::from_low_u64_be(TARGET).as_bytes());
+            push0(&mut code, 1);
+            code.push(call);
+            code.push(opcode::POP);
+            calls.push((name, code, 3 * (args as u64 + 2) + 2));
+        }
+
+        let mut opcodes = vec![
+            ("BALANCE", balance, 3 + 2),
+            ("EXTCODESIZE", extcodesize, 3 + 2),
+            ("EXTCODEHASH", extcodehash, 3 + 2),
+            ("EXTCODECOPY", extcodecopy, 4 * 3),
+        ];
+        opcodes.extend(calls);
+        opcodes
+    }
+
+    /// Returns the EXTCODEHASH of TARGET, with TARGET set to `target` in the database.
+    fn extcodehash(spec_id: SpecId, target: Option<AccountInfo>) -> (Return, H256) {
+        let mut code = Vec::new();
+        touch(&mut code, opcode::EXTCODEHASH);
+        code.extend_from_slice(&[
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x20,
+            opcode::PUSH1,
+            0x00,
+            opcode::RETURN,
+        ]);
+
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            H160::from_low_u64_be(CONTRACT),
+            AccountInfo::new(U256::zero(), 1, Bytecode::new_raw(Bytes::from(code))),
+        );
+        if let Some(target) = target {
+            db.insert_account_info(H160::from_low_u64_be(TARGET), target);
+        }
+
+        let mut evm = evm_with_db(db);
+        evm.env.cfg.spec_id = spec_id;
+        evm.env.tx.caller = H160::from_low_u64_be(0x2000);
+        evm.env.tx.transact_to = TransactTo::Call(H160::from_low_u64_be(CONTRACT));
+        let (result, _) = evm.transact();
+        let hash = match result.out {
+            TransactOut::Call(out) if out.len() == 32 => H256::from_slice(&out),
+            _ => H256::zero(),
+        };
+        (result.exit_reason, hash)
+    }
+
+    #[test]
+    fn test_extcodehash() {
+        for spec_id in [SpecId::CONSTANTINOPLE, SpecId::ISTANBUL, SpecId::LONDON] {
+            // non existing account.
+            assert_eq!(
+                extcodehash(spec_id, None),
+                (Return::Return, H256::zero()),
+                "{spec_id:?}"
+            );
+
+            // existing but empty as per EIP-161.
+            assert_eq!(
+                extcodehash(spec_id, Some(AccountInfo::default())),
+                (Return::Return, H256::zero()),
+                "{spec_id:?}"
+            );
+
+            // existing account without code.
+            let funded = AccountInfo {
+                balance: U256::one(),
+                ..Default::default()
+            };
+            assert_eq!(
+                extcodehash(spec_id, Some(funded)),
+                (Return::Return, KECCAK_EMPTY),
+                "{spec_id:?}"
+            );
+
+            // account with code.
+            let code = Bytecode::new_raw(Bytes::from(vec![opcode::STOP]));
+            let hash = code.hash();
+            assert_eq!(
+                extcodehash(spec_id, Some(AccountInfo::new(U256::zero(), 1, code))),
+                (Return::Return, hash),
+                "{spec_id:?}"
+            );
+        }
+
+        // EIP-1052 is introduced in Constantinople.
+        assert_eq!(extcodehash(SpecId::BYZANTIUM, None).0, Return::NotActivated);
+    }
+
+    /// Berlin and the later forks that change gas.
+    const EIP2929_SPECS: [SpecId; 5] = [
+        SpecId::BERLIN,
+        SpecId::LONDON,
+        SpecId::MERGE,
+        SpecId::SHANGHAI,
+        SpecId::CANCUN,
+    ];
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_eip2929_account_access() {
+        for spec_id in EIP2929_SPECS {
+            for (name, code, fixed) in account_opcodes() {
+                assert_eq!(
+                    access_gas(spec_id, &code, fixed),
+                    COLD_ACCOUNT_ACCESS_COST + WARM_STORAGE_READ_COST,
+                    "{name} at {spec_id:?}"
+                );
+
+                // access list makes the first access warm.
+                let access_list = vec![(H160::from_low_u64_be(TARGET), Vec::new())];
+                assert_eq!(
+                    gas_used_twice(spec_id, &code, access_list) - 2 * fixed,
+                    2 * WARM_STORAGE_READ_COST,
+                    "{name} with access list at {spec_id:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_eip2929_sload() {
+        let code = [opcode::PUSH1, 0x01, opcode::SLOAD, opcode::POP];
+        for spec_id in EIP2929_SPECS {
+            assert_eq!(
+                access_gas(spec_id, &code, 3 + 2),
+                COLD_SLOAD_COST + WARM_STORAGE_READ_COST,
+                "SLOAD at {spec_id:?}"
+            );
+
+            let access_list = vec![(H160::from_low_u64_be(CONTRACT), vec![U256::one()])];
+            assert_eq!(
+                gas_used_twice(spec_id, &code, access_list) - 2 * (3 + 2),
+                2 * WARM_STORAGE_READ_COST,
+                "SLOAD with access list at {spec_id:?}"
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_eip2929_precompile_and_coinbase_access() {
+        // BALANCE of `address` once.
+        let balance = |address: H160| {
+            let mut code = vec![opcode::PUSH20];
+            code.extend_from_slice(address.as_bytes());
+            code.extend_from_slice(&[opcode::BALANCE, opcode::POP]);
+            code
+        };
+        let ecrecover = H160::from_low_u64_be(1);
+        // coinbase is left at the zero address.
+        let coinbase = H160::zero();
+        for spec_id in [SpecId::BERLIN, SpecId::LONDON, SpecId::MERGE] {
+            // precompiles are warm with and without access list.
+            for access_list in [Vec::new(), vec![(ecrecover, Vec::new())]] {
+                assert_eq!(
+                    gas_used(spec_id, balance(ecrecover), access_list) - (3 + 2),
+                    WARM_STORAGE_READ_COST,
+                    "precompile at {spec_id:?}"
+                );
+            }
+
+            // coinbase is cold unless it is in the access list.
+            assert_eq!(
+                gas_used(spec_id, balance(coinbase), Vec::new()) - (3 + 2),
+                COLD_ACCOUNT_ACCESS_COST,
+                "coinbase at {spec_id:?}"
+            );
+            assert_eq!(
+                gas_used(spec_id, balance(coinbase), vec![(coinbase, Vec::new())]) - (3 + 2),
+                WARM_STORAGE_READ_COST,
+                "coinbase with access list at {spec_id:?}"
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_eip3651_warm_coinbase() {
+        // BALANCE of the zero address coinbase as first access.
+        let mut code = vec![opcode::PUSH20];
+        code.extend_from_slice(H160::zero().as_bytes());
+        code.extend_from_slice(&[opcode::BALANCE, opcode::POP]);
+        assert_eq!(
+            gas_used(SpecId::LONDON, code.clone(), Vec::new()) - (3 + 2),
+            COLD_ACCOUNT_ACCESS_COST
+        );
+        assert_eq!(
+            gas_used(SpecId::SHANGHAI, code, Vec::new()) - (3 + 2),
+            WARM_STORAGE_READ_COST
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_pre_berlin_access_is_flat() {
+        // EIP-1884 prices, repeated access costs the same.
+        for (name, code, fixed) in account_opcodes() {
+            assert_eq!(
+                access_gas(SpecId::ISTANBUL, &code, fixed),
+                2 * 700,
+                "{name} at ISTANBUL"
+            );
+        }
+        let code = [opcode::PUSH1, 0x01, opcode::SLOAD, opcode::POP];
+        assert_eq!(access_gas(SpecId::ISTANBUL, &code, 3 + 2), 2 * 800);
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_create2_hashing_cost() {
+        // init code of zeros is a single STOP, only the hashing of CREATE2 differs.
+        let create_gas = |create2: bool, len: u8| {
+            let mut code = Vec::new();
+            if create2 {
+                push0(&mut code, 1); // salt
+            }
+            code.extend_from_slice(&[opcode::PUSH1, len]);
+            push0(&mut code, 2); // offset, value
+            code.push(if create2 {
+                opcode::CREATE2
+            } else {
+                opcode::CREATE
+            });
+            code.push(opcode::POP);
+            gas_used(SpecId::LATEST, code, Vec::new())
+        };
+        for (len, words) in [(0, 0), (1, 1), (32, 1), (65, 3)] {
+            assert_eq!(
+                create_gas(true, len) - create_gas(false, len),
+                3 + gas::SHA3WORD * words,
+                "init code of {len} bytes"
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_call_huge_input_region() {
+        let huge = [opcode::PUSH32].into_iter().chain([0xff; 32]);
+        let max_usize = [opcode::PUSH8].into_iter().chain([0xff; 8]);
+        // (in offset, in len) pushed as code.
+        let regions: Vec<(Vec<u8>, Vec<u8>)> = vec![
+            (vec![opcode::PUSH1, 0x00], huge.clone().collect()),
+            (huge.collect(), vec![opcode::PUSH1, 0x20]),
+            (max_usize.collect(), vec![opcode::PUSH1, 0x20]),
+        ];
+        for (in_offset, in_len) in regions {
+            for call in [opcode::CALL, opcode::DELEGATECALL] {
+                let mut code = Vec::new();
+                push0(&mut code, 2); // out len, out offset
+                code.extend_from_slice(&in_len);
+                code.extend_from_slice(&in_offset);
+                if call == opcode::CALL {
+                    push0(&mut code, 1); // value
+                }
+                code.extend_from_slice(&[opcode::PUSH1, 0x42, opcode::GAS, call, opcode::STOP]);
+
+                let mut evm = evm_with_code(code);
+
+                let (result, _) = evm.transact();
+                assert_eq!(result.exit_reason, Return::OutOfGas);
+                assert_eq!(result.gas_used, 100_000);
+            }
+        }
+    }
+
+    /// BLOCKHASH of `number` at block `current`, block 1000 has a hash set by the host.
+    fn blockhash_at(current: u64, number: u64) -> H256 {
+        let mut code = vec![opcode::PUSH32];
+        code.extend_from_slice(&H256::from_low_u64_be(number).0);
+        code.extend_from_slice(&[
+            opcode::BLOCKHASH,
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x20,
+            opcode::PUSH1,
+            0x00,
+            opcode::RETURN,
+        ]);
+        let mut interp = interpreter(code, 100_000);
+        let mut host = MockHost::new()
+            .with_block_number(U256::from(current))
+            .with_block_hash(U256::from(1000), H256::repeat_byte(0x11));
+        assert_eq!(interp.run::<_, LatestSpec>(&mut host), Return::Return);
+        H256::from_slice(&interp.return_value())
+    }
+
+    #[test]
+    fn test_blockhash_window() {
+        let hash = H256::repeat_byte(0x11);
+        // the last 256 blocks are looked up on the host.
+        assert_eq!(blockhash_at(1001, 1000), hash);
+        assert_eq!(blockhash_at(1256, 1000), hash);
+        // the current block, future blocks and blocks older than 256 are zero.
+        assert_eq!(blockhash_at(1000, 1000), H256::zero());
+        assert_eq!(blockhash_at(999, 1000), H256::zero());
+        assert_eq!(blockhash_at(1257, 1000), H256::zero());
+        // far future numbers do not underflow.
+        assert_eq!(blockhash_at(1000, u64::MAX), H256::zero());
+    }
 }
@@ -1,5 +1,5 @@
 use crate::{interpreter::Interpreter, Host, Return, Spec, SpecId::*};
-use primitive_types::H256;
+use primitive_types::{H256, U256};
 
 pub fn chainid<H: Host, SPEC: Spec>(interp: &mut Interpreter, host: &mut H) -> Return {
     // gas!(interp, gas::BASE);
@@ -53,9 +53,97 @@ pub fn basefee<H: Host, SPEC: Spec>(interp: &mut Interpreter, host: &mut H) -> R
     Return::Continue
 }
 
+pub fn blobhash<H: Host, SPEC: Spec>(interp: &mut Interpreter, host: &mut H) -> Return {
+    // gas!(interp, gas::VERYLOW);
+    // EIP-4844: Shard Blob Transactions
+    check!(SPEC::enabled(CANCUN));
+    pop_top!(interp, index);
+    let hash = usize::try_from(*index)
+        .ok()
+        .and_then(|index| host.env().tx.blob_hashes.get(index).copied())
+        .unwrap_or_default();
+    *index = U256::from_big_endian(hash.as_bytes());
+    Return::Continue
+}
+
+pub fn blobbasefee<H: Host, SPEC: Spec>(interp: &mut Interpreter, host: &mut H) -> Return {
+    // gas!(interp, gas::BASE);
+    // EIP-7516: BLOBBASEFEE opcode
+    check!(SPEC::enabled(CANCUN));
+    push!(interp, host.env().block.blob_base_fee);
+    Return::Continue
+}
+
 pub fn origin<H: Host>(interp: &mut Interpreter, host: &mut H) -> Return {
     // gas!(interp, gas::BASE);
     let ret = H256::from(host.env().tx.caller);
     push_h256!(interp, ret);
     Return::Continue
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{opcode, test_utils::evm_with_code, ExecutionResult, Return, SpecId, TransactOut};
+
+    use primitive_types::{H256, U256};
+
+    /// Runs `code` and returns the word it left on the stack.
+    fn run_word(spec_id: SpecId, code: &[u8]) -> (ExecutionResult, Option<U256>) {
+        let mut bytecode = code.to_vec();
+        bytecode.extend([
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x20,
+            opcode::PUSH1,
+            0x00,
+            opcode::RETURN,
+        ]);
+        let mut evm = evm_with_code(bytecode);
+        evm.env.cfg.spec_id = spec_id;
+        evm.env.block.blob_base_fee = U256::from(7);
+        evm.env.tx.blob_hashes = vec![H256::repeat_byte(0x01), H256::repeat_byte(0x02)];
+
+        let (result, _) = evm.transact();
+        let word = match &result.out {
+            TransactOut::Call(out) if out.len() == 32 => Some(U256::from_big_endian(out)),
+            _ => None,
+        };
+        (result, word)
+    }
+
+    #[test]
+    fn test_blobhash() {
+        let blobhash = |index| run_word(SpecId::CANCUN, &[opcode::PUSH1, index, opcode::BLOBHASH]);
+        assert_eq!(
+            blobhash(1).1,
+            Some(U256::from_big_endian(H256::repeat_byte(0x02).as_bytes()))
+        );
+        // out of range index pushes zero.
+        assert_eq!(blobhash(2).1, Some(U256::zero()));
+        let (_, word) = run_word(
+            SpecId::CANCUN,
+            &[opcode::PUSH1, 0x01, opcode::NOT, opcode::BLOBHASH],
+        );
+        assert_eq!(word, Some(U256::zero()));
+    }
+
+    #[test]
+    fn test_blobbasefee() {
+        let (_, word) = run_word(SpecId::CANCUN, &[opcode::BLOBBASEFEE]);
+        assert_eq!(word, Some(U256::from(7)));
+    }
+
+    #[test]
+    fn test_blob_opcodes_before_cancun() {
+        for code in [
+            &[opcode::PUSH1, 0x00, opcode::BLOBHASH][..],
+            &[opcode::BLOBBASEFEE],
+        ] {
+            let (result, word) = run_word(SpecId::SHANGHAI, code);
+            assert_eq!(result.exit_reason, Return::NotActivated);
+            assert_eq!(word, None);
+        }
+    }
+}
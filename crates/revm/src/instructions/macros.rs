@@ -10,8 +10,8 @@ macro_rules! check {
 
 macro_rules! gas {
     ($interp:expr, $gas:expr) => {
-        if crate::USE_GAS {
-            if !$interp.gas.record_cost(($gas)) {
+        if $interp.use_gas() {
+            if !$interp.gas.record_cost($gas) {
                 return Return::OutOfGas;
             }
         }
@@ -20,7 +20,7 @@ macro_rules! gas {
 
 macro_rules! refund {
     ($interp:expr, $gas:expr) => {{
-        if crate::USE_GAS {
+        if $interp.use_gas() {
             $interp.gas.gas_refund($gas);
         }
     }};
@@ -28,7 +28,7 @@ macro_rules! refund {
 
 macro_rules! gas_or_fail {
     ($interp:expr, $gas:expr) => {
-        if crate::USE_GAS {
+        if $interp.use_gas() {
             match $gas {
                 Some(gas_used) => gas!($interp, gas_used),
                 None => return Return::OutOfGas,
@@ -38,25 +38,27 @@ macro_rules! gas_or_fail {
 }
 
 macro_rules! memory_resize {
-    ($interp:expr, $offset:expr, $len:expr) => {{
+    ($interp:expr, $host:expr, $offset:expr, $len:expr) => {{
         let len: usize = $len;
         let offset: usize = $offset;
         if let Some(new_size) =
             crate::interpreter::memory::next_multiple_of_32(offset.saturating_add(len))
         {
-            #[cfg(feature = "memory_limit")]
-            if new_size > ($interp.memory_limit as usize) {
-                return Return::OutOfGas;
+            if matches!($interp.memory_limit, Some(limit) if new_size as u64 > limit) {
+                return Return::MemoryLimitOOG;
             }
 
             if new_size > $interp.memory.len() {
-                if crate::USE_GAS {
+                if $interp.use_gas() {
                     let num_bytes = new_size / 32;
                     if !$interp.gas.record_memory(crate::gas::memory_gas(num_bytes)) {
                         return Return::OutOfGas;
                     }
                 }
-                $interp.memory.resize(new_size);
+                if !$host.memory_grow(new_size - $interp.memory.len()) {
+                    return Return::OutOfGas;
+                }
+                $interp.memory.resize_word_aligned(new_size);
             }
         } else {
             return Return::OutOfGas;
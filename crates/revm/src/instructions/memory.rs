@@ -1,11 +1,12 @@
-use crate::{interpreter::Interpreter, Return};
+use crate::{gas, interpreter::Interpreter, Host, Return, Spec, SpecId::*};
+use core::cmp::max;
 use primitive_types::U256;
 
-pub fn mload(interp: &mut Interpreter) -> Return {
+pub fn mload<H: Host>(interp: &mut Interpreter, host: &mut H) -> Return {
     // gas!(interp, gas::VERYLOW);
     pop!(interp, index);
     let index = as_usize_or_fail!(index, Return::OutOfGas);
-    memory_resize!(interp, index, 32);
+    memory_resize!(interp, host, index, 32);
     push!(
         interp,
         U256::from_big_endian(interp.memory.get_slice(index, 32))
@@ -13,20 +14,20 @@ pub fn mload(interp: &mut Interpreter) -> Return {
     Return::Continue
 }
 
-pub fn mstore(interp: &mut Interpreter) -> Return {
+pub fn mstore<H: Host>(interp: &mut Interpreter, host: &mut H) -> Return {
     // gas!(interp, gas::VERYLOW);
     pop!(interp, index, value);
     let index = as_usize_or_fail!(index, Return::OutOfGas);
-    memory_resize!(interp, index, 32);
+    memory_resize!(interp, host, index, 32);
     interp.memory.set_u256(index, value);
     Return::Continue
 }
 
-pub fn mstore8(interp: &mut Interpreter) -> Return {
+pub fn mstore8<H: Host>(interp: &mut Interpreter, host: &mut H) -> Return {
     // gas!(interp, gas::VERYLOW);
     pop!(interp, index, value);
     let index = as_usize_or_fail!(index, Return::OutOfGas);
-    memory_resize!(interp, index, 1);
+    memory_resize!(interp, host, index, 1);
     let value = (value.low_u32() & 0xff) as u8;
     // Safety: we resized our memory two lines above.
     unsafe { interp.memory.set_byte(index, value) }
@@ -38,3 +39,111 @@ pub fn msize(interp: &mut Interpreter) -> Return {
     push!(interp, U256::from(interp.memory.effective_len()));
     Return::Continue
 }
+
+pub fn mcopy<H: Host, SPEC: Spec>(interp: &mut Interpreter, host: &mut H) -> Return {
+    // EIP-5656: MCOPY - Memory copying instruction, an invalid opcode before Cancun.
+    if !SPEC::enabled(CANCUN) {
+        return Return::InvalidOpcode;
+    }
+    pop!(interp, dst, src, len);
+    let len = as_usize_or_fail!(len, Return::OutOfGas);
+    gas_or_fail!(interp, gas::verylowcopy_cost(len as u64));
+    if len == 0 {
+        return Return::Continue;
+    }
+    let dst = as_usize_or_fail!(dst, Return::OutOfGas);
+    let src = as_usize_or_fail!(src, Return::OutOfGas);
+    memory_resize!(interp, host, max(dst, src), len);
+    interp.memory.copy(dst, src, len);
+    Return::Continue
+}
+
+#[cfg(test)]
+mod tests {
+    #![cfg_attr(feature = "no_gas_measuring", allow(unused_imports))]
+
+    use crate::{opcode, CancunSpec, Contract, Interpreter, MockHost, Return, ShanghaiSpec, Spec};
+    use bytes::Bytes;
+    use primitive_types::{H160, U256};
+
+    /// Run `MCOPY(dst, src, len)` over 64 bytes of memory holding `0..64`. The memory is set up
+    /// without paying for it.
+    fn mcopy<SPEC: Spec>(dst: u8, src: u8, len: u8) -> (Return, Interpreter) {
+        let code = vec![
+            opcode::PUSH1,
+            len,
+            opcode::PUSH1,
+            src,
+            opcode::PUSH1,
+            dst,
+            opcode::MCOPY,
+            opcode::STOP,
+        ];
+        let contract = Contract::new_from_code::<SPEC>(
+            code.into(),
+            Bytes::new(),
+            H160::zero(),
+            H160::zero(),
+            U256::zero(),
+        );
+        let mut interp = Interpreter::new::<SPEC>(contract, 1_000);
+        interp.memory.resize(64);
+        interp.memory.set(0, &(0..64).collect::<Vec<u8>>());
+        let ret = interp.run::<_, SPEC>(&mut MockHost::new());
+        (ret, interp)
+    }
+
+    /// Memory after a memmove done on a copy of the source.
+    fn memmove(mut memory: Vec<u8>, dst: usize, src: usize, len: usize) -> Vec<u8> {
+        let size = (dst.max(src) + len).div_ceil(32) * 32;
+        memory.resize(memory.len().max(size), 0);
+        let data = memory[src..src + len].to_vec();
+        memory[dst..dst + len].copy_from_slice(&data);
+        memory
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_mcopy() {
+        let initial: Vec<u8> = (0..64).collect();
+        for (dst, src, len) in [
+            // past the end of memory, expands it by a word.
+            (64, 0, 32),
+            // overlapping forward and backward.
+            (8, 0, 40),
+            (0, 8, 40),
+            (1, 0, 63),
+            (0, 1, 63),
+        ] {
+            let (ret, interp) = mcopy::<CancunSpec>(dst, src, len);
+            assert_eq!(ret, Return::Stop);
+            let expected = memmove(initial.clone(), dst.into(), src.into(), len.into());
+            assert_eq!(interp.memory.data(), &expected, "{dst} {src} {len}");
+        }
+
+        // three PUSH1 and MCOPY of one word, memory grows to three words.
+        let (_, interp) = mcopy::<CancunSpec>(64, 0, 32);
+        let memory = interp.gas.memory();
+        assert_eq!((interp.gas.spend() - memory, memory), (9 + 6, 9));
+        // two words copied inside of memory.
+        let (_, interp) = mcopy::<CancunSpec>(0, 8, 40);
+        assert_eq!((interp.gas.spend(), interp.gas.memory()), (9 + 9, 0));
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_mcopy_zero_len() {
+        // offsets are not used and memory does not expand.
+        let (ret, interp) = mcopy::<CancunSpec>(0xff, 0xff, 0);
+        assert_eq!(ret, Return::Stop);
+        assert_eq!(interp.memory.data(), &(0..64).collect::<Vec<u8>>());
+        assert_eq!((interp.gas.spend(), interp.gas.memory()), (9 + 3, 0));
+    }
+
+    #[test]
+    fn test_mcopy_before_cancun() {
+        let (ret, interp) = mcopy::<ShanghaiSpec>(8, 0, 32);
+        assert_eq!(ret, Return::InvalidOpcode);
+        assert_eq!(interp.memory.data(), &(0..64).collect::<Vec<u8>>());
+    }
+}
@@ -48,6 +48,8 @@ pub const JUMPI: u8 = 0x57;
 pub const PC: u8 = 0x58;
 pub const MSIZE: u8 = 0x59;
 pub const JUMPDEST: u8 = 0x5b;
+pub const MCOPY: u8 = 0x5e;
+pub const PUSH0: u8 = 0x5f;
 pub const PUSH1: u8 = 0x60;
 pub const PUSH2: u8 = 0x61;
 pub const PUSH3: u8 = 0x62;
@@ -118,6 +120,8 @@ pub const INVALID: u8 = 0xfe;
 pub const ADDRESS: u8 = 0x30;
 pub const BALANCE: u8 = 0x31;
 pub const BASEFEE: u8 = 0x48;
+pub const BLOBHASH: u8 = 0x49;
+pub const BLOBBASEFEE: u8 = 0x4a;
 pub const ORIGIN: u8 = 0x32;
 pub const CALLER: u8 = 0x33;
 pub const CALLVALUE: u8 = 0x34;
@@ -137,6 +141,8 @@ pub const SELFBALANCE: u8 = 0x47;
 pub const SLOAD: u8 = 0x54;
 pub const SSTORE: u8 = 0x55;
 pub const GAS: u8 = 0x5a;
+pub const TLOAD: u8 = 0x5c;
+pub const TSTORE: u8 = 0x5d;
 pub const LOG0: u8 = 0xa0;
 pub const LOG1: u8 = 0xa1;
 pub const LOG2: u8 = 0xa2;
@@ -168,6 +174,99 @@ impl OpCode {
     pub const fn u8(&self) -> u8 {
         self.0
     }
+
+    /// Wrap any byte, including undefined opcodes that are shown as "unknown".
+    pub(crate) const fn new_unchecked(opcode: u8) -> Self {
+        Self(opcode)
+    }
+
+    /// Number of immediate bytes that follow the opcode in code. Not zero only for PUSH1-PUSH32.
+    #[inline(always)]
+    pub const fn immediate_size(&self) -> usize {
+        if self.0 >= PUSH1 && self.0 <= PUSH32 {
+            (self.0 - PUSH1 + 1) as usize
+        } else {
+            0
+        }
+    }
+
+    /// Metadata of a defined opcode, None for undefined ones. `base_gas` is taken from `spec`.
+    pub fn info(opcode: u8, spec: SpecId) -> Option<OpCodeInfo> {
+        let name = OPCODE_JUMPMAP[opcode as usize]?;
+        let (inputs, outputs) = stack_io(opcode)?;
+        Some(OpCodeInfo {
+            name,
+            inputs,
+            outputs,
+            base_gas: spec_opcode_gas(spec)[opcode as usize].get_gas() as u64,
+            state_changing: matches!(
+                opcode,
+                SSTORE
+                    | TSTORE
+                    | LOG0
+                    | LOG1
+                    | LOG2
+                    | LOG3
+                    | LOG4
+                    | CREATE
+                    | CREATE2
+                    | CALL
+                    | SELFDESTRUCT
+            ),
+        })
+    }
+}
+
+/// Metadata of an opcode, see [OpCode::info].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct OpCodeInfo {
+    pub name: &'static str,
+    /// Number of stack items the opcode pops.
+    pub inputs: u8,
+    /// Number of stack items the opcode pushes.
+    pub outputs: u8,
+    /// Static gas that is charged before execution. Opcodes with a dynamic cost charge the rest
+    /// while executing.
+    pub base_gas: u64,
+    /// Opcode changes state, these are the opcodes that are not allowed in a static call. CALL
+    /// only changes state if it transfers value.
+    pub state_changing: bool,
+}
+
+/// `(inputs, outputs)` stack items of a defined opcode.
+const fn stack_io(opcode: u8) -> Option<(u8, u8)> {
+    let io = match opcode {
+        STOP | JUMPDEST | INVALID => (0, 0),
+        ADD | MUL | SUB | DIV | SDIV | MOD | SMOD | EXP | SIGNEXTEND => (2, 1),
+        ADDMOD | MULMOD => (3, 1),
+        LT | GT | SLT | SGT | EQ | AND | OR | XOR | BYTE | SHL | SHR | SAR | SHA3 => (2, 1),
+        ISZERO | NOT => (1, 1),
+        ADDRESS | ORIGIN | CALLER | CALLVALUE | CALLDATASIZE | CODESIZE | GASPRICE
+        | RETURNDATASIZE | COINBASE | TIMESTAMP | NUMBER | DIFFICULTY | GASLIMIT | CHAINID
+        | SELFBALANCE | BASEFEE | BLOBBASEFEE | PC | MSIZE | GAS | PUSH0 => (0, 1),
+        BALANCE | CALLDATALOAD | EXTCODESIZE | EXTCODEHASH | BLOCKHASH | BLOBHASH | MLOAD
+        | SLOAD | TLOAD => (1, 1),
+        CALLDATACOPY | CODECOPY | RETURNDATACOPY | MCOPY => (3, 0),
+        EXTCODECOPY => (4, 0),
+        POP | JUMP | SELFDESTRUCT => (1, 0),
+        MSTORE | MSTORE8 | SSTORE | TSTORE | JUMPI | RETURN | REVERT => (2, 0),
+        PUSH1..=PUSH32 => (0, 1),
+        DUP1..=DUP16 => {
+            let n = opcode - DUP1 + 1;
+            (n, n + 1)
+        }
+        SWAP1..=SWAP16 => {
+            let n = opcode - SWAP1 + 2;
+            (n, n)
+        }
+        LOG0..=LOG4 => (opcode - LOG0 + 2, 0),
+        CREATE => (3, 1),
+        CREATE2 => (4, 1),
+        CALL | CALLCODE => (7, 1),
+        DELEGATECALL | STATICCALL => (6, 1),
+        _ => return None,
+    };
+    Some(io)
 }
 
 const JUMP_MASK: u32 = 0x80000000;
@@ -335,8 +434,7 @@ macro_rules! gas_opcodee {
                 gas::WARM_STORAGE_READ_COST // add only part of gas
             } else if SpecId::enabled($spec_id, SpecId::ISTANBUL) {
                 700
-            } else if SpecId::enabled($spec_id, SpecId::PETERSBURG) {
-                // constantinople
+            } else if SpecId::enabled($spec_id, SpecId::CONSTANTINOPLE) {
                 400
             } else {
                 0 // not enabled
@@ -365,8 +463,18 @@ macro_rules! gas_opcodee {
             } else {
                 0
             }),
-            /* 0x49 */ OpInfo::none(),
-            /* 0x4a */ OpInfo::none(),
+            /* 0x49  BLOBHASH */
+            OpInfo::gas(if SpecId::enabled($spec_id, SpecId::CANCUN) {
+                gas::VERYLOW
+            } else {
+                0
+            }),
+            /* 0x4a  BLOBBASEFEE */
+            OpInfo::gas(if SpecId::enabled($spec_id, SpecId::CANCUN) {
+                gas::BASE
+            } else {
+                0
+            }),
             /* 0x4b */ OpInfo::none(),
             /* 0x4c */ OpInfo::none(),
             /* 0x4d */ OpInfo::none(),
@@ -386,10 +494,25 @@ macro_rules! gas_opcodee {
             /* 0x5b  JUMPDEST */
             // gas::JUMPDEST gas is calculated in function call,
             OpInfo::jumpdest(),
-            /* 0x5c */ OpInfo::none(),
-            /* 0x5d */ OpInfo::none(),
-            /* 0x5e */ OpInfo::none(),
-            /* 0x5f */ OpInfo::none(),
+            /* 0x5c  TLOAD */
+            OpInfo::gas(if SpecId::enabled($spec_id, SpecId::CANCUN) {
+                gas::WARM_STORAGE_READ_COST
+            } else {
+                0
+            }),
+            /* 0x5d  TSTORE */
+            OpInfo::gas(if SpecId::enabled($spec_id, SpecId::CANCUN) {
+                gas::WARM_STORAGE_READ_COST
+            } else {
+                0
+            }),
+            /* 0x5e  MCOPY */ OpInfo::dynamic_gas(),
+            /* 0x5f  PUSH0 */
+            OpInfo::gas(if SpecId::enabled($spec_id, SpecId::SHANGHAI) {
+                gas::BASE
+            } else {
+                0
+            }),
             /* 0x60  PUSH1 */ OpInfo::push_opcode(),
             /* 0x61  PUSH2 */ OpInfo::push_opcode(),
             /* 0x62  PUSH3 */ OpInfo::push_opcode(),
@@ -620,10 +743,26 @@ pub const fn spec_opcode_gas(spec_id: SpecId) -> &'static [OpInfo; 256] {
             gas_opcodee!(MERGE, SpecId::MERGE);
             MERGE
         }
+        SpecId::SHANGHAI => {
+            gas_opcodee!(SHANGHAI, SpecId::SHANGHAI);
+            SHANGHAI
+        }
+        SpecId::CANCUN => {
+            gas_opcodee!(CANCUN, SpecId::CANCUN);
+            CANCUN
+        }
+        SpecId::PRAGUE => {
+            gas_opcodee!(PRAGUE, SpecId::PRAGUE);
+            PRAGUE
+        }
         SpecId::LATEST => {
             gas_opcodee!(LATEST, SpecId::LATEST);
             LATEST
         }
+        SpecId::EXPERIMENTAL_EOF => {
+            gas_opcodee!(EXPERIMENTAL_EOF, SpecId::EXPERIMENTAL_EOF);
+            EXPERIMENTAL_EOF
+        }
     }
 }
 
@@ -701,8 +840,8 @@ pub const OPCODE_JUMPMAP: [Option<&'static str>; 256] = [
     /* 0x46 */ Some("CHAINID"),
     /* 0x47 */ Some("SELFBALANCE"),
     /* 0x48 */ Some("BASEFEE"),
-    /* 0x49 */ None,
-    /* 0x4a */ None,
+    /* 0x49 */ Some("BLOBHASH"),
+    /* 0x4a */ Some("BLOBBASEFEE"),
     /* 0x4b */ None,
     /* 0x4c */ None,
     /* 0x4d */ None,
@@ -720,10 +859,10 @@ pub const OPCODE_JUMPMAP: [Option<&'static str>; 256] = [
     /* 0x59 */ Some("MSIZE"),
     /* 0x5a */ Some("GAS"),
     /* 0x5b */ Some("JUMPDEST"),
-    /* 0x5c */ None,
-    /* 0x5d */ None,
-    /* 0x5e */ None,
-    /* 0x5f */ None,
+    /* 0x5c */ Some("TLOAD"),
+    /* 0x5d */ Some("TSTORE"),
+    /* 0x5e */ Some("MCOPY"),
+    /* 0x5f */ Some("PUSH0"),
     /* 0x60 */ Some("PUSH1"),
     /* 0x61 */ Some("PUSH2"),
     /* 0x62 */ Some("PUSH3"),
@@ -885,3 +1024,47 @@ pub const OPCODE_JUMPMAP: [Option<&'static str>; 256] = [
     /* 0xfe */ Some("INVALID"),
     /* 0xff */ Some("SELFDESTRUCT"),
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opcode_info() {
+        for opcode in 0..=255u8 {
+            assert_eq!(
+                OpCode::info(opcode, SpecId::LATEST).is_some(),
+                OPCODE_JUMPMAP[opcode as usize].is_some(),
+                "0x{opcode:02x}"
+            );
+        }
+
+        let add = OpCode::info(ADD, SpecId::LATEST).unwrap();
+        assert_eq!((add.name, add.inputs, add.outputs), ("ADD", 2, 1));
+        assert_eq!(add.base_gas, 3);
+        assert!(!add.state_changing);
+
+        let sstore = OpCode::info(SSTORE, SpecId::LATEST).unwrap();
+        assert_eq!((sstore.inputs, sstore.outputs), (2, 0));
+        assert!(sstore.state_changing);
+        let tstore = OpCode::info(TSTORE, SpecId::CANCUN).unwrap();
+        assert_eq!((tstore.name, tstore.base_gas), ("TSTORE", 100));
+        assert!(tstore.state_changing);
+        assert_eq!(OpCode::info(TLOAD, SpecId::MERGE).unwrap().base_gas, 0);
+        let blobhash = OpCode::info(BLOBHASH, SpecId::CANCUN).unwrap();
+        assert_eq!(
+            (blobhash.inputs, blobhash.outputs, blobhash.base_gas),
+            (1, 1, 3)
+        );
+
+        let push0 = OpCode::info(PUSH0, SpecId::SHANGHAI).unwrap();
+        assert_eq!((push0.inputs, push0.outputs, push0.base_gas), (0, 1, 2));
+        assert_eq!(OpCode::new_unchecked(PUSH0).immediate_size(), 0);
+
+        let swap16 = OpCode::info(SWAP16, SpecId::LATEST).unwrap();
+        assert_eq!((swap16.inputs, swap16.outputs), (17, 17));
+        let log2 = OpCode::info(LOG2, SpecId::LATEST).unwrap();
+        assert_eq!((log2.inputs, log2.outputs), (4, 0));
+        assert_eq!(OpCode::info(0x0c, SpecId::LATEST), None);
+    }
+}
@@ -1,10 +1,21 @@
-use crate::{interpreter::Interpreter, Return};
+use crate::{interpreter::Interpreter, Return, Spec, SpecId::*};
+use primitive_types::U256;
 
 pub fn pop(interp: &mut Interpreter) -> Return {
     // gas!(interp, gas::BASE);
     interp.stack.reduce_one()
 }
 
+pub fn push0<SPEC: Spec>(interp: &mut Interpreter) -> Return {
+    // gas!(interp, gas::BASE);
+    // EIP-3855: PUSH0 instruction, an invalid opcode before Shanghai.
+    if !SPEC::enabled(SHANGHAI) {
+        return Return::InvalidOpcode;
+    }
+    push!(interp, U256::zero());
+    Return::Continue
+}
+
 pub fn push<const N: usize>(interp: &mut Interpreter) -> Return {
     // gas!(interp, gas::VERYLOW);
     let start = interp.instruction_pointer;
@@ -26,3 +37,54 @@ pub fn swap<const N: usize>(interp: &mut Interpreter) -> Return {
     // gas!(interp, gas::VERYLOW);
     interp.stack.swap::<N>()
 }
+
+#[cfg(all(test, not(feature = "no_gas_measuring")))]
+mod tests {
+    use crate::{
+        disassemble, opcode, spec_opcode_gas, Contract, Interpreter, LatestSpec, LondonSpec,
+        MockHost, Return, ShanghaiSpec, Spec, SpecId,
+    };
+    use bytes::Bytes;
+    use primitive_types::{H160, U256};
+
+    fn interpreter<SPEC: Spec>(code: Vec<u8>) -> Interpreter {
+        let contract = Contract::new_from_code::<SPEC>(
+            code.into(),
+            Bytes::new(),
+            H160::zero(),
+            H160::zero(),
+            U256::zero(),
+        );
+        Interpreter::new::<SPEC>(contract, 100)
+    }
+
+    #[test]
+    fn test_push0() {
+        let code = vec![opcode::PUSH0, opcode::PUSH1, 0x01, opcode::STOP];
+        let mut host = MockHost::new();
+        let mut interp = interpreter::<ShanghaiSpec>(code.clone());
+        assert_eq!(interp.step::<_, ShanghaiSpec>(&mut host), Return::Continue);
+        assert_eq!(interp.program_counter(), 1);
+        assert_eq!(interp.stack.data(), &vec![U256::zero()]);
+        assert_eq!(interp.run::<_, ShanghaiSpec>(&mut host), Return::Stop);
+        assert_eq!(interp.stack.data(), &vec![U256::zero(), U256::one()]);
+        // PUSH0, PUSH1 and STOP.
+        assert_eq!(interp.gas.spend(), 2 + 3);
+
+        let mut interp = interpreter::<LondonSpec>(code.clone());
+        assert_eq!(
+            interp.run::<_, LondonSpec>(&mut host),
+            Return::InvalidOpcode
+        );
+
+        // no immediate to skip in analysis and disassembly.
+        let ops: Vec<_> = disassemble(&code).into_iter().map(|op| op.0).collect();
+        assert_eq!(ops, vec![0, 1, 3]);
+        assert!(!spec_opcode_gas(SpecId::LATEST)[opcode::PUSH0 as usize].is_push());
+        assert!(
+            interpreter::<LatestSpec>(vec![opcode::PUSH0, opcode::JUMPDEST])
+                .contract
+                .is_valid_jump(1)
+        );
+    }
+}
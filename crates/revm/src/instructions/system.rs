@@ -1,11 +1,11 @@
 use std::cmp::min;
 
-use crate::{gas, interpreter::Interpreter, Return, Spec, SpecId::*, KECCAK_EMPTY};
+use crate::{gas, interpreter::Interpreter, Host, Return, Spec, SpecId::*, KECCAK_EMPTY};
 use primitive_types::{H256, U256};
 
 use sha3::{Digest, Keccak256};
 
-pub fn sha3(interp: &mut Interpreter) -> Return {
+pub fn sha3<H: Host>(interp: &mut Interpreter, host: &mut H) -> Return {
     pop!(interp, from, len);
     let len = as_usize_or_fail!(len, Return::OutOfGas);
     gas_or_fail!(interp, gas::sha3_cost(len as u64));
@@ -13,7 +13,7 @@ pub fn sha3(interp: &mut Interpreter) -> Return {
         KECCAK_EMPTY
     } else {
         let from = as_usize_or_fail!(from, Return::OutOfGas);
-        memory_resize!(interp, from, len);
+        memory_resize!(interp, host, from, len);
         H256::from_slice(Keccak256::digest(interp.memory.get_slice(from, len)).as_slice())
     };
 
@@ -37,12 +37,13 @@ pub fn caller(interp: &mut Interpreter) -> Return {
 
 pub fn codesize(interp: &mut Interpreter) -> Return {
     // gas!(interp, gas::BASE);
+    // running code, init code in a CREATE frame and never the code deployed at the address.
     let size = U256::from(interp.contract.bytecode.len());
     push!(interp, size);
     Return::Continue
 }
 
-pub fn codecopy(interp: &mut Interpreter) -> Return {
+pub fn codecopy<H: Host>(interp: &mut Interpreter, host: &mut H) -> Return {
     pop!(interp, memory_offset, code_offset, len);
     let len = as_usize_or_fail!(len, Return::OutOfGas);
     gas_or_fail!(interp, gas::verylowcopy_cost(len as u64));
@@ -51,7 +52,7 @@ pub fn codecopy(interp: &mut Interpreter) -> Return {
     }
     let memory_offset = as_usize_or_fail!(memory_offset, Return::OutOfGas);
     let code_offset = as_usize_saturated!(code_offset);
-    memory_resize!(interp, memory_offset, len);
+    memory_resize!(interp, host, memory_offset, len);
 
     // Safety: set_data is unsafe function and memory_resize ensures us that it is safe to call it
     interp.memory.set_data(
@@ -96,7 +97,7 @@ pub fn callvalue(interp: &mut Interpreter) -> Return {
     Return::Continue
 }
 
-pub fn calldatacopy(interp: &mut Interpreter) -> Return {
+pub fn calldatacopy<H: Host>(interp: &mut Interpreter, host: &mut H) -> Return {
     pop!(interp, memory_offset, data_offset, len);
     let len = as_usize_or_fail!(len, Return::OutOfGas);
     gas_or_fail!(interp, gas::verylowcopy_cost(len as u64));
@@ -105,7 +106,7 @@ pub fn calldatacopy(interp: &mut Interpreter) -> Return {
     }
     let memory_offset = as_usize_or_fail!(memory_offset, Return::OutOfGas);
     let data_offset = as_usize_saturated!(data_offset);
-    memory_resize!(interp, memory_offset, len);
+    memory_resize!(interp, host, memory_offset, len);
 
     // Safety: set_data is unsafe function and memory_resize ensures us that it is safe to call it
     interp
@@ -123,7 +124,7 @@ pub fn returndatasize<SPEC: Spec>(interp: &mut Interpreter) -> Return {
     Return::Continue
 }
 
-pub fn returndatacopy<SPEC: Spec>(interp: &mut Interpreter) -> Return {
+pub fn returndatacopy<H: Host, SPEC: Spec>(interp: &mut Interpreter, host: &mut H) -> Return {
     // EIP-211: New opcodes: RETURNDATASIZE and RETURNDATACOPY
     check!(SPEC::enabled(BYZANTIUM));
     pop!(interp, memory_offset, offset, len);
@@ -131,7 +132,7 @@ pub fn returndatacopy<SPEC: Spec>(interp: &mut Interpreter) -> Return {
     gas_or_fail!(interp, gas::verylowcopy_cost(len as u64));
     let memory_offset = as_usize_or_fail!(memory_offset, Return::OutOfGas);
     let data_offset = as_usize_saturated!(offset);
-    memory_resize!(interp, memory_offset, len);
+    memory_resize!(interp, host, memory_offset, len);
     let (data_end, overflow) = data_offset.overflowing_add(len);
     if overflow || data_end > interp.return_data_buffer.len() {
         return Return::OutOfOffset;
@@ -143,8 +144,118 @@ pub fn returndatacopy<SPEC: Spec>(interp: &mut Interpreter) -> Return {
     Return::Continue
 }
 
-pub fn gas(interp: &mut Interpreter) -> Return {
+pub fn gas<H: Host>(interp: &mut Interpreter, host: &mut H) -> Return {
     // gas!(interp, gas::BASE);
     push!(interp, U256::from(interp.gas.remaining()));
-    interp.add_next_gas_block(interp.program_counter() - 1)
+    interp.add_next_gas_block(host, interp.program_counter() - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{returndatacopy, returndatasize};
+    use crate::{
+        opcode, test_utils::evm_with_code, Interpreter, LatestSpec, MockHost, Return, TransactOut,
+        TransactTo,
+    };
+    use bytes::Bytes;
+    use primitive_types::{H160, U256};
+
+    fn interpreter() -> Interpreter {
+        crate::test_utils::interpreter(vec![opcode::RETURNDATASIZE, opcode::STOP], u64::MAX)
+    }
+
+    #[test]
+    fn test_returndatasize_seeded() {
+        let mut interp = interpreter();
+        interp.set_return_data(Bytes::from(vec![1, 2, 3, 4, 5]));
+        assert_eq!(returndatasize::<LatestSpec>(&mut interp), Return::Continue);
+        assert_eq!(interp.stack.pop(), Ok(U256::from(5)));
+    }
+
+    #[test]
+    fn test_returndatacopy_seeded() {
+        let mut interp = interpreter();
+        interp.set_return_data(Bytes::from(vec![0xaa, 0xbb, 0xcc]));
+        // len, offset, memory_offset
+        interp.stack.push(U256::from(2)).unwrap();
+        interp.stack.push(U256::from(1)).unwrap();
+        interp.stack.push(U256::zero()).unwrap();
+        assert_eq!(
+            returndatacopy::<_, LatestSpec>(&mut interp, &mut MockHost::new()),
+            Return::Continue
+        );
+        assert_eq!(&interp.memory.data()[..2], &[0xbb, 0xcc]);
+    }
+
+    /// Code deployed by running `init_code` and output of calling `runtime_code`.
+    fn create_and_call(init_code: Vec<u8>, runtime_code: Vec<u8>) -> (Bytes, Bytes) {
+        let mut evm = evm_with_code(runtime_code);
+        evm.env.tx.transact_to = TransactTo::create();
+        evm.env.tx.data = Bytes::from(init_code);
+        let (result, state) = evm.transact();
+        let created = match result.out {
+            TransactOut::Create(_, Some(address)) => {
+                let code = state[&address].info.code.as_ref().unwrap();
+                code.bytes().slice(..code.len())
+            }
+            out => panic!("create failed: {:?}", out),
+        };
+
+        evm.env.tx.transact_to = TransactTo::Call(H160::zero());
+        evm.env.tx.data = Bytes::new();
+        let called = match evm.transact().0.out {
+            TransactOut::Call(out) => out,
+            out => panic!("expected call output, got {:?}", out),
+        };
+        (created, called)
+    }
+
+    #[test]
+    fn test_codesize_reads_running_code() {
+        // returns CODESIZE as a word.
+        let init_code = vec![
+            opcode::CODESIZE,
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x20,
+            opcode::PUSH1,
+            0x00,
+            opcode::RETURN,
+        ];
+        let mut runtime_code = init_code.clone();
+        runtime_code.extend_from_slice(&[opcode::STOP; 3]);
+
+        let (created, called) = create_and_call(init_code, runtime_code);
+        assert_eq!(U256::from_big_endian(&created), U256::from(9));
+        assert_eq!(U256::from_big_endian(&called), U256::from(12));
+    }
+
+    #[test]
+    fn test_codecopy_reads_running_code() {
+        // returns its own 12 bytes: CODECOPY(0, 0, 12) RETURN(0, 12).
+        let init_code = vec![
+            opcode::PUSH1,
+            0x0c,
+            opcode::PUSH1,
+            0x00,
+            opcode::PUSH1,
+            0x00,
+            opcode::CODECOPY,
+            opcode::PUSH1,
+            0x0c,
+            opcode::PUSH1,
+            0x00,
+            opcode::RETURN,
+        ];
+        let mut runtime_code = init_code.clone();
+        runtime_code[1] = 0x0d;
+        runtime_code[8] = 0x0d;
+        runtime_code.push(opcode::INVALID);
+
+        let (created, called) = create_and_call(init_code.clone(), runtime_code.clone());
+        assert_eq!(created, Bytes::from(init_code));
+        assert_eq!(called, Bytes::from(runtime_code));
+    }
 }
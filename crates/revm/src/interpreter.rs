@@ -1,23 +1,67 @@
 pub mod bytecode;
 mod contract;
+mod coverage;
+mod eof;
+pub(crate) mod fusion;
 pub(crate) mod memory;
-mod stack;
+mod ngram;
+mod snapshot;
+pub(crate) mod stack;
+mod sub_call;
 
-pub use bytecode::{Bytecode, BytecodeLocked, BytecodeState};
+pub use bytecode::{validate_bytecode, Bytecode, BytecodeError, BytecodeLocked, BytecodeState};
 pub use contract::Contract;
+pub use eof::{validate_eof, EofError, EofHeader, EOF_MAGIC, EOF_VERSION};
 pub use memory::Memory;
-pub use stack::Stack;
+pub use snapshot::{InterpreterSnapshot, SnapshotError};
+pub use stack::{Stack, StackWord};
+pub use sub_call::{SubCall, SubCallResult};
 
 use crate::{
-    instructions::{eval, Return},
-    Gas, Host, Spec, USE_GAS,
+    alloc::{format, string::String, vec::Vec},
+    instructions::{eval, eval_fused, opcode::OpInfo, Return},
+    opcode, spec_opcode_gas, Gas, GasBlockGranularity, Host, OpCode, OpcodeSet, Spec, SpecId,
+    OPCODE_JUMPMAP, USE_GAS,
 };
 use bytes::Bytes;
-use core::ops::Range;
+use core::{fmt::Write, ops::Range};
+use hashbrown::HashMap as Map;
+use std::sync::Arc;
+
+/// Opcodes that read or write memory.
+fn touches_memory(opcode: u8) -> bool {
+    matches!(
+        opcode,
+        opcode::MLOAD
+            | opcode::MSTORE
+            | opcode::MSTORE8
+            | opcode::SHA3
+            | opcode::CALLDATACOPY
+            | opcode::CODECOPY
+            | opcode::EXTCODECOPY
+            | opcode::RETURNDATACOPY
+            | opcode::LOG0
+            | opcode::LOG1
+            | opcode::LOG2
+            | opcode::LOG3
+            | opcode::LOG4
+            | opcode::CREATE
+            | opcode::CALL
+            | opcode::CALLCODE
+            | opcode::RETURN
+            | opcode::DELEGATECALL
+            | opcode::CREATE2
+            | opcode::STATICCALL
+            | opcode::REVERT
+    )
+}
 
 pub const STACK_LIMIT: u64 = 1024;
 pub const CALL_STACK_LIMIT: u64 = 1024;
 
+/// Cloning is cheap for the code, it is shared with the clone. The instruction pointer of the
+/// clone points into the same buffer.
+#[derive(Clone)]
 pub struct Interpreter {
     /// Contract information and invoking data
     pub contract: Contract,
@@ -33,16 +77,56 @@ pub struct Interpreter {
     pub return_data_buffer: Bytes,
     /// Return value.
     pub return_range: Range<usize>,
-    /// Memory limit. See [`crate::CfgEnv`].
-    #[cfg(feature = "memory_limit")]
-    pub memory_limit: u64,
+    /// Memory limit in bytes, `None` is unlimited. See [`crate::CfgEnv`].
+    pub memory_limit: Option<u64>,
+    /// How static gas is charged. See [`crate::CfgEnv`].
+    pub gas_block_granularity: GasBlockGranularity,
+    /// Opcodes that halt with [Return::OpcodeDisabled]. See [`crate::CfgEnv`].
+    pub disabled_opcodes: OpcodeSet,
+    /// Halt with [Return::StepLimitReached] once this many opcodes were executed. Counted apart
+    /// from gas, bounds execution of tooling that runs without gas.
+    pub step_limit: Option<u64>,
+    /// Skip all gas accounting. See [`crate::CfgEnv`].
+    pub disable_gas: bool,
+    /// Check that the instruction pointer is inside the bytecode before every opcode. See
+    /// [`crate::CfgEnv`].
+    pub checked_execution: bool,
+    /// Run pairs of adjacent opcodes as one fused opcode. See [`crate::CfgEnv`].
+    pub fuse_opcodes: bool,
+    /// Fused opcode at every pc, built by the first step if [Interpreter::fuse_opcodes] is set
+    /// and fusion does not change what is observed or charged. See [fusion::fuse].
+    fused: Option<Arc<Vec<u8>>>,
+    /// Opcodes executed so far.
+    steps: u64,
+    /// Static gas charged ahead for the rest of the current segment in
+    /// [GasBlockGranularity::PerMemoryOp] mode.
+    prepaid_gas: u64,
+    segment_charged: bool,
+    /// Block end opcode that charged the current gas block in [GasBlockGranularity::WholeBlock]
+    /// mode, `None` for the first block.
+    gas_block_start: Option<usize>,
+    /// Set once the first gas block is charged.
+    started: bool,
+    /// Running init code of a CREATE or CREATE2.
+    pub(crate) is_create: bool,
+    /// Sub call of a suspended CALL or CREATE, see [Host::ITERATIVE_CALLS].
+    pub(crate) sub_call: Option<SubCall>,
+    /// Memory offset and length the output of the suspended call is copied to.
+    pub(crate) sub_call_out: (usize, usize),
+    sub_call_result: Option<SubCallResult>,
+    /// Length of the opcode n-grams counted by `run`, zero if off. See [Interpreter::with_ngram].
+    ngram: usize,
+    /// Last `ngram` executed opcodes.
+    opcode_window: Vec<u8>,
+    opcode_counts: Map<Vec<u8>, u64>,
+    /// Bitmap of executed program counters, `None` if off. See [Interpreter::with_pc_coverage].
+    pc_coverage: Option<Vec<u64>>,
 }
 
 impl Interpreter {
     pub fn current_opcode(&self) -> u8 {
         unsafe { *self.instruction_pointer }
     }
-    #[cfg(not(feature = "memory_limit"))]
     pub fn new<SPEC: Spec>(contract: Contract, gas_limit: u64) -> Self {
         Self {
             instruction_pointer: contract.bytecode.as_ptr(),
@@ -52,24 +136,52 @@ impl Interpreter {
             return_data_buffer: Bytes::new(),
             contract,
             gas: Gas::new(gas_limit),
+            memory_limit: None,
+            gas_block_granularity: GasBlockGranularity::WholeBlock,
+            disabled_opcodes: OpcodeSet::default(),
+            step_limit: None,
+            disable_gas: false,
+            checked_execution: false,
+            fuse_opcodes: false,
+            fused: None,
+            steps: 0,
+            prepaid_gas: 0,
+            segment_charged: false,
+            gas_block_start: None,
+            started: false,
+            is_create: false,
+            sub_call: None,
+            sub_call_out: (0, 0),
+            sub_call_result: None,
+            ngram: 0,
+            opcode_window: Vec::new(),
+            opcode_counts: Map::new(),
+            pc_coverage: None,
         }
     }
 
-    #[cfg(feature = "memory_limit")]
     pub fn new_with_memory_limit<SPEC: Spec>(
         contract: Contract,
         gas_limit: u64,
         memory_limit: u64,
     ) -> Self {
         Self {
-            instruction_pointer: contract.bytecode.as_ptr(),
-            return_range: Range::default(),
-            memory: Memory::new(),
-            stack: Stack::new(),
-            return_data_buffer: Bytes::new(),
-            contract,
-            gas: Gas::new(gas_limit),
-            memory_limit,
+            memory_limit: Some(memory_limit),
+            ..Self::new::<SPEC>(contract, gas_limit)
+        }
+    }
+
+    /// [Interpreter::new] with room for `memory_capacity` bytes of memory allocated up front, for
+    /// example the memory a prior run of the contract used. Only an allocation hint, memory still
+    /// starts empty and its expansion is charged as usual.
+    pub fn new_with_memory_capacity<SPEC: Spec>(
+        contract: Contract,
+        gas_limit: u64,
+        memory_capacity: usize,
+    ) -> Self {
+        Self {
+            memory: Memory::with_capacity(memory_capacity),
+            ..Self::new::<SPEC>(contract, gas_limit)
         }
     }
 
@@ -81,73 +193,1124 @@ impl Interpreter {
         &self.gas
     }
 
+    /// Whether this frame runs init code, CODESIZE and CODECOPY then read the init code.
+    pub fn is_create(&self) -> bool {
+        self.is_create
+    }
+
     /// Reference of interp stack.
     pub fn stack(&self) -> &Stack {
         &self.stack
     }
 
-    pub fn add_next_gas_block(&mut self, pc: usize) -> Return {
-        if USE_GAS {
+    /// Set return data buffer as if the last call returned `data`.
+    ///
+    /// Testing convenience for RETURNDATASIZE/RETURNDATACOPY without a real sub call, the buffer
+    /// is normally set by call handling.
+    pub fn set_return_data(&mut self, data: Bytes) {
+        self.return_data_buffer = data;
+    }
+
+    /// Number of opcodes executed, see [Interpreter::step_limit].
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Static gas that is already charged but not yet used by executed opcodes.
+    ///
+    /// Gas remaining before the current opcode is `gas.remaining() + prepaid_gas()`. Only the
+    /// [GasBlockGranularity::PerMemoryOp] mode charges ahead outside of precomputed gas blocks.
+    pub fn prepaid_gas(&self) -> u64 {
+        self.prepaid_gas
+    }
+
+    /// Static gas that is already charged for the current opcode and the rest of its gas block or
+    /// segment, zero when gas is charged per opcode.
+    ///
+    /// Gas remaining before the current opcode is `gas.remaining()` plus this, the difference
+    /// between two steps is the gas of the opcode in between.
+    pub fn current_block_gas_remaining(&self, spec_id: SpecId) -> u64 {
+        if !self.use_gas() || !self.started {
+            return 0;
+        }
+        match self.gas_block_granularity {
+            GasBlockGranularity::PerOpcode => 0,
+            GasBlockGranularity::PerMemoryOp => self.prepaid_gas,
+            GasBlockGranularity::WholeBlock => {
+                let (mut index, block) = match self.gas_block_start {
+                    Some(pc) => (pc + 1, self.contract.gas_block(pc)),
+                    None => (0, self.contract.first_gas_block()),
+                };
+                let infos = spec_opcode_gas(spec_id);
+                let code = self.contract.bytecode.bytecode();
+                let pc = self.program_counter();
+                let mut used = 0;
+                while index < pc {
+                    let opcode = code[index];
+                    let info = &infos[opcode as usize];
+                    // the whole block is used, the current opcode was jumped to.
+                    if info.is_gas_block_end() {
+                        return 0;
+                    }
+                    used += info.get_gas() as u64;
+                    index += 1 + OpCode::new_unchecked(opcode).immediate_size();
+                }
+                if index != pc {
+                    return 0;
+                }
+                block.saturating_sub(used)
+            }
+        }
+    }
+
+    /// Whether gas is metered, false with the `no_gas_measuring` feature or
+    /// [Interpreter::disable_gas].
+    #[inline(always)]
+    pub fn use_gas(&self) -> bool {
+        USE_GAS && !self.disable_gas
+    }
+
+    /// Charge the gas block that starts after the block end opcode at `pc` and report it with
+    /// [Host::gas_block]. Gas blocks are only charged in [GasBlockGranularity::WholeBlock] mode.
+    pub fn add_next_gas_block<H: Host>(&mut self, host: &mut H, pc: usize) -> Return {
+        if self.use_gas() && self.gas_block_granularity == GasBlockGranularity::WholeBlock {
             let gas_block = self.contract.gas_block(pc);
             if !self.gas.record_cost(gas_block) {
                 return Return::OutOfGas;
             }
+            self.gas_block_start = Some(pc);
+            if H::INSPECT {
+                host.gas_block(pc + 1, gas_block, self.gas.remaining());
+            }
         }
         Return::Continue
     }
 
     /// Return a reference of the program counter.
     pub fn program_counter(&self) -> usize {
-        // Safety: this is just subtraction of pointers, it is safe to do.
-        unsafe {
-            self.instruction_pointer
-                .offset_from(self.contract.bytecode.as_ptr()) as usize
+        // `offset_from` is UB if the pointers are not from the same allocation, so compute the
+        // offset from the addresses instead. A corrupted pointer shows up as an out of bounds pc.
+        let pc = (self.instruction_pointer as usize)
+            .wrapping_sub(self.contract.bytecode.as_ptr() as usize);
+        debug_assert!(
+            pc <= self.contract.bytecode.bytecode().len(),
+            "program counter {pc} out of bytecode bounds"
+        );
+        pc
+    }
+
+    /// Human readable snapshot of the interpreter state, useful for bug reports.
+    ///
+    /// Contains the program counter, current opcode, gas, the full stack (top last) and the memory
+    /// size.
+    pub fn debug_dump(&self) -> String {
+        let opcode = self.current_opcode();
+        let mut out = format!(
+            "pc: {}\nopcode: {} (0x{:02x})\ngas remaining: {}\ngas refunded: {}\n",
+            self.program_counter(),
+            OPCODE_JUMPMAP[opcode as usize].unwrap_or("UNKNOWN"),
+            opcode,
+            self.gas.remaining(),
+            self.gas.refunded(),
+        );
+        let _ = writeln!(out, "stack ({} items):", self.stack.len());
+        for (i, value) in self.stack.data().iter().enumerate().rev() {
+            let _ = writeln!(out, "  {i}: 0x{value:x}");
         }
+        let _ = writeln!(out, "memory size: {}", self.memory.len());
+        out
     }
 
     /// loop steps until we are finished with execution
+    ///
+    /// If [Host::ITERATIVE_CALLS] is set, execution also stops with [Return::SubCall] at every
+    /// CALL and CREATE. Take the sub call with [Interpreter::take_sub_call], run it and pass its
+    /// result to [Interpreter::resume] before calling `run` again.
+    ///
+    /// With the `catch_unwind` feature a panic inside the interpreter or host is caught and
+    /// returned as [Return::FatalExternalError], so a latent bug fails the call instead of
+    /// aborting the node. Setting up the unwind guard costs a little on every call frame and state
+    /// modified before the panic is left as it was, so the result must be treated as a failed
+    /// transaction.
     pub fn run<H: Host, SPEC: Spec>(&mut self, host: &mut H) -> Return {
+        #[cfg(feature = "catch_unwind")]
+        {
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                self.run_inner::<H, SPEC>(host)
+            }))
+            .unwrap_or(Return::FatalExternalError)
+        }
+        #[cfg(not(feature = "catch_unwind"))]
+        self.run_inner::<H, SPEC>(host)
+    }
+
+    fn run_inner<H: Host, SPEC: Spec>(&mut self, host: &mut H) -> Return {
         //let timer = std::time::Instant::now();
-        let mut ret = Return::Continue;
-        // add first gas_block
-        if USE_GAS && !self.gas.record_cost(self.contract.first_gas_block()) {
+        loop {
+            let ret = self.step::<H, SPEC>(host);
+            if ret != Return::Continue {
+                return ret;
+            }
+        }
+    }
+
+    /// Execute exactly one opcode, `run` is a loop over it.
+    ///
+    /// Returns [Return::Continue] while execution can go on, the stack, memory and gas can be read
+    /// directly between steps. Meant for interactive debuggers that drive the interpreter opcode
+    /// by opcode. The first step also charges the first gas block. [Host::step] and
+    /// [Host::step_end] are called around the opcode if [Host::INSPECT] is set. After
+    /// [Interpreter::resume] the step finishes the suspended CALL or CREATE instead.
+    #[inline(always)]
+    pub fn step<H: Host, SPEC: Spec>(&mut self, host: &mut H) -> Return {
+        if !self.start(host) {
             return Return::OutOfGas;
         }
-        while ret == Return::Continue {
-            // step
+        if let Some(ret) = self.finish_sub_call(host) {
             if H::INSPECT {
-                let ret = host.step(self, SPEC::IS_STATIC_CALL);
-                if ret != Return::Continue {
-                    return ret;
+                let inspector_ret = host.step_end(self, SPEC::IS_STATIC_CALL, ret);
+                if inspector_ret != Return::Continue {
+                    return inspector_ret;
                 }
             }
-            let opcode = unsafe { *self.instruction_pointer };
-            // Safety: In analysis we are doing padding of bytecode so that we are sure that last.
-            // byte instruction is STOP so we are safe to just increment program_counter bcs on last instruction
-            // it will do noop and just stop execution of this contract
-            self.instruction_pointer = unsafe { self.instruction_pointer.offset(1) };
-            ret = eval::<H, SPEC>(opcode, self, host);
-
-            if H::INSPECT {
-                let ret = host.step_end(self, SPEC::IS_STATIC_CALL, ret);
-                if ret != Return::Continue {
+            return ret;
+        }
+        if self.checked_execution && !self.instruction_pointer_in_bounds() {
+            return Return::OutOfOffset;
+        }
+        if let Some(limit) = self.step_limit {
+            if self.steps >= limit {
+                return Return::StepLimitReached;
+            }
+        }
+        self.steps += 1;
+        if H::INSPECT {
+            let ret = host.step(self, SPEC::IS_STATIC_CALL);
+            if ret == Return::SkipOpcode {
+                return self.skip_opcode::<H, SPEC>(host);
+            }
+            if ret != Return::Continue {
+                return ret;
+            }
+        }
+        if let Some(fused) = &self.fused {
+            // Safety: the table covers the padded code, the pc stays inside of it.
+            let fused = unsafe { *fused.get_unchecked(self.program_counter()) };
+            if fused != fusion::NONE {
+                if let Some(ret) = eval_fused(fused, self, host) {
+                    // the second opcode of the pair.
+                    self.steps += 1;
                     return ret;
                 }
             }
         }
+        let opcode = unsafe { *self.instruction_pointer };
+        if self.use_gas()
+            && self.gas_block_granularity != GasBlockGranularity::WholeBlock
+            && !self.charge_static_gas::<SPEC>(opcode)
+        {
+            return Return::OutOfGas;
+        }
+        if self.ngram != 0 {
+            self.record_ngram(opcode);
+        }
+        if self.pc_coverage.is_some() {
+            self.record_pc();
+        }
+        // Safety: In analysis we are doing padding of bytecode so that we are sure that last.
+        // byte instruction is STOP so we are safe to just increment program_counter bcs on last instruction
+        // it will do noop and just stop execution of this contract
+        let opcode_pointer = self.instruction_pointer;
+        self.instruction_pointer = unsafe { self.instruction_pointer.offset(1) };
+        let ret = eval::<H, SPEC>(opcode, self, host);
+        // leave the pc at the opcode that failed on the stack, traces report it as the error pc.
+        if matches!(ret, Return::StackUnderflow | Return::StackOverflow) {
+            self.instruction_pointer = opcode_pointer;
+        }
+
+        // step_end of a suspended CALL or CREATE is called once it is resumed.
+        if H::INSPECT && !(H::ITERATIVE_CALLS && ret == Return::SubCall) {
+            let inspector_ret = host.step_end(self, SPEC::IS_STATIC_CALL, ret);
+            if inspector_ret != Return::Continue {
+                return inspector_ret;
+            }
+        }
         ret
     }
 
+    /// Move past the current opcode without executing it, see [Return::SkipOpcode]. Static gas
+    /// the opcode was charged ahead with is given back.
+    fn skip_opcode<H: Host, SPEC: Spec>(&mut self, host: &mut H) -> Return {
+        let opcode = self.current_opcode();
+        let pc = self.program_counter();
+        let info = &spec_opcode_gas(SPEC::SPEC_ID)[opcode as usize];
+        if self.use_gas() {
+            match self.gas_block_granularity {
+                GasBlockGranularity::PerOpcode => {}
+                GasBlockGranularity::WholeBlock => {
+                    self.gas.erase_cost(info.get_gas() as u64);
+                    // the opcode would have charged the block after it.
+                    if info.is_gas_block_end() {
+                        let ret = self.add_next_gas_block(host, pc);
+                        if ret != Return::Continue {
+                            return ret;
+                        }
+                    }
+                }
+                GasBlockGranularity::PerMemoryOp => {
+                    if !self.charge_static_gas::<SPEC>(opcode) {
+                        return Return::OutOfGas;
+                    }
+                    self.gas.erase_cost(info.get_gas() as u64);
+                }
+            }
+        }
+        let size = 1 + OpCode::new_unchecked(opcode).immediate_size() as isize;
+        // Safety: bytecode is padded past the immediate bytes of its last opcode.
+        self.instruction_pointer = unsafe { self.instruction_pointer.offset(size) };
+        if H::INSPECT {
+            let inspector_ret = host.step_end(self, SPEC::IS_STATIC_CALL, Return::Continue);
+            if inspector_ret != Return::Continue {
+                return inspector_ret;
+            }
+        }
+        Return::Continue
+    }
+
+    /// Whether the instruction pointer points into the bytecode, including its padding.
+    fn instruction_pointer_in_bounds(&self) -> bool {
+        let pc = (self.instruction_pointer as usize)
+            .wrapping_sub(self.contract.bytecode.as_ptr() as usize);
+        pc < self.contract.bytecode.bytecode().len()
+    }
+
+    /// Charge the first gas block once, before the first opcode. Returns false on out of gas.
+    fn start<H: Host>(&mut self, host: &mut H) -> bool {
+        if self.started {
+            return true;
+        }
+        self.started = true;
+        // a fused pair is one step, inspectors and per opcode gas have to see both opcodes.
+        if self.fuse_opcodes
+            && !H::INSPECT
+            && (!self.use_gas() || self.gas_block_granularity == GasBlockGranularity::WholeBlock)
+            && self.step_limit.is_none()
+            && self.ngram == 0
+            && self.pc_coverage.is_none()
+        {
+            self.fused = Some(Arc::new(fusion::fuse(
+                &self.contract,
+                &self.disabled_opcodes,
+            )));
+        }
+        if !self.use_gas() || self.gas_block_granularity != GasBlockGranularity::WholeBlock {
+            return true;
+        }
+        let gas_block = self.contract.first_gas_block();
+        if !self.gas.record_cost(gas_block) {
+            return false;
+        }
+        if H::INSPECT {
+            host.gas_block(0, gas_block, self.gas.remaining());
+        }
+        true
+    }
+
+    /// Charge static gas of `opcode` when gas is not charged for whole precomputed blocks.
+    fn charge_static_gas<SPEC: Spec>(&mut self, opcode: u8) -> bool {
+        let infos = spec_opcode_gas(SPEC::SPEC_ID);
+        let info = &infos[opcode as usize];
+        match self.gas_block_granularity {
+            GasBlockGranularity::WholeBlock => true,
+            GasBlockGranularity::PerOpcode => self.gas.record_cost(info.get_gas() as u64),
+            GasBlockGranularity::PerMemoryOp => {
+                if !self.segment_charged || touches_memory(opcode) {
+                    let gas = self.segment_gas(infos);
+                    if !self.gas.record_cost(gas) {
+                        return false;
+                    }
+                    self.prepaid_gas = gas;
+                }
+                self.prepaid_gas -= info.get_gas() as u64;
+                self.segment_charged = !info.is_gas_block_end() && !touches_memory(opcode);
+                true
+            }
+        }
+    }
+
+    /// Static gas from the current opcode to the end of its segment. A segment ends after a gas
+    /// block end and before and after every opcode that touches memory.
+    fn segment_gas(&self, infos: &[OpInfo; 256]) -> u64 {
+        let code = self.contract.bytecode.bytecode();
+        let mut index = self.program_counter();
+        let mut gas = 0;
+        while let Some(&opcode) = code.get(index) {
+            let info = &infos[opcode as usize];
+            gas += info.get_gas() as u64;
+            index += if info.is_push() {
+                ((opcode - opcode::PUSH1) + 2) as usize
+            } else {
+                1
+            };
+            // memory expansion gas is charged before static gas of the opcodes after it.
+            if info.is_gas_block_end() || touches_memory(opcode) {
+                break;
+            }
+            match code.get(index) {
+                Some(&next) if !touches_memory(next) => {}
+                _ => break,
+            }
+        }
+        gas
+    }
+
     /// Copy and get the return value of the interp, if any.
     pub fn return_value(&self) -> Bytes {
-        // if start is usize max it means that our return len is zero and we need to return empty
-        if self.return_range.start == usize::MAX {
+        let value = self.return_slice();
+        if value.is_empty() {
             Bytes::new()
         } else {
-            Bytes::copy_from_slice(self.memory.get_slice(
-                self.return_range.start,
-                self.return_range.end - self.return_range.start,
-            ))
+            Bytes::copy_from_slice(value)
+        }
+    }
+
+    /// Return value borrowed from memory, [Interpreter::return_value] without the copy.
+    pub fn return_slice(&self) -> &[u8] {
+        // if start is usize max it means that our return len is zero and we need to return empty
+        if self.return_range.start == usize::MAX {
+            return &[];
+        }
+        // a range outside of the memory returns empty as well.
+        self.return_range
+            .end
+            .checked_sub(self.return_range.start)
+            .and_then(|len| self.memory.try_get_slice(self.return_range.start, len))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![cfg_attr(feature = "no_gas_measuring", allow(unused_imports))]
+
+    use super::Interpreter;
+    use crate::{
+        opcode,
+        test_utils::{evm_with_code, interpreter},
+        Bytecode, Contract, Database, EVMData, GasBlockGranularity, GasInspector, Inspector,
+        LatestSpec, MockHost, Return, SpecId, TransactTo,
+    };
+    use bytes::Bytes;
+    use primitive_types::{H160, U256};
+    use std::sync::Arc;
+
+    /// Records pc and gas remaining before every step.
+    #[derive(Default)]
+    struct GasRecorder {
+        gas_inspector: GasInspector,
+        steps: Vec<(usize, u64)>,
+    }
+
+    impl<DB: Database> Inspector<DB> for GasRecorder {
+        fn initialize_interp(
+            &mut self,
+            interp: &mut Interpreter,
+            data: &mut EVMData<'_, DB>,
+            is_static: bool,
+        ) -> Return {
+            self.gas_inspector
+                .initialize_interp(interp, data, is_static);
+            Return::Continue
         }
+
+        fn step(
+            &mut self,
+            interp: &mut Interpreter,
+            data: &mut EVMData<'_, DB>,
+            is_static: bool,
+        ) -> Return {
+            self.steps
+                .push((interp.program_counter(), self.gas_inspector.gas_remaining()));
+            self.gas_inspector.step(interp, data, is_static)
+        }
+
+        fn step_end(
+            &mut self,
+            interp: &mut Interpreter,
+            data: &mut EVMData<'_, DB>,
+            is_static: bool,
+            eval: Return,
+        ) -> Return {
+            self.gas_inspector.step_end(interp, data, is_static, eval)
+        }
+    }
+
+    fn run(code: Vec<u8>, granularity: GasBlockGranularity, gas: u64) -> (Return, GasRecorder) {
+        let mut evm = evm_with_code(code);
+        evm.env.cfg.gas_block_granularity = granularity;
+        evm.env.tx.gas_limit = 21_000 + gas;
+        let mut recorder = GasRecorder::default();
+        let (result, _) = evm.inspect(&mut recorder);
+        (result.exit_reason, recorder)
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_oog_location_per_opcode() {
+        let code = vec![
+            opcode::PUSH1,
+            0x01,
+            opcode::PUSH1,
+            0x01,
+            opcode::ADD,
+            opcode::STOP,
+        ];
+        // whole block is charged upfront and nothing runs.
+        let (ret, recorder) = run(code.clone(), GasBlockGranularity::WholeBlock, 8);
+        assert_eq!(ret, Return::OutOfGas);
+        assert!(recorder.steps.is_empty());
+
+        let (ret, recorder) = run(code, GasBlockGranularity::PerOpcode, 8);
+        assert_eq!(ret, Return::OutOfGas);
+        assert_eq!(recorder.steps, vec![(0, 8), (2, 5), (4, 2)]);
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_oog_location_per_memory_op() {
+        let code = vec![
+            opcode::PUSH1,
+            0x01,
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE,
+            opcode::STOP,
+        ];
+        // MSTORE needs 3 static and 3 memory expansion gas.
+        let (ret, recorder) = run(code.clone(), GasBlockGranularity::WholeBlock, 8);
+        assert_eq!(ret, Return::OutOfGas);
+        assert!(recorder.steps.is_empty());
+
+        let (ret, recorder) = run(code.clone(), GasBlockGranularity::PerMemoryOp, 8);
+        assert_eq!(ret, Return::OutOfGas);
+        assert_eq!(recorder.steps, vec![(0, 8), (2, 5), (4, 2)]);
+
+        let (ret, _) = run(code, GasBlockGranularity::PerMemoryOp, 12);
+        assert_eq!(ret, Return::Stop);
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_oog_location_after_memory_expansion() {
+        let code = vec![
+            opcode::PUSH1,
+            0x01,
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x01,
+            opcode::POP,
+            opcode::STOP,
+        ];
+        // MSTORE and its expansion leave 1 gas, PUSH1 after it runs out like in geth.
+        let pcs = |recorder: GasRecorder| -> Vec<usize> {
+            recorder.steps.into_iter().map(|(pc, _)| pc).collect()
+        };
+        let (ret, recorder) = run(code.clone(), GasBlockGranularity::PerOpcode, 13);
+        assert_eq!(ret, Return::OutOfGas);
+        assert_eq!(pcs(recorder), vec![0, 2, 4, 5]);
+
+        let (ret, recorder) = run(code.clone(), GasBlockGranularity::PerMemoryOp, 13);
+        assert_eq!(ret, Return::OutOfGas);
+        assert_eq!(pcs(recorder), vec![0, 2, 4, 5]);
+
+        // static gas of the whole block is more than the gas limit.
+        let (ret, recorder) = run(code, GasBlockGranularity::WholeBlock, 13);
+        assert_eq!(ret, Return::OutOfGas);
+        assert!(recorder.steps.is_empty());
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_granularity_gas_is_equal() {
+        let code = vec![
+            opcode::PUSH1,
+            0x03,
+            opcode::JUMPDEST,
+            opcode::PUSH1,
+            0x01,
+            opcode::SWAP1,
+            opcode::SUB,
+            opcode::DUP1,
+            opcode::PUSH1,
+            0x02,
+            opcode::JUMPI,
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE,
+            opcode::STOP,
+        ];
+        let (ret, whole) = run(code.clone(), GasBlockGranularity::WholeBlock, 1000);
+        assert_eq!(ret, Return::Stop);
+        for granularity in [
+            GasBlockGranularity::PerOpcode,
+            GasBlockGranularity::PerMemoryOp,
+        ] {
+            let (ret, recorder) = run(code.clone(), granularity, 1000);
+            assert_eq!(ret, Return::Stop);
+            assert_eq!(recorder.steps, whole.steps, "{:?}", granularity);
+        }
+    }
+
+    #[test]
+    fn test_debug_dump() {
+        let mut interp = interpreter(vec![opcode::ADD, opcode::STOP], 100);
+        interp.stack.push(U256::from(0x1234)).unwrap();
+        interp.stack.push(U256::from(7)).unwrap();
+
+        let dump = interp.debug_dump();
+        assert!(dump.contains("pc: 0\n"));
+        assert!(dump.contains("opcode: ADD (0x01)"));
+        assert!(dump.contains("gas remaining: 100"));
+        assert!(dump.contains("stack (2 items):\n  1: 0x7\n  0: 0x1234\n"));
+        assert!(dump.contains("memory size: 0"));
+    }
+
+    #[test]
+    fn test_return_value_range() {
+        let contract = Contract::new::<LatestSpec>(
+            Bytes::new(),
+            Bytecode::new(),
+            H160::zero(),
+            H160::zero(),
+            U256::zero(),
+        );
+        let mut interp = Interpreter::new::<LatestSpec>(contract, 100);
+        interp.memory.resize(32);
+        interp.memory.set(0, &[1, 2, 3]);
+
+        interp.return_range = 1..3;
+        assert_eq!(interp.return_value(), Bytes::from(vec![2, 3]));
+        assert_eq!(interp.return_slice(), &interp.return_value()[..]);
+        let memory = interp.memory.data().as_ptr();
+        assert_eq!(interp.return_slice().as_ptr(), memory.wrapping_add(1));
+        // zero length return.
+        interp.return_range = usize::MAX..usize::MAX;
+        assert_eq!(interp.return_value(), Bytes::new());
+        assert_eq!(interp.return_slice(), &[] as &[u8]);
+        // out of the memory or malformed.
+        #[allow(clippy::reversed_empty_ranges)]
+        let ranges = [16..48, 64..96, 3..1];
+        for range in ranges {
+            interp.return_range = range;
+            assert_eq!(interp.return_value(), Bytes::new());
+            assert_eq!(interp.return_slice(), &[] as &[u8]);
+        }
+    }
+
+    #[test]
+    fn test_stack_errors() {
+        let run = |code: Vec<u8>| {
+            let mut interp = interpreter(code, 1_000_000);
+            let mut host = MockHost::new();
+            let ret = interp.run::<_, LatestSpec>(&mut host);
+            (ret, interp.program_counter(), interp.stack.len())
+        };
+        let underflows = [
+            vec![opcode::ADD],
+            vec![opcode::PUSH1, 0x01, opcode::ADD],
+            vec![opcode::PUSH1, 0x01, opcode::SWAP1],
+            vec![opcode::PUSH1, 0x01, opcode::DUP2],
+            vec![opcode::PUSH1, 0x01, opcode::SLOAD, opcode::POP, opcode::POP],
+            vec![
+                opcode::PUSH1,
+                0x00,
+                opcode::DUP1,
+                opcode::DUP1,
+                opcode::LOG2,
+            ],
+            vec![opcode::PUSH1, 0x00, opcode::DUP1, opcode::DELEGATECALL],
+        ];
+        for code in underflows {
+            let pc = code.len() - 1;
+            let (ret, error_pc, _) = run(code);
+            assert_eq!((ret, error_pc), (Return::StackUnderflow, pc));
+        }
+
+        // 1024 items fill the stack, one more overflows.
+        let mut full = Vec::new();
+        for _ in 0..1024 {
+            full.extend([opcode::PUSH1, 0x01]);
+        }
+        for last in [
+            vec![opcode::PUSH1, 0x01],
+            vec![opcode::DUP1],
+            vec![opcode::PC],
+            vec![opcode::PUSH32; 33],
+        ] {
+            let mut code = full.clone();
+            code.extend(last);
+            assert_eq!(run(code), (Return::StackOverflow, 2048, 1024));
+        }
+        // full but not past it.
+        full.push(opcode::SWAP16);
+        assert_eq!(run(full).0, Return::Stop);
+    }
+
+    #[test]
+    fn test_checked_execution() {
+        let new_interp = |code: Vec<u8>| {
+            let mut interp = interpreter(code, 1_000_000);
+            interp.checked_execution = true;
+            interp
+        };
+        let mut host = MockHost::new();
+
+        // jump to the last byte, then run into the padding.
+        let mut interp = new_interp(vec![opcode::PUSH1, 0x03, opcode::JUMP, opcode::JUMPDEST]);
+        assert_eq!(interp.run::<_, LatestSpec>(&mut host), Return::Stop);
+        assert_eq!(interp.program_counter(), 5);
+
+        // a jump past the end is rejected by the jump table, a corrupted pointer by the check.
+        let mut interp = new_interp(vec![opcode::PUSH1, 0x10, opcode::JUMP]);
+        assert_eq!(interp.run::<_, LatestSpec>(&mut host), Return::InvalidJump);
+        let mut interp = new_interp(vec![opcode::PUSH1, 0x00, opcode::POP]);
+        let len = interp.contract.bytecode.bytecode().len();
+        for pc in [len, len + 32] {
+            interp.instruction_pointer = interp.contract.bytecode.as_ptr().wrapping_add(pc);
+            assert_eq!(interp.step::<_, LatestSpec>(&mut host), Return::OutOfOffset);
+        }
+        interp.instruction_pointer = interp.contract.bytecode.as_ptr().wrapping_sub(1);
+        assert_eq!(interp.step::<_, LatestSpec>(&mut host), Return::OutOfOffset);
+    }
+
+    #[test]
+    fn test_memory_capacity() {
+        // mstore(0x10000, 1)
+        let code = vec![
+            opcode::PUSH1,
+            0x01,
+            opcode::PUSH3,
+            0x01,
+            0x00,
+            0x00,
+            opcode::MSTORE,
+        ];
+        let run = |memory_capacity: Option<usize>| {
+            let contract = Contract::new_from_code::<LatestSpec>(
+                code.clone().into(),
+                Bytes::new(),
+                H160::zero(),
+                H160::zero(),
+                U256::zero(),
+            );
+            let mut interp = match memory_capacity {
+                Some(capacity) => Interpreter::new_with_memory_capacity::<LatestSpec>(
+                    contract, 1_000_000, capacity,
+                ),
+                None => Interpreter::new::<LatestSpec>(contract, 1_000_000),
+            };
+            assert!(interp.memory.is_empty());
+            let mut host = MockHost::new();
+            assert_eq!(interp.run::<_, LatestSpec>(&mut host), Return::Stop);
+            interp
+        };
+
+        let grown = run(None);
+        let preallocated = run(Some(0x20000));
+        assert_eq!(preallocated.memory.len(), 0x10020);
+        assert_eq!(preallocated.memory.len(), grown.memory.len());
+        assert_eq!(preallocated.gas.spend(), grown.gas.spend());
+        // the buffer was not reallocated.
+        assert_eq!(preallocated.memory.capacity(), 0x20000);
+    }
+
+    #[test]
+    fn test_step_limit() {
+        let new_interp = |code: Vec<u8>| {
+            let mut interp = interpreter(code, 1_000_000);
+            interp.step_limit = Some(10);
+            interp
+        };
+        let mut host = MockHost::new();
+
+        let code = vec![opcode::PUSH1, 0x01, opcode::PUSH1, 0x02, opcode::ADD];
+        let mut interp = new_interp(code);
+        assert_eq!(interp.run::<_, LatestSpec>(&mut host), Return::Stop);
+        assert_eq!(interp.steps(), 4);
+
+        // JUMPDEST, PUSH1 0, JUMP forever.
+        let code = vec![opcode::JUMPDEST, opcode::PUSH1, 0x00, opcode::JUMP];
+        let mut interp = new_interp(code);
+        assert_eq!(
+            interp.run::<_, LatestSpec>(&mut host),
+            Return::StepLimitReached
+        );
+        assert_eq!(interp.steps(), 10);
+        // the tenth opcode is the JUMPDEST of the fourth iteration.
+        assert_eq!(interp.program_counter(), 1);
+    }
+
+    #[test]
+    fn test_clone_mid_execution() {
+        let code = vec![
+            opcode::PUSH1,
+            0x02,
+            opcode::PUSH1,
+            0x03,
+            opcode::ADD,
+            opcode::DUP1,
+            opcode::MUL,
+            opcode::STOP,
+        ];
+        let mut interp = interpreter(code, 100);
+        let mut host = MockHost::new();
+        let stack = |values: &[u64]| -> Vec<U256> { values.iter().map(|v| (*v).into()).collect() };
+
+        for _ in 0..2 {
+            assert_eq!(interp.step::<_, LatestSpec>(&mut host), Return::Continue);
+        }
+        let mut cloned = interp.clone();
+        assert!(Arc::ptr_eq(
+            &interp.contract.bytecode,
+            &cloned.contract.bytecode
+        ));
+        assert_eq!(cloned.instruction_pointer, interp.instruction_pointer);
+        assert_eq!(cloned.program_counter(), 4);
+
+        // 2 + 3 = 5, 5 * 5 = 25 on the original.
+        while interp.step::<_, LatestSpec>(&mut host) == Return::Continue {}
+        assert_eq!(interp.stack.data(), &stack(&[25]));
+        // the clone is still before ADD.
+        assert_eq!(cloned.program_counter(), 4);
+        assert_eq!(cloned.stack.data(), &stack(&[2, 3]));
+
+        // 3 + 4 = 7, 7 * 7 = 49 on the clone.
+        cloned.stack.push(4.into()).unwrap();
+        assert_eq!(cloned.run::<_, LatestSpec>(&mut host), Return::Stop);
+        assert_eq!(cloned.stack.data(), &stack(&[2, 49]));
+        assert_eq!(cloned.gas.remaining(), interp.gas.remaining());
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_step() {
+        let code = vec![
+            opcode::PUSH1,
+            0x02,
+            opcode::PUSH1,
+            0x03,
+            opcode::ADD,
+            opcode::DUP1,
+            opcode::MUL,
+            opcode::STOP,
+        ];
+        let new_interp = |granularity| {
+            let mut interp = interpreter(code.clone(), 100);
+            interp.gas_block_granularity = granularity;
+            interp
+        };
+        let mut host = MockHost::new();
+
+        let stacks: [&[u64]; 5] = [&[2], &[2, 3], &[5], &[5, 5], &[25]];
+        let mut interp = new_interp(GasBlockGranularity::WholeBlock);
+        for stack in stacks {
+            assert_eq!(interp.step::<_, LatestSpec>(&mut host), Return::Continue);
+            let expected: Vec<U256> = stack.iter().map(|value| U256::from(*value)).collect();
+            assert_eq!(interp.stack.data(), &expected);
+            // the whole block is charged on the first step.
+            assert_eq!(interp.gas.remaining(), 100 - 17);
+        }
+        assert_eq!(interp.step::<_, LatestSpec>(&mut host), Return::Stop);
+
+        // charged opcode by opcode every step pays for its own opcode only.
+        let mut interp = new_interp(GasBlockGranularity::PerOpcode);
+        for (stack, spent) in stacks.iter().zip([3, 6, 9, 12, 17]) {
+            assert_eq!(interp.step::<_, LatestSpec>(&mut host), Return::Continue);
+            assert_eq!(
+                interp.stack.data().last(),
+                stack.last().map(|v| U256::from(*v)).as_ref()
+            );
+            assert_eq!(interp.gas.remaining(), 100 - spent);
+        }
+        assert_eq!(interp.step::<_, LatestSpec>(&mut host), Return::Stop);
+
+        // run is the same loop of steps.
+        let mut run = new_interp(GasBlockGranularity::PerOpcode);
+        assert_eq!(run.run::<_, LatestSpec>(&mut host), Return::Stop);
+        assert_eq!(run.stack.data(), interp.stack.data());
+        assert_eq!(run.gas.remaining(), interp.gas.remaining());
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_current_block_gas_remaining() {
+        let code = vec![
+            opcode::PUSH1,
+            0x02,
+            opcode::PUSH1,
+            0x03,
+            opcode::ADD,
+            opcode::PUSH1,
+            0x08,
+            opcode::JUMP, // 7: jumps to 8
+            opcode::JUMPDEST,
+            opcode::DUP1,
+            opcode::MUL,
+            opcode::STOP,
+        ];
+        let mut host = MockHost::new();
+        // gas of every opcode from the gas remaining before it and before the next one.
+        let mut opcode_gas = |granularity| {
+            let mut interp = interpreter(code.clone(), 100);
+            interp.gas_block_granularity = granularity;
+            let remaining = |interp: &Interpreter| {
+                interp.gas.remaining() + interp.current_block_gas_remaining(SpecId::LATEST)
+            };
+            let mut gas = Vec::new();
+            loop {
+                let before = remaining(&interp);
+                let ret = interp.step::<_, LatestSpec>(&mut host);
+                gas.push(before - remaining(&interp));
+                if ret != Return::Continue {
+                    return (gas, interp.contract.first_gas_block());
+                }
+            }
+        };
+
+        let (whole, first_block) = opcode_gas(GasBlockGranularity::WholeBlock);
+        assert_eq!(whole, vec![3, 3, 3, 3, 8, 1, 3, 5, 0]);
+        assert_eq!(whole[..5].iter().sum::<u64>(), first_block);
+        for granularity in [
+            GasBlockGranularity::PerOpcode,
+            GasBlockGranularity::PerMemoryOp,
+        ] {
+            assert_eq!(opcode_gas(granularity).0, whole, "{granularity:?}");
+        }
+    }
+
+    #[test]
+    fn test_ngram_counts() {
+        use crate::OpCode;
+
+        // three iterations of a loop with two PUSH1 PUSH1 pairs in its body.
+        let code = vec![
+            opcode::PUSH1,
+            0x03,
+            opcode::JUMPDEST,
+            opcode::PUSH1,
+            0x01,
+            opcode::PUSH1,
+            0x01,
+            opcode::PUSH1,
+            0x01,
+            opcode::ADD,
+            opcode::ADD,
+            opcode::POP,
+            opcode::PUSH1,
+            0x01,
+            opcode::SWAP1,
+            opcode::SUB,
+            opcode::DUP1,
+            opcode::PUSH1,
+            0x02,
+            opcode::JUMPI,
+            opcode::STOP,
+        ];
+        let mut interp = interpreter(code, 1000).with_ngram(2);
+        let mut host = MockHost::new();
+
+        assert_eq!(interp.run::<_, LatestSpec>(&mut host), Return::Stop);
+        let counts = interp.dump();
+        let push1 = OpCode::try_from_u8(opcode::PUSH1).unwrap();
+        assert_eq!(counts[0], (vec![push1, push1], 6));
+        assert!(counts[1].1 <= 3);
+        // PUSH1, 3 iterations of 13 opcodes and STOP are 41 executed opcodes. n executed opcodes
+        // give n - 1 2-grams.
+        assert_eq!(counts.iter().map(|(_, count)| count).sum::<u64>(), 40);
+    }
+
+    #[test]
+    fn test_pc_coverage() {
+        // JUMPI over `PUSH1 1 STOP` if the input is not zero.
+        let interp = |input: u8| {
+            let code = vec![
+                opcode::PUSH1,
+                input,
+                opcode::PUSH1,
+                0x08,
+                opcode::JUMPI,
+                opcode::PUSH1,
+                0x01,
+                opcode::STOP,
+                opcode::JUMPDEST,
+                opcode::STOP,
+            ];
+            interpreter(code, 1000)
+        };
+        let mut host = MockHost::new();
+
+        let mut untaken = interp(0).with_pc_coverage();
+        assert_eq!(untaken.run::<_, LatestSpec>(&mut host), Return::Stop);
+        assert_eq!(untaken.executed_pcs(), vec![0, 2, 4, 5, 7]);
+        let opcodes = untaken.executed_opcodes();
+        assert!(opcodes.contains(opcode::PUSH1) && opcodes.contains(opcode::JUMPI));
+        assert!(!opcodes.contains(opcode::JUMPDEST));
+
+        let mut taken = interp(1).with_pc_coverage();
+        assert_eq!(taken.run::<_, LatestSpec>(&mut host), Return::Stop);
+        assert_eq!(taken.executed_pcs(), vec![0, 2, 4, 8, 9]);
+        assert!(taken.executed_opcodes().contains(opcode::JUMPDEST));
+
+        let mut off = interp(1);
+        assert_eq!(off.run::<_, LatestSpec>(&mut host), Return::Stop);
+        assert!(off.executed_pcs().is_empty());
+        assert!(off.executed_opcodes().is_empty());
+    }
+
+    #[test]
+    fn test_reset_profile() {
+        let code = vec![
+            opcode::PUSH1,
+            0x02,
+            opcode::PUSH1,
+            0x03,
+            opcode::ADD,
+            opcode::DUP1,
+            opcode::MUL,
+            opcode::STOP,
+        ];
+        let mut interp = interpreter(code, 100).with_ngram(2);
+        let mut host = MockHost::new();
+
+        for _ in 0..3 {
+            interp.step::<_, LatestSpec>(&mut host);
+        }
+        let histogram = interp.opcode_histogram();
+        assert_eq!(histogram.len(), 2);
+        assert_eq!(histogram[&vec![opcode::PUSH1, opcode::PUSH1]], 1);
+        assert_eq!(histogram[&vec![opcode::PUSH1, opcode::ADD]], 1);
+
+        interp.reset_profile();
+        assert!(interp.opcode_histogram().is_empty());
+        assert_eq!(interp.run::<_, LatestSpec>(&mut host), Return::Stop);
+        // ADD before the reset still starts the first n-gram after it.
+        let histogram = interp.opcode_histogram();
+        assert_eq!(histogram.len(), 3);
+        for ngram in [
+            [opcode::ADD, opcode::DUP1],
+            [opcode::DUP1, opcode::MUL],
+            [opcode::MUL, opcode::STOP],
+        ] {
+            assert_eq!(histogram[&ngram.to_vec()], 1);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "catch_unwind")]
+    fn test_catch_unwind() {
+        use crate::{BasicHost, Env, InMemoryDB};
+        use primitive_types::H256;
+
+        /// Host with a bug in its LOG handling.
+        struct PanickingHost {
+            db: InMemoryDB,
+            env: Env,
+        }
+
+        impl BasicHost for PanickingHost {
+            type DB = InMemoryDB;
+
+            fn db(&mut self) -> &mut InMemoryDB {
+                &mut self.db
+            }
+
+            fn env(&mut self) -> &mut Env {
+                &mut self.env
+            }
+
+            fn override_log(&mut self, _address: H160, _topics: Vec<H256>, _data: Bytes) {
+                panic!("bug in log handler");
+            }
+        }
+
+        let code = vec![
+            opcode::PUSH1,
+            0x00,
+            opcode::DUP1,
+            opcode::LOG0,
+            opcode::STOP,
+        ];
+        let mut interp = interpreter(code, 100_000);
+        let mut host = PanickingHost {
+            db: InMemoryDB::default(),
+            env: Env::default(),
+        };
+        assert_eq!(
+            interp.run::<_, LatestSpec>(&mut host),
+            Return::FatalExternalError
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "out of bytecode bounds")]
+    fn test_program_counter_out_of_bounds() {
+        let mut interp = interpreter(vec![opcode::STOP], 100);
+        let len = interp.contract.bytecode.bytecode().len();
+        interp.instruction_pointer = interp.instruction_pointer.wrapping_add(len + 1);
+        interp.program_counter();
+    }
+
+    /// Records `is_create` of the frame and the value pushed by every CODESIZE.
+    #[derive(Default)]
+    struct CodeSizeRecorder {
+        in_codesize: bool,
+        sizes: Vec<(bool, U256)>,
+    }
+
+    impl<DB: Database> Inspector<DB> for CodeSizeRecorder {
+        fn step(
+            &mut self,
+            interp: &mut Interpreter,
+            _data: &mut EVMData<'_, DB>,
+            _is_static: bool,
+        ) -> Return {
+            self.in_codesize = interp.current_opcode() == opcode::CODESIZE;
+            Return::Continue
+        }
+
+        fn step_end(
+            &mut self,
+            interp: &mut Interpreter,
+            _data: &mut EVMData<'_, DB>,
+            _is_static: bool,
+            _eval: Return,
+        ) -> Return {
+            if self.in_codesize {
+                self.sizes
+                    .push((interp.is_create(), interp.stack.peek(0).unwrap()));
+            }
+            Return::Continue
+        }
+    }
+
+    #[test]
+    fn test_is_create() {
+        let init_code = vec![opcode::CODESIZE, opcode::POP, opcode::STOP];
+        let mut evm = evm_with_code(vec![opcode::CODESIZE, opcode::STOP]);
+        evm.env.tx.transact_to = TransactTo::create();
+        evm.env.tx.data = Bytes::from(init_code);
+
+        let mut recorder = CodeSizeRecorder::default();
+        evm.inspect(&mut recorder);
+        // init code length, not the length of code at the new address.
+        assert_eq!(recorder.sizes, vec![(true, U256::from(3))]);
+
+        evm.env.tx.transact_to = TransactTo::Call(H160::zero());
+        let mut recorder = CodeSizeRecorder::default();
+        evm.inspect(&mut recorder);
+        assert_eq!(recorder.sizes, vec![(false, U256::from(2))]);
     }
 }
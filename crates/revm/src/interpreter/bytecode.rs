@@ -1,10 +1,16 @@
-use super::contract::{AnalysisData, ValidJumpAddress};
-use crate::{opcode, spec_opcode_gas, Spec, KECCAK_EMPTY};
+use super::{
+    contract::{AnalysisData, ValidJumpAddress},
+    eof::{validate_eof, EofError, EOF_MAGIC},
+};
+use crate::{opcode, spec_opcode_gas, InstructionIter, Spec, SpecId, KECCAK_EMPTY};
 use bytes::Bytes;
-use primitive_types::H256;
+use primitive_types::{H160, H256};
 use sha3::{Digest, Keccak256};
 use std::sync::Arc;
 
+/// Prefix of EIP-7702 delegation designators, see [Bytecode::delegation].
+const DELEGATION_PREFIX: [u8; 3] = [0xef, 0x01, 0x00];
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BytecodeState {
@@ -27,6 +33,31 @@ pub struct Bytecode {
     state: BytecodeState,
 }
 
+/// Reason code can not be deployed, see [validate_bytecode].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BytecodeError {
+    /// EIP-3541: new code starting with the 0xEF byte is rejected from London on.
+    StartsWithEF,
+    /// Code starts with [EOF_MAGIC] but is not a valid EOF container.
+    Eof(EofError),
+}
+
+/// Check if `code` can be deployed under `spec` without building a [Bytecode].
+///
+/// This is the check done on the output of init code. 0xEF prefixed code is invalid from London
+/// on. Only [SpecId::EXPERIMENTAL_EOF], which no other spec includes, accepts EOF containers that
+/// pass [validate_eof]. Executing EOF code is not supported, only its code section is analysed
+/// for jump destinations.
+pub fn validate_bytecode(code: &[u8], spec: SpecId) -> Result<(), BytecodeError> {
+    if SpecId::enabled(spec, SpecId::EXPERIMENTAL_EOF) && code.starts_with(&EOF_MAGIC) {
+        return validate_eof(code).map(|_| ()).map_err(BytecodeError::Eof);
+    }
+    if SpecId::enabled(spec, SpecId::LONDON) && code.first() == Some(&0xEF) {
+        return Err(BytecodeError::StartsWithEF);
+    }
+    Ok(())
+}
+
 impl Default for Bytecode {
     fn default() -> Self {
         Bytecode::new()
@@ -141,6 +172,26 @@ impl Bytecode {
         }
     }
 
+    /// Instructions of the code without the padding of checked and analysed code, see
+    /// [crate::disassemble].
+    pub fn instructions(&self) -> InstructionIter<'_> {
+        InstructionIter::new(&self.bytecode[..self.len()])
+    }
+
+    /// EIP-7702 delegation designator, `0xef0100 || address`.
+    pub fn new_delegation(address: H160) -> Self {
+        let mut code = DELEGATION_PREFIX.to_vec();
+        code.extend_from_slice(address.as_bytes());
+        Self::new_raw(code.into())
+    }
+
+    /// Account the code delegates to if it is an EIP-7702 delegation designator.
+    pub fn delegation(&self) -> Option<H160> {
+        let code = &self.bytecode[..self.len()];
+        (code.len() == 23 && code.starts_with(&DELEGATION_PREFIX))
+            .then(|| H160::from_slice(&code[3..]))
+    }
+
     pub fn to_checked(self) -> Self {
         match self.state {
             BytecodeState::Raw => {
@@ -168,7 +219,7 @@ impl Bytecode {
             BytecodeState::Checked { len } => (self.bytecode, len),
             _ => return self,
         };
-        let jumptable = Self::analyze::<SPEC>(bytecode.as_ref());
+        let jumptable = Self::analyze::<SPEC>(bytecode.as_ref(), len);
 
         Self {
             bytecode,
@@ -195,8 +246,22 @@ impl Bytecode {
         }
     }
 
-    /// Analyze bytecode to get jumptable and gas blocks.
-    fn analyze<SPEC: Spec>(code: &[u8]) -> ValidJumpAddress {
+    /// Analyze bytecode to get jumptable and gas blocks. `code` is padded after its first `len`
+    /// bytes. Under [SpecId::EXPERIMENTAL_EOF] only the code section of EOF containers is
+    /// analysed, so bytes of the header and data section are never jump destinations.
+    fn analyze<SPEC: Spec>(code: &[u8], len: usize) -> ValidJumpAddress {
+        if SPEC::enabled(SpecId::EXPERIMENTAL_EOF) {
+            if let Ok(header) = validate_eof(&code[..len]) {
+                let section = Self::analyze_legacy::<SPEC>(&code[header.code_range()]);
+                let mut analysis = vec![AnalysisData::none(); code.len()];
+                analysis[header.code_range()].clone_from_slice(&section.analysis);
+                return ValidJumpAddress::new(Arc::new(analysis), 0);
+            }
+        }
+        Self::analyze_legacy::<SPEC>(code)
+    }
+
+    fn analyze_legacy<SPEC: Spec>(code: &[u8]) -> ValidJumpAddress {
         let opcode_gas = spec_opcode_gas(SPEC::SPEC_ID);
 
         let mut analysis = ValidJumpAddress {
@@ -309,3 +374,119 @@ impl BytecodeLocked {
         &self.jumptable
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_bytecode, Bytecode, BytecodeError};
+    use crate::{
+        opcode, test_utils::evm_with_code, EofError, ExperimentalEofSpec, LatestSpec, Return,
+        SpecId,
+    };
+    use bytes::Bytes;
+
+    #[test]
+    fn test_validate_ef_prefix() {
+        let code = [0xEF, 0x00, 0x01];
+        assert_eq!(
+            validate_bytecode(&code, SpecId::LONDON),
+            Err(BytecodeError::StartsWithEF)
+        );
+        assert_eq!(validate_bytecode(&code, SpecId::BERLIN), Ok(()));
+        // only the first byte matters.
+        assert_eq!(validate_bytecode(&[0x00, 0xEF], SpecId::LATEST), Ok(()));
+        assert_eq!(validate_bytecode(&[], SpecId::LATEST), Ok(()));
+    }
+
+    #[test]
+    fn test_eof() {
+        // JUMPDEST in the code section and in the data section.
+        let container = [
+            0xEF,
+            0x00,
+            0x01,
+            0x01,
+            0x00,
+            0x02,
+            0x02,
+            0x00,
+            0x01,
+            0x00,
+            opcode::JUMPDEST,
+            opcode::STOP,
+            opcode::JUMPDEST,
+        ];
+        assert_eq!(
+            validate_bytecode(&container, SpecId::EXPERIMENTAL_EOF),
+            Ok(())
+        );
+        // EOF is not part of any fork, LATEST keeps rejecting it as EIP-3541 does.
+        assert_eq!(
+            validate_bytecode(&container, SpecId::LATEST),
+            Err(BytecodeError::StartsWithEF)
+        );
+        assert_eq!(
+            validate_bytecode(&container[..12], SpecId::EXPERIMENTAL_EOF),
+            Err(BytecodeError::Eof(EofError::SizeMismatch {
+                expected: 13,
+                actual: 12
+            }))
+        );
+
+        let bytecode = || Bytecode::new_raw(Bytes::copy_from_slice(&container));
+        let locked = bytecode().lock::<ExperimentalEofSpec>();
+        assert!(locked.jumptable().is_valid(10));
+        assert!(!locked.jumptable().is_valid(12));
+        // legacy analysis in every other spec.
+        let locked = bytecode().lock::<LatestSpec>();
+        assert!(locked.jumptable().is_valid(12));
+    }
+
+    #[test]
+    fn test_analyse_short_bytecode() {
+        for code in [
+            vec![],
+            vec![opcode::STOP],
+            vec![opcode::PUSH1, 0x01, opcode::POP],
+            // push data runs past the end of the code.
+            vec![opcode::PUSH2, 0x01],
+        ] {
+            let len = code.len();
+            let locked = Bytecode::new_raw(Bytes::from(code.clone())).lock::<LatestSpec>();
+            assert_eq!(locked.len(), len);
+            assert_eq!(locked.original_bytecode_slice(), &code[..]);
+            // padded with STOP so `run` never reads past the end.
+            assert_eq!(locked.bytecode().len(), len + 33);
+            assert!(locked.bytecode()[len..].iter().all(|b| *b == opcode::STOP));
+
+            let mut evm = evm_with_code(code.clone());
+            let (result, _) = evm.transact();
+            assert_eq!(result.exit_reason, Return::Stop, "{:?}", code);
+        }
+    }
+
+    #[test]
+    fn test_instructions() {
+        // push data runs past the end of the code.
+        let code = vec![opcode::PUSH1, 0x01, opcode::DUP1, opcode::PUSH2, 0x02];
+        let raw = Bytecode::new_raw(Bytes::from(code.clone()));
+        for bytecode in [
+            raw.clone(),
+            raw.clone().to_checked(),
+            raw.to_analysed::<LatestSpec>(),
+        ] {
+            // the STOP padding is not decoded.
+            let listing: Vec<_> = bytecode
+                .instructions()
+                .map(|(pc, op, data)| (pc, op.u8(), data.map(|d| d.to_vec())))
+                .collect();
+            assert_eq!(
+                listing,
+                vec![
+                    (0, opcode::PUSH1, Some(vec![0x01])),
+                    (2, opcode::DUP1, None),
+                    (3, opcode::PUSH2, Some(vec![0x02])),
+                ]
+            );
+        }
+    }
+}
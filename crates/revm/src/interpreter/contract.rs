@@ -1,15 +1,17 @@
-use super::bytecode::{Bytecode, BytecodeLocked};
-use crate::{alloc::vec::Vec, CallContext, Spec};
+use super::bytecode::{Bytecode, BytecodeLocked, BytecodeState};
+use crate::{alloc::vec::Vec, CallContext, OpCode, Spec};
 use bytes::Bytes;
 use primitive_types::{H160, U256};
 use std::sync::Arc;
 
+#[derive(Clone)]
 pub struct Contract {
     /// Contracts data
     pub input: Bytes,
     /// Bytecode contains contract code, size of original code, analysis with gas block and jump table.
     /// Note that current code is extended with push padding and STOP at end.
-    pub bytecode: BytecodeLocked,
+    /// Shared so that cloning a contract, and an [crate::Interpreter] with it, does not copy it.
+    pub bytecode: Arc<BytecodeLocked>,
     /// Contract address
     pub address: H160,
     /// Caller of the EVM.
@@ -68,7 +70,7 @@ impl Contract {
         caller: H160,
         value: U256,
     ) -> Self {
-        let bytecode = bytecode.lock::<SPEC>();
+        let bytecode = Arc::new(bytecode.lock::<SPEC>());
         Self {
             input,
             bytecode,
@@ -78,6 +80,36 @@ impl Contract {
         }
     }
 
+    /// [Contract::new] from raw code, analysed for `SPEC`.
+    pub fn new_from_code<SPEC: Spec>(
+        code: Bytes,
+        input: Bytes,
+        caller: H160,
+        address: H160,
+        value: U256,
+    ) -> Self {
+        Self::new::<SPEC>(input, Bytecode::new_raw(code), address, caller, value)
+    }
+
+    /// [Contract::new] for code that was analysed before, for example by a code cache. The
+    /// analysis is shared instead of redone, [crate::Interpreter::run] never analyses either.
+    ///
+    /// # Panics
+    /// If `bytecode` is not [BytecodeState::Analysed].
+    pub fn new_analysed<SPEC: Spec>(
+        input: Bytes,
+        bytecode: Bytecode,
+        address: H160,
+        caller: H160,
+        value: U256,
+    ) -> Self {
+        assert!(
+            matches!(bytecode.state(), BytecodeState::Analysed { .. }),
+            "bytecode is not analysed"
+        );
+        Self::new::<SPEC>(input, bytecode, address, caller, value)
+    }
+
     pub fn is_valid_jump(&self, possition: usize) -> bool {
         self.bytecode.jumptable().is_valid(possition)
     }
@@ -89,6 +121,21 @@ impl Contract {
         self.bytecode.jumptable().first_gas_block as u64
     }
 
+    /// Immediate of the PUSH opcode at `pc` as a zero extended U256, None for other opcodes.
+    ///
+    /// Truncated PUSH data at the end of code reads the missing bytes as zero, same as execution.
+    pub fn push_value(&self, pc: usize) -> Option<U256> {
+        if pc >= self.bytecode.len() {
+            return None;
+        }
+        let code = self.bytecode.bytecode();
+        let size = OpCode::new_unchecked(code[pc]).immediate_size();
+        if size == 0 {
+            return None;
+        }
+        Some(U256::from_big_endian(&code[pc + 1..pc + 1 + size]))
+    }
+
     pub fn new_with_context<SPEC: Spec>(
         input: Bytes,
         bytecode: Bytecode,
@@ -150,7 +197,11 @@ impl ValidJumpAddress {
 
 #[cfg(test)]
 mod tests {
-    use super::AnalysisData;
+    use super::{AnalysisData, Contract};
+    use crate::{opcode, Bytecode, BytecodeState, Interpreter, LatestSpec, MockHost, Return};
+    use bytes::Bytes;
+    use primitive_types::{H160, U256};
+    use std::sync::Arc;
 
     #[test]
     pub fn test_jump_set() {
@@ -174,4 +225,186 @@ mod tests {
         assert!(jump.is_jump());
         assert_eq!(jump.gas_block(), 350);
     }
+
+    #[test]
+    pub fn test_push_value() {
+        let address = H160::repeat_byte(0xab);
+        let mut code = vec![opcode::PUSH20];
+        code.extend_from_slice(address.as_bytes());
+        code.extend_from_slice(&[opcode::BALANCE, opcode::PUSH2, 0x01]);
+        let contract = Contract::new::<LatestSpec>(
+            Bytes::new(),
+            Bytecode::new_raw(code.into()),
+            H160::zero(),
+            H160::zero(),
+            U256::zero(),
+        );
+
+        assert_eq!(
+            contract.push_value(0),
+            Some(U256::from_big_endian(address.as_bytes()))
+        );
+        assert_eq!(contract.push_value(21), None);
+        // truncated PUSH2 is padded with zero.
+        assert_eq!(contract.push_value(22), Some(U256::from(0x0100)));
+        assert_eq!(contract.push_value(24), None);
+    }
+
+    #[test]
+    pub fn test_analysed_bytecode_is_reused() {
+        // analysis cached by a host, e.g. keyed by code hash.
+        let code = vec![
+            opcode::PUSH1,
+            0x04,
+            opcode::JUMP,
+            opcode::STOP,
+            opcode::JUMPDEST,
+        ];
+        let cached = Bytecode::new_raw(code.into()).to_analysed::<LatestSpec>();
+        let contracts: Vec<Contract> = (0..2)
+            .map(|_| {
+                Contract::new::<LatestSpec>(
+                    Bytes::new(),
+                    cached.clone(),
+                    H160::zero(),
+                    H160::zero(),
+                    U256::zero(),
+                )
+            })
+            .collect();
+
+        // no contract analysed the code again, all of them share the cached jump table.
+        for contract in &contracts {
+            let analysis = &contract.bytecode.jumptable().analysis;
+            match cached.state() {
+                BytecodeState::Analysed { jumptable, .. } => {
+                    assert!(Arc::ptr_eq(analysis, &jumptable.analysis))
+                }
+                state => panic!("unexpected state {state:?}"),
+            }
+            assert!(contract.is_valid_jump(4));
+        }
+    }
+
+    #[test]
+    pub fn test_new_analysed() {
+        let code = vec![
+            opcode::PUSH1,
+            0x04,
+            opcode::JUMP,
+            opcode::STOP,
+            opcode::JUMPDEST,
+        ];
+        let cached = Bytecode::new_raw(code.into()).to_analysed::<LatestSpec>();
+        let contract = Contract::new_analysed::<LatestSpec>(
+            Bytes::new(),
+            cached.clone(),
+            H160::zero(),
+            H160::zero(),
+            U256::zero(),
+        );
+        let mut interp = Interpreter::new::<LatestSpec>(contract, 100_000);
+        assert_eq!(
+            interp.run::<_, LatestSpec>(&mut MockHost::new()),
+            Return::Stop
+        );
+
+        // the run used the cached analysis and left it as it was.
+        let locked = &interp.contract.bytecode;
+        match cached.state() {
+            BytecodeState::Analysed { jumptable, .. } => {
+                assert!(Arc::ptr_eq(
+                    &locked.jumptable().analysis,
+                    &jumptable.analysis
+                ));
+                assert_eq!(locked.jumptable(), jumptable);
+            }
+            state => panic!("unexpected state {state:?}"),
+        }
+        assert_eq!(locked.bytecode(), cached.bytes().as_ref());
+    }
+
+    #[test]
+    #[should_panic(expected = "bytecode is not analysed")]
+    pub fn test_new_analysed_raw() {
+        Contract::new_analysed::<LatestSpec>(
+            Bytes::new(),
+            Bytecode::new_raw(vec![opcode::STOP].into()),
+            H160::zero(),
+            H160::zero(),
+            U256::zero(),
+        );
+    }
+
+    #[test]
+    pub fn test_new_from_code() {
+        // return call data word 0 plus call value.
+        let code = vec![
+            opcode::PUSH1,
+            0x00,
+            opcode::CALLDATALOAD,
+            opcode::CALLVALUE,
+            opcode::ADD,
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x20,
+            opcode::PUSH1,
+            0x00,
+            opcode::RETURN,
+        ];
+        let mut input = [0u8; 32];
+        input[31] = 5;
+        let (caller, address) = (H160::repeat_byte(0x10), H160::repeat_byte(0x20));
+        let contract = Contract::new_from_code::<LatestSpec>(
+            code.into(),
+            Bytes::copy_from_slice(&input),
+            caller,
+            address,
+            U256::from(7),
+        );
+        assert_eq!((contract.caller, contract.address), (caller, address));
+        assert_eq!(contract.value, U256::from(7));
+
+        let mut interp = Interpreter::new::<LatestSpec>(contract, 100);
+        assert_eq!(
+            interp.run::<_, LatestSpec>(&mut MockHost::new()),
+            Return::Return
+        );
+        let mut expected = [0u8; 32];
+        expected[31] = 12;
+        assert_eq!(interp.return_value(), Bytes::copy_from_slice(&expected));
+    }
+
+    #[test]
+    pub fn test_jump_into_push_data() {
+        // PUSH32 of JUMPDEST bytes, then a jump to `dest` and a real JUMPDEST at 40.
+        let code = |jump: u8, dest: u8| {
+            let mut code = vec![opcode::PUSH32];
+            code.extend([opcode::JUMPDEST; 32]);
+            code.extend([opcode::POP, opcode::PUSH1, 0x01, opcode::PUSH1, dest, jump]);
+            code.extend([opcode::INVALID, opcode::JUMPDEST, opcode::STOP]);
+            Contract::new_from_code::<LatestSpec>(
+                code.into(),
+                Bytes::new(),
+                H160::zero(),
+                H160::zero(),
+                U256::zero(),
+            )
+        };
+        let contract = code(opcode::JUMP, 40);
+        assert!(contract.is_valid_jump(40));
+        assert!(!contract.is_valid_jump(0));
+        for pc in 1..=32 {
+            assert!(!contract.is_valid_jump(pc), "{pc}");
+        }
+
+        for jump in [opcode::JUMP, opcode::JUMPI] {
+            for (dest, ret) in [(40, Return::Stop), (1, Return::InvalidJump)] {
+                let mut interp = Interpreter::new::<LatestSpec>(code(jump, dest), 100_000);
+                assert_eq!(interp.run::<_, LatestSpec>(&mut MockHost::new()), ret);
+            }
+        }
+    }
 }
@@ -0,0 +1,43 @@
+use super::Interpreter;
+use crate::{alloc::vec::Vec, OpcodeSet};
+
+impl Interpreter {
+    /// Record the program counter of every opcode executed by [Interpreter::run] and
+    /// [Interpreter::step], read them with [Interpreter::executed_pcs]. Off by default, a frame
+    /// without coverage only checks that it is off.
+    pub fn with_pc_coverage(mut self) -> Self {
+        let words = self.contract.bytecode.bytecode().len().div_ceil(64);
+        self.pc_coverage = Some(vec![0; words]);
+        self
+    }
+
+    /// Program counters of the executed opcodes in ascending order. Empty unless enabled with
+    /// [Interpreter::with_pc_coverage].
+    pub fn executed_pcs(&self) -> Vec<usize> {
+        let words = self.pc_coverage.as_deref().unwrap_or_default();
+        (0..words.len() * 64)
+            .filter(|pc| words[pc / 64] & (1 << (pc % 64)) != 0)
+            .collect()
+    }
+
+    /// Opcodes at [Interpreter::executed_pcs].
+    pub fn executed_opcodes(&self) -> OpcodeSet {
+        let code = self.contract.bytecode.bytecode();
+        let mut opcodes = OpcodeSet::default();
+        for pc in self.executed_pcs() {
+            opcodes.insert(code[pc]);
+        }
+        opcodes
+    }
+
+    pub(crate) fn record_pc(&mut self) {
+        let pc = self.program_counter();
+        if let Some(word) = self
+            .pc_coverage
+            .as_mut()
+            .and_then(|words| words.get_mut(pc / 64))
+        {
+            *word |= 1 << (pc % 64);
+        }
+    }
+}
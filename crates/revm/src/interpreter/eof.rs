@@ -0,0 +1,236 @@
+use crate::OpCode;
+use core::ops::Range;
+
+/// First bytes of every EIP-3540 EOF container, `0xEF` followed by the EIP-3541 magic byte.
+pub const EOF_MAGIC: [u8; 2] = [0xEF, 0x00];
+/// The only container version, see [validate_eof].
+pub const EOF_VERSION: u8 = 0x01;
+
+const KIND_TERMINATOR: u8 = 0x00;
+const KIND_CODE: u8 = 0x01;
+const KIND_DATA: u8 = 0x02;
+
+/// Section sizes of a valid EOF container, see [validate_eof].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct EofHeader {
+    pub code_size: u16,
+    /// Zero if the container has no data section.
+    pub data_size: u16,
+}
+
+/// Reason a container is not valid EOF, see [validate_eof].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EofError {
+    /// Code does not start with [EOF_MAGIC].
+    InvalidMagic,
+    UnsupportedVersion(u8),
+    /// Code ends before the terminator of the section headers.
+    TruncatedHeader,
+    UnknownSectionKind(u8),
+    /// Code section is not the first section or the data section is repeated.
+    MisplacedSection(u8),
+    MissingCodeSection,
+    EmptySection(u8),
+    /// Container size is not the header size plus the section sizes.
+    SizeMismatch {
+        expected: usize,
+        actual: usize,
+    },
+    /// EIP-3670 undefined opcode in the code section, `pc` is relative to the section.
+    UndefinedOpcode {
+        pc: usize,
+        opcode: u8,
+    },
+    /// EIP-3670 PUSH data runs past the end of the code section.
+    TruncatedPush {
+        pc: usize,
+    },
+}
+
+impl EofHeader {
+    /// Size of the magic, version and section headers.
+    pub fn header_size(&self) -> usize {
+        let sections = if self.data_size == 0 { 1 } else { 2 };
+        EOF_MAGIC.len() + 1 + 3 * sections + 1
+    }
+
+    /// Offsets of the code section in the container.
+    pub fn code_range(&self) -> Range<usize> {
+        let start = self.header_size();
+        start..start + self.code_size as usize
+    }
+
+    /// Offsets of the data section in the container, empty without one.
+    pub fn data_range(&self) -> Range<usize> {
+        let start = self.code_range().end;
+        start..start + self.data_size as usize
+    }
+}
+
+/// Validate an EOF container as it is deployed.
+///
+/// The container is [EOF_MAGIC], [EOF_VERSION] and section headers of one kind byte and a big
+/// endian `u16` size each, ended by a zero byte. There is exactly one code section, optionally
+/// followed by one data section, neither of them empty, and the section bodies follow the
+/// headers without anything after them (EIP-3540). Every opcode of the code section is defined
+/// and PUSH data ends inside it (EIP-3670). Whether EOF is accepted at all depends on the spec,
+/// see [crate::validate_bytecode].
+pub fn validate_eof(code: &[u8]) -> Result<EofHeader, EofError> {
+    if !code.starts_with(&EOF_MAGIC) {
+        return Err(EofError::InvalidMagic);
+    }
+    match code.get(EOF_MAGIC.len()) {
+        Some(&EOF_VERSION) => (),
+        Some(&version) => return Err(EofError::UnsupportedVersion(version)),
+        None => return Err(EofError::TruncatedHeader),
+    }
+
+    let mut offset = EOF_MAGIC.len() + 1;
+    let mut code_size = None;
+    let mut data_size = None;
+    loop {
+        let kind = *code.get(offset).ok_or(EofError::TruncatedHeader)?;
+        if kind == KIND_TERMINATOR {
+            break;
+        }
+        let size = code
+            .get(offset + 1..offset + 3)
+            .ok_or(EofError::TruncatedHeader)?;
+        let size = u16::from_be_bytes([size[0], size[1]]);
+        offset += 3;
+        let section = match kind {
+            KIND_CODE if code_size.is_none() && data_size.is_none() => &mut code_size,
+            KIND_DATA if code_size.is_some() && data_size.is_none() => &mut data_size,
+            KIND_CODE | KIND_DATA => return Err(EofError::MisplacedSection(kind)),
+            kind => return Err(EofError::UnknownSectionKind(kind)),
+        };
+        if size == 0 {
+            return Err(EofError::EmptySection(kind));
+        }
+        *section = Some(size);
+    }
+
+    let header = EofHeader {
+        code_size: code_size.ok_or(EofError::MissingCodeSection)?,
+        data_size: data_size.unwrap_or(0),
+    };
+    let expected = header.data_range().end;
+    if code.len() != expected {
+        return Err(EofError::SizeMismatch {
+            expected,
+            actual: code.len(),
+        });
+    }
+    validate_code_section(&code[header.code_range()])?;
+    Ok(header)
+}
+
+/// EIP-3670 opcode validation of a code section.
+fn validate_code_section(code: &[u8]) -> Result<(), EofError> {
+    let mut pc = 0;
+    while pc < code.len() {
+        let opcode = code[pc];
+        let op = OpCode::try_from_u8(opcode).ok_or(EofError::UndefinedOpcode { pc, opcode })?;
+        let next = pc + 1 + op.immediate_size();
+        if next > code.len() {
+            return Err(EofError::TruncatedPush { pc });
+        }
+        pc = next;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_eof, EofError, EofHeader};
+    use crate::opcode;
+
+    /// Container with a code section and a data section of one byte.
+    fn container(code: &[u8]) -> Vec<u8> {
+        let mut container = vec![0xEF, 0x00, 0x01, 0x01];
+        container.extend_from_slice(&(code.len() as u16).to_be_bytes());
+        container.extend([0x02, 0x00, 0x01, 0x00]);
+        container.extend_from_slice(code);
+        container.push(0xaa);
+        container
+    }
+
+    #[test]
+    fn test_valid_container() {
+        // code section of a single STOP, no data.
+        let minimal = [0xEF, 0x00, 0x01, 0x01, 0x00, 0x01, 0x00, opcode::STOP];
+        let header = validate_eof(&minimal).unwrap();
+        assert_eq!(
+            header,
+            EofHeader {
+                code_size: 1,
+                data_size: 0
+            }
+        );
+        assert_eq!(header.code_range(), 7..8);
+
+        let code = [opcode::PUSH1, 0x01, opcode::POP, opcode::STOP];
+        let header = validate_eof(&container(&code)).unwrap();
+        assert_eq!((header.code_range(), header.data_range()), (10..14, 14..15));
+    }
+
+    #[test]
+    fn test_invalid_magic() {
+        assert_eq!(validate_eof(&[]), Err(EofError::InvalidMagic));
+        assert_eq!(
+            validate_eof(&[0xEF, 0x01, 0x01, 0x01, 0x00, 0x01, 0x00, opcode::STOP]),
+            Err(EofError::InvalidMagic)
+        );
+        assert_eq!(
+            validate_eof(&[0xEF, 0x00, 0x02, 0x01, 0x00, 0x01, 0x00, opcode::STOP]),
+            Err(EofError::UnsupportedVersion(2))
+        );
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        let code = container(&[opcode::STOP]);
+        // cut inside the version, a section header and before the terminator.
+        for len in [2, 5, 9] {
+            assert_eq!(validate_eof(&code[..len]), Err(EofError::TruncatedHeader));
+        }
+        assert_eq!(
+            validate_eof(&code[..code.len() - 1]),
+            Err(EofError::SizeMismatch {
+                expected: code.len(),
+                actual: code.len() - 1
+            })
+        );
+        assert_eq!(
+            validate_eof(&[0xEF, 0x00, 0x01, 0x00]),
+            Err(EofError::MissingCodeSection)
+        );
+        assert_eq!(
+            validate_eof(&[0xEF, 0x00, 0x01, 0x02, 0x00, 0x01, 0x00, 0xaa]),
+            Err(EofError::MisplacedSection(0x02))
+        );
+        assert_eq!(
+            validate_eof(&[0xEF, 0x00, 0x01, 0x01, 0x00, 0x00, 0x00]),
+            Err(EofError::EmptySection(0x01))
+        );
+        assert_eq!(
+            validate_eof(&[0xEF, 0x00, 0x01, 0x03, 0x00, 0x01, 0x00, 0x00]),
+            Err(EofError::UnknownSectionKind(0x03))
+        );
+    }
+
+    #[test]
+    fn test_invalid_code() {
+        assert_eq!(
+            validate_eof(&container(&[opcode::STOP, 0x0c])),
+            Err(EofError::UndefinedOpcode {
+                pc: 1,
+                opcode: 0x0c
+            })
+        );
+        assert_eq!(
+            validate_eof(&container(&[opcode::PUSH2, 0x01])),
+            Err(EofError::TruncatedPush { pc: 0 })
+        );
+    }
+}
@@ -0,0 +1,312 @@
+//! Fusion of adjacent opcodes into one fused opcode, see [crate::CfgEnv::fuse_opcodes].
+//!
+//! The code itself is never changed, CODECOPY and EXTCODECOPY read it and analysis depends on
+//! it. [fuse] builds a separate table with the fused opcode that starts at each pc, the
+//! interpreter runs the handler of a fused opcode from
+//! [crate::instructions::eval_fused] instead of the first opcode of the pair.
+use super::Contract;
+use crate::{alloc::vec, alloc::vec::Vec, opcode, InstructionIter, OpcodeSet};
+use primitive_types::U256;
+
+/// No fused opcode starts at the pc.
+pub const NONE: u8 = 0;
+/// `PUSH1 x ADD`.
+pub const PUSH1_ADD: u8 = 1;
+/// `DUP1 PUSH1 x`.
+pub const DUP1_PUSH1: u8 = 2;
+/// `PUSH1 x JUMP` with x a valid jump destination.
+pub const PUSH1_JUMP: u8 = 3;
+/// `PUSH2 x JUMPI` with x a valid jump destination.
+pub const PUSH2_JUMPI: u8 = 4;
+
+struct Fusion {
+    /// Opcodes of the pair, the first one can have an immediate.
+    pattern: [u8; 2],
+    fused: u8,
+    /// The immediate of the first opcode is the destination of the second, only valid jump
+    /// destinations are fused.
+    jump: bool,
+}
+
+/// Fused opcodes. A new fusion is an entry here and a handler in
+/// [crate::instructions::eval_fused].
+const FUSIONS: [Fusion; 4] = [
+    Fusion {
+        pattern: [opcode::PUSH1, opcode::ADD],
+        fused: PUSH1_ADD,
+        jump: false,
+    },
+    Fusion {
+        pattern: [opcode::DUP1, opcode::PUSH1],
+        fused: DUP1_PUSH1,
+        jump: false,
+    },
+    Fusion {
+        pattern: [opcode::PUSH1, opcode::JUMP],
+        fused: PUSH1_JUMP,
+        jump: true,
+    },
+    Fusion {
+        pattern: [opcode::PUSH2, opcode::JUMPI],
+        fused: PUSH2_JUMPI,
+        jump: true,
+    },
+];
+
+/// Fused opcode that starts at every pc of the padded code of `contract`, [NONE] where the
+/// opcode runs on its own. Pairs are fused from the start of the code on, an opcode is part of
+/// one pair at most. Pairs with a `disabled` opcode are not fused.
+pub fn fuse(contract: &Contract, disabled: &OpcodeSet) -> Vec<u8> {
+    let code = contract.bytecode.original_bytecode_slice();
+    let mut fused = vec![NONE; contract.bytecode.bytecode().len()];
+    let mut instructions = InstructionIter::new(code).peekable();
+    while let Some((pc, first, immediate)) = instructions.next() {
+        let second = match instructions.peek() {
+            Some((_, second, _)) => second.u8(),
+            None => break,
+        };
+        let fusion = FUSIONS.iter().find(|fusion| {
+            fusion.pattern == [first.u8(), second]
+                && !fusion.pattern.iter().any(|op| disabled.contains(*op))
+        });
+        let fusion = match fusion {
+            Some(fusion) => fusion,
+            None => continue,
+        };
+        if fusion.jump {
+            let dest = U256::from_big_endian(immediate.unwrap_or_default());
+            if dest >= U256::from(code.len()) || !contract.is_valid_jump(dest.as_usize()) {
+                continue;
+            }
+        }
+        fused[pc] = fusion.fused;
+        instructions.next();
+    }
+    fused
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fuse, DUP1_PUSH1, NONE, PUSH1_ADD, PUSH1_JUMP, PUSH2_JUMPI};
+    use crate::{
+        opcode,
+        test_utils::{evm_with_code, interpreter},
+        LatestSpec, MockHost, OpcodeSet, Return,
+    };
+    use primitive_types::U256;
+
+    /// Return, stack, gas remaining, pc and steps after running `code`.
+    fn run(
+        code: &[u8],
+        gas_limit: u64,
+        fuse_opcodes: bool,
+    ) -> (Return, Vec<U256>, u64, usize, u64) {
+        let mut interp = interpreter(code.to_vec(), gas_limit);
+        interp.fuse_opcodes = fuse_opcodes;
+        let ret = interp.run::<_, LatestSpec>(&mut MockHost::new());
+        assert_eq!(interp.fused.is_some(), fuse_opcodes);
+        (
+            ret,
+            interp.stack.data().clone(),
+            interp.gas.remaining(),
+            interp.program_counter(),
+            interp.steps(),
+        )
+    }
+
+    /// Adds 7 to an accumulator `iterations` times.
+    fn count_down(iterations: u8) -> Vec<u8> {
+        vec![
+            opcode::PUSH1,
+            0x00,
+            opcode::PUSH1,
+            iterations,
+            opcode::JUMPDEST,
+            opcode::SWAP1,
+            opcode::PUSH1,
+            0x07,
+            opcode::ADD,
+            opcode::SWAP1,
+            opcode::PUSH1,
+            0x01,
+            opcode::SWAP1,
+            opcode::SUB,
+            opcode::DUP1,
+            opcode::PUSH2,
+            0x00,
+            0x04,
+            opcode::JUMPI,
+            opcode::STOP,
+        ]
+    }
+
+    #[test]
+    fn test_fuse() {
+        let code = vec![
+            opcode::PUSH1,
+            0x01,
+            opcode::PUSH1,
+            0x02,
+            opcode::ADD,
+            opcode::DUP1,
+            opcode::PUSH1,
+            0x0a,
+            opcode::JUMP,
+            opcode::STOP,
+            opcode::JUMPDEST,
+            opcode::PUSH2,
+            0x00,
+            0x0a,
+            opcode::JUMPI,
+            opcode::PUSH1,
+            0x0a,
+            opcode::JUMP,
+            // 0x09 is not a jump destination.
+            opcode::PUSH1,
+            0x09,
+            opcode::JUMP,
+            // PUSH1 ADD inside of an immediate.
+            opcode::PUSH2,
+            opcode::PUSH1,
+            opcode::ADD,
+        ];
+        let contract = interpreter(code.clone(), 0).contract;
+        let fused = fuse(&contract, &OpcodeSet::default());
+        assert_eq!(fused.len(), contract.bytecode.bytecode().len());
+        let at = |fused: &[u8]| {
+            (0..code.len())
+                .filter(|pc| fused[*pc] != NONE)
+                .map(|pc| (pc, fused[pc]))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            at(&fused),
+            vec![
+                (2, PUSH1_ADD),
+                (5, DUP1_PUSH1),
+                (11, PUSH2_JUMPI),
+                (15, PUSH1_JUMP)
+            ]
+        );
+
+        // pairs with a disabled opcode run as they are.
+        let fused = fuse(&contract, &OpcodeSet::new(&[opcode::ADD, opcode::JUMPI]));
+        assert_eq!(at(&fused), vec![(5, DUP1_PUSH1), (15, PUSH1_JUMP)]);
+    }
+
+    #[test]
+    fn test_fused_loop() {
+        let code = count_down(5);
+        let fused = run(&code, 100_000, true);
+        assert_eq!(fused.0, Return::Stop);
+        assert_eq!(fused.1, vec![U256::from(35), U256::zero()]);
+        assert_eq!(fused, run(&code, 100_000, false));
+    }
+
+    #[test]
+    fn test_fused_transaction() {
+        let mut evm = evm_with_code(count_down(5));
+        let (unfused, _) = evm.transact();
+        evm.env.cfg.fuse_opcodes = true;
+        let (fused, _) = evm.transact();
+        assert_eq!(fused.exit_reason, Return::Stop);
+        assert_eq!(
+            (fused.exit_reason, fused.gas_used),
+            (unfused.exit_reason, unfused.gas_used)
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_fused_out_of_gas() {
+        let code = count_down(100);
+        for gas_limit in [0, 10, 100, 1000] {
+            let fused = run(&code, gas_limit, true);
+            assert_eq!(fused.0, Return::OutOfGas);
+            assert_eq!(fused, run(&code, gas_limit, false));
+        }
+    }
+
+    #[test]
+    fn test_fused_stack_errors() {
+        // PUSH1 ADD and PUSH2 JUMPI on an empty stack fail at ADD and JUMPI.
+        let cases = [
+            (
+                vec![opcode::PUSH1, 0x01, opcode::ADD],
+                Return::StackUnderflow,
+            ),
+            (
+                vec![opcode::PUSH2, 0x00, 0x04, opcode::JUMPI, opcode::JUMPDEST],
+                Return::StackUnderflow,
+            ),
+            (
+                vec![opcode::DUP1, opcode::PUSH1, 0x01],
+                Return::StackUnderflow,
+            ),
+        ];
+        for (code, ret) in cases {
+            let fused = run(&code, 100_000, true);
+            assert_eq!(fused.0, ret);
+            assert_eq!(fused, run(&code, 100_000, false));
+        }
+
+        // a push of the pair overflows a full stack.
+        let cases = [
+            (vec![opcode::DUP1, opcode::PUSH1, 0x01], 1022, Return::Stop),
+            (
+                vec![opcode::DUP1, opcode::PUSH1, 0x01],
+                1023,
+                Return::StackOverflow,
+            ),
+            (
+                vec![opcode::DUP1, opcode::PUSH1, 0x01],
+                1024,
+                Return::StackOverflow,
+            ),
+            (vec![opcode::PUSH1, 0x01, opcode::ADD], 1023, Return::Stop),
+            (
+                vec![opcode::PUSH1, 0x01, opcode::ADD],
+                1024,
+                Return::StackOverflow,
+            ),
+        ];
+        for (pair, items, ret) in cases {
+            let mut code = [opcode::PUSH1, 0x01].repeat(items);
+            code.extend_from_slice(&pair);
+            let fused = run(&code, 100_000, true);
+            assert_eq!(fused.0, ret);
+            assert_eq!(fused, run(&code, 100_000, false));
+        }
+    }
+
+    #[test]
+    fn test_fused_jumps() {
+        // PUSH1 JUMP to the JUMPDEST, then PUSH2 JUMPI with 1 and with 0.
+        let code = vec![
+            opcode::PUSH1,
+            0x04,
+            opcode::JUMP,
+            opcode::INVALID,
+            opcode::JUMPDEST,
+            opcode::PUSH1,
+            0x00,
+            opcode::PUSH2,
+            0x00,
+            0x0d,
+            opcode::JUMPI,
+            opcode::PUSH1,
+            0x01,
+            opcode::JUMPDEST,
+            opcode::PUSH2,
+            0x00,
+            0x13,
+            opcode::JUMPI,
+            opcode::INVALID,
+            opcode::JUMPDEST,
+            opcode::STOP,
+        ];
+        let fused = run(&code, 100_000, true);
+        assert_eq!(fused.0, Return::Stop);
+        assert_eq!(fused, run(&code, 100_000, false));
+    }
+}
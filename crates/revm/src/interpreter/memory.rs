@@ -22,11 +22,22 @@ impl Default for Memory {
 impl Memory {
     /// Create a new memory with the given limit.
     pub fn new() -> Self {
+        Self::with_capacity(4 * 1024) // took it from evmone
+    }
+
+    /// Empty memory that allocates room for `bytes` up front, so growing up to it does not
+    /// reallocate. Length and expansion gas are the same as with [Memory::new].
+    pub fn with_capacity(bytes: usize) -> Self {
         Self {
-            data: Vec::with_capacity(4 * 1024), // took it from evmone
+            data: Vec::with_capacity(bytes),
         }
     }
 
+    /// Bytes that memory can grow to without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.data.capacity()
+    }
+
     pub fn effective_len(&self) -> usize {
         self.data.len()
     }
@@ -57,12 +68,29 @@ impl Memory {
         self.data.resize(new_size, 0);
     }
 
+    /// Resize the memory to `byte_len` rounded up to the next word, so memory is always a
+    /// multiple of 32 bytes. Gas has to be checked beforehand, same as for [Memory::resize].
+    ///
+    /// # Panics
+    /// If the rounded up length overflows `usize`.
+    #[inline(always)]
+    pub fn resize_word_aligned(&mut self, byte_len: usize) {
+        let new_size = next_multiple_of_32(byte_len).expect("memory length overflow");
+        self.data.resize(new_size, 0);
+    }
+
     /// Get memory region at given offset. Dont check offset and size
     #[inline(always)]
     pub fn get_slice(&self, offset: usize, size: usize) -> &[u8] {
         &self.data[offset..offset + size]
     }
 
+    /// Get memory region at given offset, `None` if it does not fit in the current memory.
+    #[inline(always)]
+    pub fn try_get_slice(&self, offset: usize, len: usize) -> Option<&[u8]> {
+        self.data.get(offset..offset.checked_add(len)?)
+    }
+
     /// Set memory region at given offset
     ///
     /// # Safety
@@ -85,6 +113,13 @@ impl Memory {
         }
     }
 
+    /// Copy `len` bytes from `src` to `dst`, the ranges may overlap. Both ranges are expected to
+    /// be inside of memory.
+    #[inline(always)]
+    pub fn copy(&mut self, dst: usize, src: usize, len: usize) {
+        self.data.copy_within(src..src + len, dst);
+    }
+
     /// Set memory from data. Our memory offset+len is expected to be correct but we
     /// are doing bound checks on data/data_offeset/len and zeroing parts that is not copied.
     #[inline(always)]
@@ -117,7 +152,10 @@ pub(crate) fn next_multiple_of_32(x: usize) -> Option<usize> {
 
 #[cfg(test)]
 mod tests {
-    use super::next_multiple_of_32;
+    use super::{next_multiple_of_32, Memory};
+    use crate::{opcode, test_utils::evm_with_code, Return, TransactOut};
+
+    use primitive_types::U256;
 
     #[test]
     fn test_next_multiple_of_32() {
@@ -136,4 +174,55 @@ mod tests {
             assert_eq!(Some(next_multiple), next_multiple_of_32(x));
         }
     }
+
+    #[test]
+    fn test_resize_word_aligned() {
+        let mut memory = Memory::new();
+        memory.resize_word_aligned(1);
+        assert_eq!(memory.len(), 32);
+        memory.resize_word_aligned(32);
+        assert_eq!(memory.len(), 32);
+        memory.resize_word_aligned(33);
+        assert_eq!(memory.len(), 64);
+    }
+
+    #[test]
+    fn test_try_get_slice() {
+        let mut memory = Memory::new();
+        memory.resize(32);
+        memory.set(30, &[1, 2]);
+        assert_eq!(memory.try_get_slice(30, 2), Some(&[1, 2][..]));
+        assert_eq!(memory.try_get_slice(32, 0), Some(&[][..]));
+        assert_eq!(memory.try_get_slice(31, 2), None);
+        assert_eq!(memory.try_get_slice(usize::MAX, 1), None);
+    }
+
+    #[test]
+    fn test_single_byte_store_expands_word() {
+        // MSTORE8 a single byte at 0 and return MSIZE.
+        let code = vec![
+            opcode::PUSH1,
+            0xff,
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE8,
+            opcode::MSIZE,
+            opcode::PUSH1,
+            0x20,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x20,
+            opcode::PUSH1,
+            0x20,
+            opcode::RETURN,
+        ];
+        let mut evm = evm_with_code(code);
+
+        let (result, _) = evm.transact();
+        assert_eq!(result.exit_reason, Return::Return);
+        match result.out {
+            TransactOut::Call(out) => assert_eq!(U256::from_big_endian(&out), U256::from(32)),
+            out => panic!("unexpected output {out:?}"),
+        }
+    }
 }
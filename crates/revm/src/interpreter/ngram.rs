@@ -0,0 +1,53 @@
+use super::Interpreter;
+use crate::{alloc::vec::Vec, OpCode};
+use hashbrown::HashMap as Map;
+
+impl Interpreter {
+    /// Count every run of `n` consecutive opcodes executed by [Interpreter::run] and
+    /// [Interpreter::step], read them with [Interpreter::dump]. Zero, the default, turns
+    /// counting off.
+    pub fn with_ngram(mut self, n: usize) -> Self {
+        self.ngram = n;
+        self.opcode_window.clear();
+        self.opcode_counts.clear();
+        self
+    }
+
+    /// N-grams of executed opcodes with their counts, most frequent first. Empty unless enabled
+    /// with [Interpreter::with_ngram].
+    pub fn dump(&self) -> Vec<(Vec<OpCode>, u64)> {
+        let mut counts: Vec<_> = self.opcode_counts.iter().collect();
+        counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        counts
+            .into_iter()
+            .map(|(ngram, count)| {
+                let ngram = ngram.iter().map(|op| OpCode::new_unchecked(*op)).collect();
+                (ngram, *count)
+            })
+            .collect()
+    }
+
+    /// Count of every n-gram, keyed by its opcodes.
+    pub fn opcode_histogram(&self) -> &Map<Vec<u8>, u64> {
+        &self.opcode_counts
+    }
+
+    /// Clear the counts. The last executed opcodes are kept, so the next n-gram counted can
+    /// start before the reset.
+    pub fn reset_profile(&mut self) {
+        self.opcode_counts.clear();
+    }
+
+    pub(crate) fn record_ngram(&mut self, opcode: u8) {
+        if self.opcode_window.len() == self.ngram {
+            self.opcode_window.remove(0);
+        }
+        self.opcode_window.push(opcode);
+        if self.opcode_window.len() == self.ngram {
+            *self
+                .opcode_counts
+                .entry(self.opcode_window.clone())
+                .or_default() += 1;
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use super::{stack::STACK_LIMIT, Interpreter};
+use crate::{alloc::vec::Vec, Contract, Gas, GasBlockGranularity, Spec};
+use bytes::Bytes;
+use primitive_types::U256;
+
+/// Execution state of an [Interpreter] between two opcodes, without its [crate::Contract].
+///
+/// Taken with [Interpreter::snapshot] and resumed with [Interpreter::restore] on an interpreter
+/// built for the same contract.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InterpreterSnapshot {
+    /// Stack, top last.
+    pub stack: Vec<U256>,
+    #[cfg_attr(feature = "with-serde", serde(with = "crate::models::serde_hex_bytes"))]
+    pub memory: Bytes,
+    /// Offset of the next opcode in the contract bytecode.
+    pub program_counter: usize,
+    pub gas_limit: u64,
+    /// Spent gas without memory expansion gas.
+    pub gas_used: u64,
+    /// Spent memory expansion gas.
+    pub gas_memory: u64,
+    pub gas_refunded: i64,
+    #[cfg_attr(feature = "with-serde", serde(with = "crate::models::serde_hex_bytes"))]
+    pub return_data: Bytes,
+    pub gas_block_granularity: GasBlockGranularity,
+    /// Static gas already charged for the current segment, see [Interpreter::prepaid_gas].
+    pub prepaid_gas: u64,
+    pub segment_charged: bool,
+    /// Block end opcode that charged the current gas block, see
+    /// [Interpreter::current_block_gas_remaining].
+    #[cfg_attr(feature = "with-serde", serde(default))]
+    pub gas_block_start: Option<usize>,
+}
+
+/// Reason a snapshot can not be restored.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SnapshotError {
+    /// Program counter is past the end of the contract bytecode.
+    ProgramCounterOutOfBounds,
+    /// Stack holds more than [STACK_LIMIT] items.
+    StackOverflow,
+    /// Memory length is not a multiple of 32.
+    UnalignedMemory,
+    /// Memory is larger than the memory limit.
+    MemoryLimit,
+    /// More gas is spent than the gas limit allows.
+    InvalidGas,
+}
+
+impl Interpreter {
+    /// Interpreter for `contract` that continues from `snapshot`, see [Interpreter::restore].
+    pub fn from_snapshot<SPEC: Spec>(
+        contract: Contract,
+        snapshot: InterpreterSnapshot,
+    ) -> Result<Self, SnapshotError> {
+        let mut interp = Self::new::<SPEC>(contract, snapshot.gas_limit);
+        interp.restore(snapshot)?;
+        Ok(interp)
+    }
+
+    /// [Interpreter::from_snapshot] with a memory limit in bytes.
+    pub fn from_snapshot_with_memory_limit<SPEC: Spec>(
+        contract: Contract,
+        snapshot: InterpreterSnapshot,
+        memory_limit: u64,
+    ) -> Result<Self, SnapshotError> {
+        let mut interp =
+            Self::new_with_memory_limit::<SPEC>(contract, snapshot.gas_limit, memory_limit);
+        interp.restore(snapshot)?;
+        Ok(interp)
+    }
+
+    /// Snapshot of the execution state, taken between steps.
+    pub fn snapshot(&self) -> InterpreterSnapshot {
+        InterpreterSnapshot {
+            stack: self.stack.data().clone(),
+            memory: Bytes::copy_from_slice(self.memory.data()),
+            program_counter: self.program_counter(),
+            gas_limit: self.gas.limit(),
+            gas_used: self.gas.spend() - self.gas.memory(),
+            gas_memory: self.gas.memory(),
+            gas_refunded: self.gas.refunded(),
+            return_data: self.return_data_buffer.clone(),
+            gas_block_granularity: self.gas_block_granularity,
+            prepaid_gas: self.prepaid_gas,
+            segment_charged: self.segment_charged,
+            gas_block_start: self.gas_block_start,
+        }
+    }
+
+    /// Continue from `snapshot` on this interpreter's contract.
+    ///
+    /// Everything that would make unchecked execution unsafe is validated first and the
+    /// interpreter is left untouched on error. The snapshot has to come from the same bytecode, a
+    /// program counter that points into push data is not detected.
+    pub fn restore(&mut self, snapshot: InterpreterSnapshot) -> Result<(), SnapshotError> {
+        if snapshot.program_counter > self.contract.bytecode.len() {
+            return Err(SnapshotError::ProgramCounterOutOfBounds);
+        }
+        if snapshot.stack.len() > STACK_LIMIT {
+            return Err(SnapshotError::StackOverflow);
+        }
+        if snapshot.memory.len() & 31 != 0 {
+            return Err(SnapshotError::UnalignedMemory);
+        }
+        if matches!(self.memory_limit, Some(limit) if snapshot.memory.len() as u64 > limit) {
+            return Err(SnapshotError::MemoryLimit);
+        }
+        let gas = Gas::with_usage(
+            snapshot.gas_limit,
+            snapshot.gas_used,
+            snapshot.gas_memory,
+            snapshot.gas_refunded,
+        )
+        .filter(|gas| snapshot.prepaid_gas <= gas.spend())
+        .ok_or(SnapshotError::InvalidGas)?;
+
+        self.stack = Default::default();
+        for value in snapshot.stack {
+            // can not fail, length is checked above.
+            let _ = self.stack.push(value);
+        }
+        self.memory.resize(snapshot.memory.len());
+        self.memory.set(0, &snapshot.memory);
+        // Safety: program counter is within the bytecode, which is padded with STOP.
+        self.instruction_pointer = unsafe {
+            self.contract
+                .bytecode
+                .as_ptr()
+                .add(snapshot.program_counter)
+        };
+        self.gas = gas;
+        self.return_data_buffer = snapshot.return_data;
+        self.gas_block_granularity = snapshot.gas_block_granularity;
+        self.prepaid_gas = snapshot.prepaid_gas;
+        self.segment_charged = snapshot.segment_charged;
+        self.gas_block_start = snapshot.gas_block_start;
+        // first gas block is part of the spent gas.
+        self.started = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SnapshotError;
+    use crate::{opcode, Interpreter, LatestSpec, MockHost, Return};
+    use bytes::Bytes;
+    use primitive_types::U256;
+
+    fn interpreter() -> Interpreter {
+        // (2 + 3) stored at 0x20 and returned.
+        let code = vec![
+            opcode::PUSH1,
+            0x02,
+            opcode::PUSH1,
+            0x03,
+            opcode::ADD,
+            opcode::PUSH1,
+            0x20,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x20,
+            opcode::PUSH1,
+            0x20,
+            opcode::RETURN,
+        ];
+        crate::test_utils::interpreter(code, 1000)
+    }
+
+    #[test]
+    fn test_snapshot_resume() {
+        let mut host = MockHost::new();
+        let mut expected = interpreter();
+        assert_eq!(expected.run::<_, LatestSpec>(&mut host), Return::Return);
+
+        // stop after MSTORE, with stack and memory in use.
+        let mut interp = interpreter();
+        for _ in 0..5 {
+            assert_eq!(interp.step::<_, LatestSpec>(&mut host), Return::Continue);
+        }
+        let snapshot = interp.snapshot();
+        assert_eq!(snapshot.program_counter, 8);
+        assert_eq!(snapshot.memory.len(), 64);
+
+        let mut resumed = interpreter();
+        resumed.restore(snapshot.clone()).unwrap();
+        assert_eq!(resumed.snapshot(), snapshot);
+        assert_eq!(resumed.run::<_, LatestSpec>(&mut host), Return::Return);
+        assert_eq!(resumed.return_value(), expected.return_value());
+        assert_eq!(resumed.gas.remaining(), expected.gas.remaining());
+    }
+
+    #[cfg(feature = "with-serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let mut host = MockHost::new();
+        let mut expected = interpreter();
+        assert_eq!(expected.run::<_, LatestSpec>(&mut host), Return::Return);
+
+        let mut interp = interpreter();
+        for _ in 0..5 {
+            assert_eq!(interp.step::<_, LatestSpec>(&mut host), Return::Continue);
+        }
+        let json = serde_json::to_string(&interp.snapshot()).unwrap();
+        let snapshot: super::InterpreterSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(snapshot, interp.snapshot());
+
+        let contract = interp.contract.clone();
+        let mut resumed = Interpreter::from_snapshot::<LatestSpec>(contract, snapshot).unwrap();
+        assert_eq!(resumed.program_counter(), 8);
+        assert_eq!(resumed.run::<_, LatestSpec>(&mut host), Return::Return);
+        assert_eq!(resumed.return_value(), expected.return_value());
+        assert_eq!(resumed.gas.remaining(), expected.gas.remaining());
+    }
+
+    #[test]
+    fn test_reject_corrupt_snapshot() {
+        let snapshot = interpreter().snapshot();
+        let mut interp = interpreter();
+
+        let mut corrupt = snapshot.clone();
+        corrupt.program_counter = 14;
+        assert_eq!(
+            interp.restore(corrupt),
+            Err(SnapshotError::ProgramCounterOutOfBounds)
+        );
+
+        let mut corrupt = snapshot.clone();
+        corrupt.stack = vec![U256::zero(); 1025];
+        assert_eq!(interp.restore(corrupt), Err(SnapshotError::StackOverflow));
+
+        let mut corrupt = snapshot.clone();
+        corrupt.memory = Bytes::from(vec![0; 33]);
+        assert_eq!(interp.restore(corrupt), Err(SnapshotError::UnalignedMemory));
+
+        let mut corrupt = snapshot.clone();
+        corrupt.gas_used = 999;
+        corrupt.gas_memory = 2;
+        assert_eq!(interp.restore(corrupt), Err(SnapshotError::InvalidGas));
+
+        // the interpreter is unchanged and still valid.
+        assert_eq!(interp.snapshot(), snapshot);
+        let mut corrupt = snapshot.clone();
+        corrupt.program_counter = 13;
+        assert_eq!(interp.restore(corrupt), Ok(()));
+    }
+}
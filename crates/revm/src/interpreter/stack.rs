@@ -3,38 +3,55 @@ use primitive_types::{H256, U256};
 
 pub const STACK_LIMIT: usize = 1024;
 
+/// 256 bit word held by the [Stack].
+///
+/// Instructions work on [U256], other implementations (e.g. SIMD friendly layouts) can be
+/// plugged into the stack to benchmark its element storage.
+pub trait StackWord: Copy + Eq + core::fmt::Debug + From<U256> + Into<U256> {
+    /// Word from its four 64 bit limbs, least significant limb first.
+    fn from_limbs(limbs: [u64; 4]) -> Self;
+}
+
+impl StackWord for U256 {
+    #[inline(always)]
+    fn from_limbs(limbs: [u64; 4]) -> Self {
+        U256(limbs)
+    }
+}
+
 /// EVM stack.
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
-pub struct Stack {
-    data: Vec<U256>,
+pub struct Stack<W: StackWord = U256> {
+    data: Vec<W>,
 }
 
 #[cfg(feature = "std")]
-impl std::fmt::Display for Stack {
+impl<W: StackWord> std::fmt::Display for Stack<W> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        let to_string = |word: &W| Into::<U256>::into(*word).to_string();
         if self.data.is_empty() {
             f.write_str("[]")?;
         } else {
             f.write_str("[")?;
             for i in self.data[..self.data.len() - 1].iter() {
-                f.write_str(&i.to_string())?;
+                f.write_str(&to_string(i))?;
                 f.write_str(", ")?;
             }
-            f.write_str(&self.data.last().unwrap().to_string())?;
+            f.write_str(&to_string(self.data.last().unwrap()))?;
             f.write_str("]")?;
         }
         Ok(())
     }
 }
 
-impl Default for Stack {
+impl<W: StackWord> Default for Stack<W> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl Stack {
+impl<W: StackWord> Stack<W> {
     /// Create a new stack with given limit.
     pub fn new() -> Self {
         Self {
@@ -57,7 +74,7 @@ impl Stack {
 
     #[inline]
     /// Stack data.
-    pub fn data(&self) -> &Vec<U256> {
+    pub fn data(&self) -> &Vec<W> {
         &self.data
     }
 
@@ -75,7 +92,7 @@ impl Stack {
     #[inline]
     /// Pop a value from the stack. If the stack is already empty, returns the
     /// `StackUnderflow` error.
-    pub fn pop(&mut self) -> Result<U256, Return> {
+    pub fn pop(&mut self) -> Result<W, Return> {
         self.data.pop().ok_or(Return::StackUnderflow)
     }
 
@@ -84,11 +101,12 @@ impl Stack {
     ///
     /// # Safety
     /// The caller is responsible to check length of array
-    pub unsafe fn pop_unsafe(&mut self) -> U256 {
+    pub unsafe fn pop_unsafe(&mut self) -> W {
         let mut len = self.data.len();
         len -= 1;
+        let pop = *self.data.get_unchecked(len);
         self.data.set_len(len);
-        *self.data.get_unchecked(len)
+        pop
     }
 
     #[inline(always)]
@@ -96,7 +114,7 @@ impl Stack {
     ///
     /// # Safety
     /// The caller is responsible to check length of array
-    pub unsafe fn top_unsafe(&mut self) -> &mut U256 {
+    pub unsafe fn top_unsafe(&mut self) -> &mut W {
         let len = self.data.len();
         self.data.get_unchecked_mut(len - 1)
     }
@@ -106,7 +124,7 @@ impl Stack {
     ///
     /// # Safety
     /// The caller is responsible to check length of array
-    pub unsafe fn pop_top_unsafe(&mut self) -> (U256, &mut U256) {
+    pub unsafe fn pop_top_unsafe(&mut self) -> (W, &mut W) {
         let mut len = self.data.len();
         let pop = *self.data.get_unchecked(len - 1);
         len -= 1;
@@ -120,7 +138,7 @@ impl Stack {
     ///
     /// # Safety
     /// The caller is responsible to check length of array
-    pub unsafe fn pop2_top_unsafe(&mut self) -> (U256, U256, &mut U256) {
+    pub unsafe fn pop2_top_unsafe(&mut self) -> (W, W, &mut W) {
         let mut len = self.data.len();
         let pop1 = *self.data.get_unchecked(len - 1);
         len -= 2;
@@ -135,14 +153,15 @@ impl Stack {
     ///
     /// # Safety
     /// The caller is responsible to check length of array
-    pub unsafe fn pop2_unsafe(&mut self) -> (U256, U256) {
+    pub unsafe fn pop2_unsafe(&mut self) -> (W, W) {
         let mut len = self.data.len();
         len -= 2;
-        self.data.set_len(len);
-        (
+        let pop = (
             *self.data.get_unchecked(len + 1),
             *self.data.get_unchecked(len),
-        )
+        );
+        self.data.set_len(len);
+        pop
     }
 
     #[inline(always)]
@@ -150,15 +169,16 @@ impl Stack {
     ///
     /// # Safety
     /// The caller is responsible to check length of array
-    pub unsafe fn pop3_unsafe(&mut self) -> (U256, U256, U256) {
+    pub unsafe fn pop3_unsafe(&mut self) -> (W, W, W) {
         let mut len = self.data.len();
         len -= 3;
-        self.data.set_len(len);
-        (
+        let pop = (
             *self.data.get_unchecked(len + 2),
             *self.data.get_unchecked(len + 1),
             *self.data.get_unchecked(len),
-        )
+        );
+        self.data.set_len(len);
+        pop
     }
 
     #[inline(always)]
@@ -166,16 +186,17 @@ impl Stack {
     ///
     /// # Safety
     /// The caller is responsible to check length of array
-    pub unsafe fn pop4_unsafe(&mut self) -> (U256, U256, U256, U256) {
+    pub unsafe fn pop4_unsafe(&mut self) -> (W, W, W, W) {
         let mut len = self.data.len();
         len -= 4;
-        self.data.set_len(len);
-        (
+        let pop = (
             *self.data.get_unchecked(len + 3),
             *self.data.get_unchecked(len + 2),
             *self.data.get_unchecked(len + 1),
             *self.data.get_unchecked(len),
-        )
+        );
+        self.data.set_len(len);
+        pop
     }
 
     #[inline]
@@ -185,14 +206,14 @@ impl Stack {
         if self.data.len() + 1 > STACK_LIMIT {
             return Err(Return::StackOverflow);
         }
-        self.data.push(U256::from_big_endian(value.as_ref()));
+        self.data.push(U256::from_big_endian(value.as_ref()).into());
         Ok(())
     }
 
     #[inline]
     /// Push a new value into the stack. If it will exceed the stack limit,
     /// returns `StackOverflow` error and leaves the stack unchanged.
-    pub fn push(&mut self, value: U256) -> Result<(), Return> {
+    pub fn push(&mut self, value: W) -> Result<(), Return> {
         if self.data.len() + 1 > STACK_LIMIT {
             return Err(Return::StackOverflow);
         }
@@ -204,7 +225,7 @@ impl Stack {
     /// Peek a value at given index for the stack, where the top of
     /// the stack is at index `0`. If the index is too large,
     /// `StackError::Underflow` is returned.
-    pub fn peek(&self, no_from_top: usize) -> Result<U256, Return> {
+    pub fn peek(&self, no_from_top: usize) -> Result<W, Return> {
         if self.data.len() > no_from_top {
             Ok(self.data[self.data.len() - no_from_top - 1])
         } else {
@@ -214,31 +235,45 @@ impl Stack {
 
     #[inline(always)]
     pub fn dup<const N: usize>(&mut self) -> Return {
+        self.dup_n(N)
+    }
+
+    #[inline(always)]
+    pub fn swap<const N: usize>(&mut self) -> Return {
+        self.swap_n(N)
+    }
+
+    /// Push a copy of the `n`th value, where the top of the stack is `1`, like DUP`n`. Zero has
+    /// nothing to copy and underflows.
+    #[inline(always)]
+    pub fn dup_n(&mut self, n: usize) -> Return {
         let len = self.data.len();
-        if len < N {
+        if n == 0 || len < n {
             Return::StackUnderflow
         } else if len + 1 > STACK_LIMIT {
             Return::StackOverflow
         } else {
             // Safety: check for out of bounds is done above and it makes this safe to do.
             unsafe {
-                *self.data.get_unchecked_mut(len) = *self.data.get_unchecked(len - N);
+                let value = *self.data.get_unchecked(len - n);
+                self.data.as_mut_ptr().add(len).write(value);
                 self.data.set_len(len + 1);
             }
             Return::Continue
         }
     }
 
+    /// Swap the top of the stack with the value `n` below it, like SWAP`n`.
     #[inline(always)]
-    pub fn swap<const N: usize>(&mut self) -> Return {
+    pub fn swap_n(&mut self, n: usize) -> Return {
         let len = self.data.len();
-        if len <= N {
+        if len <= n {
             return Return::StackUnderflow;
         }
         // Safety: length is checked before so we are okay to switch bytes in unsafe way.
         unsafe {
-            let pa: *mut U256 = self.data.get_unchecked_mut(len - 1);
-            let pb: *mut U256 = self.data.get_unchecked_mut(len - 1 - N);
+            let pa: *mut W = self.data.get_unchecked_mut(len - 1);
+            let pb: *mut W = self.data.get_unchecked_mut(len - 1 - n);
             core::ptr::swap(pa, pb);
         }
         Return::Continue
@@ -247,48 +282,42 @@ impl Stack {
     /// push slice onto memory it is expected to be max 32 bytes and be contains inside H256
     #[inline(always)]
     pub fn push_slice<const N: usize>(&mut self, slice: &[u8]) -> Return {
-        let new_len = self.data.len() + 1;
-        if new_len > STACK_LIMIT {
+        if self.data.len() + 1 > STACK_LIMIT {
             return Return::StackOverflow;
         }
 
-        let slot;
-        // Safety: check above ensures us that we are okey in increment len.
-        unsafe {
-            self.data.set_len(new_len);
-            slot = self.data.get_unchecked_mut(new_len - 1);
-        }
-
-        slot.0 = [0u64; 4];
+        let mut limbs = [0u64; 4];
         let mut dangling = [0u8; 8];
         if N < 8 {
             dangling[8 - N..].copy_from_slice(slice);
-            slot.0[0] = u64::from_be_bytes(dangling);
+            limbs[0] = u64::from_be_bytes(dangling);
         } else if N < 16 {
-            slot.0[0] = u64::from_be_bytes(*arrayref::array_ref!(slice, N - 8, 8));
+            limbs[0] = u64::from_be_bytes(*arrayref::array_ref!(slice, N - 8, 8));
             if N != 8 {
                 dangling[8 * 2 - N..].copy_from_slice(&slice[..N - 8]);
-                slot.0[1] = u64::from_be_bytes(dangling);
+                limbs[1] = u64::from_be_bytes(dangling);
             }
         } else if N < 24 {
-            slot.0[0] = u64::from_be_bytes(*arrayref::array_ref!(slice, N - 8, 8));
-            slot.0[1] = u64::from_be_bytes(*arrayref::array_ref!(slice, N - 16, 8));
+            limbs[0] = u64::from_be_bytes(*arrayref::array_ref!(slice, N - 8, 8));
+            limbs[1] = u64::from_be_bytes(*arrayref::array_ref!(slice, N - 16, 8));
             if N != 16 {
                 dangling[8 * 3 - N..].copy_from_slice(&slice[..N - 16]);
-                slot.0[2] = u64::from_be_bytes(dangling);
+                limbs[2] = u64::from_be_bytes(dangling);
             }
         } else {
             // M<32
-            slot.0[0] = u64::from_be_bytes(*arrayref::array_ref!(slice, N - 8, 8));
-            slot.0[1] = u64::from_be_bytes(*arrayref::array_ref!(slice, N - 16, 8));
-            slot.0[2] = u64::from_be_bytes(*arrayref::array_ref!(slice, N - 24, 8));
+            limbs[0] = u64::from_be_bytes(*arrayref::array_ref!(slice, N - 8, 8));
+            limbs[1] = u64::from_be_bytes(*arrayref::array_ref!(slice, N - 16, 8));
+            limbs[2] = u64::from_be_bytes(*arrayref::array_ref!(slice, N - 24, 8));
             if N == 32 {
-                slot.0[3] = u64::from_be_bytes(*arrayref::array_ref!(slice, 0, 8));
+                limbs[3] = u64::from_be_bytes(*arrayref::array_ref!(slice, 0, 8));
             } else if N != 24 {
                 dangling[8 * 4 - N..].copy_from_slice(&slice[..N - 24]);
-                slot.0[3] = u64::from_be_bytes(dangling);
+                limbs[3] = u64::from_be_bytes(dangling);
             }
         }
+        // capacity is STACK_LIMIT so this never reallocates.
+        self.data.push(W::from_limbs(limbs));
         Return::Continue
     }
 
@@ -296,7 +325,7 @@ impl Stack {
     /// Set a value at given index for the stack, where the top of the
     /// stack is at index `0`. If the index is too large,
     /// `StackError::Underflow` is returned.
-    pub fn set(&mut self, no_from_top: usize, val: U256) -> Result<(), Return> {
+    pub fn set(&mut self, no_from_top: usize, val: W) -> Result<(), Return> {
         if self.data.len() > no_from_top {
             let len = self.data.len();
             self.data[len - no_from_top - 1] = val;
@@ -306,3 +335,221 @@ impl Stack {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Stack, StackWord, STACK_LIMIT};
+    use crate::Return;
+    use primitive_types::U256;
+
+    /// xorshift64, good enough to generate stacks.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        /// Random stack, a quarter of them close to the stack limit.
+        fn stack(&mut self) -> Vec<U256> {
+            let len = match self.next() % 4 {
+                0 => STACK_LIMIT - (self.next() % 20) as usize,
+                _ => (self.next() % 20) as usize,
+            };
+            (0..len)
+                .map(|_| U256([self.next(), self.next(), self.next(), self.next()]))
+                .collect()
+        }
+    }
+
+    fn to_stack(data: &[U256]) -> Stack {
+        let mut stack = Stack::new();
+        for value in data {
+            stack.push(*value).unwrap();
+        }
+        stack
+    }
+
+    fn reference_dup(data: &mut Vec<U256>, n: usize) -> Return {
+        if data.len() < n {
+            return Return::StackUnderflow;
+        }
+        if data.len() == STACK_LIMIT {
+            return Return::StackOverflow;
+        }
+        data.push(data[data.len() - n]);
+        Return::Continue
+    }
+
+    fn reference_swap(data: &mut [U256], n: usize) -> Return {
+        if data.len() < n + 1 {
+            return Return::StackUnderflow;
+        }
+        let top = data.len() - 1;
+        data.swap(top, top - n);
+        Return::Continue
+    }
+
+    fn check<const N: usize>(rng: &mut Rng) {
+        for _ in 0..200 {
+            let data = rng.stack();
+
+            let mut stack = to_stack(&data);
+            let mut expected = data.clone();
+            assert_eq!(stack.dup::<N>(), reference_dup(&mut expected, N), "DUP{N}");
+            assert_eq!(stack.data(), &expected, "DUP{N}");
+
+            let mut stack = to_stack(&data);
+            let mut expected = data.clone();
+            assert_eq!(
+                stack.swap::<N>(),
+                reference_swap(&mut expected, N),
+                "SWAP{N}"
+            );
+            assert_eq!(stack.data(), &expected, "SWAP{N}");
+
+            let mut stack = to_stack(&data);
+            let mut reference = to_stack(&data);
+            assert_eq!(stack.dup_n(N), reference.dup::<N>(), "DUP{N}");
+            assert_eq!(stack.swap_n(N), reference.swap::<N>(), "SWAP{N}");
+            assert_eq!(stack, reference, "DUP{N} SWAP{N}");
+        }
+    }
+
+    #[test]
+    fn stack_bounds() {
+        let mut stack = Stack::<U256>::new();
+        assert_eq!(stack.pop(), Err(Return::StackUnderflow));
+        assert_eq!(stack.peek(0), Err(Return::StackUnderflow));
+        assert_eq!(stack.dup_n(1), Return::StackUnderflow);
+        assert_eq!(stack.swap_n(0), Return::StackUnderflow);
+
+        for i in 0..STACK_LIMIT {
+            stack.push(U256::from(i)).unwrap();
+        }
+        assert_eq!(stack.len(), 1024);
+        assert_eq!(stack.push(U256::zero()), Err(Return::StackOverflow));
+        assert_eq!(stack.dup_n(1), Return::StackOverflow);
+        assert_eq!(stack.dup_n(0), Return::StackUnderflow);
+        assert_eq!(stack.peek(1023), Ok(U256::zero()));
+        assert_eq!(stack.peek(1024), Err(Return::StackUnderflow));
+
+        assert_eq!(stack.swap_n(1023), Return::Continue);
+        assert_eq!(stack.pop(), Ok(U256::zero()));
+        assert_eq!(stack.dup_n(1023), Return::Continue);
+        assert_eq!(stack.pop(), Ok(U256::from(1023)));
+        assert_eq!(stack.len(), 1023);
+    }
+
+    #[test]
+    fn pop_unsafe_match_reference() {
+        // the popped values are read before the length shrinks, reading them past the length
+        // was UB that got miscompiled into wrong values.
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..200 {
+            let data = rng.stack();
+            // 1 + 2 + 3 + 4 popped values and the top after them.
+            if data.len() < 11 {
+                continue;
+            }
+            let mut stack = to_stack(&data);
+            let mut expected = data.clone();
+            let mut pop = || expected.pop().unwrap();
+            // Safety: the stack holds enough values.
+            let popped = unsafe {
+                (
+                    stack.pop_unsafe(),
+                    stack.pop2_unsafe(),
+                    stack.pop3_unsafe(),
+                    stack.pop4_unsafe(),
+                    *stack.top_unsafe(),
+                )
+            };
+            assert_eq!(
+                popped,
+                (
+                    pop(),
+                    (pop(), pop()),
+                    (pop(), pop(), pop()),
+                    (pop(), pop(), pop(), pop()),
+                    pop()
+                )
+            );
+            assert_eq!(stack.len(), data.len() - 10);
+        }
+
+        // DUP of a stack one below the limit writes the spare capacity.
+        let mut stack = to_stack(&vec![U256::from(7); STACK_LIMIT - 1]);
+        assert_eq!(stack.dup::<1>(), Return::Continue);
+        assert_eq!(stack.len(), STACK_LIMIT);
+        assert_eq!(unsafe { stack.pop_unsafe() }, U256::from(7));
+    }
+
+    #[test]
+    fn dup_swap_match_reference() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        check::<1>(&mut rng);
+        check::<2>(&mut rng);
+        check::<3>(&mut rng);
+        check::<4>(&mut rng);
+        check::<5>(&mut rng);
+        check::<6>(&mut rng);
+        check::<7>(&mut rng);
+        check::<8>(&mut rng);
+        check::<9>(&mut rng);
+        check::<10>(&mut rng);
+        check::<11>(&mut rng);
+        check::<12>(&mut rng);
+        check::<13>(&mut rng);
+        check::<14>(&mut rng);
+        check::<15>(&mut rng);
+        check::<16>(&mut rng);
+    }
+
+    /// Word with a different layout than [U256], most significant limb first.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    struct ReversedWord([u64; 4]);
+
+    impl From<U256> for ReversedWord {
+        fn from(value: U256) -> Self {
+            let [a, b, c, d] = value.0;
+            Self([d, c, b, a])
+        }
+    }
+
+    impl From<ReversedWord> for U256 {
+        fn from(value: ReversedWord) -> Self {
+            let [d, c, b, a] = value.0;
+            U256([a, b, c, d])
+        }
+    }
+
+    impl StackWord for ReversedWord {
+        fn from_limbs(limbs: [u64; 4]) -> Self {
+            U256(limbs).into()
+        }
+    }
+
+    #[test]
+    fn custom_word_matches_u256() {
+        let bytes: Vec<u8> = (1..=32).collect();
+        let mut stack = Stack::<ReversedWord>::new();
+        let mut reference = Stack::<U256>::new();
+
+        assert_eq!(stack.push_slice::<32>(&bytes), Return::Continue);
+        assert_eq!(stack.push_slice::<5>(&bytes[..5]), Return::Continue);
+        assert_eq!(stack.push_slice::<17>(&bytes[..17]), Return::Continue);
+        assert_eq!(reference.push_slice::<32>(&bytes), Return::Continue);
+        assert_eq!(reference.push_slice::<5>(&bytes[..5]), Return::Continue);
+        assert_eq!(reference.push_slice::<17>(&bytes[..17]), Return::Continue);
+        assert_eq!(stack.dup::<3>(), reference.dup::<3>());
+        assert_eq!(stack.swap::<2>(), reference.swap::<2>());
+
+        let words: Vec<U256> = stack.data().iter().map(|word| (*word).into()).collect();
+        assert_eq!(&words, reference.data());
+        assert_eq!(stack.to_string(), reference.to_string());
+    }
+}
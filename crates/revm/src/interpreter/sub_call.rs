@@ -0,0 +1,59 @@
+use super::Interpreter;
+use crate::{instructions::host, CallInputs, CreateInputs, Gas, Host, Return};
+use bytes::Bytes;
+use primitive_types::H160;
+
+/// Call or create started by an opcode and handed back to the host, see
+/// [crate::Host::ITERATIVE_CALLS].
+#[derive(Clone, Debug)]
+pub enum SubCall {
+    /// CALL, CALLCODE, DELEGATECALL or STATICCALL, `is_static` is set for STATICCALL.
+    Call { inputs: CallInputs, is_static: bool },
+    /// CREATE or CREATE2.
+    Create { inputs: CreateInputs },
+}
+
+/// Outcome of a [SubCall], what [crate::Host::call] and [crate::Host::create] return.
+#[derive(Clone, Debug)]
+pub enum SubCallResult {
+    Call {
+        ret: Return,
+        gas: Gas,
+        out: Bytes,
+    },
+    Create {
+        ret: Return,
+        address: Option<H160>,
+        gas: Gas,
+        out: Bytes,
+    },
+}
+
+impl Interpreter {
+    /// Sub call of the opcode that returned [Return::SubCall].
+    pub fn take_sub_call(&mut self) -> Option<SubCall> {
+        self.sub_call.take()
+    }
+
+    /// Hand the result of the taken sub call back. The next [Interpreter::run] or
+    /// [Interpreter::step] finishes the CALL or CREATE opcode with it and continues.
+    pub fn resume(&mut self, result: SubCallResult) {
+        self.sub_call_result = Some(result);
+    }
+
+    /// Finish the suspended CALL or CREATE opcode if its result is there.
+    pub(crate) fn finish_sub_call<H: Host>(&mut self, host: &mut H) -> Option<Return> {
+        Some(match self.sub_call_result.take()? {
+            SubCallResult::Call { ret, gas, out } => {
+                let (out_offset, out_len) = self.sub_call_out;
+                host::call_return(self, host, ret, gas, out, out_offset, out_len)
+            }
+            SubCallResult::Create {
+                ret,
+                address,
+                gas,
+                out,
+            } => host::create_return(self, host, ret, address, gas, out),
+        })
+    }
+}
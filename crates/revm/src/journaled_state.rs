@@ -11,6 +11,8 @@ use crate::{db::Database, AccountInfo, Log};
 pub struct JournaledState {
     /// Current state.
     pub state: State,
+    /// EIP-1153 transient storage, discarded at the end of the transaction.
+    pub transient_storage: TransientStorage,
     /// logs
     pub logs: Vec<Log>,
     /// how deep are we in call stack.
@@ -21,13 +23,24 @@ pub struct JournaledState {
     /// so we need to take care of that difference. Set this to false if you are handling
     /// legacy transactions
     pub is_before_spurious_dragon: bool,
+    /// EIP-6780 from Cancun on: SELFDESTRUCT only deletes accounts created in the same
+    /// transaction, others just send their balance to the target.
+    pub is_cancun: bool,
     /// It is assumed that precompiles start from 0x1 address and spand next N addresses.
     /// we are using that assumption here
     pub num_of_precompiles: usize,
+    /// If set, journal entries that were not reverted are moved to `change_log` on finalize
+    /// instead of being dropped.
+    pub keep_change_log: bool,
+    /// Committed journal entries in execution order. Only filled when `keep_change_log` is set.
+    pub change_log: Vec<JournalEntry>,
+    /// Account state that replaces the database state, see [JournaledState::apply_overrides].
+    pub overrides: Map<H160, AccountOverride>,
 }
 
 pub type State = Map<H160, Account>;
 pub type Storage = Map<U256, StorageSlot>;
+pub type TransientStorage = Map<(H160, U256), U256>;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 #[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
@@ -45,6 +58,8 @@ pub struct Account {
     /// used only for pre spurious dragon hardforks where exisnting and empty was two saparate states.
     /// it became same state after EIP-161: State trie clearing
     pub is_not_existing: bool,
+    /// Created in the current transaction, see [JournaledState::is_cancun].
+    pub is_created: bool,
 }
 
 impl Account {
@@ -59,6 +74,7 @@ impl Account {
             is_destroyed: false,
             is_touched: false,
             is_not_existing: true,
+            is_created: false,
         }
     }
 }
@@ -72,6 +88,55 @@ impl From<AccountInfo> for Account {
             is_destroyed: false,
             is_touched: false,
             is_not_existing: false,
+            is_created: false,
+        }
+    }
+}
+
+/// Account state that is used instead of the one in the database, like the state overrides of
+/// `eth_call`. Fields that are None are read from the database.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AccountOverride {
+    pub balance: Option<U256>,
+    pub nonce: Option<u64>,
+    /// Code of the account, its code hash is updated with it.
+    pub code: Option<Bytecode>,
+    pub storage: Option<StorageOverride>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StorageOverride {
+    /// Storage consists of only these slots, all others are zero.
+    Replace(Map<U256, U256>),
+    /// These slots replace the ones in the database, others are read from it.
+    Merge(Map<U256, U256>),
+}
+
+impl AccountOverride {
+    fn apply(&self, account: &mut Account) {
+        if let Some(balance) = self.balance {
+            account.info.balance = balance;
+        }
+        if let Some(nonce) = self.nonce {
+            account.info.nonce = nonce;
+        }
+        if let Some(code) = &self.code {
+            account.info.code_hash = code.hash();
+            account.info.code = Some(code.clone());
+        }
+        account.is_not_existing = false;
+    }
+
+    /// Overridden value of `key`, None if it is read from the database.
+    fn storage(&self, key: &U256) -> Option<U256> {
+        match &self.storage {
+            Some(StorageOverride::Replace(slots)) => {
+                Some(slots.get(key).copied().unwrap_or_default())
+            }
+            Some(StorageOverride::Merge(slots)) => slots.get(key).copied(),
+            None => None,
         }
     }
 }
@@ -146,10 +211,22 @@ pub enum JournalEntry {
         key: U256,
         had_value: Option<U256>, //if none, storage slot was cold loaded from db and needs to be removed
     },
+    /// Account created by CREATE, CREATE2 or a create transaction, see [Account::is_created].
+    /// Action: Mark account created
+    /// Revert: Unmark account created
+    AccountCreated { address: H160 },
     /// Code changed
     /// Action: Account code changed
     /// Revert: Revert to previous bytecode.
     CodeChange { address: H160, had_code: Bytecode },
+    /// Transient storage changed, see [JournaledState::tstore].
+    /// Action: Transient storage change
+    /// Revert: Revert to previous value
+    TransientStorageChange {
+        address: H160,
+        key: U256,
+        had_value: U256,
+    },
 }
 
 /// SubRoutine checkpoint that will help us to go back from this
@@ -162,11 +239,16 @@ impl JournaledState {
     pub fn new(num_of_precompiles: usize) -> JournaledState {
         Self {
             state: Map::new(),
+            transient_storage: Map::new(),
             logs: Vec::new(),
             journal: vec![vec![]],
             depth: 0,
             is_before_spurious_dragon: false,
+            is_cancun: false,
             num_of_precompiles,
+            keep_change_log: false,
+            change_log: Vec::new(),
+            overrides: Map::new(),
         }
     }
 
@@ -196,6 +278,7 @@ impl JournaledState {
     /// do cleanup and return modified state
     pub fn finalize(&mut self) -> (State, Vec<Log>) {
         let state = mem::take(&mut self.state);
+        self.transient_storage.clear();
 
         let state = state
             .into_iter()
@@ -203,11 +286,31 @@ impl JournaledState {
             .collect();
 
         let logs = mem::take(&mut self.logs);
-        self.journal = vec![vec![]];
+        let journal = mem::replace(&mut self.journal, vec![vec![]]);
+        if self.keep_change_log {
+            self.change_log.extend(journal.into_iter().flatten());
+        }
         self.depth = 0;
         (state, logs)
     }
 
+    /// Journal entries that survived execution, in the order they happened.
+    ///
+    /// Each entry describes how to undo one state change. Empty unless `keep_change_log` is set.
+    pub fn change_log(&self) -> &[JournalEntry] {
+        &self.change_log
+    }
+
+    /// Use `overrides` instead of the database state of their accounts.
+    ///
+    /// Overrides are applied when an account or slot is loaded from the database, so they do not
+    /// make accounts or slots warm. Execution changes overridden state like any other, and those
+    /// changes are reverted back to the overridden state. An override replaces an earlier one of
+    /// the same account, accounts that are already loaded are not changed.
+    pub fn apply_overrides(&mut self, overrides: Map<H160, AccountOverride>) {
+        self.overrides.extend(overrides);
+    }
+
     /// Use it with load_account function.
     pub fn account(&self, address: H160) -> &Account {
         self.state.get(&address).unwrap() // Always assume that acc is already loaded
@@ -228,7 +331,7 @@ impl JournaledState {
             .unwrap()
             .push(JournalEntry::CodeChange {
                 address,
-                had_code: code.clone(),
+                had_code: account.info.code.clone().unwrap_or_default(),
             });
 
         account.info.code_hash = code.hash();
@@ -331,16 +434,17 @@ impl JournaledState {
 
         acc.info.code_hash = KECCAK_EMPTY;
         acc.info.code = None;
+        acc.is_created = true;
 
-        self.journal
-            .last_mut()
-            .unwrap()
-            .push(JournalEntry::AccountTouched { address });
+        let journal = self.journal.last_mut().unwrap();
+        journal.push(JournalEntry::AccountTouched { address });
+        journal.push(JournalEntry::AccountCreated { address });
         Ok(true)
     }
 
     fn journal_revert(
         state: &mut State,
+        transient_storage: &mut TransientStorage,
         journal_entries: Vec<JournalEntry>,
         is_spurious_dragon_enabled: bool,
     ) {
@@ -380,6 +484,9 @@ impl JournaledState {
                     let to = state.get_mut(&to).unwrap();
                     to.info.balance -= balance;
                 }
+                JournalEntry::AccountCreated { address } => {
+                    state.get_mut(&address).unwrap().is_created = false;
+                }
                 JournalEntry::NonceChange { address } => {
                     state.get_mut(&address).unwrap().info.nonce -= 1;
                 }
@@ -400,10 +507,23 @@ impl JournaledState {
                     acc.info.code_hash = had_code.hash();
                     acc.info.code = Some(had_code);
                 }
+                JournalEntry::TransientStorageChange {
+                    address,
+                    key,
+                    had_value,
+                } => {
+                    if had_value.is_zero() {
+                        transient_storage.remove(&(address, key));
+                    } else {
+                        transient_storage.insert((address, key), had_value);
+                    }
+                }
             }
         }
     }
 
+    /// Start a new journal frame, changes after it can be undone with
+    /// [JournaledState::checkpoint_revert]. Checkpoints nest and are closed in reverse order.
     pub fn checkpoint(&mut self) -> JournalCheckpoint {
         let checkpoint = JournalCheckpoint {
             log_i: self.logs.len(),
@@ -414,13 +534,18 @@ impl JournaledState {
         checkpoint
     }
 
+    /// Close the last checkpoint and keep its changes. They are still undone if an enclosing
+    /// checkpoint is reverted.
     pub fn checkpoint_commit(&mut self) {
         self.depth -= 1;
     }
 
+    /// Undo account, storage, transient storage and log changes since `checkpoint`, including
+    /// those of checkpoints committed after it.
     pub fn checkpoint_revert(&mut self, checkpoint: JournalCheckpoint) {
         let is_spurious_dragon_enabled = !self.is_before_spurious_dragon;
         let state = &mut self.state;
+        let transient_storage = &mut self.transient_storage;
         self.depth -= 1;
         // iterate over last N journals sets and revert our global state
         let leng = self.journal.len();
@@ -428,7 +553,14 @@ impl JournaledState {
             .iter_mut()
             .rev()
             .take(leng - checkpoint.journal_i)
-            .for_each(|cs| Self::journal_revert(state, mem::take(cs), is_spurious_dragon_enabled));
+            .for_each(|cs| {
+                Self::journal_revert(
+                    state,
+                    transient_storage,
+                    mem::take(cs),
+                    is_spurious_dragon_enabled,
+                )
+            });
 
         self.logs.truncate(checkpoint.log_i);
         self.journal.truncate(checkpoint.journal_i);
@@ -444,9 +576,15 @@ impl JournaledState {
         let (is_cold, target_exists) = self.load_account_exist(target, db)?;
         // transfer all the balance
         let acc = self.state.get_mut(&address).unwrap();
-        let balance = mem::take(&mut acc.info.balance);
+        let is_destroyed = !self.is_cancun || acc.is_created;
+        // EIP-6780: an account that stays keeps its balance if it is its own target.
+        let balance = if is_destroyed || address != target {
+            mem::take(&mut acc.info.balance)
+        } else {
+            U256::zero()
+        };
         let previously_destroyed = acc.is_destroyed;
-        acc.is_destroyed = true;
+        acc.is_destroyed |= is_destroyed;
         // In case that target and destroyed addresses are same, balance will be lost.
         // ref: https://github.com/ethereum/go-ethereum/blob/141cd425310b503c5678e674a8c3872cf46b7086/core/vm/instructions.go#L832-L833
         // https://github.com/ethereum/go-ethereum/blob/141cd425310b503c5678e674a8c3872cf46b7086/core/state/statedb.go#L449
@@ -483,11 +621,14 @@ impl JournaledState {
         Ok(match self.state.entry(address) {
             Entry::Occupied(entry) => (entry.into_mut(), false),
             Entry::Vacant(vac) => {
-                let account = if let Some(account) = db.basic(address)? {
+                let mut account = if let Some(account) = db.basic(address)? {
                     account.into()
                 } else {
                     Account::new_not_existing()
                 };
+                if let Some(account_override) = self.overrides.get(&address) {
+                    account_override.apply(&mut account);
+                }
 
                 // journal loading of account. AccessList touch.
                 self.journal
@@ -550,8 +691,14 @@ impl JournaledState {
             Entry::Occupied(occ) => (occ.get().present_value, false),
             Entry::Vacant(vac) => {
                 // if storage was cleared, we dont need to ping db.
+                let overridden = self
+                    .overrides
+                    .get(&address)
+                    .and_then(|account_override| account_override.storage(&key));
                 let value = if account.storage_cleared {
                     U256::zero()
+                } else if let Some(value) = overridden {
+                    value
                 } else {
                     db.storage(address, key)?
                 };
@@ -607,13 +754,48 @@ impl JournaledState {
         Ok((slot.original_value, present, new, is_cold))
     }
 
+    /// Transient storage value of `address` at `key`, zero if it was not set.
+    pub fn tload(&mut self, address: H160, key: U256) -> U256 {
+        self.transient_storage
+            .get(&(address, key))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Set transient storage value of `address` at `key`. Zero values are removed.
+    pub fn tstore(&mut self, address: H160, key: U256, new: U256) {
+        let had_value = if new.is_zero() {
+            self.transient_storage.remove(&(address, key))
+        } else {
+            self.transient_storage.insert((address, key), new)
+        }
+        .unwrap_or_default();
+
+        if had_value != new {
+            self.journal
+                .last_mut()
+                .unwrap()
+                .push(JournalEntry::TransientStorageChange {
+                    address,
+                    key,
+                    had_value,
+                });
+        }
+    }
+
+    /// Logs emitted so far in emission order, those of reverted checkpoints are removed.
+    pub fn logs(&self) -> &[Log] {
+        &self.logs
+    }
+
     /// push log into subroutine
     pub fn log(&mut self, log: Log) {
         self.logs.push(log);
     }
 }
 
-fn is_precompile(address: H160, num_of_precompiles: usize) -> bool {
+/// Address is one of the `num_of_precompiles` precompiles starting at 0x01.
+pub(crate) fn is_precompile(address: H160, num_of_precompiles: usize) -> bool {
     if !address[..18].iter().all(|i| *i == 0) {
         return false;
     }
@@ -624,6 +806,69 @@ fn is_precompile(address: H160, num_of_precompiles: usize) -> bool {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::{opcode, test_utils::evm_with_db, InMemoryDB, TransactTo};
+    use bytes::Bytes;
+
+    #[test]
+    fn test_change_log_sstore_order() {
+        // sstore(1, 1), sstore(2, 2), sstore(1, 3)
+        let code = Bytes::from(vec![
+            opcode::PUSH1,
+            0x01,
+            opcode::PUSH1,
+            0x01,
+            opcode::SSTORE,
+            opcode::PUSH1,
+            0x02,
+            opcode::PUSH1,
+            0x02,
+            opcode::SSTORE,
+            opcode::PUSH1,
+            0x03,
+            opcode::PUSH1,
+            0x01,
+            opcode::SSTORE,
+            opcode::STOP,
+        ]);
+        let contract = H160::from_low_u64_be(0x1000);
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            contract,
+            AccountInfo::new(U256::zero(), 1, Bytecode::new_raw(code)),
+        );
+
+        let mut evm = evm_with_db(db);
+        evm.env.cfg.keep_change_log = true;
+        evm.env.tx.transact_to = TransactTo::Call(contract);
+        let (result, _) = evm.transact();
+        assert_eq!(result.exit_reason, Return::Stop);
+
+        let sstores: Vec<_> = result
+            .change_log
+            .iter()
+            .filter_map(|entry| match entry {
+                JournalEntry::StorageChage {
+                    address,
+                    key,
+                    had_value: Some(had_value),
+                } => Some((*address, *key, *had_value)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            sstores,
+            vec![
+                (contract, U256::from(1), U256::zero()),
+                (contract, U256::from(2), U256::zero()),
+                (contract, U256::from(1), U256::from(1)),
+            ]
+        );
+
+        // disabled by default.
+        evm.env.cfg.keep_change_log = false;
+        let (result, _) = evm.transact();
+        assert!(result.change_log.is_empty());
+    }
 
     #[test]
     fn test_is_precompile() {
@@ -672,4 +917,121 @@ mod test {
             "0x000..3 is precompile"
         );
     }
+
+    #[test]
+    fn test_transient_storage_revert() {
+        let address = H160::repeat_byte(0x01);
+        let key = U256::one();
+        let mut journal = JournaledState::new(0);
+        journal.tstore(address, key, U256::from(1));
+
+        let checkpoint = journal.checkpoint();
+        journal.tstore(address, key, U256::from(2));
+        journal.tstore(address, U256::from(2), U256::from(3));
+        assert_eq!(journal.tload(address, key), U256::from(2));
+        journal.checkpoint_revert(checkpoint);
+        assert_eq!(journal.tload(address, key), U256::from(1));
+        assert_eq!(journal.tload(address, U256::from(2)), U256::zero());
+
+        let checkpoint = journal.checkpoint();
+        journal.tstore(address, key, U256::zero());
+        journal.checkpoint_revert(checkpoint);
+        assert_eq!(journal.tload(address, key), U256::from(1));
+
+        journal.finalize();
+        assert!(journal.transient_storage.is_empty());
+    }
+
+    #[test]
+    fn test_nested_checkpoints() {
+        let address = H160::repeat_byte(0x01);
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(address, AccountInfo::from_balance(U256::one()));
+        let mut journal = JournaledState::new(0);
+        journal.load_account(address, &mut db).unwrap();
+        let slot = |journal: &mut JournaledState, db: &mut InMemoryDB, key: u64| {
+            journal.sload(address, U256::from(key), db).unwrap().0
+        };
+        let log = || Log {
+            address,
+            topics: Vec::new(),
+            data: Bytes::new(),
+        };
+
+        journal
+            .sstore(address, U256::one(), U256::from(1), &mut db)
+            .unwrap();
+        let outer = journal.checkpoint();
+        journal
+            .sstore(address, U256::one(), U256::from(2), &mut db)
+            .unwrap();
+        journal.log(log());
+
+        // committed inner changes are kept.
+        journal.checkpoint();
+        journal
+            .sstore(address, U256::from(2), U256::from(3), &mut db)
+            .unwrap();
+        journal.checkpoint_commit();
+        assert_eq!(slot(&mut journal, &mut db, 2), U256::from(3));
+
+        // reverted inner changes are undone, the outer ones are kept.
+        let reverted = journal.checkpoint();
+        journal
+            .sstore(address, U256::one(), U256::from(4), &mut db)
+            .unwrap();
+        journal.log(log());
+        journal.checkpoint_revert(reverted);
+        assert_eq!(slot(&mut journal, &mut db, 1), U256::from(2));
+        assert_eq!(journal.logs.len(), 1);
+
+        // reverting the outer checkpoint undoes the committed inner one too.
+        journal.checkpoint_revert(outer);
+        assert_eq!(slot(&mut journal, &mut db, 1), U256::from(1));
+        assert_eq!(slot(&mut journal, &mut db, 2), U256::zero());
+        assert!(journal.logs.is_empty());
+        assert_eq!(journal.depth(), 0);
+    }
+
+    #[test]
+    fn test_overrides_revert() {
+        let address = H160::repeat_byte(0x01);
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(address, AccountInfo::from_balance(U256::one()));
+        let mut journal = JournaledState::new(0);
+        journal.apply_overrides(Map::from([(
+            address,
+            AccountOverride {
+                balance: Some(U256::from(10)),
+                storage: Some(StorageOverride::Merge(Map::from([(
+                    U256::one(),
+                    U256::from(42),
+                )]))),
+                ..Default::default()
+            },
+        )]));
+
+        // loaded in the reverted checkpoint, loading it again applies the overrides again.
+        let checkpoint = journal.checkpoint();
+        let (account, _) = journal.load_account(address, &mut db).unwrap();
+        assert_eq!(account.info.balance, U256::from(10));
+        journal
+            .sstore(address, U256::one(), U256::from(3), &mut db)
+            .unwrap();
+        journal.checkpoint_revert(checkpoint);
+        assert!(!journal.state.contains_key(&address));
+
+        journal.load_account(address, &mut db).unwrap();
+        let checkpoint = journal.checkpoint();
+        journal
+            .sstore(address, U256::one(), U256::from(3), &mut db)
+            .unwrap();
+        journal
+            .transfer(&address, &H160::zero(), U256::from(4), &mut db)
+            .unwrap();
+        journal.checkpoint_revert(checkpoint);
+        let (value, _) = journal.sload(address, U256::one(), &mut db).unwrap();
+        assert_eq!(value, U256::from(42));
+        assert_eq!(journal.account(address).info.balance, U256::from(10));
+    }
 }
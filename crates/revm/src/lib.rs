@@ -1,6 +1,8 @@
 #![allow(dead_code)]
 //#![no_std]
 
+mod basic_host;
+mod bloom;
 pub mod db;
 mod evm;
 mod evm_impl;
@@ -9,27 +11,60 @@ mod inspector;
 mod instructions;
 mod interpreter;
 mod journaled_state;
+mod mock_host;
 mod models;
+mod revert_reason;
 mod specification;
+#[cfg(test)]
+mod test_utils;
+mod transaction;
 
+pub use basic_host::BasicHost;
+pub use bloom::Bloom;
 pub use evm_impl::{create2_address, create_address, EVMData, Host};
 
 pub type DummyStateDB = InMemoryDB;
 
 pub use db::{Database, DatabaseCommit, InMemoryDB};
-pub use evm::{evm_inner, new, EVM};
+pub use evm::{evm_inner, evm_inner_with_options, new, EVMOptions, ESTIMATE_GAS_BUFFER, EVM};
 pub use gas::Gas;
-pub use inspector::{GasInspector, Inspector, NoOpInspector};
+#[cfg(feature = "std")]
+pub use inspector::binary_trace::{BinaryTraceReader, BinaryTraceWriter, TraceRecord};
+#[cfg(feature = "tracing")]
+pub use inspector::tracing::TracingInspector;
+pub use inspector::{
+    access_list::AccessListInspector,
+    balance_transfer::{BalanceTransfer, BalanceTransferInspector},
+    call_depth::CallDepthInspector,
+    call_output::CallOutputInspector,
+    call_trace::{CallFrame, CallKind, CallTraceInspector},
+    memory_profile::MemoryProfileInspector,
+    multi::MultiInspector,
+    opcode_gas::{GasProfiler, OpcodeGasInspector, OpcodeGasStats},
+    revert_context::RevertInspector,
+    storage_access::{StorageAccess, StorageAccessInspector},
+    GasInspector, Inspector, NoOpInspector,
+};
 pub use instructions::{
-    opcode::{self, spec_opcode_gas, OpCode, OPCODE_JUMPMAP},
+    disassembler::{disassemble, reachable_code, split_deploy_code, InstructionIter},
+    opcode::{self, spec_opcode_gas, OpCode, OpCodeInfo, OPCODE_JUMPMAP},
     Return,
 };
 pub use interpreter::{
-    Bytecode, BytecodeLocked, BytecodeState, Contract, Interpreter, Memory, Stack,
+    validate_bytecode, validate_eof, Bytecode, BytecodeError, BytecodeLocked, BytecodeState,
+    Contract, EofError, EofHeader, Interpreter, InterpreterSnapshot, Memory, SnapshotError, Stack,
+    StackWord, SubCall, SubCallResult, EOF_MAGIC, EOF_VERSION,
+};
+pub use journaled_state::{
+    Account, AccountOverride, JournalEntry, JournaledState, StorageOverride,
 };
-pub use journaled_state::{Account, JournalEntry, JournaledState};
+pub use mock_host::MockHost;
 pub use models::*;
+pub use revert_reason::{decode_revert_reason, RevertReason, ERROR_SELECTOR, PANIC_SELECTOR};
 pub use specification::*;
+pub use transaction::{
+    decode_raw_tx, recover_sender, RawTxError, ACCESS_LIST_TX_TYPE, DYNAMIC_FEE_TX_TYPE,
+};
 
 extern crate alloc;
 
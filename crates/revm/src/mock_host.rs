@@ -0,0 +1,179 @@
+use crate::{
+    alloc::vec::Vec, interpreter::bytecode::Bytecode, AccountInfo, BasicHost, Database, Env,
+    InMemoryDB, Log,
+};
+use bytes::Bytes;
+use hashbrown::HashMap as Map;
+use primitive_types::{H160, H256, U256};
+
+/// [BasicHost] over an [InMemoryDB], for tests that run an [crate::Interpreter] directly.
+///
+/// Accounts, storage and block environment are set with the `with_*` builder methods. Storage
+/// writes go to the database, transient storage is kept until the host is dropped and logs are
+/// collected in [MockHost::logs]. Sub calls and creates revert as with every [BasicHost].
+#[derive(Clone, Debug, Default)]
+pub struct MockHost {
+    pub db: InMemoryDB,
+    pub env: Env,
+    pub logs: Vec<Log>,
+    /// Value of every written slot before its first write.
+    original: Map<(H160, U256), U256>,
+    transient: Map<(H160, U256), U256>,
+}
+
+impl MockHost {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_balance(mut self, address: H160, balance: U256) -> Self {
+        self.update_info(address, |info| info.balance = balance);
+        self
+    }
+
+    pub fn with_code(mut self, address: H160, code: Bytecode) -> Self {
+        self.update_info(address, |info| info.code = Some(code));
+        self
+    }
+
+    pub fn with_storage(mut self, address: H160, index: U256, value: U256) -> Self {
+        self.set_storage(address, index, value);
+        self
+    }
+
+    pub fn with_block_number(mut self, number: U256) -> Self {
+        self.env.block.number = number;
+        self
+    }
+
+    /// Hash returned by BLOCKHASH for `number`, others are the hash of the number.
+    pub fn with_block_hash(mut self, number: U256, hash: H256) -> Self {
+        self.db.block_hashes.insert(number, hash);
+        self
+    }
+
+    /// Current value of a storage slot.
+    pub fn storage(&mut self, address: H160, index: U256) -> U256 {
+        self.db.storage(address, index).unwrap_or_default()
+    }
+
+    fn update_info(&mut self, address: H160, update: impl FnOnce(&mut AccountInfo)) {
+        let mut info = self
+            .db
+            .accounts
+            .get(&address)
+            .map(|account| account.info.clone())
+            .unwrap_or_default();
+        update(&mut info);
+        self.db.insert_account_info(address, info);
+    }
+
+    fn set_storage(&mut self, address: H160, index: U256, value: U256) {
+        self.db
+            .accounts
+            .entry(address)
+            .or_default()
+            .storage
+            .insert(index, value);
+    }
+}
+
+impl BasicHost for MockHost {
+    type DB = InMemoryDB;
+
+    fn db(&mut self) -> &mut InMemoryDB {
+        &mut self.db
+    }
+
+    fn env(&mut self) -> &mut Env {
+        &mut self.env
+    }
+
+    fn override_sstore(
+        &mut self,
+        address: H160,
+        index: U256,
+        value: U256,
+    ) -> Option<(U256, U256, U256, bool)> {
+        let present = self.storage(address, index);
+        let original = *self.original.entry((address, index)).or_insert(present);
+        self.set_storage(address, index, value);
+        Some((original, present, value, false))
+    }
+
+    fn override_tload(&mut self, address: H160, index: U256) -> U256 {
+        self.transient
+            .get(&(address, index))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn override_tstore(&mut self, address: H160, index: U256, value: U256) {
+        self.transient.insert((address, index), value);
+    }
+
+    fn override_log(&mut self, address: H160, topics: Vec<H256>, data: Bytes) {
+        self.logs.push(Log {
+            address,
+            topics,
+            data,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MockHost;
+    use crate::{opcode, Bytecode, Contract, Host, Interpreter, LatestSpec, Return};
+    use bytes::Bytes;
+    use primitive_types::{H160, H256, U256};
+
+    #[test]
+    fn test_store_and_log() {
+        let address = H160::repeat_byte(0xaa);
+        // slot 1 = slot 0 + BLOCKHASH(number - 1), then LOG0 of nothing.
+        let code = Bytecode::new_raw(Bytes::from(vec![
+            opcode::PUSH1,
+            0x00,
+            opcode::SLOAD,
+            opcode::PUSH1,
+            0x01,
+            opcode::NUMBER,
+            opcode::SUB,
+            opcode::BLOCKHASH,
+            opcode::ADD,
+            opcode::PUSH1,
+            0x01,
+            opcode::SSTORE,
+            opcode::PUSH1,
+            0x00,
+            opcode::PUSH1,
+            0x00,
+            opcode::LOG0,
+        ]));
+        let mut host = MockHost::new()
+            .with_balance(address, U256::from(7))
+            .with_code(address, code.clone())
+            .with_storage(address, U256::zero(), U256::from(2))
+            .with_block_number(U256::from(10))
+            .with_block_hash(U256::from(9), H256::from_low_u64_be(40));
+        assert_eq!(host.balance(address), Some((U256::from(7), false)));
+        assert_eq!(host.code(address).unwrap().0.hash(), code.hash());
+
+        let contract = Contract::new::<LatestSpec>(
+            Bytes::new(),
+            host.code(address).unwrap().0,
+            address,
+            H160::zero(),
+            U256::zero(),
+        );
+        let mut interp = Interpreter::new::<LatestSpec>(contract, 1_000_000);
+        assert_eq!(interp.run::<_, LatestSpec>(&mut host), Return::Stop);
+
+        assert_eq!(host.storage(address, U256::one()), U256::from(42));
+        assert_eq!(host.logs.len(), 1);
+        assert_eq!(host.logs[0].address, address);
+        // balance and code are kept by the builder methods.
+        assert_eq!(host.balance(address), Some((U256::from(7), false)));
+    }
+}
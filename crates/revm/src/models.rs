@@ -1,6 +1,8 @@
 use core::cmp::min;
 
-use crate::{alloc::vec::Vec, interpreter::bytecode::Bytecode, Return, SpecId};
+use crate::{
+    alloc::vec::Vec, interpreter::bytecode::Bytecode, Interpreter, JournalEntry, Return, SpecId,
+};
 use bytes::Bytes;
 use primitive_types::{H160, H256, U256};
 
@@ -72,6 +74,7 @@ impl AccountInfo {
 }
 
 /// Inputs for a call.
+#[derive(Clone, Debug)]
 #[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CallInputs {
     /// The target of the call.
@@ -87,6 +90,7 @@ pub struct CallInputs {
     pub context: CallContext,
 }
 
+#[derive(Clone, Debug)]
 #[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CreateInputs {
     pub caller: H160,
@@ -97,6 +101,153 @@ pub struct CreateInputs {
     pub gas_limit: u64,
 }
 
+impl CallInputs {
+    /// Builder of a plain CALL from the zero address with no value, input and gas.
+    pub fn builder() -> CallInputsBuilder {
+        CallInputsBuilder::default()
+    }
+}
+
+/// Builder of [CallInputs], see [CallInputs::builder].
+///
+/// Only CALL and STATICCALL are built, the context of CALLCODE and DELEGATECALL depends on the
+/// calling frame and is set on the built inputs.
+#[derive(Clone, Debug, Default)]
+pub struct CallInputsBuilder {
+    caller: H160,
+    contract: H160,
+    value: U256,
+    input: Bytes,
+    gas_limit: u64,
+    is_static: bool,
+}
+
+impl CallInputsBuilder {
+    pub fn caller(mut self, caller: H160) -> Self {
+        self.caller = caller;
+        self
+    }
+
+    pub fn contract(mut self, contract: H160) -> Self {
+        self.contract = contract;
+        self
+    }
+
+    /// Value transferred from the caller to the contract, ignored by a static call.
+    pub fn value(mut self, value: U256) -> Self {
+        self.value = value;
+        self
+    }
+
+    pub fn input(mut self, input: Bytes) -> Self {
+        self.input = input;
+        self
+    }
+
+    pub fn gas_limit(mut self, gas_limit: u64) -> Self {
+        self.gas_limit = gas_limit;
+        self
+    }
+
+    /// Build a STATICCALL instead of a CALL.
+    pub fn is_static(mut self, is_static: bool) -> Self {
+        self.is_static = is_static;
+        self
+    }
+
+    pub fn to_call(self) -> CallInputs {
+        let (scheme, value) = if self.is_static {
+            (CallScheme::StaticCall, U256::zero())
+        } else {
+            (CallScheme::Call, self.value)
+        };
+        CallInputs {
+            contract: self.contract,
+            transfer: Transfer {
+                source: self.caller,
+                target: self.contract,
+                value,
+            },
+            input: self.input,
+            gas_limit: self.gas_limit,
+            context: CallContext {
+                address: self.contract,
+                caller: self.caller,
+                code_address: self.contract,
+                apparent_value: value,
+                scheme,
+            },
+        }
+    }
+}
+
+impl CreateInputs {
+    /// Builder of a plain CREATE from the zero address with no value, init code and gas.
+    pub fn builder() -> CreateInputsBuilder {
+        CreateInputsBuilder::default()
+    }
+}
+
+/// Builder of [CreateInputs], see [CreateInputs::builder].
+#[derive(Clone, Debug)]
+pub struct CreateInputsBuilder {
+    caller: H160,
+    scheme: CreateScheme,
+    value: U256,
+    init_code: Bytes,
+    gas_limit: u64,
+}
+
+impl Default for CreateInputsBuilder {
+    fn default() -> Self {
+        Self {
+            caller: H160::zero(),
+            scheme: CreateScheme::Create,
+            value: U256::zero(),
+            init_code: Bytes::new(),
+            gas_limit: 0,
+        }
+    }
+}
+
+impl CreateInputsBuilder {
+    pub fn caller(mut self, caller: H160) -> Self {
+        self.caller = caller;
+        self
+    }
+
+    /// Build a CREATE2 with `salt` instead of a CREATE.
+    pub fn salt(mut self, salt: U256) -> Self {
+        self.scheme = CreateScheme::Create2 { salt };
+        self
+    }
+
+    pub fn value(mut self, value: U256) -> Self {
+        self.value = value;
+        self
+    }
+
+    pub fn init_code(mut self, init_code: Bytes) -> Self {
+        self.init_code = init_code;
+        self
+    }
+
+    pub fn gas_limit(mut self, gas_limit: u64) -> Self {
+        self.gas_limit = gas_limit;
+        self
+    }
+
+    pub fn to_create(self) -> CreateInputs {
+        CreateInputs {
+            caller: self.caller,
+            scheme: self.scheme,
+            value: self.value,
+            init_code: self.init_code,
+            gas_limit: self.gas_limit,
+        }
+    }
+}
+
 pub struct CreateData {}
 
 #[derive(Clone, Debug)]
@@ -198,6 +349,8 @@ pub struct BlockEnv {
     /// basefee is added in EIP1559 London upgrade
     pub basefee: U256,
     pub gas_limit: U256,
+    /// EIP-4844 price of blob gas, read by BLOBBASEFEE.
+    pub blob_base_fee: U256,
 }
 
 #[derive(Clone, Debug)]
@@ -215,6 +368,26 @@ pub struct TxEnv {
     pub chain_id: Option<u64>,
     pub nonce: Option<u64>,
     pub access_list: Vec<(H160, Vec<U256>)>,
+    /// EIP-7702 authorizations of a set code transaction, applied in order before the call.
+    pub authorization_list: Vec<Authorization>,
+    /// EIP-4844 versioned hashes of the blobs of the transaction, read by BLOBHASH.
+    pub blob_hashes: Vec<H256>,
+}
+
+/// EIP-7702 authorization to set the code of `authority` to a delegation to `address`.
+///
+/// The signature is checked by whoever builds the [TxEnv], only the signer recovered from it is
+/// kept, same as [TxEnv::caller].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Authorization {
+    /// Zero for any chain.
+    pub chain_id: U256,
+    /// Account whose code the authority runs, zero to clear the delegation.
+    pub address: H160,
+    pub nonce: u64,
+    /// Signer of the authorization, None if its signature is invalid.
+    pub authority: Option<H160>,
 }
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
@@ -231,15 +404,84 @@ pub struct CfgEnv {
     /// Default: Analyse
     pub perf_analyse_created_bytecodes: AnalysisKind,
     /// If some it will effects EIP-170: Contract code size limit. Usefull to increase this because of tests.
-    /// By default it is 0x6000 (~25kb).
+    /// By default it is 0x6000 (~25kb). The EIP-3860 init code limit is twice this.
     pub limit_contract_code_size: Option<usize>,
-    /// A hard memory limit in bytes beyond which [Memory] cannot be resized.
+    /// A hard memory limit in bytes beyond which [Memory] cannot be resized, growing memory above
+    /// it fails the frame with [crate::Return::MemoryLimitOOG]. `None` is unlimited.
     ///
     /// In cases where the gas limit may be extraordinarily high, it is recommended to set this to
-    /// a sane value to prevent memory allocation panics. Defaults to `2^32 - 1` bytes per
-    /// EIP-1985.
-    #[cfg(feature = "memory_limit")]
-    pub memory_limit: u64,
+    /// a sane value to prevent memory allocation panics.
+    /// Default: None, or `2^32 - 1` bytes per EIP-1985 with the `memory_limit` feature.
+    pub memory_limit: Option<u64>,
+    /// Keep the journal of committed state changes and return it in [ExecutionResult::change_log].
+    /// Default: false
+    pub keep_change_log: bool,
+    /// How static gas is charged, see [GasBlockGranularity].
+    /// Default: WholeBlock
+    pub gas_block_granularity: GasBlockGranularity,
+    /// Limit in bytes on the memory of all call frames together. Growing memory above it fails
+    /// the frame with OutOfGas.
+    /// Default: None
+    pub total_memory_limit: Option<usize>,
+    /// Memoize STATICCALLs within a transaction and reuse the result of an identical call with the
+    /// same caller, callee and input if no state was written in between.
+    ///
+    /// This is meant for simulation of view heavy calls and is NOT consensus safe: a cache hit
+    /// charges the gas spent by the first call, does not warm the accounts and slots the call would
+    /// have accessed, does not touch them and skips inspector callbacks of the sub call. The result
+    /// is only reused if the call got at least as much gas as the first call spent, but code that
+    /// inspects `GAS` can still observe the difference. Any state write and any reverted call frame
+    /// clears the cache.
+    /// Default: false
+    pub static_call_cache: bool,
+    /// Factor applied to the gas cost of the precompile at the given address, for chains that
+    /// reprice precompiles.
+    /// Default: empty
+    pub precompile_gas_multipliers: Vec<(H160, u64)>,
+    /// Opcodes that halt the frame with [Return::OpcodeDisabled], to run untrusted code without
+    /// some capabilities like CALL or SELFDESTRUCT. Not consensus compatible if set.
+    /// Default: empty
+    pub disabled_opcodes: OpcodeSet,
+    /// Handlers of opcodes that are undefined in the spec, for research forks that try out new
+    /// opcodes. A handler charges its own gas, defined opcodes can not be overridden and
+    /// undefined opcodes without a handler halt with [Return::OpcodeNotFound]. Not consensus
+    /// compatible if set.
+    /// Default: empty
+    #[cfg_attr(feature = "with-serde", serde(skip))]
+    pub opcode_handlers: Vec<(u8, OpcodeHandler)>,
+    /// Skip all gas accounting at runtime, like the `no_gas_measuring` feature does at compile
+    /// time. For gasless simulation like symbolic execution: nothing runs out of gas and no
+    /// intrinsic gas is charged. Same as with the feature the gas limit paid up front is not given
+    /// back, so use a zero gas price. Not consensus compatible if set.
+    /// Default: false
+    pub disable_gas: bool,
+    /// Check that the instruction pointer is inside the bytecode before every opcode and halt
+    /// with [Return::OutOfOffset] if it is not. Execution relies on analysis padding the bytecode
+    /// with STOP and on validated jumps, this guards against a corrupted instruction pointer at a
+    /// small cost per opcode.
+    /// Default: false
+    pub checked_execution: bool,
+    /// Depth of nested calls and creates above which they fail with [Return::CallTooDeep]. The
+    /// transaction's call is at depth one.
+    /// Default: 1024
+    pub call_stack_limit: u64,
+    /// Limit in bytes on the transaction data, larger transactions are rejected with
+    /// [Return::InputSizeLimit] before any gas is charged. For running untrusted input.
+    /// Default: None
+    pub max_input_size: Option<usize>,
+    /// Limit in bytes on the code a call or create frame runs, checked before the code is
+    /// analysed. Frames with larger code fail with [Return::CodeSizeLimit], a create transaction
+    /// is rejected up front. Unlike the EIP-170 limit of [CfgEnv::max_code_size] this bounds the
+    /// code that is executed, not the code that is deployed.
+    /// Default: None
+    pub max_executed_code_size: Option<usize>,
+    /// Run pairs of adjacent opcodes like `PUSH1 x ADD` as one fused opcode, which saves the
+    /// dispatch of the second opcode. Results and gas are the same as without fusion. The pairs
+    /// of a frame's code are found before its first opcode, which costs a pass over the code, so
+    /// this pays off for loops and not for short calls into large contracts. Not used while
+    /// inspecting, with a step limit or when gas is charged per opcode or memory operation.
+    /// Default: false
+    pub fuse_opcodes: bool,
 }
 
 #[derive(Clone, Default, Debug, Eq, PartialEq)]
@@ -251,6 +493,91 @@ pub enum AnalysisKind {
     Analyse,
 }
 
+/// Granularity at which the interpreter charges static opcode gas.
+///
+/// Precomputed gas blocks are fastest, but an out of gas error is reported at the start of the
+/// block and not at the opcode that ran out of gas. The finer modes trade speed for an exact
+/// location.
+#[derive(Clone, Copy, Default, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GasBlockGranularity {
+    /// Charge gas of the whole basic block at its start.
+    #[default]
+    WholeBlock,
+    /// Charge gas before every opcode.
+    PerOpcode,
+    /// Like [GasBlockGranularity::WholeBlock], but blocks are also split before and after every
+    /// opcode that touches memory. Memory expansion gas is charged before static gas of the
+    /// following opcodes, so running out of gas in memory expansion happens where geth reports it.
+    PerMemoryOp,
+}
+
+/// Handler of an undefined opcode, see [CfgEnv::opcode_handlers]. Runs with the program counter
+/// past the opcode.
+pub type OpcodeHandler = fn(&mut Interpreter) -> Return;
+
+/// Set of opcodes as a bitmap.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OpcodeSet([u64; 4]);
+
+impl OpcodeSet {
+    pub fn new(opcodes: &[u8]) -> Self {
+        let mut set = Self::default();
+        for &opcode in opcodes {
+            set.insert(opcode);
+        }
+        set
+    }
+
+    pub fn insert(&mut self, opcode: u8) {
+        self.0[opcode as usize >> 6] |= 1 << (opcode & 63);
+    }
+
+    pub fn remove(&mut self, opcode: u8) {
+        self.0[opcode as usize >> 6] &= !(1 << (opcode & 63));
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, opcode: u8) -> bool {
+        self.0[opcode as usize >> 6] & (1 << (opcode & 63)) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == [0; 4]
+    }
+}
+
+/// EIP-170 limit of the deployed code size.
+pub const MAX_CODE_SIZE: usize = 0x6000;
+/// EIP-3860 limit of the init code size.
+pub const MAX_INITCODE_SIZE: usize = 2 * MAX_CODE_SIZE;
+
+impl CfgEnv {
+    /// Handler of an undefined `opcode`, see [CfgEnv::opcode_handlers].
+    pub fn opcode_handler(&self, opcode: u8) -> Option<OpcodeHandler> {
+        self.opcode_handlers
+            .iter()
+            .find(|(handled, _)| *handled == opcode)
+            .map(|(_, handler)| *handler)
+    }
+
+    /// Deployed code size limit, [CfgEnv::limit_contract_code_size] or [MAX_CODE_SIZE].
+    pub fn max_code_size(&self) -> usize {
+        self.limit_contract_code_size.unwrap_or(MAX_CODE_SIZE)
+    }
+
+    /// Whether `len` bytes of code exceed [CfgEnv::max_executed_code_size].
+    pub fn exceeds_executed_code_size(&self, len: usize) -> bool {
+        matches!(self.max_executed_code_size, Some(limit) if len > limit)
+    }
+
+    /// Init code size limit, twice [CfgEnv::max_code_size].
+    pub fn max_initcode_size(&self) -> usize {
+        self.max_code_size().saturating_mul(2)
+    }
+}
+
 impl Default for CfgEnv {
     fn default() -> CfgEnv {
         CfgEnv {
@@ -259,8 +586,20 @@ impl Default for CfgEnv {
             perf_all_precompiles_have_balance: false,
             perf_analyse_created_bytecodes: Default::default(),
             limit_contract_code_size: None,
-            #[cfg(feature = "memory_limit")]
-            memory_limit: 2u64.pow(32) - 1,
+            memory_limit: cfg!(feature = "memory_limit").then(|| 2u64.pow(32) - 1),
+            keep_change_log: false,
+            gas_block_granularity: GasBlockGranularity::default(),
+            total_memory_limit: None,
+            static_call_cache: false,
+            precompile_gas_multipliers: Vec::new(),
+            disabled_opcodes: OpcodeSet::default(),
+            opcode_handlers: Vec::new(),
+            disable_gas: false,
+            checked_execution: false,
+            call_stack_limit: crate::interpreter::CALL_STACK_LIMIT,
+            max_input_size: None,
+            max_executed_code_size: None,
+            fuse_opcodes: false,
         }
     }
 }
@@ -274,6 +613,7 @@ impl Default for BlockEnv {
             timestamp: U256::one(),
             difficulty: U256::zero(),
             basefee: U256::zero(),
+            blob_base_fee: U256::zero(),
         }
     }
 }
@@ -291,23 +631,34 @@ impl Default for TxEnv {
             chain_id: None,
             nonce: None,
             access_list: Vec::new(),
+            authorization_list: Vec::new(),
+            blob_hashes: Vec::new(),
         }
     }
 }
 
 impl Env {
+    /// Gas price the sender pays, see [effective_gas_price]. `tx.gas_price` is the max fee of
+    /// EIP-1559 transactions, which have a `tx.gas_priority_fee`, and the price of others.
     pub fn effective_gas_price(&self) -> U256 {
-        if self.tx.gas_priority_fee.is_none() {
-            self.tx.gas_price
-        } else {
-            min(
-                self.tx.gas_price,
-                self.block.basefee + self.tx.gas_priority_fee.unwrap(),
-            )
+        match self.tx.gas_priority_fee {
+            Some(max_priority_fee) => {
+                effective_gas_price(self.block.basefee, self.tx.gas_price, max_priority_fee)
+            }
+            None => self.tx.gas_price,
         }
     }
 }
 
+/// EIP-1559 gas price of a transaction: the base fee plus the priority fee, capped at the max fee.
+///
+/// The coinbase gets the effective price minus the base fee. Transactions with a max fee below
+/// the base fee are invalid and rejected with [Return::GasPriceLessThenBasefee] before this is
+/// used.
+pub fn effective_gas_price(base_fee: U256, max_fee: U256, max_priority_fee: U256) -> U256 {
+    min(max_fee, base_fee.saturating_add(max_priority_fee))
+}
+
 /// Transfer from source to target, with given value.
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
@@ -408,13 +759,46 @@ pub(crate) mod serde_hex_bytes_opt {
     }
 }
 
+/// Parts of the gas charged for a transaction, see [ExecutionResult::gas_breakdown].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct GasBreakdown {
+    /// Base, calldata, create and access list cost paid before execution.
+    pub intrinsic: u64,
+    /// Gas spent by execution, without the refund.
+    pub execution: u64,
+    /// Refund given back, already capped.
+    pub refunded: u64,
+    /// EIP-4844 blob gas of the blob hashes from Cancun on, paid at the blob base fee and not part
+    /// of [GasBreakdown::total].
+    pub blob: u64,
+}
+
+impl GasBreakdown {
+    /// Charged gas, equal to [ExecutionResult::gas_used].
+    pub fn total(&self) -> u64 {
+        self.intrinsic + self.execution - self.refunded
+    }
+}
+
+/// Result of a transaction with everything needed for its receipt.
+///
+/// Gas adds up as `gas_used + gas_refunded` is the gas spent before the refund, and the sender
+/// pays for `gas_used` out of `gas_limit`.
 #[derive(Clone, Debug)]
 pub struct ExecutionResult {
     pub exit_reason: Return,
     pub out: TransactOut,
+    /// Gas charged to the sender, after the refund.
     pub gas_used: u64,
+    /// Refund given back, already capped by EIP-3529 or the pre-London quotient.
     pub gas_refunded: u64,
+    /// Gas limit of the transaction.
+    pub gas_limit: u64,
+    /// Split of `gas_used`, None if the transaction was rejected before execution.
+    pub gas_breakdown: Option<GasBreakdown>,
     pub logs: Vec<Log>,
+    /// State changes as journal entries, see [CfgEnv::keep_change_log].
+    pub change_log: Vec<JournalEntry>,
 }
 
 impl ExecutionResult {
@@ -424,7 +808,283 @@ impl ExecutionResult {
             out: TransactOut::None,
             gas_used: 0,
             gas_refunded: 0,
+            gas_limit: 0,
+            gas_breakdown: None,
             logs: Vec::new(),
+            change_log: Vec::new(),
         }
     }
+
+    /// Logs of the transaction in emission order. Logs of reverted frames are dropped, so there
+    /// are none if the transaction reverted.
+    pub fn logs(&self) -> &[Log] {
+        &self.logs
+    }
+
+    /// Status of the receipt, true if the transaction did not revert or halt.
+    pub fn is_success(&self) -> bool {
+        self.exit_reason.is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![cfg_attr(feature = "no_gas_measuring", allow(unused_imports))]
+
+    use super::{
+        effective_gas_price, CallInputs, CallScheme, CreateInputs, CreateScheme, Log, TransactTo,
+    };
+    use crate::{opcode, test_utils::evm_with_db, AccountInfo, Bytecode, InMemoryDB, Return};
+    use bytes::Bytes;
+    use primitive_types::{H160, H256, U256};
+
+    #[test]
+    fn test_call_inputs_builder() {
+        let (caller, contract) = (H160::repeat_byte(0x10), H160::repeat_byte(0x20));
+        let inputs = CallInputs::builder()
+            .caller(caller)
+            .contract(contract)
+            .value(U256::from(5))
+            .gas_limit(21_000)
+            .to_call();
+        assert_eq!(inputs.contract, contract);
+        assert_eq!(
+            (inputs.transfer.source, inputs.transfer.target),
+            (caller, contract)
+        );
+        assert_eq!(inputs.transfer.value, U256::from(5));
+        assert_eq!(inputs.input, Bytes::new());
+        assert_eq!(inputs.gas_limit, 21_000);
+        assert_eq!(inputs.context.address, contract);
+        assert_eq!(inputs.context.caller, caller);
+        assert_eq!(inputs.context.code_address, contract);
+        assert_eq!(inputs.context.apparent_value, U256::from(5));
+        assert_eq!(inputs.context.scheme, CallScheme::Call);
+
+        // a static call transfers nothing.
+        let inputs = CallInputs::builder()
+            .value(U256::from(5))
+            .is_static(true)
+            .to_call();
+        assert_eq!(inputs.transfer.value, U256::zero());
+        assert_eq!(inputs.context.apparent_value, U256::zero());
+        assert_eq!(inputs.context.scheme, CallScheme::StaticCall);
+    }
+
+    #[test]
+    fn test_create_inputs_builder() {
+        let inputs = CreateInputs::builder().to_create();
+        assert_eq!(inputs.scheme, CreateScheme::Create);
+
+        let inputs = CreateInputs::builder()
+            .caller(H160::repeat_byte(0x10))
+            .salt(U256::from(7))
+            .init_code(Bytes::from_static(&[0x00]))
+            .gas_limit(100_000)
+            .to_create();
+        assert_eq!(inputs.caller, H160::repeat_byte(0x10));
+        assert_eq!(
+            inputs.scheme,
+            CreateScheme::Create2 {
+                salt: U256::from(7)
+            }
+        );
+        assert_eq!(inputs.value, U256::zero());
+        assert_eq!(inputs.init_code, Bytes::from_static(&[0x00]));
+        assert_eq!(inputs.gas_limit, 100_000);
+    }
+
+    /// LOG1 without data for every topic.
+    fn log_topics(topics: &[u8]) -> Vec<u8> {
+        topics
+            .iter()
+            .flat_map(|topic| {
+                [
+                    opcode::PUSH1,
+                    *topic,
+                    opcode::PUSH1,
+                    0x00,
+                    opcode::PUSH1,
+                    0x00,
+                    opcode::LOG1,
+                ]
+            })
+            .collect()
+    }
+
+    fn run_logs(accounts: &[(H160, Vec<u8>)]) -> (Return, Vec<Log>) {
+        let mut db = InMemoryDB::default();
+        for (address, code) in accounts {
+            db.insert_account_info(
+                *address,
+                AccountInfo::new(U256::zero(), 1, Bytecode::new_raw(code.clone().into())),
+            );
+        }
+        let mut evm = evm_with_db(db);
+        evm.env.tx.transact_to = TransactTo::Call(accounts[0].0);
+        let (result, _) = evm.transact();
+        (result.exit_reason, result.logs().to_vec())
+    }
+
+    #[test]
+    fn test_logs() {
+        let contract = H160::repeat_byte(0x20);
+        let log = |topic: u64| Log {
+            address: contract,
+            topics: vec![H256::from_low_u64_be(topic)],
+            data: Bytes::new(),
+        };
+        let mut code = log_topics(&[1, 2]);
+        code.push(opcode::STOP);
+        assert_eq!(
+            run_logs(&[(contract, code.clone())]),
+            (Return::Stop, vec![log(1), log(2)])
+        );
+
+        code.pop();
+        code.extend([opcode::PUSH1, 0x00, opcode::DUP1, opcode::REVERT]);
+        assert_eq!(
+            run_logs(&[(contract, code.clone())]),
+            (Return::Revert, Vec::new())
+        );
+
+        // only the logs of the reverted call are dropped.
+        let reverting = H160::repeat_byte(0x30);
+        let mut caller = vec![
+            opcode::PUSH1,
+            0x00,
+            opcode::DUP1,
+            opcode::DUP1,
+            opcode::DUP1,
+        ];
+        caller.extend([opcode::DUP1, opcode::PUSH20]);
+        caller.extend_from_slice(reverting.as_bytes());
+        caller.extend([opcode::GAS, opcode::CALL, opcode::POP]);
+        caller.extend(log_topics(&[3]));
+        caller.push(opcode::STOP);
+        assert_eq!(
+            run_logs(&[(contract, caller), (reverting, code)]),
+            (Return::Stop, vec![log(3)])
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_effective_gas_price() {
+        let price = |base_fee: u64, max_fee: u64, max_priority_fee: u64| {
+            effective_gas_price(base_fee.into(), max_fee.into(), max_priority_fee.into())
+        };
+        assert_eq!(price(10, 15, 3), U256::from(13));
+        // the priority fee is capped by the max fee.
+        assert_eq!(price(10, 12, 3), U256::from(12));
+        assert_eq!(price(15, 15, 3), U256::from(15));
+
+        let (caller, coinbase) = (H160::repeat_byte(0x10), H160::repeat_byte(0xcb));
+        let transfer = |base_fee: u64| {
+            let mut db = InMemoryDB::default();
+            db.insert_account_info(caller, AccountInfo::from_balance(U256::from(1_000_000)));
+            let mut evm = evm_with_db(db);
+            evm.env.block.basefee = base_fee.into();
+            evm.env.block.coinbase = coinbase;
+            evm.env.tx.transact_to = TransactTo::Call(H160::repeat_byte(0x20));
+            evm.env.tx.gas_limit = 21_000;
+            evm.env.tx.gas_price = U256::from(15);
+            evm.env.tx.gas_priority_fee = Some(U256::from(3));
+            let (result, state) = evm.transact();
+            let balance = |address| state.get(&address).map(|account| account.info.balance);
+            (result.exit_reason, balance(caller), balance(coinbase))
+        };
+        // the sender pays the effective price, the coinbase gets the priority fee.
+        assert_eq!(
+            transfer(10),
+            (
+                Return::Stop,
+                Some(U256::from(1_000_000 - 13 * 21_000)),
+                Some(U256::from(3 * 21_000))
+            )
+        );
+        assert_eq!(
+            transfer(15),
+            (
+                Return::Stop,
+                Some(U256::from(1_000_000 - 15 * 21_000)),
+                Some(U256::zero())
+            )
+        );
+        assert_eq!(transfer(16), (Return::GasPriceLessThenBasefee, None, None));
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_gas_measuring"))]
+    fn test_gas_summary() {
+        let (caller, contract) = (H160::repeat_byte(0x10), H160::repeat_byte(0x20));
+        // clear slot 0 for a refund, then write slot 1.
+        let code = vec![
+            opcode::PUSH1,
+            0x00,
+            opcode::PUSH1,
+            0x00,
+            opcode::SSTORE,
+            opcode::PUSH1,
+            0x01,
+            opcode::PUSH1,
+            0x01,
+            opcode::SSTORE,
+            opcode::STOP,
+        ];
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(caller, AccountInfo::from_balance(U256::from(1_000_000)));
+        db.insert_account_info(
+            contract,
+            AccountInfo::new(U256::zero(), 1, Bytecode::new_raw(code.into())),
+        );
+        db.insert_account_storage(contract, U256::zero(), U256::one())
+            .unwrap();
+        let mut evm = evm_with_db(db);
+        evm.env.tx.transact_to = TransactTo::Call(contract);
+        evm.env.tx.gas_price = U256::from(2);
+
+        let (result, state) = evm.transact();
+        assert!(result.is_success());
+        assert_eq!(result.gas_limit, 100_000);
+        // four PUSH1, a cold clear and a cold write of a new slot.
+        let spent = 21_000 + 4 * 3 + 5_000 + 22_100;
+        assert_eq!(result.gas_used + result.gas_refunded, spent);
+        assert_eq!(result.gas_refunded, 4_800);
+        assert_eq!(result.gas_breakdown.unwrap().total(), result.gas_used);
+        assert_eq!(
+            state[&caller].info.balance,
+            U256::from(1_000_000 - 2 * result.gas_used)
+        );
+
+        // clearing slots 0 and 1 refunds more than a fifth of the spent gas.
+        let clearing = vec![
+            opcode::PUSH1,
+            0x00,
+            opcode::DUP1,
+            opcode::SSTORE,
+            opcode::PUSH1,
+            0x00,
+            opcode::PUSH1,
+            0x01,
+            opcode::SSTORE,
+            opcode::STOP,
+        ];
+        let db = evm.db().unwrap();
+        db.insert_account_info(
+            contract,
+            AccountInfo::new(U256::zero(), 1, Bytecode::new_raw(clearing.into())),
+        );
+        db.insert_account_storage(contract, U256::one(), U256::one())
+            .unwrap();
+        let (result, _) = evm.transact();
+        let spent = 21_000 + 3 * 3 + 3 + 2 * 5_000;
+        assert_eq!(result.gas_used + result.gas_refunded, spent);
+        assert_eq!(result.gas_refunded, spent / 5);
+
+        evm.env.tx.gas_limit = 20_000;
+        let (result, _) = evm.transact();
+        assert!(!result.is_success());
+        assert_eq!((result.gas_used, result.gas_limit), (0, 20_000));
+    }
 }
@@ -0,0 +1,110 @@
+use crate::alloc::string::String;
+use bytes::Bytes;
+use primitive_types::U256;
+
+/// Selector of Solidity `Error(string)`, used by `revert("...")` and `require`.
+pub const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+/// Selector of Solidity `Panic(uint256)`, used by failing asserts, overflows and the like.
+pub const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Reason of a revert, see [decode_revert_reason].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RevertReason {
+    /// Message of `Error(string)`.
+    Error(String),
+    /// Code of `Panic(uint256)`.
+    Panic(U256),
+    /// Output that is neither of the above, including malformed ones.
+    Raw(Bytes),
+}
+
+/// Decode the output of a reverted call as a Solidity `Error(string)` or `Panic(uint256)`.
+pub fn decode_revert_reason(output: &Bytes) -> RevertReason {
+    let decoded = match output.get(..4) {
+        Some(selector) if selector == ERROR_SELECTOR => {
+            decode_string(&output[4..]).map(RevertReason::Error)
+        }
+        Some(selector) if selector == PANIC_SELECTOR && output.len() == 36 => {
+            Some(RevertReason::Panic(U256::from_big_endian(&output[4..])))
+        }
+        _ => None,
+    };
+    decoded.unwrap_or_else(|| RevertReason::Raw(output.clone()))
+}
+
+/// ABI decode a single `string` argument.
+fn decode_string(data: &[u8]) -> Option<String> {
+    let word = |offset: usize| -> Option<usize> {
+        let word = data.get(offset..offset.checked_add(32)?)?;
+        let value = U256::from_big_endian(word);
+        (value <= U256::from(usize::MAX)).then(|| value.as_usize())
+    };
+    let offset = word(0)?;
+    let len = word(offset)?;
+    let start = offset.checked_add(32)?;
+    let bytes = data.get(start..start.checked_add(len)?)?;
+    String::from_utf8(bytes.to_vec()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_revert_reason, RevertReason};
+    use bytes::Bytes;
+    use primitive_types::U256;
+
+    #[test]
+    fn test_error_string() {
+        // revert("Not enough Ether provided.")
+        let output = hex::decode(concat!(
+            "08c379a0",
+            "0000000000000000000000000000000000000000000000000000000000000020",
+            "000000000000000000000000000000000000000000000000000000000000001a",
+            "4e6f7420656e6f7567682045746865722070726f76696465642e000000000000",
+        ))
+        .unwrap();
+        assert_eq!(
+            decode_revert_reason(&Bytes::from(output.clone())),
+            RevertReason::Error("Not enough Ether provided.".into())
+        );
+
+        // message longer than the output is kept raw.
+        let truncated = Bytes::from(output[..output.len() - 8].to_vec());
+        assert_eq!(
+            decode_revert_reason(&truncated),
+            RevertReason::Raw(truncated.clone())
+        );
+    }
+
+    #[test]
+    fn test_panic() {
+        // arithmetic overflow.
+        let output = hex::decode(concat!(
+            "4e487b71",
+            "0000000000000000000000000000000000000000000000000000000000000011",
+        ))
+        .unwrap();
+        assert_eq!(
+            decode_revert_reason(&Bytes::from(output)),
+            RevertReason::Panic(U256::from(0x11))
+        );
+    }
+
+    #[test]
+    fn test_raw() {
+        assert_eq!(
+            decode_revert_reason(&Bytes::new()),
+            RevertReason::Raw(Bytes::new())
+        );
+        let output = Bytes::from_static(&[0xde, 0xad, 0xbe, 0xef, 0x01]);
+        assert_eq!(
+            decode_revert_reason(&output),
+            RevertReason::Raw(output.clone())
+        );
+        // selector without arguments.
+        let output = Bytes::from_static(&[0x4e, 0x48, 0x7b, 0x71]);
+        assert_eq!(
+            decode_revert_reason(&output),
+            RevertReason::Raw(output.clone())
+        );
+    }
+}
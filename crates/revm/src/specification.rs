@@ -25,7 +25,13 @@ pub enum SpecId {
     ARROW_GLACIER = 13,   // Arrow Glacier	        13773000
     GRAY_GLACIER = 14,    // Gray Glacier	        15050000
     MERGE = 15,           // Paris/Merge	        TBD (Depends on difficulty)
-    LATEST = 16,
+    SHANGHAI = 16,        // Shanghai               only EIP-3651, EIP-3855 and EIP-3860
+    CANCUN = 17, // Cancun                 only EIP-1153, EIP-4844, EIP-5656, EIP-6780 and EIP-7516
+    PRAGUE = 18, // Prague                 only EIP-7702 set code transactions
+    LATEST = 19,
+    // LATEST with EIP-3540 and EIP-3670 EOF validation. Not part of LATEST because EOF code is
+    // deployed but can not be executed yet.
+    EXPERIMENTAL_EOF = 20,
 }
 
 impl SpecId {
@@ -36,7 +42,8 @@ impl SpecId {
             }
             BYZANTIUM | CONSTANTINOPLE | PETERSBURG => PrecompileId::BYZANTIUM,
             ISTANBUL | MUIR_GLACIER => PrecompileId::ISTANBUL,
-            BERLIN | LONDON | ARROW_GLACIER | GRAY_GLACIER | MERGE | LATEST => PrecompileId::BERLIN,
+            BERLIN | LONDON | ARROW_GLACIER | GRAY_GLACIER | MERGE | SHANGHAI | CANCUN | PRAGUE
+            | LATEST | EXPERIMENTAL_EOF => PrecompileId::BERLIN,
         }
     }
 
@@ -62,6 +69,9 @@ impl From<&str> for SpecId {
             "Berlin" => SpecId::BERLIN,
             "London" => SpecId::LONDON,
             "Merge" => SpecId::MERGE,
+            "Shanghai" => SpecId::SHANGHAI,
+            "Cancun" => SpecId::CANCUN,
+            "Prague" => SpecId::PRAGUE,
             _ => SpecId::LATEST,
         }
     }
@@ -134,7 +144,8 @@ pub(crate) mod spec_impl {
     spec!(TANGERINE);
     spec!(SPURIOUS_DRAGON);
     spec!(BYZANTIUM);
-    // CONSTANTINOPLE was overriden with PETERSBURG
+    // CONSTANTINOPLE was overriden with PETERSBURG on mainnet, kept for its EIP-1283 SSTORE
+    spec!(CONSTANTINOPLE);
     spec!(PETERSBURG);
     spec!(ISTANBUL);
     // MUIR_GLACIER no EVM spec change
@@ -143,11 +154,18 @@ pub(crate) mod spec_impl {
     // ARROW_GLACIER no EVM spec change
     // GRAT_GLACIER no EVM spec change
     spec!(MERGE);
+    spec!(SHANGHAI);
+    spec!(CANCUN);
+    spec!(PRAGUE);
     spec!(LATEST);
+    spec!(EXPERIMENTAL_EOF);
 }
 
 pub use spec_impl::BERLIN::SpecImpl as BerlinSpec;
 pub use spec_impl::BYZANTIUM::SpecImpl as ByzantiumSpec;
+pub use spec_impl::CANCUN::SpecImpl as CancunSpec;
+pub use spec_impl::CONSTANTINOPLE::SpecImpl as ConstantinopleSpec;
+pub use spec_impl::EXPERIMENTAL_EOF::SpecImpl as ExperimentalEofSpec;
 pub use spec_impl::FRONTIER::SpecImpl as FrontierSpec;
 pub use spec_impl::HOMESTEAD::SpecImpl as HomesteadSpec;
 pub use spec_impl::ISTANBUL::SpecImpl as IstanbulSpec;
@@ -155,5 +173,7 @@ pub use spec_impl::LATEST::SpecImpl as LatestSpec;
 pub use spec_impl::LONDON::SpecImpl as LondonSpec;
 pub use spec_impl::MERGE::SpecImpl as MergeSpec;
 pub use spec_impl::PETERSBURG::SpecImpl as PetersburgSpec;
+pub use spec_impl::PRAGUE::SpecImpl as PragueSpec;
+pub use spec_impl::SHANGHAI::SpecImpl as ShanghaiSpec;
 pub use spec_impl::SPURIOUS_DRAGON::SpecImpl as SpuriousDragonSpec;
 pub use spec_impl::TANGERINE::SpecImpl as TangerineSpec;
@@ -0,0 +1,32 @@
+use crate::{db::BenchmarkDB, Bytecode, Contract, Interpreter, LatestSpec, TransactTo, EVM};
+use bytes::Bytes;
+use primitive_types::{H160, U256};
+
+/// [EVM] over `db` with a transaction from `0x1010..10` to the zero address and a gas limit
+/// of 100_000.
+pub(crate) fn evm_with_db<DB>(db: DB) -> EVM<DB> {
+    let mut evm = crate::new();
+    evm.database(db);
+    evm.env.tx.caller = H160::repeat_byte(0x10);
+    evm.env.tx.transact_to = TransactTo::Call(H160::zero());
+    evm.env.tx.gas_limit = 100_000;
+    evm
+}
+
+/// [evm_with_db] over a [BenchmarkDB] with `code` at the zero address.
+pub(crate) fn evm_with_code(code: Vec<u8>) -> EVM<BenchmarkDB> {
+    evm_with_db(BenchmarkDB::new_bytecode(Bytecode::new_raw(code.into())))
+}
+
+/// [Interpreter] running `code` under [LatestSpec] with `gas_limit`, called by the zero address
+/// on the zero address with no input and no value.
+pub(crate) fn interpreter(code: Vec<u8>, gas_limit: u64) -> Interpreter {
+    let contract = Contract::new_from_code::<LatestSpec>(
+        code.into(),
+        Bytes::new(),
+        H160::zero(),
+        H160::zero(),
+        U256::zero(),
+    );
+    Interpreter::new::<LatestSpec>(contract, gas_limit)
+}
@@ -0,0 +1,500 @@
+use crate::{alloc::vec::Vec, precompiles::recover_address, TransactTo, TxEnv};
+use bytes::Bytes;
+use primitive_types::{H160, H256, U256};
+use rlp::{DecoderError, Rlp, RlpStream};
+use sha3::{Digest, Keccak256};
+
+/// Order of the secp256k1 curve.
+const SECP256K1N: U256 = U256([
+    0xbfd25e8cd0364141,
+    0xbaaedce6af48a03b,
+    0xfffffffffffffffe,
+    0xffffffffffffffff,
+]);
+
+/// Half the order of the secp256k1 curve, `s` values above it are rejected since EIP-2.
+const SECP256K1N_HALF: U256 = U256([
+    0xdfe92f46681b20a0,
+    0x5d576e7357a4501d,
+    0xffffffffffffffff,
+    0x7fffffffffffffff,
+]);
+
+/// EIP-2718 type of EIP-2930 access list transactions.
+pub const ACCESS_LIST_TX_TYPE: u8 = 0x01;
+/// EIP-2718 type of EIP-1559 dynamic fee transactions.
+pub const DYNAMIC_FEE_TX_TYPE: u8 = 0x02;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RawTxError {
+    /// Transaction is not valid RLP or has wrong number of fields.
+    Rlp(DecoderError),
+    /// EIP-2718 transaction type that is not supported.
+    UnsupportedType(u8),
+    /// Signature is malformed or the signer can't be recovered from it.
+    InvalidSignature,
+}
+
+impl From<DecoderError> for RawTxError {
+    fn from(error: DecoderError) -> Self {
+        Self::Rlp(error)
+    }
+}
+
+/// Decode a raw signed transaction into [TxEnv], the caller is recovered from the signature.
+///
+/// Supports legacy (optionally EIP-155 replay protected), EIP-2930 and EIP-1559 transactions.
+/// Typed transactions are prefixed with their EIP-2718 type byte, legacy transactions start with
+/// an RLP list.
+pub fn decode_raw_tx(raw: &[u8]) -> Result<TxEnv, RawTxError> {
+    let signed = decode_signed(raw)?;
+    let caller = signed.recover()?;
+    Ok(TxEnv {
+        caller,
+        ..signed.tx
+    })
+}
+
+/// Recover the sender and transaction hash of a raw signed transaction.
+///
+/// The signing hash follows the rules of the transaction type, including EIP-155 replay protection
+/// for legacy transactions. Signatures with `r` or `s` outside of `[1, n)`, `s` above `n / 2`
+/// (EIP-2) or an invalid `v` are rejected, as are bytes after the transaction, which would give
+/// the same transaction another hash.
+pub fn recover_sender(raw: &[u8]) -> Result<(H160, H256), RawTxError> {
+    let signed = decode_signed(raw)?;
+    Ok((signed.recover()?, signed.hash))
+}
+
+/// Decoded transaction with the hash that was signed and its `r || s || recovery id` signature.
+struct SignedTx {
+    tx: TxEnv,
+    /// Transaction hash, of the envelope that was decoded.
+    hash: H256,
+    signing_hash: H256,
+    signature: [u8; 65],
+}
+
+impl SignedTx {
+    fn recover(&self) -> Result<H160, RawTxError> {
+        recover_address(&self.signature, self.signing_hash.as_fixed_bytes())
+            .ok_or(RawTxError::InvalidSignature)
+    }
+}
+
+fn decode_signed(raw: &[u8]) -> Result<SignedTx, RawTxError> {
+    match raw.first() {
+        None => Err(DecoderError::RlpIsTooShort.into()),
+        Some(byte) if *byte >= 0xc0 => decode_legacy(raw),
+        Some(&ACCESS_LIST_TX_TYPE) => decode_typed(ACCESS_LIST_TX_TYPE, raw),
+        Some(&DYNAMIC_FEE_TX_TYPE) => decode_typed(DYNAMIC_FEE_TX_TYPE, raw),
+        Some(tx_type) => Err(RawTxError::UnsupportedType(*tx_type)),
+    }
+}
+
+/// `rlp([nonce, gas_price, gas_limit, to, value, data, v, r, s])`
+fn decode_legacy(raw: &[u8]) -> Result<SignedTx, RawTxError> {
+    let rlp = whole_item(raw)?;
+    if rlp.item_count()? != 9 {
+        return Err(DecoderError::RlpIncorrectListLen.into());
+    }
+    let v: u64 = rlp.val_at(6)?;
+    // EIP-155: v = chain_id * 2 + 35 + recovery id
+    let (chain_id, recovery_id) = match v {
+        27 | 28 => (None, v - 27),
+        35.. => (Some((v - 35) / 2), (v - 35) % 2),
+        _ => return Err(RawTxError::InvalidSignature),
+    };
+
+    let mut stream = RlpStream::new_list(if chain_id.is_some() { 9 } else { 6 });
+    for i in 0..6 {
+        stream.append_raw(rlp.at(i)?.as_raw(), 1);
+    }
+    if let Some(chain_id) = chain_id {
+        stream.append(&chain_id);
+        stream.append_empty_data();
+        stream.append_empty_data();
+    }
+
+    Ok(SignedTx {
+        tx: TxEnv {
+            caller: H160::zero(),
+            gas_limit: rlp.val_at(2)?,
+            gas_price: rlp.val_at(1)?,
+            gas_priority_fee: None,
+            transact_to: decode_to(&rlp.at(3)?)?,
+            value: rlp.val_at(4)?,
+            data: Bytes::from(rlp.val_at::<Vec<u8>>(5)?),
+            chain_id,
+            nonce: Some(rlp.val_at(0)?),
+            access_list: Vec::new(),
+            authorization_list: Vec::new(),
+            blob_hashes: Vec::new(),
+        },
+        hash: keccak256(raw),
+        signing_hash: keccak256(&stream.out()),
+        signature: signature(&rlp, 7, recovery_id)?,
+    })
+}
+
+/// EIP-2930: `0x01 || rlp([chain_id, nonce, gas_price, gas_limit, to, value, data, access_list,
+/// y_parity, r, s])`
+///
+/// EIP-1559: `0x02 || rlp([chain_id, nonce, max_priority_fee_per_gas, max_fee_per_gas, gas_limit,
+/// to, value, data, access_list, y_parity, r, s])`
+fn decode_typed(tx_type: u8, raw: &[u8]) -> Result<SignedTx, RawTxError> {
+    let rlp = whole_item(&raw[1..])?;
+    // dynamic fee transactions have one more fee field, the rest is shifted by one.
+    let shift = usize::from(tx_type == DYNAMIC_FEE_TX_TYPE);
+    let fields = 8 + shift;
+    if rlp.item_count()? != fields + 3 {
+        return Err(DecoderError::RlpIncorrectListLen.into());
+    }
+    let (gas_price, gas_priority_fee) = if tx_type == DYNAMIC_FEE_TX_TYPE {
+        (rlp.val_at(3)?, Some(rlp.val_at(2)?))
+    } else {
+        (rlp.val_at(2)?, None)
+    };
+
+    let mut stream = RlpStream::new_list(fields);
+    for i in 0..fields {
+        stream.append_raw(rlp.at(i)?.as_raw(), 1);
+    }
+    let mut signing_payload = Vec::with_capacity(1 + stream.len());
+    signing_payload.push(tx_type);
+    signing_payload.extend_from_slice(&stream.out());
+
+    let recovery_id: u64 = rlp.val_at(fields)?;
+    if recovery_id > 1 {
+        return Err(RawTxError::InvalidSignature);
+    }
+
+    Ok(SignedTx {
+        tx: TxEnv {
+            caller: H160::zero(),
+            gas_limit: rlp.val_at(3 + shift)?,
+            gas_price,
+            gas_priority_fee,
+            transact_to: decode_to(&rlp.at(4 + shift)?)?,
+            value: rlp.val_at(5 + shift)?,
+            data: Bytes::from(rlp.val_at::<Vec<u8>>(6 + shift)?),
+            chain_id: Some(rlp.val_at(0)?),
+            nonce: Some(rlp.val_at(1)?),
+            access_list: decode_access_list(&rlp.at(7 + shift)?)?,
+            authorization_list: Vec::new(),
+            blob_hashes: Vec::new(),
+        },
+        hash: keccak256(raw),
+        signing_hash: keccak256(&signing_payload),
+        signature: signature(&rlp, fields + 1, recovery_id)?,
+    })
+}
+
+/// RLP item that spans all of `data`, [Rlp] itself ignores bytes after the first item.
+fn whole_item(data: &[u8]) -> Result<Rlp<'_>, DecoderError> {
+    let rlp = Rlp::new(data);
+    if rlp.payload_info()?.total() != data.len() {
+        return Err(DecoderError::RlpInconsistentLengthAndData);
+    }
+    Ok(rlp)
+}
+
+/// Empty `to` creates a contract.
+fn decode_to(rlp: &Rlp) -> Result<TransactTo, DecoderError> {
+    if rlp.is_empty() {
+        Ok(TransactTo::create())
+    } else {
+        Ok(TransactTo::Call(rlp.as_val()?))
+    }
+}
+
+/// `[[address, [storage_key, ...]], ...]`
+fn decode_access_list(rlp: &Rlp) -> Result<Vec<(H160, Vec<U256>)>, DecoderError> {
+    rlp.iter()
+        .map(|item| {
+            let keys = item
+                .at(1)?
+                .iter()
+                .map(|key| Ok(U256::from_big_endian(key.as_val::<H256>()?.as_bytes())))
+                .collect::<Result<_, DecoderError>>()?;
+            Ok((item.val_at(0)?, keys))
+        })
+        .collect()
+}
+
+/// `r || s || recovery id` from the `r` and `s` fields at `index` and `index + 1`.
+fn signature(rlp: &Rlp, index: usize, recovery_id: u64) -> Result<[u8; 65], RawTxError> {
+    let r: U256 = rlp.val_at(index)?;
+    let s: U256 = rlp.val_at(index + 1)?;
+    if r.is_zero() || r >= SECP256K1N || s.is_zero() || s > SECP256K1N_HALF {
+        return Err(RawTxError::InvalidSignature);
+    }
+    let mut signature = [0u8; 65];
+    r.to_big_endian(&mut signature[..32]);
+    s.to_big_endian(&mut signature[32..64]);
+    signature[64] = recovery_id as u8;
+    Ok(signature)
+}
+
+fn keccak256(data: &[u8]) -> H256 {
+    H256::from_slice(Keccak256::digest(data).as_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::str::FromStr;
+    use rlp::Encodable;
+    use secp256k1::{Message, Secp256k1, SecretKey};
+
+    /// Address of the `0x4646..46` key used in the EIP-155 example.
+    fn sender() -> H160 {
+        H160::from_str("9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f").unwrap()
+    }
+
+    /// Sign `rlp(fields)` as a typed transaction with the `0x4646..46` key.
+    fn sign_typed(tx_type: u8, fields: &[&dyn Encodable]) -> Vec<u8> {
+        let mut stream = RlpStream::new_list(fields.len());
+        for field in fields {
+            stream.append_raw(&field.rlp_bytes(), 1);
+        }
+        let mut payload = vec![tx_type];
+        payload.extend_from_slice(&stream.out());
+
+        let secp = Secp256k1::new();
+        let key = SecretKey::from_slice(&[0x46; 32]).unwrap();
+        let message = Message::from_slice(keccak256(&payload).as_bytes()).unwrap();
+        let (recovery_id, signature) = secp
+            .sign_ecdsa_recoverable(&message, &key)
+            .serialize_compact();
+
+        let mut stream = RlpStream::new_list(fields.len() + 3);
+        for field in fields {
+            stream.append_raw(&field.rlp_bytes(), 1);
+        }
+        stream.append(&(recovery_id.to_i32() as u64));
+        stream.append(&U256::from_big_endian(&signature[..32]));
+        stream.append(&U256::from_big_endian(&signature[32..]));
+        let mut raw = vec![tx_type];
+        raw.extend_from_slice(&stream.out());
+        raw
+    }
+
+    struct AccessList(Vec<(H160, Vec<H256>)>);
+
+    impl Encodable for AccessList {
+        fn rlp_append(&self, s: &mut RlpStream) {
+            s.begin_list(self.0.len());
+            for (address, keys) in &self.0 {
+                s.begin_list(2);
+                s.append(address);
+                s.append_list(keys);
+            }
+        }
+    }
+
+    #[test]
+    fn test_legacy_eip155() {
+        // example from EIP-155
+        let raw = hex::decode("f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83").unwrap();
+        let signed = decode_signed(&raw).unwrap();
+        assert_eq!(
+            signed.signing_hash,
+            H256::from_str("daf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53")
+                .unwrap()
+        );
+
+        let tx = decode_raw_tx(&raw).unwrap();
+        assert_eq!(tx.caller, sender());
+        assert_eq!(tx.nonce, Some(9));
+        assert_eq!(tx.chain_id, Some(1));
+        assert_eq!(tx.gas_price, U256::from(20_000_000_000u64));
+        assert_eq!(tx.gas_priority_fee, None);
+        assert_eq!(tx.gas_limit, 21_000);
+        assert!(matches!(tx.transact_to, TransactTo::Call(to) if to == H160::repeat_byte(0x35)));
+        assert_eq!(tx.value, U256::exp10(18));
+        assert!(tx.data.is_empty());
+        assert!(tx.access_list.is_empty());
+    }
+
+    #[test]
+    fn test_access_list_tx() {
+        let raw = sign_typed(
+            ACCESS_LIST_TX_TYPE,
+            &[
+                &5u64,
+                &0u64,
+                &U256::from(1_000),
+                &50_000u64,
+                &H160::repeat_byte(0x35),
+                &U256::from(7),
+                &vec![0x12u8, 0x34],
+                &AccessList(vec![(
+                    H160::repeat_byte(0xaa),
+                    vec![H256::from_low_u64_be(1), H256::from_low_u64_be(2)],
+                )]),
+            ],
+        );
+        let tx = decode_raw_tx(&raw).unwrap();
+        assert_eq!(tx.caller, sender());
+        assert_eq!(tx.chain_id, Some(5));
+        assert_eq!(tx.nonce, Some(0));
+        assert_eq!(tx.gas_price, U256::from(1_000));
+        assert_eq!(tx.gas_priority_fee, None);
+        assert_eq!(tx.gas_limit, 50_000);
+        assert!(matches!(tx.transact_to, TransactTo::Call(to) if to == H160::repeat_byte(0x35)));
+        assert_eq!(tx.value, U256::from(7));
+        assert_eq!(tx.data, Bytes::from(vec![0x12, 0x34]));
+        assert_eq!(
+            tx.access_list,
+            vec![(H160::repeat_byte(0xaa), vec![U256::from(1), U256::from(2)])]
+        );
+    }
+
+    #[test]
+    fn test_dynamic_fee_create_tx() {
+        let raw = sign_typed(
+            DYNAMIC_FEE_TX_TYPE,
+            &[
+                &1u64,
+                &3u64,
+                &U256::from(2),
+                &U256::from(100),
+                &100_000u64,
+                &"",
+                &U256::zero(),
+                &vec![0x60u8, 0x00],
+                &AccessList(Vec::new()),
+            ],
+        );
+        let tx = decode_raw_tx(&raw).unwrap();
+        assert_eq!(tx.caller, sender());
+        assert_eq!(tx.chain_id, Some(1));
+        assert_eq!(tx.nonce, Some(3));
+        assert_eq!(tx.gas_price, U256::from(100));
+        assert_eq!(tx.gas_priority_fee, Some(U256::from(2)));
+        assert_eq!(tx.gas_limit, 100_000);
+        assert!(matches!(tx.transact_to, TransactTo::Create(_)));
+        assert_eq!(tx.data, Bytes::from(vec![0x60, 0x00]));
+        assert!(tx.access_list.is_empty());
+    }
+
+    /// EIP-155 example with the signature fields replaced.
+    fn resign_legacy(v: u64, r: U256, s: U256) -> Vec<u8> {
+        let raw = hex::decode("f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83").unwrap();
+        let rlp = Rlp::new(&raw);
+        let mut stream = RlpStream::new_list(9);
+        for i in 0..6 {
+            stream.append_raw(rlp.at(i).unwrap().as_raw(), 1);
+        }
+        stream.append(&v).append(&r).append(&s);
+        stream.out().to_vec()
+    }
+
+    #[test]
+    fn test_recover_sender() {
+        // signed by web3 with key 0x4c0883a6..
+        let expected = H160::from_str("2c7536e3605d9c16a7a3d7b1898e529396a65c23").unwrap();
+        let vectors = [
+            (
+                "f869808504e3b29200831e848094f0109fc8df283027b6285cc889f5aa624eac1f55843b9aca008025a0c9cf86333bcb065d140032ecaab5d9281bde80f21b9687b3e94161de42d51895a0727a108a0b8d101465414033c3f705a9c7b826e596766046ee1183dbc8aeaa68",
+                "de8db924885b0803d2edc335f745b2b8750c8848744905684c20b987443a9593",
+            ),
+            (
+                "f86a8086d55698372431831e848094f0109fc8df283027b6285cc889f5aa624eac1f55843b9aca008025a009ebb6ca057a0535d6186462bc0b465b561c94a295bdb0621fc19208ab149a9ca0440ffd775ce91a833ab410777204d5341a6f9fa91216a6f3ee2c051fea6a0428",
+                "d8f64a42b57be0d565f385378db2f6bf324ce14a594afc05de90436e9ce01f60",
+            ),
+        ];
+        for (raw, hash) in vectors {
+            let (sender, tx_hash) = recover_sender(&hex::decode(raw).unwrap()).unwrap();
+            assert_eq!(sender, expected);
+            assert_eq!(tx_hash, H256::from_str(hash).unwrap());
+        }
+
+        let raw = sign_typed(
+            DYNAMIC_FEE_TX_TYPE,
+            &[
+                &1u64,
+                &0u64,
+                &U256::from(1),
+                &U256::from(1),
+                &21_000u64,
+                &H160::repeat_byte(0x35),
+                &U256::zero(),
+                &Vec::<u8>::new(),
+                &AccessList(Vec::new()),
+            ],
+        );
+        assert_eq!(recover_sender(&raw).unwrap(), (sender(), keccak256(&raw)));
+    }
+
+    #[test]
+    fn test_reject_malformed_signature() {
+        let r = U256::from_str("28ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276")
+            .unwrap();
+        let s = U256::from_str("67cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83")
+            .unwrap();
+        let raw = resign_legacy(37, r, s);
+        assert_eq!(recover_sender(&raw).unwrap(), (sender(), keccak256(&raw)));
+        // trailing bytes would give the same transaction another hash.
+        let mut with_junk = raw.clone();
+        with_junk.push(0x00);
+        assert!(matches!(
+            recover_sender(&with_junk),
+            Err(RawTxError::Rlp(DecoderError::RlpInconsistentLengthAndData))
+        ));
+
+        let invalid = [
+            // malleable signature, `(r, n - s)` recovers the same sender with the other parity.
+            (38, r, SECP256K1N - s),
+            (37, U256::zero(), s),
+            (37, r, U256::zero()),
+            (37, SECP256K1N, s),
+            (37, r, SECP256K1N_HALF + 1),
+            // v that is neither 27/28 nor a valid EIP-155 value.
+            (0, r, s),
+            (29, r, s),
+        ];
+        for (v, r, s) in invalid {
+            assert!(matches!(
+                recover_sender(&resign_legacy(v, r, s)),
+                Err(RawTxError::InvalidSignature)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_reject_trailing_bytes() {
+        let legacy = hex::decode("f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83").unwrap();
+        let typed = sign_typed(
+            ACCESS_LIST_TX_TYPE,
+            &[
+                &1u64,
+                &0u64,
+                &U256::from(1),
+                &21_000u64,
+                &H160::repeat_byte(0x35),
+                &U256::zero(),
+                &Vec::<u8>::new(),
+                &AccessList(Vec::new()),
+            ],
+        );
+        for raw in [legacy, typed] {
+            assert_eq!(decode_raw_tx(&raw).unwrap().caller, sender());
+            let mut with_junk = raw.clone();
+            with_junk.extend_from_slice(&[0xde, 0xad]);
+            assert!(matches!(
+                decode_raw_tx(&with_junk),
+                Err(RawTxError::Rlp(DecoderError::RlpInconsistentLengthAndData))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_unsupported_type() {
+        assert!(matches!(
+            decode_raw_tx(&[0x03, 0xc0]),
+            Err(RawTxError::UnsupportedType(0x03))
+        ));
+    }
+}
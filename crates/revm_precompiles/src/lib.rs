@@ -13,11 +13,12 @@ mod modexp;
 mod secp256k1;
 
 pub use error::Return;
+pub use secp256k1::recover_address;
 
 /// libraries for no_std flag
 #[macro_use]
 extern crate alloc;
-use alloc::vec::Vec;
+use alloc::{sync::Arc, vec::Vec};
 use core::fmt;
 
 use hashbrown::HashMap;
@@ -61,11 +62,14 @@ impl PrecompileOutput {
 pub type PrecompileResult = Result<PrecompileOutput, Return>;
 
 pub type StandardPrecompileFn = fn(&[u8], u64) -> PrecompileResult;
-pub type CustomPrecompileFn = fn(&[u8], u64) -> PrecompileResult;
+/// Precompile added by the user, see [Precompiles::insert_custom].
+pub type CustomPrecompileFn = Arc<dyn Fn(&[u8], u64) -> PrecompileResult + Send + Sync>;
 
 #[derive(Clone, Debug)]
 pub struct Precompiles {
     fun: HashMap<Address, Precompile>,
+    /// Factor applied to the gas cost of precompiles, missing addresses are charged as is.
+    gas_multipliers: HashMap<Address, u64>,
 }
 
 impl Default for Precompiles {
@@ -116,7 +120,10 @@ impl Precompiles {
             ]
             .into_iter()
             .collect();
-            Self { fun }
+            Self {
+                fun,
+                gas_multipliers: HashMap::new(),
+            }
         })
     }
 
@@ -208,6 +215,24 @@ impl Precompiles {
     pub fn len(&self) -> usize {
         self.fun.len()
     }
+
+    /// Add a precompile at `address`, replacing the built-in one if there is any. It is called
+    /// with the input and gas limit of the call and charged like a built-in precompile.
+    pub fn insert_custom(&mut self, address: Address, fun: CustomPrecompileFn) {
+        self.fun.insert(address, Precompile::Custom(fun));
+    }
+
+    /// Charge `multiplier` times the base gas cost of the precompile at `address`.
+    ///
+    /// Lets chains reprice precompiles without reimplementing them.
+    pub fn set_gas_multiplier(&mut self, address: Address, multiplier: u64) {
+        self.gas_multipliers.insert(address, multiplier);
+    }
+
+    /// Factor applied to the gas cost of the precompile at `address`, 1 if it was not repriced.
+    pub fn gas_multiplier(&self, address: &Address) -> u64 {
+        self.gas_multipliers.get(address).copied().unwrap_or(1)
+    }
 }
 
 /// const fn for making an address by concatenating the bytes from two given numbers,
@@ -59,6 +59,13 @@ mod secp256k1 {
     }
 }
 
+/// Recover the signer address from a `r || s || recovery id` signature over the hash `msg`.
+///
+/// Returns None if the signature is invalid.
+pub fn recover_address(sig: &[u8; 65], msg: &[u8; 32]) -> Option<Address> {
+    secp256k1::ecrecover(sig, msg).ok()
+}
+
 fn ec_recover_run(i: &[u8], target_gas: u64) -> PrecompileResult {
     let cost = gas_query(ECRECOVER_BASE, target_gas)?;
     let mut input = [0u8; 128];
@@ -0,0 +1,38 @@
+//! Analyse and run arbitrary bytecode against a [MockHost].
+//!
+//! The input is a big endian `u16` gas limit followed by the bytecode. Execution is checked, so
+//! an instruction pointer that leaves the code fails with [Return::OutOfOffset] instead of reading
+//! out of bounds, and it ends after at most [STEP_LIMIT] opcodes so no input can hang.
+//!
+//! Run with `cargo fuzz run execute` from the repository root, `corpus/execute` is seeded with
+//! real contracts.
+#![no_main]
+
+use bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+use primitive_types::{H160, U256};
+use revm::{Bytecode, Contract, Interpreter, LatestSpec, MockHost, Return};
+
+const STEP_LIMIT: u64 = 100_000;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((gas_limit, code)) = data.split_first_chunk::<2>() else {
+        return;
+    };
+    let gas_limit = u16::from_be_bytes(*gas_limit) as u64;
+    let contract = Contract::new::<LatestSpec>(
+        Bytes::new(),
+        Bytecode::new_raw(Bytes::copy_from_slice(code)),
+        H160::repeat_byte(0x20),
+        H160::repeat_byte(0x10),
+        U256::zero(),
+    );
+    let mut interp = Interpreter::new::<LatestSpec>(contract, gas_limit);
+    interp.checked_execution = true;
+    interp.step_limit = Some(STEP_LIMIT);
+
+    let ret = interp.run::<_, LatestSpec>(&mut MockHost::new());
+    assert_ne!(ret, Return::OutOfOffset, "instruction pointer left the code");
+    assert_ne!(ret, Return::Continue);
+    assert!(interp.gas.spend() <= gas_limit);
+});